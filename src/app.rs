@@ -1,18 +1,82 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{self};
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, MouseEventKind};
+use anyhow::{Result, bail};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseEventKind,
+};
 use crossterm::execute;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
-use ratatui::prelude::*;
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use log::{error, warn};
 use ratatui::backend::CrosstermBackend;
+use ratatui::prelude::*;
 use ratatui::widgets::ListState;
+use serde_json::{Value, json};
+use tokio::sync::Semaphore;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 
-use crate::memory::{KeywordStore, TranslationStore};
-use crate::syosetu::{Chapter, NovelSite, Translator};
-use crate::ui::{draw_directory, draw_loading, draw_reading};
+use crate::control::{ControlCommand, ControlRequest};
+use crate::cost::{Estimate, Pricing, estimate_chapter};
+use crate::export;
+use crate::library::Removal;
+use crate::memory::{
+    CHARACTER_FIELDS, Character, Conflict, MergeStrategy, NovelSettings, Revision, SETTINGS_FIELDS,
+    blacklist_keyword, merge_glossaries, promote_keyword,
+};
+use crate::metrics;
+use crate::mining::{Candidate, DEFAULT_MIN_COUNT, TermMiner};
+use crate::pipeline::{
+    Inconsistency, Pipeline, check_consistency, commit_keywords, count_usage, known_terms,
+    load_categories, load_conflicts, mine_candidates, process_chapter, queue_candidates,
+    record_stamps, replace_in_translations, resolve_conflict, rollback_translation,
+    save_translation, source_changed, translate_missing_titles, translate_synopsis,
+};
+use crate::syosetu::{Chapter, NOTE_LABELS_JA, NovelInfo, cancellable, is_cancelled};
+use crate::translate::{Keyword, KeywordCategory, StreamEvent, is_circuit_open};
+use crate::ui::{
+    draw_blacklist, draw_characters, draw_conflicts, draw_consistency, draw_directory,
+    draw_glossary, draw_history, draw_info, draw_loading, draw_pending_keywords, draw_reading,
+    draw_settings,
+};
+
+/// 后台章节任务回传给界面的消息
+pub enum TaskEvent {
+    /// 章节任务取得并发名额，开始抓取与翻译
+    Started { path: String },
+    /// 章节抓取与翻译完成
+    Done {
+        path: String,
+        keywords: Vec<Keyword>,
+    },
+    /// 正文译文的流式进度
+    Partial { path: String, event: StreamEvent },
+    /// 任务失败或被取消
+    Failed { path: String, error: anyhow::Error },
+    /// 章节标题翻译完成
+    Titles(Result<HashMap<String, String>>),
+    /// 作品简介翻译完成
+    Synopsis(Result<String>),
+    /// 已缓存章节的译名一致性检查完成
+    Consistency(Result<Vec<Inconsistency>>),
+    /// 本地挖掘专有名词候选完成
+    Candidates(Result<Vec<Candidate>>),
+    /// 重新统计专有名词出现次数完成
+    Usage(Result<HashMap<String, usize>>),
+    /// 打开的已缓存章节原文检查完成，`result` 为原文是否在翻译后被改动
+    SourceChecked { path: String, result: Result<bool> },
+    /// 章节原文抓取完成，得到预估的翻译用量
+    Estimate {
+        path: String,
+        result: Result<Estimate>,
+    },
+}
 
 /// 应用在目录界面中的输入模式
 #[derive(Clone, Copy, PartialEq)]
@@ -23,19 +87,77 @@ pub enum InputMode {
     Search,
 }
 
+/// 对照表界面中正在进行的输入
+#[derive(Clone, PartialEq)]
+pub enum GlossaryInput {
+    /// 输入搜索内容，列表随输入过滤
+    Search,
+    /// 修改选中词条的译名
+    Edit(String),
+    /// 新增词条时输入原文
+    NewTerm(String),
+    /// 新增词条时输入译名
+    NewTranslation { term: String, translation: String },
+    /// 输入要并入当前对照表的小说编号
+    MergeFrom(String),
+    /// 修改译名后确认是否改写已缓存章节中的旧译名，`chapters` 为预览的受影响章节及替换次数
+    ConfirmReplace {
+        old: String,
+        new: String,
+        chapters: Vec<(String, usize)>,
+    },
+}
+
+/// 目录界面中等待确认的清理及将被删除的记录数
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cleanup {
+    /// 按 `D` 删除本小说的全部数据
+    Remove(Removal),
+    /// 按 `O` 删除目录中已不存在的章节的记录
+    Prune(Removal),
+}
+
 /// 程序当前所处的状态
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AppState {
     /// 正在加载目录
     LoadingDir,
     /// 显示目录列表
     Directory,
-    /// 正在加载章节内容
-    LoadingChapter,
     /// 阅读模式
     Reading,
+    /// 作品信息面板
+    Info,
+    /// 人物设定列表，可修改或删除
+    Characters,
+    /// 本小说的设置面板
+    Settings,
+    /// 待审核的新专有名词，可接受、修改或拒绝
+    PendingKeywords,
+    /// 专有名词对照表，可搜索、新增、修改或删除
+    Glossary,
+    /// 本小说的屏蔽词列表，可新增或移除
+    Blacklist,
+    /// 译文没有使用对照译名的已缓存章节
+    Consistency,
+    /// 提取结果与对照表译名不一致的词条，选择保留哪个译名
+    Conflicts,
+    /// 正在阅读章节的旧译文，可预览差异并恢复
+    History,
+}
+
+/// 目录列表中的一行
+#[derive(Clone, PartialEq)]
+pub enum DirRow {
+    /// 章（卷）标题，可折叠
+    Arc(String),
+    /// 章节，值为在 `chapters` 中的下标
+    Chapter(usize),
 }
 
+/// 默认同时翻译的章节数
+pub const DEFAULT_CONCURRENCY: usize = 2;
+
 /// 保存 UI 状态及缓存数据
 pub struct App {
     /// 当前所处的状态
@@ -44,98 +166,1998 @@ pub struct App {
     pub mode: InputMode,
     /// 全部章节列表
     pub chapters: Vec<Chapter>,
-    /// 根据搜索过滤后的索引
-    pub filtered: Vec<usize>,
+    /// 根据搜索与折叠状态生成的目录行
+    pub filtered: Vec<DirRow>,
+    /// 已折叠的章（卷）标题
+    pub collapsed: HashSet<String>,
     /// 当前选中项在 `filtered` 中的索引
     pub selected: usize,
     /// 搜索框内容
     pub search: String,
-    /// 原文内容
-    pub content: String,
     /// 翻译结果
     pub translation: String,
+    /// 阅读界面中的章节路径
+    pub reading: Option<String>,
+    /// 打开时发现原文在翻译后被作者改动的章节，等待确认是否重新抓取并翻译
+    pub source_changed: Option<String>,
+    /// 正在翻译的章节已生成的正文译文，键为章节路径
+    pub streaming: HashMap<String, String>,
+    /// 当前阅读章节的插图地址
+    pub illustrations: Vec<String>,
     /// 阅读时的滚动位置
     pub scroll: u16,
+    /// 阅读时是否显示振り仮名
+    pub show_furigana: bool,
     /// 小说的唯一 id
     pub novel_id: String,
+    /// 作品信息，站点不提供时为空
+    pub info: NovelInfo,
+    /// 作品简介译文
+    pub synopsis: Option<String>,
+    /// 人物设定，进入人物界面时从存储重新读取
+    pub characters: Vec<Character>,
+    /// 人物界面中选中的人物下标
+    pub character_selected: usize,
+    /// 人物界面中选中的字段，对应 [`CHARACTER_FIELDS`]
+    pub character_field: usize,
+    /// 正在编辑的字段内容，未编辑时为 `None`
+    pub character_edit: Option<String>,
+    /// 本小说覆盖全局参数的设置
+    pub settings: NovelSettings,
+    /// 设置面板中选中的字段，对应 [`SETTINGS_FIELDS`]
+    pub settings_field: usize,
+    /// 正在编辑的设置内容，未编辑时为 `None`
+    pub settings_edit: Option<String>,
+    /// 等待审核的新专有名词，按原文排序
+    pub pending_keywords: Vec<(String, String)>,
+    /// 审核界面中选中的词条下标
+    pub pending_selected: usize,
+    /// 正在修改的译名，未修改时为 `None`
+    pub pending_edit: Option<String>,
+    /// 对照表界面中按搜索内容过滤并排序后的词条
+    pub glossary: Vec<(String, String)>,
+    /// 对照表界面的搜索内容
+    pub glossary_search: String,
+    /// 对照表界面中选中的词条下标
+    pub glossary_selected: usize,
+    /// 对照表界面中正在进行的输入
+    pub glossary_input: Option<GlossaryInput>,
+    /// 对照表词条的类别，含全局词条的类别
+    pub categories: HashMap<String, KeywordCategory>,
+    /// 各词条在已翻译章节原文中的出现次数
+    pub usage: HashMap<String, usize>,
+    /// 对照表界面按出现次数从多到少排列，而不是按原文
+    pub glossary_by_usage: bool,
+    /// 本小说的屏蔽词，按原文排序
+    pub blacklist: Vec<String>,
+    /// 屏蔽词界面中选中的下标
+    pub blacklist_selected: usize,
+    /// 正在输入的新屏蔽词，未输入时为 `None`
+    pub blacklist_input: Option<String>,
+    /// 待处理的译名冲突：原文、对照表中的译名与冲突记录
+    pub conflicts: Vec<(String, String, Conflict)>,
+    /// 冲突界面中选中的下标
+    pub conflict_selected: usize,
+    /// 一致性检查的结果，检查进行中为 `None`
+    pub consistency: Option<Vec<Inconsistency>>,
+    /// 一致性检查界面中选中的下标
+    pub consistency_selected: usize,
+    /// 正在阅读章节的旧译文，按时间从旧到新
+    pub history: Vec<Revision>,
+    /// 历史界面中选中的下标
+    pub history_selected: usize,
+    /// 等待确认的清理，未在确认时为 `None`
+    pub cleanup: Option<Cleanup>,
     /// 已知的翻译对照表
     pub keywords: HashMap<String, String>,
+    /// 上次读取对照表时文件的修改时间，用于发现外部编辑
+    keywords_modified: Option<SystemTime>,
+    /// 章节标题译文，键为章节路径
+    pub titles: HashMap<String, String>,
+    /// 是否显示原文标题
+    pub show_original_titles: bool,
     /// 本地已缓存章节路径
     pub cached_chapters: HashSet<String>,
+    /// 翻译时各章节的版本标记，键为章节路径
+    pub stamps: HashMap<String, String>,
+    /// 译文未沿用对照表译名的原文术语，键为章节路径
+    pub drift: HashMap<String, Vec<String>>,
+    /// 重试后仍然可疑的章节译文及原因，键为章节路径
+    pub suspects: HashMap<String, String>,
+    /// 按 `e` 预估的各章节翻译用量，键为章节路径
+    pub estimates: HashMap<String, Estimate>,
+    /// 预估费用使用的单价
+    pricing: Pricing,
+    /// 按 `M` 在本地挖掘专有名词候选时使用的分词器
+    miner: Arc<TermMiner>,
+    /// 正在后台处理的章节及其取消令牌，包括等待并发名额的章节
+    pub processing: HashMap<String, CancellationToken>,
+    /// 已提交但尚在等待并发名额的章节路径
+    pub waiting: HashSet<String>,
+    /// 限制同时翻译的章节数
+    slots: Arc<Semaphore>,
+    /// 暂停期间等待启动的章节路径
+    pub queued: VecDeque<String>,
+    /// 是否暂停启动新任务
+    pub paused: bool,
+    /// 底部状态栏显示的提示信息
+    pub message: Option<String>,
+    /// 翻译服务熔断时显示在目录顶部的警告，恢复队列后清除
+    pub banner: Option<String>,
+    /// 根取消令牌，退出时取消所有后台任务
+    cancel: CancellationToken,
+    /// 正在运行的后台任务
+    tasks: JoinSet<()>,
+    events_tx: UnboundedSender<TaskEvent>,
+    events_rx: UnboundedReceiver<TaskEvent>,
+    /// 控制 socket 转发来的请求
+    control_rx: Option<UnboundedReceiver<ControlRequest>>,
 }
 
 impl App {
     /// 根据小说 id 创建新的应用状态
     pub fn new(novel_id: String) -> Self {
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
         App {
             state: AppState::LoadingDir,
             mode: InputMode::Navigate,
             chapters: Vec::new(),
             filtered: Vec::new(),
+            collapsed: HashSet::new(),
             selected: 0,
             search: String::new(),
-            content: String::new(),
             translation: String::new(),
+            reading: None,
+            source_changed: None,
+            streaming: HashMap::new(),
+            illustrations: Vec::new(),
             scroll: 0,
+            show_furigana: true,
             novel_id,
+            info: NovelInfo::default(),
+            synopsis: None,
+            characters: Vec::new(),
+            character_selected: 0,
+            character_field: 0,
+            character_edit: None,
+            settings: NovelSettings::default(),
+            settings_field: 0,
+            settings_edit: None,
+            pending_keywords: Vec::new(),
+            pending_selected: 0,
+            pending_edit: None,
+            glossary: Vec::new(),
+            glossary_search: String::new(),
+            glossary_selected: 0,
+            glossary_input: None,
+            categories: HashMap::new(),
+            usage: HashMap::new(),
+            glossary_by_usage: false,
+            blacklist: Vec::new(),
+            blacklist_selected: 0,
+            blacklist_input: None,
+            conflicts: Vec::new(),
+            conflict_selected: 0,
+            consistency: None,
+            consistency_selected: 0,
+            history: Vec::new(),
+            history_selected: 0,
+            cleanup: None,
             keywords: HashMap::new(),
+            keywords_modified: None,
+            titles: HashMap::new(),
+            show_original_titles: false,
             cached_chapters: HashSet::new(),
+            stamps: HashMap::new(),
+            drift: HashMap::new(),
+            suspects: HashMap::new(),
+            estimates: HashMap::new(),
+            pricing: Pricing::default(),
+            miner: Arc::new(TermMiner::katakana()),
+            processing: HashMap::new(),
+            waiting: HashSet::new(),
+            slots: Arc::new(Semaphore::new(DEFAULT_CONCURRENCY)),
+            queued: VecDeque::new(),
+            paused: false,
+            message: None,
+            banner: None,
+            cancel: CancellationToken::new(),
+            tasks: JoinSet::new(),
+            events_tx,
+            events_rx,
+            control_rx: None,
         }
     }
 
-    /// 根据搜索框内容重新过滤章节列表
-    pub fn apply_filter(&mut self) {
-        if self.search.is_empty() {
-            self.filtered = (0..self.chapters.len()).collect();
+    /// 接收来自控制 socket 的请求
+    pub fn with_control(mut self, rx: UnboundedReceiver<ControlRequest>) -> Self {
+        self.control_rx = Some(rx);
+        self
+    }
+
+    /// 设置同时翻译的章节数上限，至少为 1
+    pub fn with_concurrency(self, limit: usize) -> Self {
+        self.with_slots(Arc::new(Semaphore::new(limit.max(1))))
+    }
+
+    /// 与其他调度者共用翻译名额
+    pub fn with_slots(mut self, slots: Arc<Semaphore>) -> Self {
+        self.slots = slots;
+        self
+    }
+
+    /// 设置预估费用使用的单价
+    pub fn with_pricing(mut self, pricing: Pricing) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// 设置挖掘专有名词候选使用的分词器
+    pub fn with_term_miner(mut self, miner: TermMiner) -> Self {
+        self.miner = Arc::new(miner);
+        self
+    }
+
+    /// 将章节加入处理队列，暂停时仅排队，否则立即启动
+    pub fn enqueue(&mut self, chapter: &Chapter, pipeline: &Pipeline) {
+        if self.paused {
+            if !self.processing.contains_key(&chapter.path) && !self.queued.contains(&chapter.path)
+            {
+                self.queued.push_back(chapter.path.clone());
+            }
         } else {
-            let q = self.search.to_lowercase();
-            self.filtered = self
+            self.spawn_processing(chapter, pipeline);
+        }
+    }
+
+    /// 切换暂停状态，恢复时启动所有排队中的章节
+    pub fn set_paused(&mut self, paused: bool, pipeline: &Pipeline) {
+        self.paused = paused;
+        if !paused {
+            self.banner = None;
+            while let Some(path) = self.queued.pop_front() {
+                if let Some(chapter) = self.chapters.iter().find(|c| c.path == path).cloned() {
+                    self.spawn_processing(&chapter, pipeline);
+                }
+            }
+        }
+    }
+
+    /// 在后台抓取并翻译章节，完成后通过事件通道回传结果
+    pub fn spawn_processing(&mut self, chapter: &Chapter, pipeline: &Pipeline) {
+        if self.processing.contains_key(&chapter.path) {
+            return;
+        }
+        let token = self.cancel.child_token();
+        self.processing.insert(chapter.path.clone(), token.clone());
+        self.waiting.insert(chapter.path.clone());
+        metrics::QUEUE_DEPTH.set(self.processing.len() as i64);
+        let slots = self.slots.clone();
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let path = chapter.path.clone();
+        let previous = self
+            .chapters
+            .iter()
+            .position(|c| c.path == chapter.path)
+            .and_then(|i| i.checked_sub(1))
+            .map(|i| self.chapters[i].path.clone());
+        let (glossary, title) = (self.glossary_pairs(), self.info.title.clone());
+        let tx = self.events_tx.clone();
+        self.tasks.spawn(async move {
+            let ctx = pipeline
+                .context(glossary, &title)
+                .await
+                .with_previous_chapter(previous);
+            // 等待名额期间也可取消
+            let _permit =
+                match cancellable(&token, async { Ok(slots.acquire_owned().await?) }).await {
+                    Ok(permit) => permit,
+                    Err(error) => {
+                        let _ = tx.send(TaskEvent::Failed { path, error });
+                        return;
+                    }
+                };
+            let _ = tx.send(TaskEvent::Started { path: path.clone() });
+            let (partial_tx, mut partial_rx) = mpsc::unbounded_channel();
+            let work = async {
+                let partial_tx = partial_tx;
+                process_chapter(&pipeline, &novel_id, &path, &ctx, Some(&partial_tx), &token).await
+            };
+            // 正文片段随生成转发给界面，翻译结束后通道关闭
+            let forward = async {
+                while let Some(event) = partial_rx.recv().await {
+                    let _ = tx.send(TaskEvent::Partial {
+                        path: path.clone(),
+                        event,
+                    });
+                }
+            };
+            let (result, ()) = tokio::join!(work, forward);
+            let event = match result {
+                Ok(keywords) => TaskEvent::Done { path, keywords },
+                Err(error) => TaskEvent::Failed { path, error },
+            };
+            let _ = tx.send(event);
+        });
+    }
+
+    /// 在后台读取章节原文（未缓存时抓取）并预估翻译用量，不调用翻译服务
+    fn spawn_estimate(&mut self, chapter: &Chapter, pipeline: &Pipeline) {
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let path = chapter.path.clone();
+        let (glossary, title) = (self.glossary_pairs(), self.info.title.clone());
+        let pricing = self.pricing;
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.tasks.spawn(async move {
+            let ctx = pipeline.context(glossary, &title).await;
+            let result = pipeline
+                .source()
+                .fetch(&novel_id, &path, &token)
+                .await
+                .map(|sections| {
+                    estimate_chapter(&sections.join(NOTE_LABELS_JA), &ctx.keywords, &pricing)
+                });
+            let _ = tx.send(TaskEvent::Estimate { path, result });
+        });
+    }
+
+    /// 在后台重新抓取打开的已缓存章节原文，与翻译时的摘要比较
+    fn spawn_source_check(&mut self, path: &str, pipeline: &Pipeline) {
+        if self.processing.contains_key(path) {
+            return;
+        }
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let path = path.to_string();
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.tasks.spawn(async move {
+            let result = source_changed(&pipeline, &novel_id, &path, &token).await;
+            let _ = tx.send(TaskEvent::SourceChecked { path, result });
+        });
+    }
+
+    /// 确认重新抓取原文已改动的章节：删除缓存的原文后重新翻译，旧译文存入历史
+    async fn refetch_changed_source(&mut self, pipeline: &Pipeline) {
+        let Some(path) = self.source_changed.take() else {
+            return;
+        };
+        let Some(chapter) = self.chapters.iter().find(|c| c.path == path).cloned() else {
+            return;
+        };
+        if let Err(e) = pipeline.source_store.remove(&self.novel_id, &path).await {
+            warn!("Failed to drop the cached source of {path}: {e:?}");
+        }
+        self.enqueue(&chapter, pipeline);
+        self.message = Some(format!("Re-translating {}", chapter.title));
+    }
+
+    /// 预估选中章节的用量；选中章（卷）标题时预估其中所有未缓存的章节
+    fn estimate_selected(&mut self, pipeline: &Pipeline) {
+        let targets: Vec<Chapter> = match self.filtered.get(self.selected) {
+            Some(DirRow::Chapter(idx)) => vec![self.chapters[*idx].clone()],
+            Some(DirRow::Arc(name)) => self
                 .chapters
                 .iter()
-                .enumerate()
-                .filter_map(|(i, ch)| {
-                    if ch.title.to_lowercase().contains(&q) || (i + 1).to_string().contains(&q) {
-                        Some(i)
-                    } else {
-                        None
+                .filter(|c| c.arc.as_ref() == Some(name))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        let targets: Vec<Chapter> = targets
+            .into_iter()
+            .filter(|c| {
+                !self.cached_chapters.contains(&c.path) && !self.estimates.contains_key(&c.path)
+            })
+            .collect();
+        for chapter in &targets {
+            self.spawn_estimate(chapter, pipeline);
+        }
+        self.message = Some(format!("Estimating {} chapters", targets.len()));
+    }
+
+    /// 尚未翻译的章节的预估用量合计及章节数
+    pub fn pending_estimate(&self) -> (Estimate, usize) {
+        let mut total = Estimate::default();
+        let mut count = 0;
+        for (path, estimate) in &self.estimates {
+            if !self.cached_chapters.contains(path) {
+                total += *estimate;
+                count += 1;
+            }
+        }
+        (total, count)
+    }
+
+    /// 在后台翻译尚无译名的章节标题
+    fn spawn_title_translation(&mut self, pipeline: &Pipeline) {
+        if self
+            .chapters
+            .iter()
+            .all(|c| self.titles.contains_key(&c.path))
+        {
+            return;
+        }
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let chapters = self.chapters.clone();
+        let (glossary, title) = (self.glossary_pairs(), self.info.title.clone());
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.tasks.spawn(async move {
+            let ctx = pipeline.context(glossary, &title).await;
+            let result =
+                translate_missing_titles(&pipeline, &novel_id, &chapters, &ctx, &token).await;
+            let _ = tx.send(TaskEvent::Titles(result));
+        });
+    }
+
+    /// 在后台翻译作品简介
+    fn spawn_synopsis_translation(&mut self, pipeline: &Pipeline) {
+        if self.info.synopsis.is_empty() {
+            return;
+        }
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let synopsis = self.info.synopsis.clone();
+        let (glossary, title) = (self.glossary_pairs(), self.info.title.clone());
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.tasks.spawn(async move {
+            let ctx = pipeline.context(glossary, &title).await;
+            let result = translate_synopsis(&pipeline, &novel_id, &synopsis, &ctx, &token).await;
+            let _ = tx.send(TaskEvent::Synopsis(result));
+        });
+    }
+
+    /// 在后台检查已缓存章节的译名一致性并进入检查结果界面
+    fn spawn_consistency_check(&mut self, pipeline: &Pipeline) {
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let chapters = self.chapters.clone();
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.consistency = None;
+        self.consistency_selected = 0;
+        self.message = None;
+        self.state = AppState::Consistency;
+        self.tasks.spawn(async move {
+            let result = check_consistency(
+                pipeline.source(),
+                pipeline.kw_store.as_ref(),
+                pipeline.trans_store.as_ref(),
+                pipeline.drift_store.as_ref(),
+                &novel_id,
+                &chapters,
+                &token,
+            )
+            .await;
+            let _ = tx.send(TaskEvent::Consistency(result));
+        });
+    }
+
+    /// 在后台读取已缓存章节的原文，按当前对照表统计各词条的出现次数
+    fn spawn_usage_count(&mut self, pipeline: &Pipeline) {
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let chapters = self.chapters.clone();
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.message = Some("Counting term usage...".to_string());
+        self.tasks.spawn(async move {
+            let result = count_usage(
+                pipeline.source(),
+                pipeline.kw_store.as_ref(),
+                pipeline.trans_store.as_ref(),
+                pipeline.usage_store.as_ref(),
+                &novel_id,
+                &chapters,
+                &token,
+            )
+            .await;
+            let _ = tx.send(TaskEvent::Usage(result));
+        });
+    }
+
+    /// 在后台从已缓存章节的原文中挖掘专有名词候选，完成后放入待审核列表
+    fn spawn_candidate_mining(&mut self, pipeline: &Pipeline) {
+        let pipeline = pipeline.clone();
+        let novel_id = self.novel_id.clone();
+        let miner = self.miner.clone();
+        let chapters: Vec<Chapter> = self
+            .chapters
+            .iter()
+            .filter(|c| self.cached_chapters.contains(&c.path))
+            .cloned()
+            .collect();
+        let token = self.cancel.child_token();
+        let tx = self.events_tx.clone();
+        self.message = Some(format!(
+            "Mining term candidates in {} chapters",
+            chapters.len()
+        ));
+        self.tasks.spawn(async move {
+            let known = known_terms(
+                pipeline.kw_store.as_ref(),
+                pipeline.pending_store.as_ref(),
+                pipeline.blacklist_store.as_ref(),
+                &novel_id,
+            )
+            .await;
+            let result = match known {
+                Ok(known) => {
+                    mine_candidates(
+                        pipeline.source(),
+                        &novel_id,
+                        &miner,
+                        &chapters,
+                        &known,
+                        DEFAULT_MIN_COUNT,
+                        &token,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            let _ = tx.send(TaskEvent::Candidates(result));
+        });
+    }
+
+    /// 当前的专有名词表，随翻译任务传入并在任务中由 `Pipeline::context` 垫上全局词条
+    fn glossary_pairs(&self) -> Vec<(String, String)> {
+        self.keywords
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// 界面中显示的作品名，站点未提供时使用小说 id
+    pub fn novel_title(&self) -> &str {
+        if self.info.title.is_empty() {
+            &self.novel_id
+        } else {
+            &self.info.title
+        }
+    }
+
+    /// 取消指定章节的后台任务，或将其移出等待队列
+    pub fn cancel_processing(&mut self, path: &str) {
+        self.queued.retain(|p| p != path);
+        if let Some(token) = self.processing.get(path) {
+            token.cancel();
+        }
+    }
+
+    /// 根据路径或从 1 开始的序号查找章节
+    fn find_chapter(&self, reference: &str) -> Option<Chapter> {
+        match reference.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| self.chapters.get(i)),
+            Err(_) => self.chapters.iter().find(|c| c.path == reference),
+        }
+        .cloned()
+    }
+
+    /// 执行控制 socket 发来的命令
+    fn handle_control(
+        &mut self,
+        command: ControlCommand,
+        pipeline: &Pipeline,
+    ) -> Result<Value, String> {
+        match command {
+            ControlCommand::Status => Ok(json!({
+                "novel_id": self.novel_id,
+                "title": self.novel_title(),
+                "chapters": self.chapters.len(),
+                "cached": self.cached_chapters.len(),
+                "processing": self
+                    .processing
+                    .keys()
+                    .filter(|p| !self.waiting.contains(*p))
+                    .collect::<Vec<_>>(),
+                "waiting": self.waiting,
+                "queued": self.queued,
+                "paused": self.paused,
+            })),
+            ControlCommand::Queue(reference) => {
+                let chapter = self
+                    .find_chapter(&reference)
+                    .ok_or_else(|| format!("unknown chapter: {reference}"))?;
+                if self.cached_chapters.contains(&chapter.path) {
+                    return Ok(json!({"path": chapter.path, "status": "cached"}));
+                }
+                self.enqueue(&chapter, pipeline);
+                let status = if self.paused { "queued" } else { "processing" };
+                Ok(json!({"path": chapter.path, "status": status}))
+            }
+            ControlCommand::Cancel(reference) => {
+                let chapter = self
+                    .find_chapter(&reference)
+                    .ok_or_else(|| format!("unknown chapter: {reference}"))?;
+                self.cancel_processing(&chapter.path);
+                Ok(json!({"path": chapter.path}))
+            }
+            ControlCommand::Pause => {
+                self.set_paused(true, pipeline);
+                Ok(json!({"paused": true}))
+            }
+            ControlCommand::Resume => {
+                self.set_paused(false, pipeline);
+                Ok(json!({"paused": false}))
+            }
+        }
+    }
+
+    /// 处理后台任务已回传的全部事件，不等待新的事件
+    pub async fn handle_events(&mut self, pipeline: &Pipeline) -> Result<()> {
+        while let Ok(event) = self.events_rx.try_recv() {
+            self.handle_event(event, pipeline).await?;
+        }
+        Ok(())
+    }
+
+    /// 处理后台任务回传的事件
+    async fn handle_event(&mut self, event: TaskEvent, pipeline: &Pipeline) -> Result<()> {
+        match event {
+            TaskEvent::Started { path } => {
+                self.waiting.remove(&path);
+            }
+            TaskEvent::Done { path, keywords } => {
+                self.finish_processing(&path);
+                commit_keywords(
+                    pipeline,
+                    &self.novel_id,
+                    &path,
+                    &mut self.keywords,
+                    keywords,
+                )
+                .await?;
+                if pipeline.review_keywords {
+                    self.load_pending(pipeline).await?;
+                }
+                self.conflicts = load_conflicts(pipeline, &self.novel_id).await?;
+                self.usage = pipeline.usage_store.load(&self.novel_id)?;
+                if let Some(chapter) = self.chapters.iter().find(|c| c.path == path) {
+                    record_stamps(pipeline, &self.novel_id, std::slice::from_ref(chapter))?;
+                    if let Some(stamp) = chapter.stamp() {
+                        self.stamps.insert(path.clone(), stamp.to_string());
+                    }
+                }
+                self.drift = pipeline.drift_store.load(&self.novel_id).await?;
+                self.suspects = pipeline.suspect_store.load(&self.novel_id).await?;
+                let title = self.chapter_title(&path);
+                self.message = Some(match (self.suspects.get(&path), self.drift.get(&path)) {
+                    (Some(reason), _) => format!("Translated, but suspect ({reason}): {title}"),
+                    (None, Some(terms)) => format!(
+                        "Translated with glossary drift ({}): {title}",
+                        terms.join(", ")
+                    ),
+                    (None, None) => format!("Translated: {title}"),
+                });
+                self.streaming.remove(&path);
+                // 正在阅读该章节时换成带前書き/後書き的完整译文
+                if self.state == AppState::Reading && self.reading.as_deref() == Some(&path) {
+                    self.open_cached(&path, pipeline).await?;
+                }
+                self.cached_chapters.insert(path);
+            }
+            TaskEvent::Partial { path, event } => {
+                let text = self.streaming.entry(path.clone()).or_default();
+                match event {
+                    StreamEvent::Delta(delta) => text.push_str(&delta),
+                    StreamEvent::Restart => text.clear(),
+                }
+                if self.state == AppState::Reading && self.reading.as_deref() == Some(&path) {
+                    self.translation = text.clone();
+                }
+            }
+            TaskEvent::Failed { path, error } => {
+                self.finish_processing(&path);
+                self.streaming.remove(&path);
+                let title = self.chapter_title(&path);
+                if is_cancelled(&error) {
+                    self.message = Some(format!("Cancelled: {title}"));
+                } else if is_circuit_open(&error) {
+                    // 服务持续失败：暂停队列，章节放回队列等待恢复
+                    error!("Chapter {path} failed: {error:?}");
+                    if !self.queued.contains(&path) {
+                        self.queued.push_back(path);
+                    }
+                    self.paused = true;
+                    self.banner = Some(format!("{error} — queue paused, press 'p' to resume"));
+                } else {
+                    error!("Chapter {path} failed: {error:?}");
+                    self.message = Some(format!("Failed: {title}: {error}"));
+                }
+            }
+            TaskEvent::Titles(Ok(titles)) => self.titles.extend(titles),
+            TaskEvent::Titles(Err(e)) if is_cancelled(&e) => {}
+            TaskEvent::Titles(Err(e)) => {
+                error!("Title translation failed: {e:?}");
+                self.message = Some(format!("Title translation failed: {e}"));
+            }
+            TaskEvent::Synopsis(Ok(synopsis)) => self.synopsis = Some(synopsis),
+            TaskEvent::Consistency(Ok(report)) => {
+                self.drift = pipeline.drift_store.load(&self.novel_id).await?;
+                self.consistency = Some(report);
+            }
+            TaskEvent::Consistency(Err(e)) if is_cancelled(&e) => {}
+            TaskEvent::Candidates(Ok(candidates)) => {
+                let queued =
+                    queue_candidates(pipeline.pending_store.as_ref(), &self.novel_id, &candidates)
+                        .await?;
+                self.load_pending(pipeline).await?;
+                self.message = Some(format!(
+                    "Queued {queued} term candidates for review ('K'), edit their translations"
+                ));
+            }
+            TaskEvent::Candidates(Err(e)) if is_cancelled(&e) => {}
+            TaskEvent::Usage(Ok(usage)) => {
+                self.usage = usage;
+                self.filter_glossary();
+                self.message = Some("Counted term usage in cached chapters".to_string());
+            }
+            TaskEvent::Usage(Err(e)) if is_cancelled(&e) => {}
+            TaskEvent::Usage(Err(e)) => {
+                error!("Counting term usage failed: {e:?}");
+                self.message = Some(format!("Counting term usage failed: {e}"));
+            }
+            TaskEvent::Candidates(Err(e)) => {
+                error!("Mining term candidates failed: {e:?}");
+                self.message = Some(format!("Mining term candidates failed: {e}"));
+            }
+            TaskEvent::Consistency(Err(e)) => {
+                error!("Consistency check failed: {e:?}");
+                self.message = Some(format!("Consistency check failed: {e}"));
+                self.consistency = Some(Vec::new());
+            }
+            TaskEvent::Synopsis(Err(e)) if is_cancelled(&e) => {}
+            TaskEvent::Synopsis(Err(e)) => {
+                error!("Synopsis translation failed: {e:?}");
+                self.message = Some(format!("Synopsis translation failed: {e}"));
+            }
+            TaskEvent::Estimate {
+                path,
+                result: Ok(estimate),
+            } => {
+                self.estimates.insert(path, estimate);
+            }
+            TaskEvent::SourceChecked {
+                path,
+                result: Ok(true),
+            } => {
+                if self.reading.as_deref() == Some(&path) {
+                    self.source_changed = Some(path);
+                }
+            }
+            TaskEvent::SourceChecked {
+                result: Ok(false), ..
+            } => {}
+            TaskEvent::SourceChecked { result: Err(e), .. } if is_cancelled(&e) => {}
+            // 离线时照常阅读缓存的译文
+            TaskEvent::SourceChecked {
+                path,
+                result: Err(e),
+            } => {
+                warn!("Checking the source of {path} failed: {e:?}");
+            }
+            TaskEvent::Estimate { result: Err(e), .. } if is_cancelled(&e) => {}
+            TaskEvent::Estimate {
+                path,
+                result: Err(e),
+            } => {
+                error!("Estimating {path} failed: {e:?}");
+                self.message = Some(format!("Estimate failed: {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 从存储读取人物设定并进入人物界面
+    fn open_characters(&mut self, pipeline: &Pipeline) {
+        match pipeline.character_store.load(&self.novel_id) {
+            Ok(characters) => {
+                self.characters = characters;
+                self.character_selected = 0;
+                self.character_field = 0;
+                self.character_edit = None;
+                self.state = AppState::Characters;
+            }
+            Err(e) => {
+                error!("Loading characters failed: {e:?}");
+                self.message = Some(format!("Loading characters failed: {e}"));
+            }
+        }
+    }
+
+    /// 修改选中人物的设定并写回存储。
+    /// 先重新读取存储，避免覆盖后台任务在此期间补充的人物
+    fn update_character(
+        &mut self,
+        pipeline: &Pipeline,
+        edit: impl FnOnce(&mut Vec<Character>, usize),
+    ) {
+        let Some(name) = self
+            .characters
+            .get(self.character_selected)
+            .map(|c| c.name.clone())
+        else {
+            return;
+        };
+        let result = pipeline
+            .character_store
+            .load(&self.novel_id)
+            .and_then(|mut characters| {
+                if let Some(idx) = characters.iter().position(|c| c.name == name) {
+                    edit(&mut characters, idx);
+                    pipeline.character_store.save(&self.novel_id, &characters)?;
+                }
+                Ok(characters)
+            });
+        match result {
+            Ok(characters) => {
+                self.characters = characters;
+                self.character_selected = self
+                    .character_selected
+                    .min(self.characters.len().saturating_sub(1));
+            }
+            Err(e) => {
+                error!("Saving characters failed: {e:?}");
+                self.message = Some(format!("Saving characters failed: {e}"));
+            }
+        }
+    }
+
+    /// 从存储读取本小说的设置并进入设置面板
+    fn open_settings(&mut self, pipeline: &Pipeline) {
+        match pipeline.settings_store.load(&self.novel_id) {
+            Ok(settings) => {
+                self.settings = settings;
+                self.settings_edit = None;
+                self.state = AppState::Settings;
+            }
+            Err(e) => {
+                error!("Loading settings failed: {e:?}");
+                self.message = Some(format!("Loading settings failed: {e}"));
+            }
+        }
+    }
+
+    /// 修改选中的设置项并保存，之后开始翻译的章节生效
+    fn update_setting(&mut self, pipeline: &Pipeline, value: &str) {
+        let mut settings = self.settings.clone();
+        let result = settings
+            .set_field(self.settings_field, value)
+            .and_then(|()| pipeline.settings_store.save(&self.novel_id, &settings));
+        match result {
+            Ok(()) => self.settings = settings,
+            Err(e) => self.message = Some(format!("Invalid setting: {e}")),
+        }
+    }
+
+    /// 从存储重新读取待审核的专有名词
+    async fn load_pending(&mut self, pipeline: &Pipeline) -> Result<()> {
+        let mut pending: Vec<(String, String)> = pipeline
+            .pending_store
+            .load(&self.novel_id)
+            .await?
+            .into_iter()
+            .collect();
+        pending.sort();
+        self.pending_selected = self.pending_selected.min(pending.len().saturating_sub(1));
+        self.pending_keywords = pending;
+        Ok(())
+    }
+
+    /// 审核选中的专有名词：给出译名时以该译名加入对照表，否则拒绝；之后移出待审核列表
+    async fn review_pending(&mut self, pipeline: &Pipeline, translation: Option<String>) {
+        let Some((jp, _)) = self.pending_keywords.get(self.pending_selected).cloned() else {
+            return;
+        };
+        if let Err(e) = self
+            .resolve_pending(pipeline, &jp, translation.as_deref())
+            .await
+        {
+            error!("Reviewing keyword {jp} failed: {e:?}");
+            self.message = Some(format!("Reviewing keyword failed: {e}"));
+        }
+    }
+
+    /// 拒绝选中的专有名词并加入屏蔽词，之后不再提取
+    async fn blacklist_pending(&mut self, pipeline: &Pipeline) {
+        let Some((jp, _)) = self.pending_keywords.get(self.pending_selected).cloned() else {
+            return;
+        };
+        let result = match pipeline.blacklist_store.add(&self.novel_id, &jp) {
+            Ok(_) => self.resolve_pending(pipeline, &jp, None).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("Blacklisting keyword {jp} failed: {e:?}");
+            self.message = Some(format!("Blacklisting keyword failed: {e}"));
+        }
+    }
+
+    /// 接受全部待审核的专有名词
+    async fn accept_all_pending(&mut self, pipeline: &Pipeline) {
+        for (jp, zh) in std::mem::take(&mut self.pending_keywords) {
+            if let Err(e) = self.resolve_pending(pipeline, &jp, Some(&zh)).await {
+                error!("Reviewing keyword {jp} failed: {e:?}");
+                self.message = Some(format!("Reviewing keyword failed: {e}"));
+                break;
+            }
+        }
+    }
+
+    /// 按审核结果写入对照表并移出待审核列表
+    async fn resolve_pending(
+        &mut self,
+        pipeline: &Pipeline,
+        jp: &str,
+        translation: Option<&str>,
+    ) -> Result<()> {
+        if let Some(zh) = translation {
+            pipeline.kw_store.set(&self.novel_id, jp, zh).await?;
+            self.keywords.insert(jp.to_string(), zh.to_string());
+        }
+        pipeline.pending_store.remove(&self.novel_id, jp).await?;
+        self.load_pending(pipeline).await
+    }
+
+    /// 从存储重新读取对照表并进入对照表界面
+    async fn open_glossary(&mut self, pipeline: &Pipeline) {
+        let loaded = pipeline
+            .kw_store
+            .load(&self.novel_id)
+            .await
+            .and_then(|keywords| {
+                Ok((
+                    keywords,
+                    load_categories(pipeline, &self.novel_id)?,
+                    pipeline.usage_store.load(&self.novel_id)?,
+                ))
+            });
+        match loaded {
+            Ok((keywords, categories, usage)) => {
+                self.keywords = keywords;
+                self.categories = categories;
+                self.usage = usage;
+                self.glossary_input = None;
+                self.glossary_selected = 0;
+                self.message = None;
+                self.filter_glossary();
+                self.state = AppState::Glossary;
+            }
+            Err(e) => {
+                error!("Loading glossary failed: {e:?}");
+                self.message = Some(format!("Loading glossary failed: {e}"));
+            }
+        }
+    }
+
+    /// 对照表文件被外部编辑后重新读取，以免内存中的旧词条在下次保存时写回文件；
+    /// 文件格式有误时保留当前词条并提示
+    async fn reload_keywords(&mut self, pipeline: &Pipeline) {
+        let modified = pipeline.kw_store.modified();
+        if modified == self.keywords_modified {
+            return;
+        }
+        self.keywords_modified = modified;
+        match pipeline.kw_store.load(&self.novel_id).await {
+            // 本程序自己写入时内存中的词条已是最新
+            Ok(keywords) if keywords == self.keywords => {}
+            Ok(keywords) => {
+                self.keywords = keywords;
+                let selected = self
+                    .glossary
+                    .get(self.glossary_selected)
+                    .map(|(jp, _)| jp.clone());
+                self.filter_glossary();
+                if let Some(idx) =
+                    selected.and_then(|jp| self.glossary.iter().position(|(term, _)| *term == jp))
+                {
+                    self.glossary_selected = idx;
+                }
+                self.message = Some("Glossary reloaded from disk".to_string());
+            }
+            Err(e) => {
+                error!("Reloading glossary failed: {e:?}");
+                self.message = Some(format!("Reloading glossary failed: {e}"));
+            }
+        }
+    }
+
+    /// 按搜索内容模糊匹配原文或译名，重新生成对照表界面的列表；没有搜索内容时按原文排序；
+    /// 开启 `glossary_by_usage` 时出现次数多的在前
+    pub fn filter_glossary(&mut self) {
+        let query = self.glossary_search.trim();
+        let mut rows: Vec<(Option<usize>, (String, String))> = self
+            .keywords
+            .iter()
+            .map(|(jp, zh)| {
+                let score = match (fuzzy_score(query, jp), fuzzy_score(query, zh)) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                (score, (jp.clone(), zh.clone()))
+            })
+            .filter(|(score, _)| score.is_some())
+            .collect();
+        if self.glossary_by_usage {
+            let usage = |jp: &str| self.usage.get(jp).copied().unwrap_or_default();
+            rows.sort_by(|(a_score, (a, _)), (b_score, (b, _))| {
+                usage(b)
+                    .cmp(&usage(a))
+                    .then_with(|| a_score.cmp(b_score))
+                    .then_with(|| a.cmp(b))
+            });
+        } else {
+            rows.sort();
+        }
+        self.glossary = rows.into_iter().map(|(_, row)| row).collect();
+        self.glossary_selected = self
+            .glossary_selected
+            .min(self.glossary.len().saturating_sub(1));
+    }
+
+    /// 新增或修改词条并立即写入对照表
+    async fn set_glossary_term(&mut self, pipeline: &Pipeline, jp: &str, zh: &str) {
+        match pipeline.kw_store.set(&self.novel_id, jp, zh).await {
+            Ok(()) => {
+                self.keywords.insert(jp.to_string(), zh.to_string());
+                self.filter_glossary();
+                if let Some(idx) = self.glossary.iter().position(|(term, _)| term == jp) {
+                    self.glossary_selected = idx;
+                }
+            }
+            Err(e) => {
+                error!("Saving keyword {jp} failed: {e:?}");
+                self.message = Some(format!("Saving keyword failed: {e}"));
+            }
+        }
+    }
+
+    /// 删除选中的词条并立即写入对照表
+    async fn remove_glossary_term(&mut self, pipeline: &Pipeline) {
+        let Some((jp, _)) = self.glossary.get(self.glossary_selected).cloned() else {
+            return;
+        };
+        match pipeline.kw_store.remove(&self.novel_id, &jp).await {
+            Ok(_) => {
+                self.keywords.remove(&jp);
+                self.filter_glossary();
+            }
+            Err(e) => {
+                error!("Removing keyword {jp} failed: {e:?}");
+                self.message = Some(format!("Removing keyword failed: {e}"));
+            }
+        }
+    }
+
+    /// 将选中词条的类别切换为下一个，最后一个之后回到未分类
+    fn cycle_glossary_category(&mut self, pipeline: &Pipeline) {
+        let Some((jp, _)) = self.glossary.get(self.glossary_selected).cloned() else {
+            return;
+        };
+        let next = match self.categories.get(&jp) {
+            None => KeywordCategory::ALL.first().copied(),
+            Some(current) => KeywordCategory::ALL
+                .iter()
+                .position(|c| c == current)
+                .and_then(|idx| KeywordCategory::ALL.get(idx + 1))
+                .copied(),
+        };
+        match pipeline.category_store.set(&self.novel_id, &jp, next) {
+            Ok(()) => match next {
+                Some(category) => {
+                    self.categories.insert(jp, category);
+                }
+                None => {
+                    self.categories.remove(&jp);
+                }
+            },
+            Err(e) => {
+                error!("Saving category of {jp} failed: {e:?}");
+                self.message = Some(format!("Saving category failed: {e}"));
+            }
+        }
+    }
+
+    /// 屏蔽选中的词条，之后提取到的同名词条不再加入对照表
+    async fn blacklist_glossary_term(&mut self, pipeline: &Pipeline) {
+        let Some((jp, _)) = self.glossary.get(self.glossary_selected).cloned() else {
+            return;
+        };
+        let result = blacklist_keyword(
+            pipeline.kw_store.as_ref(),
+            pipeline.blacklist_store.as_ref(),
+            &self.novel_id,
+            &jp,
+        )
+        .await;
+        match result {
+            Ok(_) => {
+                self.keywords.remove(&jp);
+                self.filter_glossary();
+                self.message = Some(format!("Blacklisted {jp}"));
+            }
+            Err(e) => {
+                error!("Blacklisting keyword {jp} failed: {e:?}");
+                self.message = Some(format!("Blacklisting keyword failed: {e}"));
+            }
+        }
+    }
+
+    /// 从存储读取本小说的屏蔽词并进入屏蔽词界面
+    fn open_blacklist(&mut self, pipeline: &Pipeline) {
+        match pipeline.blacklist_store.load(&self.novel_id) {
+            Ok(blacklist) => {
+                self.blacklist = blacklist;
+                self.blacklist_selected = 0;
+                self.blacklist_input = None;
+                self.state = AppState::Blacklist;
+            }
+            Err(e) => {
+                error!("Loading blacklist failed: {e:?}");
+                self.message = Some(format!("Loading blacklist failed: {e}"));
+            }
+        }
+    }
+
+    /// 新增或移除屏蔽词后重新读取列表，新增时选中新加入的屏蔽词
+    async fn update_blacklist(&mut self, pipeline: &Pipeline, jp: &str, add: bool) {
+        let store = pipeline.blacklist_store.as_ref();
+        let result = if add {
+            blacklist_keyword(pipeline.kw_store.as_ref(), store, &self.novel_id, jp).await
+        } else {
+            store.remove(&self.novel_id, jp)
+        };
+        match result.and_then(|_| store.load(&self.novel_id)) {
+            Ok(blacklist) => {
+                if add {
+                    self.keywords.remove(jp);
+                    self.blacklist_selected = blacklist.iter().position(|t| t == jp).unwrap_or(0);
+                }
+                self.blacklist_selected = self
+                    .blacklist_selected
+                    .min(blacklist.len().saturating_sub(1));
+                self.blacklist = blacklist;
+            }
+            Err(e) => {
+                error!("Updating blacklist failed: {e:?}");
+                self.message = Some(format!("Updating blacklist failed: {e}"));
+            }
+        }
+    }
+
+    /// 解决选中的译名冲突，`retranslate` 为真时把译文与结果不一致的已缓存章节加入翻译队列
+    async fn resolve_selected_conflict(
+        &mut self,
+        pipeline: &Pipeline,
+        accept: bool,
+        retranslate: bool,
+    ) {
+        let Some((jp, ..)) = self.conflicts.get(self.conflict_selected).cloned() else {
+            return;
+        };
+        let result = async {
+            let affected = resolve_conflict(pipeline, &self.novel_id, &jp, accept).await?;
+            self.keywords = pipeline.kw_store.load(&self.novel_id).await?;
+            self.conflicts = load_conflicts(pipeline, &self.novel_id).await?;
+            anyhow::Ok(affected)
+        }
+        .await;
+        let affected = match result {
+            Ok(affected) => affected,
+            Err(e) => {
+                error!("Resolving conflict of {jp} failed: {e:?}");
+                self.message = Some(format!("Resolving conflict failed: {e}"));
+                return;
+            }
+        };
+        self.conflict_selected = self
+            .conflict_selected
+            .min(self.conflicts.len().saturating_sub(1));
+        if !retranslate {
+            self.message = Some(format!(
+                "{jp}: {} chapters may still use the other translation",
+                affected.len()
+            ));
+            return;
+        }
+        let chapters: Vec<Chapter> = self
+            .chapters
+            .iter()
+            .filter(|c| affected.contains(&c.path) && self.cached_chapters.contains(&c.path))
+            .cloned()
+            .collect();
+        for chapter in &chapters {
+            self.enqueue(chapter, pipeline);
+        }
+        self.message = Some(format!("{jp}: re-translating {} chapters", chapters.len()));
+    }
+
+    /// 处理译名冲突界面中的按键
+    async fn handle_conflict_key(&mut self, code: KeyCode, pipeline: &Pipeline) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.state = AppState::Directory,
+            KeyCode::Char('j') | KeyCode::Down
+                if self.conflict_selected + 1 < self.conflicts.len() =>
+            {
+                self.conflict_selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.conflict_selected = self.conflict_selected.saturating_sub(1);
+            }
+            KeyCode::Char('c') => self.resolve_selected_conflict(pipeline, false, false).await,
+            KeyCode::Char('C') => self.resolve_selected_conflict(pipeline, false, true).await,
+            KeyCode::Char('n') => self.resolve_selected_conflict(pipeline, true, false).await,
+            KeyCode::Char('N') => self.resolve_selected_conflict(pipeline, true, true).await,
+            _ => {}
+        }
+    }
+
+    /// 处理一致性检查界面中的按键，Enter 打开选中的章节
+    async fn handle_consistency_key(&mut self, code: KeyCode, pipeline: &Pipeline) -> Result<()> {
+        let count = self.consistency.as_ref().map_or(0, Vec::len);
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.state = AppState::Directory,
+            KeyCode::Char('j') | KeyCode::Down if self.consistency_selected + 1 < count => {
+                self.consistency_selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.consistency_selected = self.consistency_selected.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                let path = self
+                    .consistency
+                    .as_ref()
+                    .and_then(|report| report.get(self.consistency_selected))
+                    .map(|item| item.path.clone());
+                if let Some(path) = path
+                    && self.open_cached(&path, pipeline).await?
+                {
+                    self.scroll = 0;
+                    self.reading = Some(path);
+                    self.state = AppState::Reading;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 打开正在阅读章节的历史界面，默认选中最近的旧译文；翻译进行中时不打开
+    pub async fn open_history(&mut self, pipeline: &Pipeline) {
+        let Some(path) = self.reading.clone() else {
+            return;
+        };
+        if self.processing.contains_key(&path) {
+            return;
+        }
+        self.history = match pipeline.history_store.load(&self.novel_id, &path) {
+            Ok(revisions) => revisions,
+            Err(e) => {
+                error!("Loading history of {path} failed: {e:?}");
+                Vec::new()
+            }
+        };
+        self.history_selected = self.history.len().saturating_sub(1);
+        self.message = None;
+        self.state = AppState::History;
+    }
+
+    /// 处理历史界面中的按键，`r` 或 Enter 恢复选中的旧译文并回到阅读界面
+    pub async fn handle_history_key(&mut self, code: KeyCode, pipeline: &Pipeline) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('h') => {
+                self.state = AppState::Reading;
+            }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.history_selected + 1 < self.history.len() =>
+            {
+                self.history_selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.history_selected = self.history_selected.saturating_sub(1);
+            }
+            KeyCode::Char('r') | KeyCode::Enter if !self.history.is_empty() => {
+                let Some(path) = self.reading.clone() else {
+                    return;
+                };
+                let index = self.history_selected;
+                let restored = rollback_translation(
+                    pipeline.trans_store.as_ref(),
+                    pipeline.history_store.as_ref(),
+                    &self.novel_id,
+                    &path,
+                    index,
+                )
+                .await;
+                match restored {
+                    Ok(text) => {
+                        self.translation = text;
+                        self.scroll = 0;
+                        self.state = AppState::Reading;
+                    }
+                    Err(e) => {
+                        error!("Reverting {path} failed: {e:?}");
+                        self.message = Some(format!("Reverting failed: {e}"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 用 `$VISUAL` 或 `$EDITOR`（默认 `vi`）修改正在阅读章节的译文；
+    /// 编辑或保存失败时显示在状态栏，不退出界面
+    async fn edit_translation(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+        pipeline: &Pipeline,
+    ) -> Result<()> {
+        let Some(path) = self.reading.clone() else {
+            return Ok(());
+        };
+        if self.processing.contains_key(&path) {
+            return Ok(());
+        }
+        let editor = ["VISUAL", "EDITOR"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
+
+        // 编辑器需要正常的终端，结束后恢复全屏界面
+        disable_raw_mode()?;
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+        let edited = self.edit_reading(&editor, pipeline).await;
+        enable_raw_mode()?;
+        execute!(
+            terminal.backend_mut(),
+            EnterAlternateScreen,
+            EnableMouseCapture
+        )?;
+        terminal.clear()?;
+
+        if let Err(e) = edited {
+            error!("Editing {path} with {editor} failed: {e:?}");
+            self.message = Some(format!("Editing failed: {e}"));
+        }
+        Ok(())
+    }
+
+    /// 用 `editor` 修改正在阅读章节的译文：编辑器正常退出且内容有变化时保存，
+    /// 原译文存入历史。返回是否保存了新译文
+    pub async fn edit_reading(&mut self, editor: &str, pipeline: &Pipeline) -> Result<bool> {
+        let Some(path) = self.reading.clone() else {
+            return Ok(false);
+        };
+        if self.processing.contains_key(&path) {
+            return Ok(false);
+        }
+        // 随机文件名、仅本用户可读写（0600），退出时自动删除
+        let file = tempfile::Builder::new()
+            .prefix("syosetu-rs-")
+            .suffix(".txt")
+            .tempfile()?;
+        tokio::fs::write(file.path(), &self.translation).await?;
+        let status = tokio::process::Command::new(editor)
+            .arg(file.path())
+            .status()
+            .await?;
+        if !status.success() {
+            bail!("{editor} exited with {status}");
+        }
+        let text = tokio::fs::read_to_string(file.path()).await?;
+        if text == self.translation {
+            return Ok(false);
+        }
+        save_translation(
+            pipeline.trans_store.as_ref(),
+            pipeline.history_store.as_ref(),
+            &self.novel_id,
+            &path,
+            &text,
+        )
+        .await?;
+        self.translation = text;
+        Ok(true)
+    }
+
+    /// 处理屏蔽词界面中的按键，退出时回到对照表
+    async fn handle_blacklist_key(&mut self, code: KeyCode, pipeline: &Pipeline) {
+        let Some(buffer) = self.blacklist_input.as_mut() else {
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => self.open_glossary(pipeline).await,
+                KeyCode::Char('j') | KeyCode::Down
+                    if self.blacklist_selected + 1 < self.blacklist.len() =>
+                {
+                    self.blacklist_selected += 1;
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.blacklist_selected = self.blacklist_selected.saturating_sub(1);
+                }
+                KeyCode::Char('a') => self.blacklist_input = Some(String::new()),
+                KeyCode::Char('d') => {
+                    if let Some(jp) = self.blacklist.get(self.blacklist_selected).cloned() {
+                        self.update_blacklist(pipeline, &jp, false).await;
+                    }
+                }
+                _ => {}
+            }
+            return;
+        };
+        match code {
+            KeyCode::Char(c) => buffer.push(c),
+            KeyCode::Backspace => {
+                buffer.pop();
+            }
+            KeyCode::Esc => self.blacklist_input = None,
+            KeyCode::Enter => {
+                let jp = buffer.trim().to_string();
+                self.blacklist_input = None;
+                if !jp.is_empty() {
+                    self.update_blacklist(pipeline, &jp, true).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 将选中的词条移到全局专有名词表，之后的翻译仍会使用
+    async fn promote_glossary_term(&mut self, pipeline: &Pipeline) {
+        let Some((jp, _)) = self.glossary.get(self.glossary_selected).cloned() else {
+            return;
+        };
+        match promote_keyword(pipeline.kw_store.as_ref(), &self.novel_id, &jp).await {
+            Ok(_) => {
+                self.keywords.remove(&jp);
+                self.filter_glossary();
+                self.message = Some(format!("Promoted {jp} to the global glossary"));
+            }
+            Err(e) => {
+                error!("Promoting keyword {jp} failed: {e:?}");
+                self.message = Some(format!("Promoting keyword failed: {e}"));
+            }
+        }
+    }
+
+    /// 对照表界面中导入导出使用的文件
+    fn glossary_file(&self) -> PathBuf {
+        PathBuf::from(format!("{}.glossary.csv", self.novel_id))
+    }
+
+    /// 将对照表导出为 CSV，便于在电子表格中批量编辑
+    async fn export_glossary(&mut self, pipeline: &Pipeline) {
+        let file = self.glossary_file();
+        self.message = Some(
+            match export::export_glossary(pipeline.kw_store.as_ref(), &self.novel_id, &file).await {
+                Ok(count) => format!("Exported {count} terms to {}", file.display()),
+                Err(e) => format!("Exporting glossary failed: {e}"),
+            },
+        );
+    }
+
+    /// 从导出的 CSV 导入修改过的词条，文件中的译名覆盖已有译名
+    async fn import_glossary(&mut self, pipeline: &Pipeline) {
+        let file = self.glossary_file();
+        let kw_store = pipeline.kw_store.as_ref();
+        let result = async {
+            let counts = export::import_glossary(kw_store, &self.novel_id, &file, false).await?;
+            anyhow::Ok((counts, kw_store.load(&self.novel_id).await?))
+        }
+        .await;
+        self.message = Some(match result {
+            Ok(((added, changed), keywords)) => {
+                self.keywords = keywords;
+                self.filter_glossary();
+                format!(
+                    "Imported {added} new terms and updated {changed} from {}",
+                    file.display()
+                )
+            }
+            Err(e) => format!("Importing glossary failed: {e}"),
+        });
+    }
+
+    /// 将另一部小说的对照表并入当前小说，译名不同的词条记为冲突等待处理
+    async fn merge_glossary(&mut self, pipeline: &Pipeline, from: &str) {
+        let merged = async {
+            let report = merge_glossaries(
+                pipeline.kw_store.as_ref(),
+                pipeline.category_store.as_ref(),
+                pipeline.conflict_store.as_ref(),
+                from,
+                &self.novel_id,
+                MergeStrategy::Review,
+            )
+            .await?;
+            self.keywords = pipeline.kw_store.load(&self.novel_id).await?;
+            self.categories = load_categories(pipeline, &self.novel_id)?;
+            self.conflicts = load_conflicts(pipeline, &self.novel_id).await?;
+            anyhow::Ok(report)
+        }
+        .await;
+        self.message = Some(match merged {
+            Ok(report) => {
+                self.filter_glossary();
+                format!(
+                    "Merged {} terms from {from}, {} conflicts ('X')",
+                    report.added,
+                    report.conflicts.len()
+                )
+            }
+            Err(e) => {
+                error!("Merging glossary of {from} failed: {e:?}");
+                format!("Merging glossary failed: {e}")
+            }
+        });
+    }
+
+    /// 译名改变后预览已缓存章节中需要改写的旧译名，有受影响的章节时请求确认
+    async fn offer_replacement(&mut self, pipeline: &Pipeline, old: &str, new: &str) {
+        let preview = replace_in_translations(
+            pipeline.trans_store.as_ref(),
+            pipeline.history_store.as_ref(),
+            pipeline.kw_store.as_ref(),
+            &self.novel_id,
+            old,
+            new,
+            false,
+        )
+        .await;
+        match preview {
+            Ok(chapters) if chapters.is_empty() => {}
+            Ok(mut chapters) => {
+                // 按目录顺序列出
+                let order: HashMap<&str, usize> = self
+                    .chapters
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (c.path.as_str(), i))
+                    .collect();
+                chapters.sort_by_key(|(path, _)| order.get(path.as_str()).copied());
+                self.glossary_input = Some(GlossaryInput::ConfirmReplace {
+                    old: old.to_string(),
+                    new: new.to_string(),
+                    chapters,
+                });
+            }
+            Err(e) => {
+                error!("Previewing replacement of {old} failed: {e:?}");
+                self.message = Some(format!("Previewing replacement failed: {e}"));
+            }
+        }
+    }
+
+    /// 确认是否改写已缓存章节中的旧译名：`y` 改写，`n` 或 Esc 保持原样
+    async fn handle_replace_key(&mut self, code: KeyCode, pipeline: &Pipeline) {
+        let (KeyCode::Char('y') | KeyCode::Char('n') | KeyCode::Esc) = code else {
+            return;
+        };
+        let Some(GlossaryInput::ConfirmReplace { old, new, .. }) = self.glossary_input.take()
+        else {
+            return;
+        };
+        if code != KeyCode::Char('y') {
+            return;
+        }
+        let replaced = replace_in_translations(
+            pipeline.trans_store.as_ref(),
+            pipeline.history_store.as_ref(),
+            pipeline.kw_store.as_ref(),
+            &self.novel_id,
+            &old,
+            &new,
+            true,
+        )
+        .await;
+        self.message = Some(match replaced {
+            Ok(chapters) => {
+                let total: usize = chapters.iter().map(|(_, count)| count).sum();
+                format!(
+                    "Replaced {old} with {new} {total} times in {} chapters",
+                    chapters.len()
+                )
+            }
+            Err(e) => {
+                error!("Replacing {old} failed: {e:?}");
+                format!("Replacing {old} failed: {e}")
+            }
+        });
+    }
+
+    /// 统计将被删除的记录并等待确认：`prune` 为真时只清理目录中已不存在的章节，
+    /// 否则删除本小说的全部数据
+    async fn preview_cleanup(&mut self, pipeline: &Pipeline, prune: bool) {
+        // 进行中的翻译完成后会重新写入译文
+        if !prune && !self.processing.is_empty() {
+            self.message =
+                Some("Cancel running translations before deleting the novel".to_string());
+            return;
+        }
+        let result = if prune {
+            pipeline
+                .library
+                .prune(&self.novel_id, &self.toc_paths(), false)
+                .await
+                .map(Cleanup::Prune)
+        } else {
+            pipeline
+                .library
+                .remove(&self.novel_id, false)
+                .await
+                .map(Cleanup::Remove)
+        };
+        match result {
+            Ok(Cleanup::Remove(removal) | Cleanup::Prune(removal)) if removal.is_empty() => {
+                self.message = Some("Nothing to delete".to_string());
+            }
+            Ok(cleanup) => self.cleanup = Some(cleanup),
+            Err(e) => {
+                error!("Counting stored data failed: {e:?}");
+                self.message = Some(format!("Counting stored data failed: {e}"));
+            }
+        }
+    }
+
+    /// 确认清理：`y` 删除，`n` 或 Esc 取消；删除后重新读取目录的标记
+    async fn handle_cleanup_key(&mut self, code: KeyCode, pipeline: &Pipeline) {
+        let (KeyCode::Char('y') | KeyCode::Char('n') | KeyCode::Esc) = code else {
+            return;
+        };
+        let Some(cleanup) = self.cleanup.take() else {
+            return;
+        };
+        if code != KeyCode::Char('y') {
+            return;
+        }
+        let result = match cleanup {
+            Cleanup::Remove(_) => pipeline.library.remove(&self.novel_id, true).await,
+            Cleanup::Prune(_) => {
+                let chapters = self.toc_paths();
+                pipeline
+                    .library
+                    .prune(&self.novel_id, &chapters, true)
+                    .await
+            }
+        };
+        let result = match result {
+            Ok(removal) => self.load_stored(pipeline).await.map(|()| removal),
+            Err(e) => Err(e),
+        };
+        self.message = Some(match result {
+            Ok(removal) => format!("Deleted {} records", removal.values().sum::<usize>()),
+            Err(e) => {
+                error!("Deleting stored data failed: {e:?}");
+                format!("Deleting stored data failed: {e}")
+            }
+        });
+    }
+
+    /// 目录中全部章节的路径
+    fn toc_paths(&self) -> HashSet<String> {
+        self.chapters.iter().map(|c| c.path.clone()).collect()
+    }
+
+    /// 从存储读取对照表、已缓存章节及目录中显示的各种标记
+    async fn load_stored(&mut self, pipeline: &Pipeline) -> Result<()> {
+        self.keywords_modified = pipeline.kw_store.modified();
+        self.keywords = pipeline.kw_store.load(&self.novel_id).await?;
+        self.cached_chapters = pipeline
+            .trans_store
+            .list(&self.novel_id)
+            .await?
+            .into_iter()
+            .collect();
+        self.titles = pipeline.title_store.load(&self.novel_id)?;
+        self.load_stamps(pipeline)?;
+        self.drift = pipeline.drift_store.load(&self.novel_id).await?;
+        self.suspects = pipeline.suspect_store.load(&self.novel_id).await?;
+        self.load_pending(pipeline).await?;
+        self.conflicts = load_conflicts(pipeline, &self.novel_id).await?;
+        Ok(())
+    }
+
+    /// 处理对照表界面中的按键
+    async fn handle_glossary_key(&mut self, code: KeyCode, pipeline: &Pipeline) {
+        let Some(input) = self.glossary_input.as_mut() else {
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => self.state = AppState::Directory,
+                KeyCode::Char('j') | KeyCode::Down
+                    if self.glossary_selected + 1 < self.glossary.len() =>
+                {
+                    self.glossary_selected += 1;
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.glossary_selected = self.glossary_selected.saturating_sub(1);
+                }
+                KeyCode::Char('/') => self.glossary_input = Some(GlossaryInput::Search),
+                KeyCode::Char('a') => {
+                    self.glossary_input = Some(GlossaryInput::NewTerm(String::new()));
+                }
+                KeyCode::Enter => {
+                    self.glossary_input = self
+                        .glossary
+                        .get(self.glossary_selected)
+                        .map(|(_, zh)| GlossaryInput::Edit(zh.clone()));
+                }
+                KeyCode::Char('d') => self.remove_glossary_term(pipeline).await,
+                KeyCode::Char('P') => self.promote_glossary_term(pipeline).await,
+                KeyCode::Char('c') => self.cycle_glossary_category(pipeline),
+                KeyCode::Char('b') => self.blacklist_glossary_term(pipeline).await,
+                KeyCode::Char('B') => self.open_blacklist(pipeline),
+                KeyCode::Char('x') => self.export_glossary(pipeline).await,
+                KeyCode::Char('i') => self.import_glossary(pipeline).await,
+                KeyCode::Char('m') => {
+                    self.glossary_input = Some(GlossaryInput::MergeFrom(String::new()));
+                }
+                KeyCode::Char('o') => {
+                    self.glossary_by_usage = !self.glossary_by_usage;
+                    self.glossary_selected = 0;
+                    self.filter_glossary();
+                }
+                KeyCode::Char('u') => self.spawn_usage_count(pipeline),
+                _ => {}
+            }
+            return;
+        };
+        let buffer = match input {
+            GlossaryInput::Search => &mut self.glossary_search,
+            GlossaryInput::Edit(buffer)
+            | GlossaryInput::NewTerm(buffer)
+            | GlossaryInput::MergeFrom(buffer) => buffer,
+            GlossaryInput::NewTranslation { translation, .. } => translation,
+            GlossaryInput::ConfirmReplace { .. } => {
+                return self.handle_replace_key(code, pipeline).await;
+            }
+        };
+        match code {
+            KeyCode::Char(c) => buffer.push(c),
+            KeyCode::Backspace => {
+                buffer.pop();
+            }
+            // 取消搜索时清空搜索内容
+            KeyCode::Esc => {
+                if *input == GlossaryInput::Search {
+                    self.glossary_search.clear();
+                }
+                self.glossary_input = None;
+            }
+            KeyCode::Enter => match self.glossary_input.take() {
+                Some(GlossaryInput::Edit(zh)) if !zh.trim().is_empty() => {
+                    if let Some((jp, old)) = self.glossary.get(self.glossary_selected).cloned() {
+                        self.set_glossary_term(pipeline, &jp, zh.trim()).await;
+                        self.offer_replacement(pipeline, &old, zh.trim()).await;
                     }
-                })
-                .collect();
+                }
+                Some(GlossaryInput::NewTerm(term)) if !term.trim().is_empty() => {
+                    let term = term.trim().to_string();
+                    let translation = self.keywords.get(&term).cloned().unwrap_or_default();
+                    self.glossary_input = Some(GlossaryInput::NewTranslation { term, translation });
+                }
+                Some(GlossaryInput::NewTranslation { term, translation })
+                    if !translation.trim().is_empty() =>
+                {
+                    self.set_glossary_term(pipeline, &term, translation.trim())
+                        .await;
+                }
+                Some(GlossaryInput::MergeFrom(from)) if !from.trim().is_empty() => {
+                    self.merge_glossary(pipeline, from.trim()).await;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        if self.glossary_input == Some(GlossaryInput::Search) {
+            self.filter_glossary();
+        }
+    }
+
+    /// 打开已缓存的章节译文，章节尚未缓存时返回 `false`
+    async fn open_cached(&mut self, path: &str, pipeline: &Pipeline) -> Result<bool> {
+        let Some(trans) = pipeline.trans_store.load(&self.novel_id, path).await? else {
+            return Ok(false);
+        };
+        self.translation = trans;
+        self.illustrations = pipeline
+            .illust_store
+            .load(&self.novel_id)?
+            .remove(path)
+            .unwrap_or_default();
+        Ok(true)
+    }
+
+    /// 章节任务结束后移出处理列表
+    fn finish_processing(&mut self, path: &str) {
+        self.processing.remove(path);
+        self.waiting.remove(path);
+        metrics::QUEUE_DEPTH.set(self.processing.len() as i64);
+    }
+
+    /// 根据章节路径查找标题，找不到时返回路径本身
+    pub fn chapter_title(&self, path: &str) -> String {
+        self.chapters
+            .iter()
+            .find(|c| c.path == path)
+            .map(|c| self.display_title(c).to_string())
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    /// 已缓存的章节在目录中的版本标记与翻译时不同，即缓存后被改稿
+    pub fn is_stale(&self, chapter: &Chapter) -> bool {
+        self.cached_chapters.contains(&chapter.path)
+            && matches!(
+                (chapter.stamp(), self.stamps.get(&chapter.path)),
+                (Some(now), Some(then)) if now != then
+            )
+    }
+
+    /// 读取版本标记；此前缓存但没有标记的章节以目录中的当前标记为准
+    fn load_stamps(&mut self, pipeline: &Pipeline) -> Result<()> {
+        self.stamps = pipeline.stamp_store.load(&self.novel_id)?;
+        let unrecorded: Vec<Chapter> = self
+            .chapters
+            .iter()
+            .filter(|c| {
+                self.cached_chapters.contains(&c.path) && !self.stamps.contains_key(&c.path)
+            })
+            .cloned()
+            .collect();
+        record_stamps(pipeline, &self.novel_id, &unrecorded)?;
+        for chapter in &unrecorded {
+            if let Some(stamp) = chapter.stamp() {
+                self.stamps.insert(chapter.path.clone(), stamp.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 重新翻译所有缓存后被改稿的章节，缓存的旧原文先删除以便重新抓取
+    async fn retranslate_stale(&mut self, pipeline: &Pipeline) {
+        let stale: Vec<Chapter> = self
+            .chapters
+            .iter()
+            .filter(|c| self.is_stale(c))
+            .cloned()
+            .collect();
+        for chapter in &stale {
+            self.drop_stale_source(chapter, pipeline).await;
+            self.enqueue(chapter, pipeline);
+        }
+        self.message = Some(format!("Re-translating {} updated chapters", stale.len()));
+    }
+
+    /// 使用当前的专有名词表重新翻译选中的已缓存章节，旧译文存入历史
+    async fn retranslate_selected(&mut self, pipeline: &Pipeline) {
+        let Some(idx) = self.selected_chapter() else {
+            return;
+        };
+        let chapter = self.chapters[idx].clone();
+        if !self.cached_chapters.contains(&chapter.path) {
+            self.message = Some("Chapter is not translated yet, press Enter".to_string());
+            return;
+        }
+        self.drop_stale_source(&chapter, pipeline).await;
+        self.enqueue(&chapter, pipeline);
+        self.message = Some(format!("Re-translating {}", chapter.title));
+    }
+
+    /// 章节缓存后被改稿时删除缓存的原文，重新翻译时抓取新版本
+    async fn drop_stale_source(&self, chapter: &Chapter, pipeline: &Pipeline) {
+        if !self.is_stale(chapter) {
+            return;
+        }
+        if let Err(e) = pipeline
+            .source_store
+            .remove(&self.novel_id, &chapter.path)
+            .await
+        {
+            warn!(
+                "Failed to drop the cached source of {}: {e:?}",
+                chapter.path
+            );
+        }
+    }
+
+    /// 目录中显示的标题，有译名且未切换到原文时显示译名
+    pub fn display_title<'a>(&'a self, chapter: &'a Chapter) -> &'a str {
+        match self.titles.get(&chapter.path) {
+            Some(title) if !self.show_original_titles => title,
+            _ => &chapter.title,
+        }
+    }
+
+    /// 根据搜索框内容重新过滤章节列表，并在章（卷）变化处插入标题行；
+    /// 搜索时忽略折叠状态
+    pub fn apply_filter(&mut self) {
+        let q = self.search.to_lowercase();
+        let mut rows = Vec::new();
+        let mut arc = None;
+        for (i, ch) in self.chapters.iter().enumerate() {
+            // 原文与译名均可匹配
+            let translated = self.titles.get(&ch.path).map(|t| t.to_lowercase());
+            let matched = q.is_empty()
+                || ch.title.to_lowercase().contains(&q)
+                || translated.is_some_and(|t| t.contains(&q))
+                || (i + 1).to_string().contains(&q);
+            if !matched {
+                continue;
+            }
+            if let Some(name) = &ch.arc {
+                if arc != Some(name) {
+                    arc = Some(name);
+                    rows.push(DirRow::Arc(name.clone()));
+                }
+                if q.is_empty() && self.collapsed.contains(name) {
+                    continue;
+                }
+            }
+            rows.push(DirRow::Chapter(i));
         }
+        self.filtered = rows;
         if self.selected >= self.filtered.len() {
             self.selected = 0;
         }
     }
 
+    /// 当前选中行对应的章节下标，选中标题行时为 `None`
+    fn selected_chapter(&self) -> Option<usize> {
+        match self.filtered.get(self.selected)? {
+            DirRow::Chapter(idx) => Some(*idx),
+            DirRow::Arc(_) => None,
+        }
+    }
+
+    /// 折叠或展开章（卷），光标停留在标题行上
+    fn toggle_arc(&mut self, name: &str) {
+        if !self.collapsed.remove(name) {
+            self.collapsed.insert(name.to_string());
+        }
+        self.apply_filter();
+        let header = DirRow::Arc(name.to_string());
+        self.selected = self
+            .filtered
+            .iter()
+            .position(|row| *row == header)
+            .unwrap_or(0);
+    }
+
     /// 主事件循环，处理渲染与用户输入
-    pub async fn run(
-        mut self,
-        url: &str,
-        site: &dyn NovelSite,
-        translator: &Translator,
-        kw_store: &dyn KeywordStore,
-        trans_store: &dyn TranslationStore,
-    ) -> Result<()> {
+    pub async fn run(mut self, url: &str, pipeline: Pipeline) -> Result<()> {
         // 初始化终端并进入全屏模式
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
         // 读取目录
         terminal.draw(|f| draw_loading(f, "Loading directory..."))?;
-        let chapters = site.fetch_directory(url).await?;
+        let chapters = pipeline.site.fetch_directory(url, &self.cancel).await?;
         self.chapters = chapters;
         self.apply_filter();
+        // 作品信息只用于显示，获取失败时不影响阅读
+        self.info = match pipeline.site.fetch_info(url, &self.cancel).await {
+            Ok(info) => info,
+            Err(e) => {
+                error!("Failed to fetch novel info: {e:?}");
+                NovelInfo::default()
+            }
+        };
         self.state = AppState::Directory;
 
         // 加载翻译对照表以及已缓存章节列表
-        self.keywords = kw_store.load(&self.novel_id)?;
-        self.cached_chapters = trans_store
-            .list(&self.novel_id)?
-            .into_iter()
-            .collect();
+        self.load_stored(&pipeline).await?;
+        self.spawn_title_translation(&pipeline);
+        self.spawn_synopsis_translation(&pipeline);
 
         // `ListState` 用于追踪列表光标位置
         let mut list_state = ListState::default();
@@ -145,11 +2167,35 @@ impl App {
         let tick_rate = Duration::from_millis(200);
         let mut last_tick = Instant::now();
         loop {
+            self.reload_keywords(&pipeline).await;
+            self.handle_events(&pipeline).await?;
+            let mut requests = Vec::new();
+            if let Some(rx) = self.control_rx.as_mut() {
+                while let Ok(req) = rx.try_recv() {
+                    requests.push(req);
+                }
+            }
+            for req in requests {
+                let reply = match req.novel {
+                    Some(novel) if novel != self.novel_id => Err(format!("unknown novel: {novel}")),
+                    _ => self.handle_control(req.command, &pipeline),
+                };
+                let _ = req.reply.send(reply);
+            }
+
             terminal.draw(|f| match self.state {
                 AppState::LoadingDir => draw_loading(f, "Loading directory..."),
                 AppState::Directory => draw_directory(f, &self, &mut list_state),
-                AppState::LoadingChapter => draw_loading(f, "Loading chapter..."),
                 AppState::Reading => draw_reading(f, &self),
+                AppState::Info => draw_info(f, &self),
+                AppState::Characters => draw_characters(f, &self),
+                AppState::PendingKeywords => draw_pending_keywords(f, &self),
+                AppState::Glossary => draw_glossary(f, &self),
+                AppState::Blacklist => draw_blacklist(f, &self),
+                AppState::Consistency => draw_consistency(f, &self),
+                AppState::Conflicts => draw_conflicts(f, &self),
+                AppState::History => draw_history(f, &self),
+                AppState::Settings => draw_settings(f, &self),
             })?;
 
             let timeout = tick_rate
@@ -158,62 +2204,90 @@ impl App {
 
             if event::poll(timeout)? {
                 match event::read()? {
-                    Event::Key(k) => match self.state {
+                    // Windows 终端会同时上报按下与松开事件，只处理按下
+                    Event::Key(k) if k.kind == KeyEventKind::Press => match self.state {
                         AppState::Directory => match self.mode {
+                            InputMode::Navigate if self.cleanup.is_some() => {
+                                self.handle_cleanup_key(k.code, &pipeline).await;
+                            }
                             InputMode::Navigate => match k.code {
-                                KeyCode::Char('j') | KeyCode::Down => {
-                                    if self.selected + 1 < self.filtered.len() {
-                                        self.selected += 1;
-                                        list_state.select(Some(self.selected));
-                                    }
+                                KeyCode::Char('j') | KeyCode::Down
+                                    if self.selected + 1 < self.filtered.len() =>
+                                {
+                                    self.selected += 1;
+                                    list_state.select(Some(self.selected));
                                 }
-                                KeyCode::Char('k') | KeyCode::Up => {
-                                    if self.selected > 0 {
-                                        self.selected -= 1;
+                                KeyCode::Char('k') | KeyCode::Up if self.selected > 0 => {
+                                    self.selected -= 1;
+                                    list_state.select(Some(self.selected));
+                                }
+                                KeyCode::Enter => match self.filtered.get(self.selected).cloned() {
+                                    Some(DirRow::Arc(name)) => {
+                                        self.toggle_arc(&name);
                                         list_state.select(Some(self.selected));
                                     }
-                                }
-                                KeyCode::Enter => {
-                                    if let Some(&idx) = self.filtered.get(self.selected) {
-                                        let chapter = &self.chapters[idx];
-                                        self.scroll = 0;
-                                        if let Some(trans) = trans_store.load(&self.novel_id, &chapter.path)? {
-                                            self.translation = trans;
+                                    Some(DirRow::Chapter(idx)) => {
+                                        let chapter = self.chapters[idx].clone();
+                                        if self.open_cached(&chapter.path, &pipeline).await? {
+                                            self.scroll = 0;
+                                            self.spawn_source_check(&chapter.path, &pipeline);
+                                            self.reading = Some(chapter.path);
                                             self.state = AppState::Reading;
                                         } else {
-                                            self.state = AppState::LoadingChapter;
-                                            terminal.draw(|f| draw_loading(f, "Loading chapter..."))?;
-                                            let content = site.fetch_chapter(&chapter.path).await?;
-                                            self.content = content.clone();
-                                            let existing: Vec<(String, String)> = self
-                                                .keywords
-                                                .iter()
-                                                .map(|(k, v)| (k.clone(), v.clone()))
-                                                .collect();
-                                            let trans = translator.translate_text(&content, &existing).await?;
-                                            self.translation = trans.clone();
-                                            let existing_lines: Vec<String> = existing
-                                                .iter()
-                                                .map(|(jp, zh)| {
-                                                    format!("{{\"japanese\":\"{}\",\"chinese\":\"{}\"}}", jp, zh)
-                                                })
-                                                .collect();
-                                            let new_keywords = translator
-                                                .extract_keywords(&self.translation, &self.content, existing_lines)
-                                                .await?;
-                                            for line in new_keywords {
-                                                if let Ok(val) = serde_json::from_str::<HashMap<String, String>>(&line) {
-                                                    if let (Some(jp), Some(zh)) = (val.get("japanese"), val.get("chinese")) {
-                                                        self.keywords.entry(jp.to_string()).or_insert(zh.to_string());
-                                                    }
-                                                }
+                                            self.enqueue(&chapter, &pipeline);
+                                            // 翻译已开始时进入阅读界面，边生成边显示
+                                            if self.processing.contains_key(&chapter.path) {
+                                                self.scroll = 0;
+                                                self.translation = self
+                                                    .streaming
+                                                    .get(&chapter.path)
+                                                    .cloned()
+                                                    .unwrap_or_default();
+                                                self.illustrations.clear();
+                                                self.reading = Some(chapter.path);
+                                                self.state = AppState::Reading;
                                             }
-                                            kw_store.save(&self.novel_id, &self.keywords)?;
-                                            trans_store.save(&self.novel_id, &chapter.path, &self.translation)?;
-                                            self.cached_chapters.insert(chapter.path.clone());
-                                            self.state = AppState::Reading;
                                         }
                                     }
+                                    None => {}
+                                },
+                                KeyCode::Char('c') => {
+                                    if let Some(idx) = self.selected_chapter() {
+                                        let path = self.chapters[idx].path.clone();
+                                        self.cancel_processing(&path);
+                                    }
+                                }
+                                KeyCode::Char('p') => {
+                                    self.set_paused(!self.paused, &pipeline);
+                                    self.message = Some(
+                                        if self.paused { "Paused" } else { "Resumed" }.to_string(),
+                                    );
+                                }
+                                KeyCode::Char('t') => {
+                                    self.show_original_titles = !self.show_original_titles;
+                                }
+                                KeyCode::Char('u') => self.retranslate_stale(&pipeline).await,
+                                KeyCode::Char('r') => self.retranslate_selected(&pipeline).await,
+                                KeyCode::Char('e') => self.estimate_selected(&pipeline),
+                                KeyCode::Char('i') => {
+                                    self.scroll = 0;
+                                    self.state = AppState::Info;
+                                }
+                                KeyCode::Char('C') => self.open_characters(&pipeline),
+                                KeyCode::Char('s') => self.open_settings(&pipeline),
+                                KeyCode::Char('g') => self.open_glossary(&pipeline).await,
+                                KeyCode::Char('v') => self.spawn_consistency_check(&pipeline),
+                                KeyCode::Char('M') => self.spawn_candidate_mining(&pipeline),
+                                KeyCode::Char('D') => self.preview_cleanup(&pipeline, false).await,
+                                KeyCode::Char('O') => self.preview_cleanup(&pipeline, true).await,
+                                KeyCode::Char('X') => {
+                                    self.conflict_selected = 0;
+                                    self.message = None;
+                                    self.state = AppState::Conflicts;
+                                }
+                                KeyCode::Char('K') => {
+                                    self.pending_edit = None;
+                                    self.state = AppState::PendingKeywords;
                                 }
                                 KeyCode::Char('/') => {
                                     self.mode = InputMode::Search;
@@ -240,55 +2314,219 @@ impl App {
                                 _ => {}
                             },
                         },
+                        AppState::Info => match k.code {
+                            KeyCode::Char('q') | KeyCode::Char('i') | KeyCode::Esc => {
+                                self.state = AppState::Directory;
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                self.scroll = self.scroll.saturating_add(1);
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                self.scroll = self.scroll.saturating_sub(1);
+                            }
+                            _ => {}
+                        },
+                        AppState::Characters => match self.character_edit.as_mut() {
+                            Some(buffer) => match k.code {
+                                KeyCode::Esc => self.character_edit = None,
+                                KeyCode::Enter => {
+                                    let value = buffer.trim().to_string();
+                                    let field = self.character_field;
+                                    self.character_edit = None;
+                                    // 名字不能为空，否则之后无法按名字合并
+                                    if field > 0 || !value.is_empty() {
+                                        self.update_character(&pipeline, |characters, idx| {
+                                            *characters[idx].field_mut(field) = value;
+                                        });
+                                    }
+                                }
+                                KeyCode::Backspace => {
+                                    buffer.pop();
+                                }
+                                KeyCode::Char(c) => buffer.push(c),
+                                _ => {}
+                            },
+                            None => match k.code {
+                                KeyCode::Char('q') | KeyCode::Esc => {
+                                    self.state = AppState::Directory;
+                                }
+                                KeyCode::Char('j') | KeyCode::Down
+                                    if self.character_selected + 1 < self.characters.len() =>
+                                {
+                                    self.character_selected += 1;
+                                }
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    self.character_selected =
+                                        self.character_selected.saturating_sub(1);
+                                }
+                                KeyCode::Tab => {
+                                    self.character_field =
+                                        (self.character_field + 1) % CHARACTER_FIELDS.len();
+                                }
+                                KeyCode::Enter => {
+                                    let field = self.character_field;
+                                    self.character_edit = self
+                                        .characters
+                                        .get(self.character_selected)
+                                        .map(|c| c.fields()[field].to_string());
+                                }
+                                KeyCode::Char('d') => {
+                                    self.update_character(&pipeline, |characters, idx| {
+                                        characters.remove(idx);
+                                    });
+                                }
+                                _ => {}
+                            },
+                        },
+                        AppState::Settings => match self.settings_edit.as_mut() {
+                            Some(buffer) => match k.code {
+                                KeyCode::Esc => self.settings_edit = None,
+                                KeyCode::Enter => {
+                                    let value = std::mem::take(buffer);
+                                    self.settings_edit = None;
+                                    self.update_setting(&pipeline, &value);
+                                }
+                                KeyCode::Backspace => {
+                                    buffer.pop();
+                                }
+                                KeyCode::Char(c) => buffer.push(c),
+                                _ => {}
+                            },
+                            None => match k.code {
+                                KeyCode::Char('q') | KeyCode::Esc => {
+                                    self.state = AppState::Directory;
+                                }
+                                KeyCode::Char('j') | KeyCode::Down
+                                    if self.settings_field + 1 < SETTINGS_FIELDS.len() =>
+                                {
+                                    self.settings_field += 1;
+                                }
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    self.settings_field = self.settings_field.saturating_sub(1);
+                                }
+                                KeyCode::Enter => {
+                                    self.settings_edit =
+                                        Some(self.settings.field(self.settings_field));
+                                }
+                                KeyCode::Char('d') => self.update_setting(&pipeline, ""),
+                                _ => {}
+                            },
+                        },
+                        AppState::Glossary => self.handle_glossary_key(k.code, &pipeline).await,
+                        AppState::Blacklist => self.handle_blacklist_key(k.code, &pipeline).await,
+                        AppState::Consistency => {
+                            self.handle_consistency_key(k.code, &pipeline).await?
+                        }
+                        AppState::Conflicts => self.handle_conflict_key(k.code, &pipeline).await,
+                        AppState::History => self.handle_history_key(k.code, &pipeline).await,
+                        AppState::PendingKeywords => match self.pending_edit.as_mut() {
+                            Some(buffer) => match k.code {
+                                KeyCode::Esc => self.pending_edit = None,
+                                KeyCode::Enter => {
+                                    let value = buffer.trim().to_string();
+                                    self.pending_edit = None;
+                                    if !value.is_empty() {
+                                        self.review_pending(&pipeline, Some(value)).await;
+                                    }
+                                }
+                                KeyCode::Backspace => {
+                                    buffer.pop();
+                                }
+                                KeyCode::Char(c) => buffer.push(c),
+                                _ => {}
+                            },
+                            None => match k.code {
+                                KeyCode::Char('q') | KeyCode::Esc => {
+                                    self.state = AppState::Directory;
+                                }
+                                KeyCode::Char('j') | KeyCode::Down
+                                    if self.pending_selected + 1 < self.pending_keywords.len() =>
+                                {
+                                    self.pending_selected += 1;
+                                }
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    self.pending_selected = self.pending_selected.saturating_sub(1);
+                                }
+                                KeyCode::Char('a') => {
+                                    let zh = self
+                                        .pending_keywords
+                                        .get(self.pending_selected)
+                                        .map(|(_, zh)| zh.clone());
+                                    self.review_pending(&pipeline, zh).await;
+                                }
+                                KeyCode::Char('A') => self.accept_all_pending(&pipeline).await,
+                                KeyCode::Char('d') => self.review_pending(&pipeline, None).await,
+                                KeyCode::Char('b') => self.blacklist_pending(&pipeline).await,
+                                KeyCode::Enter => {
+                                    self.pending_edit = self
+                                        .pending_keywords
+                                        .get(self.pending_selected)
+                                        .map(|(_, zh)| zh.clone());
+                                }
+                                _ => {}
+                            },
+                        },
                         AppState::Reading => match k.code {
                             KeyCode::Char('q') | KeyCode::Esc => {
+                                self.reading = None;
+                                self.source_changed = None;
                                 self.state = AppState::Directory;
                             }
+                            KeyCode::Char('y') if self.source_changed.is_some() => {
+                                self.refetch_changed_source(&pipeline).await;
+                            }
+                            KeyCode::Char('n') => {
+                                self.source_changed = None;
+                            }
                             KeyCode::Char('j') | KeyCode::Down => {
                                 self.scroll = self.scroll.saturating_add(1);
                             }
                             KeyCode::Char('k') | KeyCode::Up => {
                                 self.scroll = self.scroll.saturating_sub(1);
                             }
+                            KeyCode::Char('f') => {
+                                self.show_furigana = !self.show_furigana;
+                            }
+                            KeyCode::Char('h') => self.open_history(&pipeline).await,
+                            KeyCode::Char('E') => {
+                                self.edit_translation(&mut terminal, &pipeline).await?
+                            }
                             KeyCode::PageDown => {
                                 let h = terminal.size()?.height;
-                                self.scroll = self
-                                    .scroll
-                                    .saturating_add(h.saturating_sub(1));
+                                self.scroll = self.scroll.saturating_add(h.saturating_sub(1));
                             }
                             KeyCode::PageUp => {
                                 let h = terminal.size()?.height;
-                                self.scroll = self
-                                    .scroll
-                                    .saturating_sub(h.saturating_sub(1));
+                                self.scroll = self.scroll.saturating_sub(h.saturating_sub(1));
                             }
                             _ => {}
                         },
                         _ => {}
                     },
-                    Event::Mouse(m) => {
-                        match self.state {
-                            AppState::Directory => {
-                                if let MouseEventKind::Down(_) = m.kind {
-                                    let row = m.row as usize;
-                                    if row < self.filtered.len() {
-                                        self.selected = row;
-                                        list_state.select(Some(self.selected));
-                                    }
+                    Event::Mouse(m) => match self.state {
+                        AppState::Directory => {
+                            // 列表带边框，第一项位于第 1 行，且需加上滚动偏移
+                            if let MouseEventKind::Down(_) = m.kind
+                                && let Some(row) = (m.row as usize).checked_sub(1)
+                            {
+                                let idx = list_state.offset() + row;
+                                if idx < self.filtered.len() {
+                                    self.selected = idx;
+                                    list_state.select(Some(self.selected));
                                 }
                             }
-                            AppState::Reading => match m.kind {
-                                MouseEventKind::ScrollDown => {
-                                    self.scroll = self.scroll.saturating_add(1);
-                                }
-                                MouseEventKind::ScrollUp => {
-                                    self.scroll = self.scroll.saturating_sub(1);
-                                }
-                                _ => {}
-                            },
-                            _ => {}
                         }
-                    }
+                        AppState::Reading => match m.kind {
+                            MouseEventKind::ScrollDown => {
+                                self.scroll = self.scroll.saturating_add(1);
+                            }
+                            MouseEventKind::ScrollUp => {
+                                self.scroll = self.scroll.saturating_sub(1);
+                            }
+                            _ => {}
+                        },
+                        _ => {}
+                    },
                     Event::Resize(_, _) => {}
                     _ => {}
                 }
@@ -300,8 +2538,30 @@ impl App {
         }
 
         disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
         terminal.show_cursor()?;
+
+        // 退出前取消所有后台任务，避免请求在程序结束后继续计费
+        self.cancel.cancel();
+        while self.tasks.join_next().await.is_some() {}
         Ok(())
     }
 }
+
+/// 模糊匹配：`query` 的字符按顺序出现在 `text` 中时返回得分（越小越接近，
+/// 开头处连续出现为 0），否则返回 `None`；忽略大小写
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    for c in query.to_lowercase().chars() {
+        let found = pos + text[pos..].iter().position(|&t| t == c)?;
+        score += found - pos;
+        pos = found + 1;
+    }
+    Some(score)
+}
@@ -0,0 +1,251 @@
+//! `backup` 与 `restore` 子命令使用的备份文件
+//!
+//! 备份是一个 zip 文件：指定小说时每部小说一个 `novels/<novel_id>.json`，内容为该小说
+//! 的专有名词表、译文、剧情摘要与设置，恢复时并入当前的存储；备份全部数据时则把数据
+//! 目录与配置目录中的文件原样放在 `data/` 与 `config/` 下，恢复时写回对应目录。
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::memory::{
+    HistoryStore, KeywordStore, NovelSettings, SettingsStore, SummaryStore, TranslationStore,
+};
+use crate::pipeline::save_translation;
+
+/// 备份文件中小说条目所在的目录
+const NOVELS_DIR: &str = "novels/";
+
+/// 备份文件中数据目录文件所在的目录
+const DATA_DIR: &str = "data";
+
+/// 备份文件中配置目录文件所在的目录
+const CONFIG_DIR: &str = "config";
+
+/// 备份全部数据时不打包的文件：跨进程锁文件、含登录会话不宜随备份传播的 Cookie，
+/// 以及 `--data-dir` 时同在一个目录下的 HTTP 缓存与日志
+fn skipped(name: &str) -> bool {
+    name.ends_with(".lock")
+        || name.ends_with(".log")
+        || name == "cookies.json"
+        || name == "http_cache"
+}
+
+/// 备份与恢复单部小说用到的存储
+#[derive(Clone, Copy)]
+pub struct BackupStores<'a> {
+    pub keywords: &'a dyn KeywordStore,
+    pub translations: &'a dyn TranslationStore,
+    /// 恢复时被覆盖的不同译文存入历史
+    pub history: &'a dyn HistoryStore,
+    pub summaries: &'a dyn SummaryStore,
+    pub settings: &'a dyn SettingsStore,
+}
+
+/// 一部小说的备份内容
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NovelBackup {
+    pub novel_id: String,
+    /// 专有名词表，原文 → 译名
+    #[serde(default)]
+    pub keywords: BTreeMap<String, String>,
+    /// 章节译文，章节路径 → 译文
+    #[serde(default)]
+    pub translations: BTreeMap<String, String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub settings: NovelSettings,
+}
+
+impl NovelBackup {
+    /// 从存储中读取一部小说的内容
+    pub async fn collect(stores: BackupStores<'_>, novel_id: &str) -> Result<Self> {
+        let keywords = stores.keywords.load(novel_id).await?.into_iter().collect();
+        let mut translations = BTreeMap::new();
+        for chapter in stores.translations.list(novel_id).await? {
+            if let Some(text) = stores.translations.load(novel_id, &chapter).await? {
+                translations.insert(chapter, text);
+            }
+        }
+        Ok(NovelBackup {
+            novel_id: novel_id.to_string(),
+            keywords,
+            translations,
+            summary: stores.summaries.load(novel_id)?,
+            settings: stores.settings.load(novel_id)?,
+        })
+    }
+
+    /// 是否没有任何内容，通常是小说编号有误
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+            && self.translations.is_empty()
+            && self.summary.is_none()
+            && self.settings == NovelSettings::default()
+    }
+
+    /// 写回存储：备份中的词条与译文覆盖同名的已有内容，其余已有内容保持不变；
+    /// 返回恢复的章节数
+    pub async fn restore(&self, stores: BackupStores<'_>) -> Result<usize> {
+        let id = &self.novel_id;
+        for (jp, zh) in &self.keywords {
+            stores.keywords.set(id, jp, zh).await?;
+        }
+        for (chapter, text) in &self.translations {
+            save_translation(stores.translations, stores.history, id, chapter, text).await?;
+        }
+        if let Some(summary) = &self.summary {
+            stores.summaries.save(id, summary)?;
+        }
+        if self.settings != NovelSettings::default() {
+            stores.settings.save(id, &self.settings)?;
+        }
+        Ok(self.translations.len())
+    }
+}
+
+/// 备份文件的内容
+#[derive(Debug, Default)]
+pub struct Backup {
+    pub novels: Vec<NovelBackup>,
+    /// 数据目录与配置目录中的文件，以 `data/`、`config/` 开头的相对路径 → 内容
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl Backup {
+    /// 读取数据目录与配置目录中的全部文件（含子目录），跳过锁文件与 Cookie；
+    /// 不存在的目录视为空，两者相同时只读取一次
+    pub fn from_dirs(data: &Path, config: &Path) -> Result<Self> {
+        let mut backup = Backup::default();
+        for (root, name) in [(data, DATA_DIR), (config, CONFIG_DIR)] {
+            if root.is_dir() && !(name == CONFIG_DIR && config == data) {
+                collect_files(root, Path::new(name), 1, &mut backup.files)?;
+            }
+        }
+        Ok(backup)
+    }
+
+    /// 以 deflate 压缩写为 zip 文件
+    pub fn write(&self, out: &Path) -> Result<()> {
+        let mut zip = ZipWriter::new(
+            File::create(out).with_context(|| format!("failed to create {}", out.display()))?,
+        );
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(true);
+        for novel in &self.novels {
+            zip.start_file(format!("{NOVELS_DIR}{}.json", novel.novel_id), options)?;
+            zip.write_all(serde_json::to_string_pretty(novel)?.as_bytes())?;
+        }
+        for (path, content) in &self.files {
+            // zip 中统一使用 `/` 分隔
+            let name: Vec<_> = path.iter().map(|part| part.to_string_lossy()).collect();
+            zip.start_file(name.join("/"), options)?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// 读取 [`Self::write`] 写出的 zip 文件
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut zip = ZipArchive::new(file)
+            .with_context(|| format!("{} is not a backup archive", path.display()))?;
+        let mut backup = Backup::default();
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry.name().to_string();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            if [DATA_DIR, CONFIG_DIR]
+                .iter()
+                .any(|dir| name.starts_with(&format!("{dir}/")))
+            {
+                let relative = PathBuf::from(&name);
+                // 拒绝指向数据目录与配置目录以外的路径
+                if !relative
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+                {
+                    bail!("unsafe path {name} in {}", path.display());
+                }
+                backup.files.insert(relative, content);
+            } else if name.starts_with(NOVELS_DIR) {
+                let novel = serde_json::from_slice(&content)
+                    .with_context(|| format!("failed to parse {name}"))?;
+                backup.novels.push(novel);
+            }
+        }
+        if backup.novels.is_empty() && backup.files.is_empty() {
+            bail!("{} contains no backup data", path.display());
+        }
+        Ok(backup)
+    }
+
+    /// 把文件写回数据目录 `data` 与配置目录 `config`；已存在的文件在 `overwrite` 为假时
+    /// 保持不变。返回写入与跳过的文件
+    pub fn restore_files(
+        &self,
+        data: &Path,
+        config: &Path,
+        overwrite: bool,
+    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+        let (mut written, mut kept) = (Vec::new(), Vec::new());
+        for (relative, content) in &self.files {
+            let mut parts = relative.iter();
+            let root = match parts.next().and_then(|part| part.to_str()) {
+                Some(DATA_DIR) => data,
+                Some(CONFIG_DIR) => config,
+                _ => bail!("unexpected backup entry {}", relative.display()),
+            };
+            let target = root.join(parts.as_path());
+            if target.exists() && !overwrite {
+                kept.push(target);
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, content)
+                .with_context(|| format!("failed to write {}", target.display()))?;
+            written.push(target);
+        }
+        Ok((written, kept))
+    }
+}
+
+/// 递归读取 `root` 下的文件，`relative` 的前 `skip` 段是备份中的目录名而非实际路径
+fn collect_files(
+    root: &Path,
+    relative: &Path,
+    skip: usize,
+    files: &mut BTreeMap<PathBuf, Vec<u8>>,
+) -> Result<()> {
+    let dir = root.join(relative.iter().skip(skip).collect::<PathBuf>());
+    for entry in fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_str().is_some_and(skipped) {
+            continue;
+        }
+        let path = relative.join(&name);
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, skip, files)?;
+        } else {
+            files.insert(path, fs::read(entry.path())?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use reqwest::multipart::{Form, Part};
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use crate::export::{self, ExportOptions};
+use crate::http::HttpClient;
+use crate::memory::{TitleStore, TranslationStore};
+use crate::pipeline::with_translated_titles;
+use crate::syosetu::{Chapter, NovelSite, cancellable, ruby_to_parens};
+
+const TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+
+/// Telegram 单条消息的最大字符数
+pub const MESSAGE_LIMIT: usize = 4096;
+
+/// 聊天中的命令，小说与章节序号从 1 开始
+#[derive(Clone, Debug, PartialEq)]
+pub enum BotCommand {
+    Help,
+    Novels,
+    Chapters { novel: usize },
+    Read { novel: usize, chapter: usize },
+    Epub { novel: usize, range: Option<String> },
+    Unknown,
+}
+
+impl BotCommand {
+    /// 解析一条消息；群聊中的命令形如 `/novels@my_bot`
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts = text.split_whitespace();
+        let command = parts.next().unwrap_or("").split('@').next().unwrap_or("");
+        let mut number = |what: &str, hint: &str| -> Result<usize> {
+            let arg = parts
+                .next()
+                .ok_or_else(|| anyhow!("missing {what} number{hint}"))?;
+            arg.parse()
+                .map_err(|_| anyhow!("invalid {what} number {arg}{hint}"))
+        };
+        Ok(match command {
+            "/start" | "/help" => BotCommand::Help,
+            "/novels" => BotCommand::Novels,
+            "/chapters" => BotCommand::Chapters {
+                novel: number("novel", ", see /novels")?,
+            },
+            "/read" => BotCommand::Read {
+                novel: number("novel", ", see /novels")?,
+                chapter: number("chapter", "")?,
+            },
+            "/epub" => BotCommand::Epub {
+                novel: number("novel", ", see /novels")?,
+                range: parts.next().map(str::to_string),
+            },
+            _ => BotCommand::Unknown,
+        })
+    }
+}
+
+/// 把文本按行拆分为不超过 `limit` 个字符的消息；单行超长时在字符边界处切开
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut chunk = String::new();
+    let mut chunk_chars = 0;
+    for line in text.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        let pieces: Vec<&[char]> = if chars.is_empty() {
+            vec![&[]]
+        } else {
+            chars.chunks(limit.max(1)).collect()
+        };
+        for piece in pieces {
+            // 接在已有内容之后时前面还有一个换行符
+            if !chunk.is_empty() && chunk_chars + 1 + piece.len() > limit {
+                messages.push(std::mem::take(&mut chunk));
+                chunk_chars = 0;
+            }
+            if !chunk.is_empty() {
+                chunk.push('\n');
+                chunk_chars += 1;
+            }
+            chunk.extend(piece);
+            chunk_chars += piece.len();
+        }
+    }
+    if !chunk.trim().is_empty() {
+        messages.push(chunk);
+    }
+    messages
+}
+
+/// 机器人模式的配置
+#[derive(Clone, Debug)]
+pub struct BotConfig {
+    /// BotFather 分配的 token
+    pub token: String,
+    /// 只响应此会话，新译文也只推送到此会话；其他会话的消息一律忽略
+    pub allowed_chat: i64,
+    /// 检查新译文的间隔
+    pub poll_interval: Duration,
+    /// 访问 Telegram API 使用的客户端，通常与站点共用
+    pub client: HttpClient,
+}
+
+/// 机器人关注的小说
+pub struct FollowedNovel {
+    /// 目录页地址
+    pub url: String,
+    /// 小说 id
+    pub novel_id: String,
+    /// 对应的站点实现
+    pub site: Arc<dyn NovelSite>,
+}
+
+/// 基于 Telegram Bot API 的远程阅读服务
+pub struct Bot {
+    config: BotConfig,
+    client: HttpClient,
+    novels: Vec<FollowedNovel>,
+    trans_store: Arc<dyn TranslationStore>,
+    title_store: Arc<dyn TitleStore>,
+    /// 各小说的目录缓存
+    directories: HashMap<String, Vec<Chapter>>,
+    /// 各小说已通知过的缓存章节
+    known: HashMap<String, HashSet<String>>,
+}
+
+impl Bot {
+    pub fn new(
+        config: BotConfig,
+        novels: Vec<FollowedNovel>,
+        trans_store: Arc<dyn TranslationStore>,
+        title_store: Arc<dyn TitleStore>,
+    ) -> Result<Self> {
+        Ok(Bot {
+            client: config.client.clone(),
+            config,
+            novels,
+            trans_store,
+            title_store,
+            directories: HashMap::new(),
+            known: HashMap::new(),
+        })
+    }
+
+    fn api_url(&self, method: &str) -> String {
+        format!("{TELEGRAM_API_BASE}/bot{}/{method}", self.config.token)
+    }
+
+    /// 调用 Bot API 并取出 `result` 字段
+    async fn call(&self, method: &str, body: &Value) -> Result<Value> {
+        let req = self.client.post(self.api_url(method)).json(body);
+        self.send(method, req).await
+    }
+
+    /// 发送请求并取出 `result` 字段；请求地址中含有 token，出错时从错误中去掉，
+    /// 以免写入日志或回复到聊天中
+    async fn send(&self, method: &str, req: reqwest_middleware::RequestBuilder) -> Result<Value> {
+        let resp: Value = req
+            .send()
+            .await
+            .map_err(|e| self.redact(e))?
+            .json()
+            .await
+            .map_err(|e| self.redact(e.into()))?;
+        if resp["ok"].as_bool() != Some(true) {
+            return Err(anyhow!(
+                "telegram {method} failed: {}",
+                resp["description"].as_str().unwrap_or("unknown error")
+            ));
+        }
+        Ok(resp["result"].clone())
+    }
+
+    /// 去掉错误中的请求地址与 token
+    fn redact(&self, e: reqwest_middleware::Error) -> anyhow::Error {
+        match e {
+            reqwest_middleware::Error::Reqwest(e) => e.without_url().into(),
+            // 中间件（重试、熔断等）的错误信息中可能带有完整地址
+            e => anyhow!(
+                "{}",
+                format!("{e:#}").replace(&self.config.token, "<token>")
+            ),
+        }
+    }
+
+    /// 发送文本，超长时由 [`split_message`] 拆分为多条消息
+    async fn send_text(&self, chat: i64, text: &str) -> Result<()> {
+        for chunk in split_message(text, MESSAGE_LIMIT) {
+            self.call("sendMessage", &json!({"chat_id": chat, "text": chunk}))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 以文件形式发送
+    async fn send_document(&self, chat: i64, file_name: &str, bytes: Vec<u8>) -> Result<()> {
+        let form = Form::new().text("chat_id", chat.to_string()).part(
+            "document",
+            Part::bytes(bytes).file_name(file_name.to_string()),
+        );
+        let req = self
+            .client
+            .post(self.api_url("sendDocument"))
+            .multipart(form);
+        self.send("sendDocument", req).await?;
+        Ok(())
+    }
+
+    /// 取得小说目录，首次访问时抓取，已翻译的标题替换为译名
+    async fn directory(&mut self, idx: usize, cancel: &CancellationToken) -> Result<&[Chapter]> {
+        let novel = &self.novels[idx];
+        if !self.directories.contains_key(&novel.novel_id) {
+            let chapters = novel.site.fetch_directory(&novel.url, cancel).await?;
+            let titles = self.title_store.load(&novel.novel_id)?;
+            let chapters = with_translated_titles(&chapters, &titles);
+            self.directories.insert(novel.novel_id.clone(), chapters);
+        }
+        Ok(&self.directories[&novel.novel_id])
+    }
+
+    /// 将 `/cmd 2` 中的小说序号（从 1 开始）转换为下标
+    fn novel_index(&self, n: usize) -> Result<usize> {
+        n.checked_sub(1)
+            .filter(|i| *i < self.novels.len())
+            .ok_or_else(|| anyhow!("unknown novel {n}, see /novels"))
+    }
+
+    /// 处理一条聊天命令
+    async fn handle_command(
+        &mut self,
+        chat: i64,
+        text: &str,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        match BotCommand::parse(text)? {
+            BotCommand::Help => {
+                self.send_text(
+                    chat,
+                    "/novels - followed novels\n/chapters <novel> - cached chapters\n/read <novel> <chapter> - chapter text\n/epub <novel> [range] - EPUB file",
+                )
+                .await
+            }
+            BotCommand::Novels => {
+                let mut lines = Vec::new();
+                for (i, novel) in self.novels.iter().enumerate() {
+                    let cached = self.trans_store.list(&novel.novel_id).await?.len();
+                    lines.push(format!("{}. {} ({cached} cached)", i + 1, novel.novel_id));
+                }
+                self.send_text(chat, &lines.join("\n")).await
+            }
+            BotCommand::Chapters { novel } => {
+                let idx = self.novel_index(novel)?;
+                let novel_id = self.novels[idx].novel_id.clone();
+                let cached: HashSet<String> = self
+                    .trans_store
+                    .list(&novel_id)
+                    .await?
+                    .into_iter()
+                    .collect();
+                let lines: Vec<String> = self
+                    .directory(idx, cancel)
+                    .await?
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| cached.contains(&c.path))
+                    .map(|(i, c)| format!("{}. {}", i + 1, c.title))
+                    .collect();
+                let text = if lines.is_empty() {
+                    "No cached chapters".to_string()
+                } else {
+                    lines.join("\n")
+                };
+                self.send_text(chat, &text).await
+            }
+            BotCommand::Read { novel, chapter: n } => {
+                let idx = self.novel_index(novel)?;
+                let novel_id = self.novels[idx].novel_id.clone();
+                let chapter = self
+                    .directory(idx, cancel)
+                    .await?
+                    .get(n.wrapping_sub(1))
+                    .cloned()
+                    .ok_or_else(|| anyhow!("unknown chapter {n}"))?;
+                let text = self
+                    .trans_store
+                    .load(&novel_id, &chapter.path)
+                    .await?
+                    .ok_or_else(|| anyhow!("chapter {n} is not translated yet"))?;
+                let text = ruby_to_parens(&text);
+                self.send_text(chat, &format!("{}\n\n{text}", chapter.title))
+                    .await
+            }
+            BotCommand::Epub { novel, range } => {
+                let idx = self.novel_index(novel)?;
+                let novel_id = self.novels[idx].novel_id.clone();
+                let all = self.directory(idx, cancel).await?.to_vec();
+                let chapters: Vec<Chapter> = match range {
+                    Some(spec) => export::parse_chapter_ranges(&spec, all.len())?
+                        .into_iter()
+                        .map(|i| all[i].clone())
+                        .collect(),
+                    None => all,
+                };
+                let file_name = format!("{novel_id}.epub");
+                let path = std::env::temp_dir().join(format!("bot-{chat}-{file_name}"));
+                export::export_epub(
+                    &path,
+                    &novel_id,
+                    &novel_id,
+                    &chapters,
+                    self.trans_store.as_ref(),
+                    &ExportOptions::default(),
+                )
+                .await?;
+                let bytes = std::fs::read(&path)?;
+                let _ = std::fs::remove_file(&path);
+                self.send_document(chat, &file_name, bytes).await
+            }
+            BotCommand::Unknown => self.send_text(chat, "Unknown command, see /help").await,
+        }
+    }
+
+    /// 检查关注小说的新译文并通知订阅的会话
+    async fn notify_new_translations(&mut self, cancel: &CancellationToken) -> Result<()> {
+        for idx in 0..self.novels.len() {
+            let novel_id = self.novels[idx].novel_id.clone();
+            let cached: HashSet<String> = self
+                .trans_store
+                .list(&novel_id)
+                .await?
+                .into_iter()
+                .collect();
+            let Some(known) = self.known.get(&novel_id) else {
+                // 第一次检查只记录现状，不发送通知
+                self.known.insert(novel_id, cached);
+                continue;
+            };
+            let new: Vec<String> = cached.difference(known).cloned().collect();
+            if new.is_empty() {
+                continue;
+            }
+            // 新译文可能属于目录缓存之后才更新的章节，重新抓取目录
+            self.directories.remove(&novel_id);
+            let titles: Vec<String> = self
+                .directory(idx, cancel)
+                .await?
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| new.contains(&c.path))
+                .map(|(i, c)| format!("{}. {}", i + 1, c.title))
+                .collect();
+            let text = format!("New translations for {novel_id}:\n{}", titles.join("\n"));
+            self.send_text(self.config.allowed_chat, &text).await?;
+            self.known.insert(novel_id, cached);
+        }
+        Ok(())
+    }
+
+    /// 长轮询接收消息并定期推送新译文，直到令牌被取消
+    pub async fn run(mut self, cancel: CancellationToken) -> Result<()> {
+        info!("Telegram bot started for {} novels", self.novels.len());
+        let mut offset = 0i64;
+        let mut last_check = tokio::time::Instant::now() - self.config.poll_interval;
+        loop {
+            if last_check.elapsed() >= self.config.poll_interval {
+                last_check = tokio::time::Instant::now();
+                if let Err(e) = self.notify_new_translations(&cancel).await {
+                    warn!("Failed to check new translations: {e:?}");
+                }
+            }
+            let body = json!({"offset": offset, "timeout": 30, "allowed_updates": ["message"]});
+            let updates = match cancellable(&cancel, self.call("getUpdates", &body)).await {
+                Ok(updates) => updates,
+                Err(e) if cancel.is_cancelled() => {
+                    info!("Telegram bot stopped: {e}");
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("getUpdates failed: {e:?}");
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+            for update in updates.as_array().into_iter().flatten() {
+                offset = offset.max(update["update_id"].as_i64().unwrap_or(0) + 1);
+                let (Some(chat), Some(text)) = (
+                    update.pointer("/message/chat/id").and_then(Value::as_i64),
+                    update.pointer("/message/text").and_then(Value::as_str),
+                ) else {
+                    continue;
+                };
+                if chat != self.config.allowed_chat {
+                    warn!("Ignoring message from chat {chat}");
+                    continue;
+                }
+                if let Err(e) = self.handle_command(chat, text, &cancel).await {
+                    let _ = self.send_text(chat, &format!("Error: {e}")).await;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::net::SocketAddr;
+
+use anyhow::{Result, bail};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+/// 外部脚本可以发送的控制命令
+#[derive(Debug)]
+pub enum ControlCommand {
+    /// 查询当前状态
+    Status,
+    /// 将章节加入处理队列，参数为章节路径或从 1 开始的序号
+    Queue(String),
+    /// 取消章节任务
+    Cancel(String),
+    /// 暂停启动新任务
+    Pause,
+    /// 恢复启动任务
+    Resume,
+}
+
+/// 转发给应用的控制请求，处理结果通过 `reply` 返回
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    /// `novel` 参数指定的小说 id，`serve` 同时提供多部小说时用于选择
+    pub novel: Option<String>,
+    pub reply: oneshot::Sender<Result<Value, String>>,
+}
+
+/// JSON-RPC 2.0 请求
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const UNAUTHORIZED: i64 = -32001;
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// 从 `{"chapter": ...}` 参数中取出章节引用，接受字符串或数字
+fn chapter_param(params: &Value) -> Option<String> {
+    match params.get("chapter")? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// 将方法名与参数转换为控制命令
+fn parse_command(method: &str, params: &Value) -> Result<ControlCommand, (i64, &'static str)> {
+    let chapter = || chapter_param(params).ok_or((INVALID_PARAMS, "missing `chapter` param"));
+    match method {
+        "status" => Ok(ControlCommand::Status),
+        "queue" => Ok(ControlCommand::Queue(chapter()?)),
+        "cancel" => Ok(ControlCommand::Cancel(chapter()?)),
+        "pause" => Ok(ControlCommand::Pause),
+        "resume" => Ok(ControlCommand::Resume),
+        _ => Err((METHOD_NOT_FOUND, "method not found")),
+    }
+}
+
+/// 处理单行 JSON-RPC 请求并生成响应，`params.token` 须与 `token` 一致
+async fn handle_line(line: &str, token: &str, tx: &mpsc::UnboundedSender<ControlRequest>) -> Value {
+    let req: RpcRequest = match serde_json::from_str(line) {
+        Ok(req) => req,
+        Err(_) => return rpc_error(Value::Null, PARSE_ERROR, "parse error"),
+    };
+    if req.params.get("token").and_then(Value::as_str) != Some(token) {
+        return rpc_error(req.id, UNAUTHORIZED, "invalid or missing token");
+    }
+    let novel = req
+        .params
+        .get("novel")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let command = match parse_command(&req.method, &req.params) {
+        Ok(command) => command,
+        Err((code, message)) => return rpc_error(req.id, code, message),
+    };
+    let (reply, rx) = oneshot::channel();
+    if tx
+        .send(ControlRequest {
+            command,
+            novel,
+            reply,
+        })
+        .is_err()
+    {
+        return rpc_error(req.id, INTERNAL_ERROR, "application is shutting down");
+    }
+    match rx.await {
+        Ok(Ok(result)) => json!({"jsonrpc": "2.0", "id": req.id, "result": result}),
+        Ok(Err(message)) => rpc_error(req.id, INVALID_PARAMS, &message),
+        Err(_) => rpc_error(req.id, INTERNAL_ERROR, "request dropped"),
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    token: &str,
+    tx: mpsc::UnboundedSender<ControlRequest>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut resp = handle_line(&line, token, &tx).await.to_string();
+        resp.push('\n');
+        writer.write_all(resp.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// 检查控制 socket 的地址，只接受回环地址
+pub fn check_addr(addr: SocketAddr) -> Result<()> {
+    if !addr.ip().is_loopback() {
+        bail!("control socket must listen on a loopback address, not {addr}");
+    }
+    Ok(())
+}
+
+/// 在本机地址上监听控制连接，每行一个 JSON-RPC 2.0 请求，直到令牌被取消。
+/// 只接受回环地址；同一台机器上的其他用户也能连接，因此每个请求的 `params.token`
+/// 须与 `token` 一致
+pub async fn serve(
+    addr: SocketAddr,
+    token: String,
+    tx: mpsc::UnboundedSender<ControlRequest>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    check_addr(addr)?;
+    if token.is_empty() {
+        bail!("control socket needs a non-empty token");
+    }
+    let listener = TcpListener::bind(addr).await?;
+    info!("Control socket listening on {addr}");
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            res = listener.accept() => res?,
+        };
+        let tx = tx.clone();
+        let token = token.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &token, tx).await {
+                warn!("Control connection {peer} closed: {e}");
+            }
+        });
+    }
+}
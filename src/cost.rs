@@ -0,0 +1,120 @@
+use std::ops::AddAssign;
+
+use crate::translate::{CHUNK_CHARS, split_chunks};
+
+/// 每次请求中提示词（不含原文与对照表）大致占用的 token 数
+const PROMPT_OVERHEAD: usize = 200;
+
+/// 提取专有名词时回复的大致 token 数
+const EXTRACT_OUTPUT: usize = 300;
+
+/// 按字符类别估算 token 数：DeepSeek 等模型的分词器中英文约 0.3 token/字符，
+/// 中日文约 0.6 token/字，其他字符按 1 token 计
+pub fn count_tokens(text: &str) -> usize {
+    let weight: f64 = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii() {
+                0.3
+            } else if is_cjk(c) {
+                0.6
+            } else {
+                1.0
+            }
+        })
+        .sum();
+    weight.ceil() as usize
+}
+
+/// 汉字、假名以及全角标点
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{ff00}'..='\u{ffef}')
+}
+
+/// 翻译服务的单价（美元/百万 token）；按字符计费的服务以 `input` 作为每百万字符的价格
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+    /// 按原文字符数计费（DeepL）
+    pub per_character: bool,
+}
+
+impl Pricing {
+    /// 常用模型的公开价格，未知模型（如本地 Ollama）按免费计算；
+    /// 价格可能变动，可用 `--price-input`/`--price-output` 覆盖
+    pub fn for_model(model: &str) -> Pricing {
+        let (input, output) = match model {
+            m if m.starts_with("deepseek-reasoner") => (0.55, 2.19),
+            m if m.starts_with("deepseek") => (0.27, 1.10),
+            m if m.starts_with("gemini-2.5-pro") => (1.25, 10.0),
+            m if m.starts_with("gemini-2.5-flash") => (0.30, 2.50),
+            "deepl" => {
+                return Pricing {
+                    input: 25.0,
+                    output: 0.0,
+                    per_character: true,
+                };
+            }
+            _ => (0.0, 0.0),
+        };
+        Pricing {
+            input,
+            output,
+            per_character: false,
+        }
+    }
+}
+
+/// 一章或多章的预估用量与费用
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Estimate {
+    pub prompt_tokens: usize,
+    pub output_tokens: usize,
+    /// 预估费用（美元）
+    pub cost: f64,
+}
+
+impl AddAssign for Estimate {
+    fn add_assign(&mut self, other: Estimate) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// 估算翻译一章原文的用量：正文按 `CHUNK_CHARS` 分段请求，每段附带提示词与对照表，
+/// 译文长度按与原文相当计算；之后还需一次发送原文与译文的专有名词提取
+pub fn estimate_chapter(
+    source: &str,
+    keywords: &[(String, String)],
+    pricing: &Pricing,
+) -> Estimate {
+    let source_tokens = count_tokens(source);
+    let glossary: usize = keywords
+        .iter()
+        .map(|(jp, zh)| count_tokens(jp) + count_tokens(zh) + 2)
+        .sum();
+    let chunks = split_chunks(source, CHUNK_CHARS).len();
+    let prompt_tokens = source_tokens
+        + chunks * (PROMPT_OVERHEAD + glossary)
+        + PROMPT_OVERHEAD
+        + glossary
+        + source_tokens * 2;
+    let output_tokens = source_tokens + EXTRACT_OUTPUT;
+    let cost = if pricing.per_character {
+        source.chars().count() as f64 * pricing.input / 1e6
+    } else {
+        (prompt_tokens as f64 * pricing.input + output_tokens as f64 * pricing.output) / 1e6
+    };
+    Estimate {
+        prompt_tokens,
+        output_tokens,
+        cost,
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use http::Extensions;
+use log::warn;
+use regex::Regex;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{ClientBuilder, Middleware, Next};
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::http::HttpClient;
+use crate::syosetu::cancellable;
+
+/// 检查 robots.txt 时使用的爬虫名称
+pub const ROBOTS_AGENT: &str = "syosetu-rs";
+
+/// 令牌桶中的剩余令牌及上次补充的时间
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// 按域名限速的令牌桶：每个域名最多连续发出 `burst` 次请求，之后每隔 `interval` 补充一次，
+/// 避免批量翻译时请求过密被站点封禁
+#[derive(Default)]
+pub struct RateLimiter {
+    interval: Duration,
+    burst: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// `interval` 为零时不限速
+    pub fn new(interval: Duration, burst: u32) -> Self {
+        RateLimiter {
+            interval,
+            burst: burst.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 等待 `url` 所在域名的令牌；本地文件等没有域名的地址不限速
+    pub async fn acquire(&self, url: &str, cancel: &CancellationToken) -> Result<()> {
+        if self.interval.is_zero() {
+            return Ok(());
+        }
+        let Some(host) = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+        else {
+            return Ok(());
+        };
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap();
+                let now = Instant::now();
+                let bucket = buckets.entry(host.clone()).or_insert(Bucket {
+                    tokens: f64::from(self.burst),
+                    last: now,
+                });
+                let refill =
+                    now.duration_since(bucket.last).as_secs_f64() / self.interval.as_secs_f64();
+                bucket.tokens = (bucket.tokens + refill).min(f64::from(self.burst));
+                bucket.last = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return Ok(());
+                }
+                self.interval.mul_f64(1.0 - bucket.tokens)
+            };
+            cancellable(cancel, async {
+                tokio::time::sleep(wait).await;
+                Ok(())
+            })
+            .await?;
+        }
+    }
+}
+
+/// 某个域名的抓取规则
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HostRule {
+    /// 同一域名两次请求的最小间隔，为零时不限速
+    pub delay: Duration,
+    /// 间隔生效前允许连续发出的请求数
+    pub burst: u32,
+    /// 同一域名同时进行的请求数上限，为 0 时不限制
+    pub max_concurrent: usize,
+    /// 是否遵守 robots.txt
+    pub robots: bool,
+}
+
+/// `crawl.toml` 中某个域名对默认规则的覆盖，未填写的项沿用默认值
+///
+/// ```toml
+/// ["kakuyomu.jp"]
+/// delay_ms = 3000
+/// max_concurrent = 1
+/// robots = true
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostOverride {
+    pub delay_ms: Option<u64>,
+    pub burst: Option<u32>,
+    pub max_concurrent: Option<usize>,
+    pub robots: Option<bool>,
+}
+
+impl HostOverride {
+    fn apply(&self, rule: HostRule) -> HostRule {
+        HostRule {
+            delay: self.delay_ms.map_or(rule.delay, Duration::from_millis),
+            burst: self.burst.unwrap_or(rule.burst),
+            max_concurrent: self.max_concurrent.unwrap_or(rule.max_concurrent),
+            robots: self.robots.unwrap_or(rule.robots),
+        }
+    }
+}
+
+/// 读取按域名覆盖的抓取规则，文件不存在时返回空表
+pub fn load_crawl_config(path: &Path) -> Result<HashMap<String, HostOverride>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    toml::from_str(&text).map_err(|e| anyhow!("invalid crawl config {}: {e}", path.display()))
+}
+
+/// 规则及按该规则限速的令牌桶
+struct RuleState {
+    rule: HostRule,
+    limiter: RateLimiter,
+}
+
+impl RuleState {
+    fn new(rule: HostRule) -> Self {
+        RuleState {
+            limiter: RateLimiter::new(rule.delay, rule.burst),
+            rule,
+        }
+    }
+}
+
+/// 每次抓取前都要经过的抓取策略：按域名限制并发数与请求间隔，并可检查 robots.txt；
+/// 规则按域名（含子域名）配置，未配置的域名使用默认规则
+pub struct CrawlPolicy {
+    default: RuleState,
+    /// 域名及其规则，匹配时取最长的域名
+    hosts: Vec<(String, RuleState)>,
+    /// 各主机的并发槽位
+    slots: Mutex<HashMap<String, Arc<Semaphore>>>,
+    /// 下载 robots.txt 使用的客户端，为空时不检查
+    robots_client: Option<HttpClient>,
+    /// 各站点已下载的 robots.txt
+    robots: Mutex<HashMap<String, Arc<Robots>>>,
+}
+
+impl Default for CrawlPolicy {
+    fn default() -> Self {
+        Self::new(HostRule::default())
+    }
+}
+
+/// 抓取期间占用的并发槽位，释放后同一主机的下一个请求才能开始
+pub struct CrawlPermit {
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl CrawlPolicy {
+    pub fn new(default: HostRule) -> Self {
+        CrawlPolicy {
+            default: RuleState::new(default),
+            hosts: Vec::new(),
+            slots: Mutex::new(HashMap::new()),
+            robots_client: None,
+            robots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 按域名覆盖默认规则
+    pub fn with_overrides(mut self, overrides: &HashMap<String, HostOverride>) -> Self {
+        for (domain, o) in overrides {
+            let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+            self.hosts
+                .push((domain, RuleState::new(o.apply(self.default.rule))));
+        }
+        self
+    }
+
+    /// 设置下载 robots.txt 使用的客户端；未设置时即使规则要求也不检查
+    pub fn with_robots_client(mut self, client: HttpClient) -> Self {
+        self.robots_client = Some(client);
+        self
+    }
+
+    /// 为客户端加上本策略：之后经它发送的每个请求（含目录分页与重试）都先调用
+    /// [`Self::acquire`]。站点抓取页面须使用这样的客户端；翻译接口与下载 robots.txt
+    /// 的客户端不经过策略
+    pub fn guard(self: &Arc<Self>, client: HttpClient) -> HttpClient {
+        ClientBuilder::from_client(client)
+            .with(CrawlMiddleware(self.clone()))
+            .build()
+    }
+
+    /// 主机适用的规则
+    fn state_for(&self, host: &str) -> &RuleState {
+        self.hosts
+            .iter()
+            .filter(|(domain, _)| {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|h| h.ends_with('.'))
+            })
+            .max_by_key(|(domain, _)| domain.len())
+            .map_or(&self.default, |(_, state)| state)
+    }
+
+    /// 主机适用的规则
+    pub fn rule_for(&self, host: &str) -> HostRule {
+        self.state_for(&host.to_ascii_lowercase()).rule
+    }
+
+    /// 抓取 `url` 前调用：检查 robots.txt，取得并发槽位后等待请求间隔；
+    /// 返回的槽位需保持到抓取结束。本地文件等没有主机的地址不受限制
+    pub async fn acquire(&self, url: &str, cancel: &CancellationToken) -> Result<CrawlPermit> {
+        let Some(parsed) = Url::parse(url).ok().filter(|u| u.host_str().is_some()) else {
+            return Ok(CrawlPermit { _slot: None });
+        };
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+        let state = self.state_for(&host);
+        if state.rule.robots && !self.robots_allows(&parsed, cancel).await? {
+            bail!("{url} is disallowed by robots.txt");
+        }
+        let permit = if state.rule.max_concurrent > 0 {
+            let slots = self
+                .slots
+                .lock()
+                .unwrap()
+                .entry(host)
+                .or_insert_with(|| Arc::new(Semaphore::new(state.rule.max_concurrent)))
+                .clone();
+            Some(cancellable(cancel, async { Ok(slots.acquire_owned().await?) }).await?)
+        } else {
+            None
+        };
+        state.limiter.acquire(url, cancel).await?;
+        Ok(CrawlPermit { _slot: permit })
+    }
+
+    /// 按站点的 robots.txt 判断能否抓取；下载失败或不存在时视为允许
+    async fn robots_allows(&self, url: &Url, cancel: &CancellationToken) -> Result<bool> {
+        let Some(client) = &self.robots_client else {
+            return Ok(true);
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return Ok(true);
+        }
+        let origin = url.origin().ascii_serialization();
+        let cached = self.robots.lock().unwrap().get(&origin).cloned();
+        let robots = match cached {
+            Some(robots) => robots,
+            None => {
+                let robots_url = format!("{origin}/robots.txt");
+                let fetched = cancellable(cancel, async {
+                    let resp = client.get(&robots_url).send().await?;
+                    if !resp.status().is_success() {
+                        return Ok(String::new());
+                    }
+                    Ok(resp.text().await?)
+                })
+                .await;
+                let text = match fetched {
+                    Ok(text) => text,
+                    Err(e) if crate::syosetu::is_cancelled(&e) => return Err(e),
+                    Err(e) => {
+                        warn!("Failed to fetch {robots_url}: {e}");
+                        String::new()
+                    }
+                };
+                let robots = Arc::new(Robots::parse(&text, ROBOTS_AGENT));
+                self.robots.lock().unwrap().insert(origin, robots.clone());
+                robots
+            }
+        };
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        Ok(robots.allows(&path))
+    }
+}
+
+/// 请求前取得抓取策略的槽位，收到响应头后释放
+struct CrawlMiddleware(Arc<CrawlPolicy>);
+
+#[async_trait]
+impl Middleware for CrawlMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // 取消时调用方会丢弃整个请求，这里无需单独的取消令牌
+        let _permit = self
+            .0
+            .acquire(req.url().as_str(), &CancellationToken::new())
+            .await
+            .map_err(reqwest_middleware::Error::Middleware)?;
+        next.run(req, extensions).await
+    }
+}
+
+/// 一条 robots.txt 规则：（是否允许，路径模式）
+type RobotsRule = (bool, String);
+
+/// robots.txt 中适用于指定爬虫的 Allow/Disallow 规则
+#[derive(Clone, Debug, Default)]
+pub struct Robots {
+    rules: Vec<RobotsRule>,
+}
+
+impl Robots {
+    /// 解析 robots.txt，优先使用名称匹配 `agent` 的分组，没有时使用 `*` 分组
+    pub fn parse(text: &str, agent: &str) -> Self {
+        let agent = agent.to_ascii_lowercase();
+        let mut groups: Vec<(Vec<String>, Vec<RobotsRule>)> = Vec::new();
+        let mut in_rules = true;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    // 连续的 User-agent 行属于同一分组
+                    if in_rules {
+                        groups.push((Vec::new(), Vec::new()));
+                        in_rules = false;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.0.push(value.to_ascii_lowercase());
+                    }
+                }
+                field @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // 空的 Disallow 表示不限制
+                    if let Some(group) = groups.last_mut().filter(|_| !value.is_empty()) {
+                        group.1.push((field == "allow", value.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let named = groups.iter().find(|(agents, _)| {
+            agents
+                .iter()
+                .any(|a| a != "*" && agent.contains(a.as_str()))
+        });
+        let rules = named
+            .or_else(|| {
+                groups
+                    .iter()
+                    .find(|(agents, _)| agents.iter().any(|a| a == "*"))
+            })
+            .map(|(_, rules)| rules.clone())
+            .unwrap_or_default();
+        Robots { rules }
+    }
+
+    /// 路径（含查询参数）是否允许抓取：取最长的匹配规则，长度相同时 Allow 优先
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| robots_match(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+/// robots.txt 路径模式匹配：前缀匹配，支持 `*` 通配与结尾的 `$`
+fn robots_match(pattern: &str, path: &str) -> bool {
+    static SPECIAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*|\$$").unwrap());
+    if !SPECIAL.is_match(pattern) {
+        return path.starts_with(pattern);
+    }
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let regex = pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    let regex = format!("^{regex}{}", if anchored { "$" } else { "" });
+    Regex::new(&regex).is_ok_and(|re| re.is_match(path))
+}
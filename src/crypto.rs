@@ -0,0 +1,102 @@
+//! 存储文件的加密
+//!
+//! 密钥由口令经 Argon2id 派生，盐与用于校验口令的密文保存在数据目录的
+//! [`ENCRYPTION_FILE`] 中；每次加密使用随机 nonce（XChaCha20-Poly1305）。
+
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+/// 记录盐与校验值的文件名，位于数据目录
+pub const ENCRYPTION_FILE: &str = "encryption.json";
+
+/// 校验口令时加密的固定内容
+const CHECK: &[u8] = b"syosetu-rs";
+
+/// nonce 的长度，位于每段密文之前
+const NONCE_LEN: usize = 24;
+
+/// 保存在 [`ENCRYPTION_FILE`] 中的派生参数
+#[derive(Serialize, Deserialize)]
+struct KeyParams {
+    salt: Vec<u8>,
+    /// 以派生的密钥加密的 [`CHECK`]，口令不同时无法解密
+    check: Vec<u8>,
+}
+
+/// 以口令派生的密钥加密与解密存储文件
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+}
+
+impl Cipher {
+    /// 由口令派生密钥；`dir` 下没有 [`ENCRYPTION_FILE`] 时生成新的盐并记录，
+    /// 否则口令与首次使用时不同则报错
+    pub fn open(dir: &Path, passphrase: &str) -> Result<Self> {
+        let path = dir.join(ENCRYPTION_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                let params: KeyParams = serde_json::from_str(&content)
+                    .with_context(|| format!("failed to parse {}", path.display()))?;
+                let cipher = Self::derive(passphrase, &params.salt)?;
+                if cipher.decrypt(&params.check).ok().as_deref() != Some(CHECK) {
+                    bail!("wrong passphrase for the encrypted stores");
+                }
+                Ok(cipher)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let mut salt = vec![0; 16];
+                OsRng.fill_bytes(&mut salt);
+                let cipher = Self::derive(passphrase, &salt)?;
+                let params = KeyParams {
+                    check: cipher.encrypt(CHECK)?,
+                    salt,
+                };
+                std::fs::write(&path, serde_json::to_string_pretty(&params)?)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+                Ok(cipher)
+            }
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    /// 以 Argon2id 默认参数派生 256 位密钥
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow!("failed to derive the key: {e}"))?;
+        Ok(Cipher {
+            aead: XChaCha20Poly1305::new(&key.into()),
+        })
+    }
+
+    /// 加密，结果为 nonce 与密文
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self
+            .aead
+            .encrypt(&nonce, plain)
+            .map_err(|_| anyhow!("encryption failed"))?;
+        let mut out = nonce.to_vec();
+        out.extend(sealed);
+        Ok(out)
+    }
+
+    /// 解密 [`Self::encrypt`] 的结果；口令不同或内容被改动时报错
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            bail!("encrypted data is truncated");
+        }
+        let (nonce, sealed) = data.split_at(NONCE_LEN);
+        self.aead
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| anyhow!("decryption failed: wrong passphrase or corrupted data"))
+    }
+}
@@ -0,0 +1,743 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::memory::{KeywordStore, TranslationStore};
+use crate::syosetu::{Chapter, illustration_index, ruby_to_html, strip_ruby};
+use crate::translate::TargetLang;
+
+/// 记录上次导出内容的清单文件名
+const MANIFEST_FILE: &str = ".export-manifest.json";
+
+/// 清单中单个章节的导出记录
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct ManifestEntry {
+    /// 输出文件名
+    file: String,
+    /// 导出时译文的哈希
+    hash: String,
+}
+
+/// 导出目录中的清单，用于增量更新
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    /// 章节路径到导出记录的映射
+    chapters: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    fn load(dir: &Path) -> Manifest {
+        fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 一次导出的统计结果
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    /// 新写入或重新生成的章节数
+    pub written: usize,
+    /// 与上次相同而跳过的章节数
+    pub unchanged: usize,
+    /// 已不再导出而删除的文件数
+    pub removed: usize,
+}
+
+/// 计算文本的 SHA-256 十六进制摘要
+pub fn content_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 转义 HTML 特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 导出时的排版选项
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// 竖排（縦書き），页面从右向左翻页
+    pub vertical: bool,
+    /// 去掉振り仮名，否则以 `<ruby>` 显示
+    pub strip_furigana: bool,
+    /// 各章节的插图地址，键为章节路径；没有地址的 `[挿絵 #n]` 原样保留
+    pub illustrations: HashMap<String, Vec<String>>,
+    /// 译文的语言，写入页面与电子书元数据
+    pub lang: TargetLang,
+}
+
+/// 竖排使用的样式表
+const VERTICAL_CSS: &str = "html {
+  writing-mode: vertical-rl;
+  -webkit-writing-mode: vertical-rl;
+  -epub-writing-mode: vertical-rl;
+}
+body { line-height: 1.8; }
+p { margin: 0; text-indent: 1em; }
+";
+
+/// mdBook 竖排时附加的样式表文件名
+const MDBOOK_VERTICAL_CSS_FILE: &str = "vertical.css";
+
+/// mdBook 竖排使用的样式表，只作用于正文区域，侧边栏与菜单仍为横排
+const MDBOOK_VERTICAL_CSS: &str = ".content main {
+  writing-mode: vertical-rl;
+  -webkit-writing-mode: vertical-rl;
+  height: 80vh;
+  max-width: none;
+  overflow-x: auto;
+}
+.content main p { margin: 0 0.5em; text-indent: 1em; }
+.content main blockquote { margin: 0 1em; }
+";
+
+impl ExportOptions {
+    /// 需要嵌入页面的样式
+    fn css(&self) -> &'static str {
+        if self.vertical { VERTICAL_CSS } else { "" }
+    }
+
+    /// 指定章节的插图地址
+    fn images(&self, path: &str) -> &[String] {
+        self.illustrations.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// 处理已转义文本中的注音
+    fn ruby(&self, escaped: &str) -> String {
+        if self.strip_furigana {
+            strip_ruby(escaped)
+        } else {
+            ruby_to_html(escaped)
+        }
+    }
+}
+
+fn page(title: &str, body: &str, options: &ExportOptions) -> String {
+    let lang = options.lang;
+    let style = match options.css() {
+        "" => String::new(),
+        css => format!("<style>\n{css}</style>\n"),
+    };
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n{style}</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+/// 将译文按行转换为段落；插图占位符替换为 `images` 中的图片，
+/// `embed` 为假时只给出链接（EPUB 不允许引用远程图片）
+fn paragraphs(text: &str, images: &[String], embed: bool, options: &ExportOptions) -> String {
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(
+            |l| match illustration_index(l).and_then(|i| images.get(i)) {
+                Some(url) if embed => format!(
+                    "<p><img src=\"{}\" alt=\"{}\"/></p>\n",
+                    escape_html(url),
+                    escape_html(l.trim())
+                ),
+                Some(url) => format!(
+                    "<p><a href=\"{}\">{}</a></p>\n",
+                    escape_html(url),
+                    escape_html(l.trim())
+                ),
+                None => format!("<p>{}</p>\n", options.ruby(&escape_html(l))),
+            },
+        )
+        .collect()
+}
+
+fn chapter_page(title: &str, text: &str, images: &[String], options: &ExportOptions) -> String {
+    let mut body = format!(
+        "<h1>{}</h1>\n{}",
+        escape_html(title),
+        paragraphs(text, images, true, options)
+    );
+    body.push_str("<p><a href=\"index.html\">目录</a></p>\n");
+    page(title, &body, options)
+}
+
+/// 将已缓存的章节译文导出为静态 HTML 目录
+///
+/// 导出目录中保存一份清单，再次导出时只重写新增或译文有变化的章节，
+/// 并始终重新生成 `index.html`，适合对连载中的小说定期导出。
+pub async fn export_html(
+    out_dir: &Path,
+    novel_title: &str,
+    novel_id: &str,
+    chapters: &[Chapter],
+    store: &dyn TranslationStore,
+    options: &ExportOptions,
+) -> Result<ExportReport> {
+    fs::create_dir_all(out_dir)?;
+    let old = Manifest::load(out_dir);
+    let mut manifest = Manifest::default();
+    let mut report = ExportReport::default();
+    let mut index = format!("<h1>{}</h1>\n<ol>\n", escape_html(novel_title));
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let Some(text) = store.load(novel_id, &chapter.path).await? else {
+            continue;
+        };
+        let entry = ManifestEntry {
+            file: format!("{:04}.html", i + 1),
+            // 排版选项变化时也需要重新生成
+            hash: content_hash(&format!(
+                "{}\n{}\n{}\n{}\n{}",
+                options.css(),
+                options.strip_furigana,
+                options.images(&chapter.path).join("\n"),
+                chapter.title,
+                text
+            )),
+        };
+        if old.chapters.get(&chapter.path) == Some(&entry) && out_dir.join(&entry.file).exists() {
+            report.unchanged += 1;
+        } else {
+            fs::write(
+                out_dir.join(&entry.file),
+                chapter_page(
+                    &chapter.title,
+                    &text,
+                    options.images(&chapter.path),
+                    options,
+                ),
+            )?;
+            report.written += 1;
+        }
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            entry.file,
+            escape_html(&chapter.title)
+        ));
+        manifest.chapters.insert(chapter.path.clone(), entry);
+    }
+    index.push_str("</ol>\n");
+
+    // 删除目录变动后不再对应任何章节的旧文件
+    let current: Vec<&str> = manifest
+        .chapters
+        .values()
+        .map(|e| e.file.as_str())
+        .collect();
+    for entry in old.chapters.values() {
+        if !current.contains(&entry.file.as_str()) && out_dir.join(&entry.file).exists() {
+            fs::remove_file(out_dir.join(&entry.file))?;
+            report.removed += 1;
+        }
+    }
+
+    fs::write(
+        out_dir.join("index.html"),
+        page(novel_title, &index, options),
+    )?;
+    manifest.save(out_dir)?;
+    Ok(report)
+}
+
+/// 解析形如 `1-10,15` 的章节范围（从 1 开始），返回去重排序后的下标
+pub fn parse_chapter_ranges(spec: &str, total: usize) -> Result<Vec<usize>> {
+    let mut indices = std::collections::BTreeSet::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (a.trim().parse::<usize>()?, b.trim().parse::<usize>()?),
+            None => {
+                let n = part.parse::<usize>()?;
+                (n, n)
+            }
+        };
+        if start == 0 || start > end || end > total {
+            anyhow::bail!("invalid chapter range `{part}` (1-{total})");
+        }
+        indices.extend(start - 1..end);
+    }
+    Ok(indices.into_iter().collect())
+}
+
+fn xhtml(title: &str, body: &str, options: &ExportOptions) -> String {
+    let lang = options.lang;
+    let style = if options.vertical {
+        "<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n"
+    } else {
+        ""
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" xml:lang=\"{lang}\" lang=\"{lang}\">\n<head>\n<meta charset=\"UTF-8\"/>\n<title>{}</title>\n{style}</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+/// 将已缓存的章节译文打包为 EPUB 3，返回写入的章节数
+pub async fn export_epub(
+    out: &Path,
+    novel_title: &str,
+    novel_id: &str,
+    chapters: &[Chapter],
+    store: &dyn TranslationStore,
+    options: &ExportOptions,
+) -> Result<usize> {
+    let mut items = Vec::new();
+    for chapter in chapters {
+        if let Some(text) = store.load(novel_id, &chapter.path).await? {
+            items.push((chapter, text));
+        }
+    }
+    if items.is_empty() {
+        anyhow::bail!("no cached translations to export");
+    }
+    if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut zip = ZipWriter::new(File::create(out)?);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // mimetype 必须是第一个且不压缩的文件
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>
+"#)?;
+
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    let mut nav = String::from("<nav epub:type=\"toc\" id=\"toc\">\n<ol>\n");
+    for (i, (chapter, text)) in items.iter().enumerate() {
+        let file = format!("c{:04}.xhtml", i + 1);
+        let body = format!(
+            "<h1>{}</h1>\n{}",
+            escape_html(&chapter.title),
+            paragraphs(text, options.images(&chapter.path), false, options)
+        );
+        zip.start_file(format!("OEBPS/{file}"), deflated)?;
+        zip.write_all(xhtml(&chapter.title, &body, options).as_bytes())?;
+        manifest.push_str(&format!(
+            "<item id=\"c{i}\" href=\"{file}\" media-type=\"application/xhtml+xml\"/>\n"
+        ));
+        spine.push_str(&format!("<itemref idref=\"c{i}\"/>\n"));
+        nav.push_str(&format!(
+            "<li><a href=\"{file}\">{}</a></li>\n",
+            escape_html(&chapter.title)
+        ));
+    }
+    nav.push_str("</ol>\n</nav>\n");
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(xhtml(novel_title, &nav, options).as_bytes())?;
+    if options.vertical {
+        zip.start_file("OEBPS/style.css", deflated)?;
+        zip.write_all(VERTICAL_CSS.as_bytes())?;
+        manifest.push_str("<item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n");
+    }
+    let progression = if options.vertical {
+        " page-progression-direction=\"rtl\""
+    } else {
+        ""
+    };
+
+    let lang = options.lang;
+    let identifier = content_hash(&format!("{novel_id}\n{}", items.len()));
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="bookid" xml:lang="{lang}">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="bookid">urn:syosetu-rs:{identifier}</dc:identifier>
+<dc:title>{}</dc:title>
+<dc:language>{lang}</dc:language>
+<meta property="dcterms:modified">2000-01-01T00:00:00Z</meta>
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+{manifest}</manifest>
+<spine{progression}>
+{spine}</spine>
+</package>
+"#,
+        escape_html(novel_title)
+    );
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(opf.as_bytes())?;
+    zip.finish()?;
+    Ok(items.len())
+}
+
+/// 转义 Markdown 中有特殊含义的 ASCII 标点
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '!'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// 生成单章的 Markdown 页面；提供原文时逐段对照，段落数不一致则先列原文再列译文
+fn chapter_markdown(
+    title: &str,
+    text: &str,
+    source: Option<&str>,
+    images: &[String],
+    options: &ExportOptions,
+) -> String {
+    let lines = |s: &str| -> Vec<String> {
+        s.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(
+                |l| match illustration_index(l).and_then(|i| images.get(i)) {
+                    Some(url) => format!("![{}]({url})", escape_markdown(l)),
+                    None => options.ruby(&escape_markdown(l)),
+                },
+            )
+            .collect()
+    };
+    let mut md = format!("# {}\n\n", escape_markdown(title));
+    let translated = lines(text);
+    match source.map(lines) {
+        Some(original) if original.len() == translated.len() => {
+            for (jp, zh) in original.iter().zip(&translated) {
+                md.push_str(&format!("> {jp}\n\n{zh}\n\n"));
+            }
+        }
+        Some(original) => {
+            for jp in &original {
+                md.push_str(&format!("> {jp}\n>\n"));
+            }
+            md.push_str("\n---\n\n");
+            for zh in &translated {
+                md.push_str(&format!("{zh}\n\n"));
+            }
+        }
+        None => {
+            for zh in &translated {
+                md.push_str(&format!("{zh}\n\n"));
+            }
+        }
+    }
+    md
+}
+
+/// 将已缓存的章节译文导出为 mdBook 项目，返回写入的章节数
+///
+/// 生成 `book.toml` 与 `src/SUMMARY.md`，每章一页，可直接 `mdbook build`；
+/// 章节带有章（卷）标题时在目录中按分部分组。写入的页面记录在清单中，再次导出时
+/// 只删除清单中已不再导出的页面；目录非空且没有清单时拒绝写入，以免覆盖其他项目。
+/// `sources` 提供章节原文（键为章节路径）时生成中日对照页面，注音按 `options` 处理；
+/// 竖排时正文区域（含对照页面）使用附加的竖排样式表。
+pub async fn export_mdbook(
+    out_dir: &Path,
+    novel_title: &str,
+    novel_id: &str,
+    chapters: &[Chapter],
+    store: &dyn TranslationStore,
+    sources: Option<&HashMap<String, String>>,
+    options: &ExportOptions,
+) -> Result<usize> {
+    let non_empty = fs::read_dir(out_dir).is_ok_and(|mut entries| entries.next().is_some());
+    if non_empty && !out_dir.join(MANIFEST_FILE).exists() {
+        anyhow::bail!(
+            "{} is not empty and was not written by an earlier mdbook export; choose an empty or new directory",
+            out_dir.display()
+        );
+    }
+    let old = Manifest::load(out_dir);
+    let mut manifest = Manifest::default();
+    let src = out_dir.join("src");
+    fs::create_dir_all(&src)?;
+    let mut summary = format!(
+        "# Summary\n\n[{}](README.md)\n\n",
+        escape_markdown(novel_title)
+    );
+    let mut count = 0;
+    let mut arc = None;
+    for (i, chapter) in chapters.iter().enumerate() {
+        let Some(text) = store.load(novel_id, &chapter.path).await? else {
+            continue;
+        };
+        // 章（卷）标题作为 mdBook 的分部标题
+        if chapter.arc.is_some() && chapter.arc != arc {
+            arc = chapter.arc.clone();
+            let title = arc.as_deref().unwrap_or_default();
+            summary.push_str(&format!("\n# {}\n\n", escape_markdown(title)));
+        }
+        let source = sources
+            .and_then(|s| s.get(&chapter.path))
+            .map(String::as_str);
+        let file = format!("{:04}.md", i + 1);
+        let page = chapter_markdown(
+            &chapter.title,
+            &text,
+            source,
+            options.images(&chapter.path),
+            options,
+        );
+        fs::write(src.join(&file), &page)?;
+        summary.push_str(&format!(
+            "- [{}]({file})\n",
+            escape_markdown(&chapter.title)
+        ));
+        manifest.chapters.insert(
+            chapter.path.clone(),
+            ManifestEntry {
+                hash: content_hash(&page),
+                file,
+            },
+        );
+        count += 1;
+    }
+    if count == 0 {
+        anyhow::bail!("no cached translations to export");
+    }
+    // 只删除本导出器写过、这次不再导出的页面
+    for entry in old.chapters.values() {
+        let stale = !manifest.chapters.values().any(|e| e.file == entry.file);
+        if stale && src.join(&entry.file).exists() {
+            fs::remove_file(src.join(&entry.file))?;
+        }
+    }
+    fs::write(src.join("SUMMARY.md"), summary)?;
+    fs::write(
+        src.join("README.md"),
+        format!("# {}\n", escape_markdown(novel_title)),
+    )?;
+    // TOML 基本字符串与 JSON 字符串的转义规则兼容
+    let mut book = format!(
+        "[book]\ntitle = {}\nlanguage = \"{}\"\nsrc = \"src\"\n\n[output.html]\n",
+        serde_json::to_string(novel_title)?,
+        options.lang
+    );
+    let css = out_dir.join(MDBOOK_VERTICAL_CSS_FILE);
+    if options.vertical {
+        fs::write(&css, MDBOOK_VERTICAL_CSS)?;
+        book.push_str(&format!(
+            "additional-css = [\"{MDBOOK_VERTICAL_CSS_FILE}\"]\n"
+        ));
+    } else if css.exists() {
+        fs::remove_file(&css)?;
+    }
+    fs::write(out_dir.join("book.toml"), book)?;
+    manifest.save(out_dir)?;
+    Ok(count)
+}
+
+/// Yomitan 单个 term bank 文件中的最大条目数
+const TERM_BANK_SIZE: usize = 10_000;
+
+/// 将专有名词表导出为 Yomitan 可导入的词典压缩包
+///
+/// 每个日文条目生成一个 term，释义为对应的译名，浏览器中悬停原文人名即可看到
+/// 已确定的中文译法。
+pub fn export_yomitan(out: &Path, title: &str, keywords: &BTreeMap<String, String>) -> Result<()> {
+    if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut zip = ZipWriter::new(File::create(out)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let index = json!({
+        "title": title,
+        "revision": content_hash(&serde_json::to_string(keywords)?)[..12],
+        "format": 3,
+        "sequenced": false,
+        "author": "syosetu-rs",
+        "description": "Glossary exported from syosetu-rs",
+        "sourceLanguage": "ja",
+    });
+    zip.start_file("index.json", options)?;
+    zip.write_all(serde_json::to_string(&index)?.as_bytes())?;
+
+    let terms: Vec<_> = keywords
+        .iter()
+        .map(|(jp, zh)| json!([jp, "", "", "", 0, [zh], 0, ""]))
+        .collect();
+    for (i, bank) in terms.chunks(TERM_BANK_SIZE).enumerate() {
+        zip.start_file(format!("term_bank_{}.json", i + 1), options)?;
+        zip.write_all(serde_json::to_string(bank)?.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// 专有名词表的表格文件格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlossaryFormat {
+    /// 逗号分隔，字段按 RFC 4180 加引号
+    Csv,
+    /// 制表符分隔
+    Tsv,
+}
+
+impl GlossaryFormat {
+    /// 按扩展名判断格式，`.tsv`/`.txt` 为制表符分隔，其余按 CSV 处理
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("tsv") || ext.eq_ignore_ascii_case("txt") => {
+                GlossaryFormat::Tsv
+            }
+            _ => GlossaryFormat::Csv,
+        }
+    }
+
+    /// 字段分隔符
+    fn delimiter(self) -> char {
+        match self {
+            GlossaryFormat::Csv => ',',
+            GlossaryFormat::Tsv => '\t',
+        }
+    }
+}
+
+/// 导出文件的表头，导入时跳过
+const GLOSSARY_HEADER: [&str; 2] = ["japanese", "translated"];
+
+/// 将专有名词表写成带表头的两列表格，可直接用电子表格软件编辑
+pub fn glossary_to_table(keywords: &BTreeMap<String, String>, format: GlossaryFormat) -> String {
+    let delimiter = format.delimiter();
+    let field = |text: &str| {
+        if text.contains([delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    };
+    let mut out = GLOSSARY_HEADER.join(&delimiter.to_string());
+    out.push('\n');
+    for (jp, zh) in keywords {
+        out.push_str(&format!("{}{delimiter}{}\n", field(jp), field(zh)));
+    }
+    out
+}
+
+/// 解析表格中的专有名词：取前两列为原文与译名，忽略其余列、表头、空行及任一列为空的行
+pub fn parse_glossary_table(text: &str, format: GlossaryFormat) -> Vec<(String, String)> {
+    let text = text.trim_start_matches('\u{feff}');
+    parse_rows(text, format.delimiter())
+        .into_iter()
+        .filter_map(|row| {
+            let mut fields = row.into_iter().map(|f| f.trim().to_string());
+            let jp = fields.next()?;
+            let zh = fields.next()?;
+            if jp.is_empty() || zh.is_empty() || jp.eq_ignore_ascii_case(GLOSSARY_HEADER[0]) {
+                return None;
+            }
+            Some((jp, zh))
+        })
+        .collect()
+}
+
+/// 按 RFC 4180 拆分行与字段，引号内可包含分隔符与换行
+fn parse_rows(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// 将小说的专有名词表导出为 CSV/TSV 文件（按扩展名判断），返回词条数
+pub async fn export_glossary(
+    kw_store: &dyn KeywordStore,
+    novel_id: &str,
+    out: &Path,
+) -> Result<usize> {
+    let keywords: BTreeMap<String, String> = kw_store.load(novel_id).await?.into_iter().collect();
+    if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(
+        out,
+        glossary_to_table(&keywords, GlossaryFormat::from_path(out)),
+    )?;
+    Ok(keywords.len())
+}
+
+/// 从 CSV/TSV 文件导入专有名词，返回新增与修改的词条数；
+/// `keep_existing` 时已有词条保持不变，否则以文件中的译名为准
+pub async fn import_glossary(
+    kw_store: &dyn KeywordStore,
+    novel_id: &str,
+    path: &Path,
+    keep_existing: bool,
+) -> Result<(usize, usize)> {
+    let text = fs::read_to_string(path)?;
+    let existing = kw_store.load(novel_id).await?;
+    let mut added = HashMap::new();
+    let mut changed = Vec::new();
+    for (jp, zh) in parse_glossary_table(&text, GlossaryFormat::from_path(path)) {
+        match existing.get(&jp) {
+            None => {
+                added.insert(jp, zh);
+            }
+            Some(old) if *old != zh && !keep_existing => changed.push((jp, zh)),
+            Some(_) => {}
+        }
+    }
+    if !added.is_empty() {
+        kw_store.save(novel_id, &added).await?;
+    }
+    for (jp, zh) in &changed {
+        kw_store.set(novel_id, jp, zh).await?;
+    }
+    Ok((added.len(), changed.len()))
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use http::Extensions;
+use http::header::{
+    CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use log::{debug, warn};
+use reqwest::{Client, Method, Proxy, Request, Response, ResponseBuilderExt, StatusCode, Url};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex, RawCookie};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_util::sync::CancellationToken;
+
+use crate::syosetu::{cancellable, is_transient};
+
+/// 发送请求时默认使用的 UA 字符串
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36 Edg/136.0.0.0";
+
+/// 站点与翻译器共用的 HTTP 客户端，请求会依次经过中间件
+pub type HttpClient = ClientWithMiddleware;
+
+/// 站点客户端共用的 Cookie 存储，可保存到文件并在下次启动时读回
+pub type CookieJar = CookieStoreMutex;
+
+/// 构建 HTTP 客户端时使用的公共配置
+#[derive(Clone, Debug)]
+pub struct HttpOptions {
+    /// 请求携带的 UA
+    pub user_agent: String,
+    /// 连接失败、超时、5xx 与 429 响应时的最大重试次数，为 0 时不重试；
+    /// 这是站点与翻译接口唯一的重试层（`--retries`）
+    pub max_retries: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍（`--retry-delay-ms`）
+    pub retry_delay: Duration,
+    /// 代理地址，支持 `http://`、`https://` 与 `socks5://`（`socks5h://` 由代理解析域名）
+    pub proxy: Option<String>,
+    /// 整个请求（含读取响应）的超时；翻译接口响应较慢，默认值需留足余量
+    pub timeout: Duration,
+    /// 建立连接的超时
+    pub connect_timeout: Duration,
+    /// 共用的 Cookie 存储；为空时每个客户端各自在内存中保存 Cookie
+    pub cookies: Option<Arc<CookieJar>>,
+    /// 响应缓存目录；带 `ETag`/`Last-Modified` 的 GET 响应会保存在这里，之后以条件请求验证
+    pub cache_dir: Option<PathBuf>,
+    /// 忽略已缓存的响应重新下载（新的响应仍会写入缓存）
+    pub refresh: bool,
+    /// 替代网络发送请求的实现，例如回放样例页面的 `FixtureFetcher`
+    pub fetcher: Option<Arc<dyn HttpFetcher>>,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_retries: 2,
+            retry_delay: Duration::from_secs(1),
+            proxy: None,
+            timeout: Duration::from_secs(300),
+            connect_timeout: Duration::from_secs(10),
+            cookies: None,
+            cache_dir: None,
+            refresh: false,
+            fetcher: None,
+        }
+    }
+}
+
+impl HttpOptions {
+    /// 按 `max_retries` 与 `retry_delay` 生成的重试策略
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_retries + 1,
+            base_delay: self.retry_delay,
+            ..Default::default()
+        }
+    }
+}
+
+/// 按配置构建带中间件的客户端，所有跨站点的 HTTP 行为都在这里统一配置
+pub fn build_client(options: &HttpOptions) -> Result<HttpClient> {
+    let mut builder = Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .user_agent(&options.user_agent)
+        .timeout(options.timeout)
+        .connect_timeout(options.connect_timeout)
+        // 目录与章节页压缩后小得多；解压在中间件之前完成，缓存保存的是解压后的正文
+        .gzip(true)
+        // 客户端在所有站点与翻译任务间共享，保留足够的空闲连接供并发任务复用
+        .pool_max_idle_per_host(16)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60));
+    if let Some(proxy) = &options.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    builder = match &options.cookies {
+        Some(jar) => builder.cookie_provider(jar.clone()),
+        None => builder.cookie_store(true),
+    };
+    let client = builder.build()?;
+    let mut builder = ClientBuilder::new(client);
+    if let Some(dir) = &options.cache_dir {
+        fs::create_dir_all(dir)?;
+        builder = builder.with(CacheMiddleware {
+            dir: dir.clone(),
+            refresh: options.refresh,
+        });
+    }
+    builder = builder.with(RetryMiddleware {
+        policy: options.retry_policy(),
+    });
+    // 放在最内层，缓存与重试等中间件照常生效
+    if let Some(fetcher) = &options.fetcher {
+        builder = builder.with(FetcherMiddleware(fetcher.clone()));
+    }
+    Ok(builder.build())
+}
+
+/// 读取保存的 Cookie，文件不存在时返回空存储；已过期的 Cookie 会被丢弃
+pub fn load_cookie_jar(path: &Path) -> Result<CookieJar> {
+    let store = match fs::File::open(path) {
+        Ok(file) => cookie_store::serde::json::load(BufReader::new(file))
+            .map_err(|e| anyhow!("failed to read {}: {e}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => CookieStore::default(),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(CookieJar::new(store))
+}
+
+/// 保存 Cookie；会话 Cookie 也一并保存，以便保留浏览器中导入的登录状态
+pub fn save_cookie_jar(jar: &CookieJar, path: &Path) -> Result<()> {
+    let store = jar.lock().map_err(|_| anyhow!("cookie jar poisoned"))?;
+    let mut out = Vec::new();
+    cookie_store::serde::json::save_incl_expired_and_nonpersistent(&store, &mut out)
+        .map_err(|e| anyhow!("failed to serialize cookies: {e}"))?;
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// 导入浏览器导出的 Netscape 格式 `cookies.txt`，返回导入的条目数
+///
+/// 每行依次为域名、是否包含子域名、路径、是否仅限 HTTPS、过期时间（Unix 秒，0 为会话 Cookie）、
+/// 名称与值，以制表符分隔；`#HttpOnly_` 前缀的行同样是有效条目，其余 `#` 开头的行为注释。
+pub fn import_netscape_cookies(jar: &CookieJar, text: &str) -> Result<usize> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let mut store = jar.lock().map_err(|_| anyhow!("cookie jar poisoned"))?;
+    let mut imported = 0;
+    for line in text.lines() {
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(rest) => (rest, true),
+            None if line.starts_with('#') => continue,
+            None => (line, false),
+        };
+        let fields: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+        let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+            continue;
+        };
+        let expires: u64 = expires.parse().unwrap_or(0);
+        if expires != 0 && expires <= now {
+            continue;
+        }
+        let host = domain.trim_start_matches('.');
+        let mut cookie = format!("{name}={value}; Path={path}");
+        // 不包含子域名时为 host-only Cookie，不能带 Domain 属性
+        if subdomains.eq_ignore_ascii_case("TRUE") {
+            cookie.push_str(&format!("; Domain={host}"));
+        }
+        if expires != 0 {
+            cookie.push_str(&format!("; Max-Age={}", expires - now));
+        }
+        if secure.eq_ignore_ascii_case("TRUE") {
+            cookie.push_str("; Secure");
+        }
+        if http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        let Ok(raw) = RawCookie::parse(cookie) else {
+            continue;
+        };
+        let url = Url::parse(&format!("https://{host}{path}"))?;
+        if store.insert_raw(&raw, &url).is_ok() {
+            imported += 1;
+        }
+    }
+    Ok(imported)
+}
+
+/// 指定地址应携带的 Cookie 请求头，供不经过 reqwest 的下载方式使用
+pub fn cookie_header(jar: &CookieJar, url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let store = jar.lock().ok()?;
+    let header = store
+        .get_request_values(&url)
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join("; ");
+    (!header.is_empty()).then_some(header)
+}
+
+/// 请求失败时的重试策略：指数退避，并在等待时间上加入随机抖动；
+/// 客户端的重试中间件与不经过客户端的 curl 下载共用
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// 最多尝试的次数（含第一次），为 1 时不重试
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub base_delay: Duration,
+    /// 单次等待时间的上限
+    pub max_delay: Duration,
+    /// 等待时间随机浮动的比例（0 到 1）
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次失败（从 1 开始）后的等待时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        // 不引入随机数依赖，用当前时间的纳秒部分作为抖动来源
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let unit = f64::from(nanos % 10_000) / 10_000.0 * 2.0 - 1.0;
+        exp.mul_f64((1.0 + self.jitter.clamp(0.0, 1.0) * unit).max(0.0))
+    }
+
+    /// 执行 `op`，遇到网络错误时按策略等待后重试；取消与解析错误立即返回
+    pub async fn run<T, F, Fut>(
+        &self,
+        what: &str,
+        cancel: &CancellationToken,
+        mut op: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(e) if attempt < self.max_attempts && is_transient(&e) => {
+                    let delay = self.delay(attempt);
+                    warn!("{what} failed (attempt {attempt}), retrying in {delay:?}: {e}");
+                    cancellable(cancel, async {
+                        tokio::time::sleep(delay).await;
+                        Ok(())
+                    })
+                    .await?;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// 对连接错误、超时、5xx 与 429 响应进行重试：429 按 `Retry-After` 等待，其余按指数退避
+struct RetryMiddleware {
+    policy: RetryPolicy,
+}
+
+/// 愿意遵从的 `Retry-After` 上限，更长时直接返回 429 交给调用方处理
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// 解析 `Retry-After`：秒数或 HTTP 日期
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[async_trait]
+impl Middleware for RetryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            // 请求体为流时无法复制，只能发送一次
+            let Some(cloned) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+            let res = next.clone().run(cloned, extensions).await;
+            let backoff = self.policy.delay(attempt + 1);
+            let delay = match &res {
+                Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    match retry_after(resp) {
+                        Some(wait) if wait > MAX_RETRY_AFTER => None,
+                        Some(wait) => Some(wait),
+                        None => Some(backoff),
+                    }
+                }
+                Ok(resp) if resp.status().is_server_error() => Some(backoff),
+                Ok(_) => None,
+                Err(e) if e.is_connect() || e.is_timeout() => Some(backoff),
+                Err(_) => None,
+            };
+            let Some(delay) = delay.filter(|_| attempt + 1 < self.policy.max_attempts) else {
+                return res;
+            };
+            attempt += 1;
+            warn!("Retrying {} in {delay:?} (attempt {attempt})", req.url());
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// 缓存响应的元数据，正文单独保存在同名的 `.body` 文件中
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_type: Option<String>,
+}
+
+/// 按地址缓存 GET 响应，再次请求时附带验证头，服务端返回 304 时直接使用缓存的正文
+struct CacheMiddleware {
+    dir: PathBuf,
+    refresh: bool,
+}
+
+impl CacheMiddleware {
+    fn paths(&self, url: &Url) -> (PathBuf, PathBuf) {
+        let key: String = Sha256::digest(url.as_str().as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        (
+            self.dir.join(format!("{key}.json")),
+            self.dir.join(format!("{key}.body")),
+        )
+    }
+
+    fn load(&self, url: &Url) -> Option<(CacheEntry, Vec<u8>)> {
+        let (meta, body) = self.paths(url);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(meta).ok()?).ok()?;
+        // 摘要相同但地址不同时视为未命中
+        if entry.url != url.as_str() {
+            return None;
+        }
+        Some((entry, fs::read(body).ok()?))
+    }
+
+    fn save(&self, entry: &CacheEntry, body: &[u8]) -> Result<()> {
+        let (meta, body_path) = self.paths(&Url::parse(&entry.url)?);
+        fs::write(body_path, body)?;
+        fs::write(meta, serde_json::to_vec(entry)?)?;
+        Ok(())
+    }
+}
+
+/// 用缓存或已读取的正文重新构造响应
+fn rebuild_response(
+    status: StatusCode,
+    url: Url,
+    content_type: Option<&str>,
+    body: Vec<u8>,
+) -> Response {
+    let mut builder = http::Response::builder().status(status).url(url);
+    if let Some(content_type) = content_type {
+        builder = builder.header(CONTENT_TYPE, content_type);
+    }
+    Response::from(builder.body(body).expect("valid cached response"))
+}
+
+#[async_trait]
+impl Middleware for CacheMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if req.method() != Method::GET {
+            return next.run(req, extensions).await;
+        }
+        let url = req.url().clone();
+        let cached = if self.refresh { None } else { self.load(&url) };
+        if let Some((entry, _)) = &cached {
+            let headers = req.headers_mut();
+            if let Some(etag) = entry.etag.as_deref().and_then(|v| v.parse().ok()) {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = entry.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+                headers.insert(IF_MODIFIED_SINCE, date);
+            }
+        }
+        let resp = next.run(req, extensions).await?;
+        if resp.status() == StatusCode::NOT_MODIFIED
+            && let Some((entry, body)) = cached
+        {
+            debug!("Using cached response for {url}");
+            return Ok(rebuild_response(
+                StatusCode::OK,
+                url,
+                entry.content_type.as_deref(),
+                body,
+            ));
+        }
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v: &http::HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let entry = CacheEntry {
+            url: url.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            content_type: header(CONTENT_TYPE),
+        };
+        // 没有验证头的响应无法发起条件请求，缓存也没有意义
+        if resp.status() != StatusCode::OK
+            || (entry.etag.is_none() && entry.last_modified.is_none())
+        {
+            return Ok(resp);
+        }
+        let status = resp.status();
+        let final_url = resp.url().clone();
+        let body = resp.bytes().await?.to_vec();
+        // 重定向后的地址与请求地址不同，只按请求地址缓存
+        if let Err(e) = self.save(&entry, &body) {
+            warn!("Failed to cache {url}: {e:?}");
+        }
+        Ok(rebuild_response(
+            status,
+            final_url,
+            entry.content_type.as_deref(),
+            body,
+        ))
+    }
+}
+
+/// 发送请求的底层实现；设置后客户端不再访问网络，所有站点与翻译器的请求都交给它处理
+#[async_trait]
+pub trait HttpFetcher: Send + Sync + fmt::Debug {
+    async fn fetch(&self, req: Request) -> Result<Response>;
+}
+
+/// 按地址回放保存的 HTML/JSON，未登记的地址返回 404，便于离线测试站点解析与翻译流程
+#[derive(Debug, Default)]
+pub struct FixtureFetcher {
+    responses: HashMap<String, (&'static str, Vec<u8>)>,
+}
+
+impl FixtureFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个 HTML 页面，地址需与请求完全一致（含查询参数）
+    pub fn with_page(mut self, url: &str, html: impl Into<Vec<u8>>) -> Self {
+        self.responses
+            .insert(url.to_string(), ("text/html; charset=utf-8", html.into()));
+        self
+    }
+
+    /// 登记一个 JSON 响应，例如站点接口或翻译 API 的回复
+    pub fn with_json(mut self, url: &str, json: impl Into<Vec<u8>>) -> Self {
+        self.responses
+            .insert(url.to_string(), ("application/json", json.into()));
+        self
+    }
+}
+
+#[async_trait]
+impl HttpFetcher for FixtureFetcher {
+    async fn fetch(&self, req: Request) -> Result<Response> {
+        let url = req.url().clone();
+        Ok(match self.responses.get(url.as_str()) {
+            Some((content_type, body)) => {
+                rebuild_response(StatusCode::OK, url, Some(content_type), body.clone())
+            }
+            None => rebuild_response(StatusCode::NOT_FOUND, url, None, Vec::new()),
+        })
+    }
+}
+
+/// 将请求交给 `HttpFetcher`，不再调用后续的网络层
+struct FetcherMiddleware(Arc<dyn HttpFetcher>);
+
+#[async_trait]
+impl Middleware for FetcherMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        self.0
+            .fetch(req)
+            .await
+            .map_err(reqwest_middleware::Error::Middleware)
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// 发送到 Kindle 所需的 SMTP 配置
+#[derive(Clone, Debug)]
+pub struct KindleConfig {
+    /// SMTP 服务器地址，使用 STARTTLS 连接
+    pub smtp_host: String,
+    /// SMTP 端口
+    pub smtp_port: u16,
+    /// SMTP 用户名
+    pub username: String,
+    /// SMTP 密码
+    pub password: String,
+    /// 发件人地址，需要在亚马逊账户中加入已认可的发件人列表
+    pub from: String,
+    /// Kindle 接收地址（xxx@kindle.com）
+    pub to: String,
+}
+
+/// 将导出的电子书作为附件发送到 Kindle 邮箱
+pub async fn send_to_kindle(config: &KindleConfig, title: &str, book: &Path) -> Result<()> {
+    let file_name = book
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("invalid attachment path {}", book.display()))?
+        .to_string();
+    let content_type = ContentType::parse("application/epub+zip")?;
+    let email = Message::builder()
+        .from(config.from.parse()?)
+        .to(config.to.parse()?)
+        .subject(title)
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(format!("{title}\n\nSent by syosetu-rs")))
+                .singlepart(Attachment::new(file_name).body(fs::read(book)?, content_type)),
+        )?;
+    let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+        .port(config.smtp_port)
+        .credentials(Credentials::new(
+            config.username.clone(),
+            config.password.clone(),
+        ))
+        .build();
+    mailer.send(email).await?;
+    Ok(())
+}
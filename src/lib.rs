@@ -0,0 +1,28 @@
+//! syosetu 小说抓取与翻译的核心库
+//!
+//! 默认启用的 `tui` 特性提供终端界面，关闭后仅保留抓取、翻译与存储逻辑，
+//! 可作为服务端或其他程序的依赖使用。
+
+pub mod backup;
+pub mod bot;
+pub mod control;
+pub mod cost;
+pub mod crawl;
+pub mod crypto;
+pub mod export;
+pub mod http;
+pub mod kindle;
+pub mod library;
+pub mod memory;
+pub mod metrics;
+pub mod mining;
+pub mod paths;
+pub mod pipeline;
+pub mod server;
+pub mod syosetu;
+pub mod translate;
+
+#[cfg(feature = "tui")]
+pub mod app;
+#[cfg(feature = "tui")]
+pub mod ui;
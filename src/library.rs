@@ -0,0 +1,317 @@
+//! 按小说统计、删除与清理存储中的数据：`library` 子命令与目录界面中的 `D`（删除本小说的
+//! 全部缓存）、`O`（清理目录中已不存在的章节）共用
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+
+use crate::crypto::Cipher;
+use crate::memory::{Encoding, FileLock, GLOBAL_GLOSSARY, StoreUsage, TranslationStore};
+
+/// 报告中译文存储的名称
+const TRANSLATIONS: &str = "translations";
+
+/// 顶层以小说 id 为键的 JSON 存储文件，不经具体的存储类型直接按键读写
+#[derive(Clone)]
+pub struct NovelFile {
+    path: PathBuf,
+    /// 小说所在的顶层字段，如 `examples.json` 中的 `novels`；为 `None` 时小说 id 即顶层键
+    section: Option<&'static str>,
+    /// 小说的记录是否以章节路径为键，清理时据此删除目录中已不存在的章节
+    by_chapter: bool,
+    /// 与对应存储相同的加密设置
+    encoding: Encoding,
+}
+
+impl NovelFile {
+    /// 以小说 id 为顶层键的文件
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        NovelFile {
+            path: path.into(),
+            section: None,
+            by_chapter: false,
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// 文件由对应的存储加密保存（`--encrypt`）
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 小说的记录以章节路径为键
+    pub fn by_chapter(mut self) -> Self {
+        self.by_chapter = true;
+        self
+    }
+
+    /// 小说位于顶层字段 `section` 之下
+    pub fn in_section(mut self, section: &'static str) -> Self {
+        self.section = Some(section);
+        self
+    }
+
+    /// 报告中使用的名称，即文件名
+    fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// 读取整个文件；文件不存在时为空，格式错误时报错，以免随后的写入清空文件
+    fn read(&self) -> Result<Value> {
+        let Some(content) = self.encoding.read_blocking(&self.path)? else {
+            return Ok(Value::Object(Map::new()));
+        };
+        serde_json::from_slice(&content)
+            .with_context(|| format!("failed to parse {}", self.path.display()))
+    }
+
+    /// 文件中以小说 id 为键的部分
+    fn novels<'a>(&self, root: &'a mut Value) -> Option<&'a mut Map<String, Value>> {
+        match self.section {
+            Some(section) => root.get_mut(section)?.as_object_mut(),
+            None => root.as_object_mut(),
+        }
+    }
+
+    /// 各小说的记录数与序列化后的长度
+    pub fn usage(&self) -> Result<HashMap<String, StoreUsage>> {
+        let mut root = self.read()?;
+        let Some(novels) = self.novels(&mut root) else {
+            return Ok(HashMap::new());
+        };
+        Ok(novels
+            .iter()
+            .map(|(novel_id, value)| {
+                let usage = StoreUsage {
+                    entries: entries(value),
+                    bytes: value.to_string().len() as u64,
+                };
+                (novel_id.clone(), usage)
+            })
+            .collect())
+    }
+
+    /// 删除小说的全部记录，返回删除的条数；`apply` 为假时只统计
+    pub fn remove(&self, novel_id: &str, apply: bool) -> Result<usize> {
+        let _lock = apply.then(|| FileLock::acquire(&self.path)).transpose()?;
+        let mut root = self.read()?;
+        let Some(novels) = self.novels(&mut root) else {
+            return Ok(0);
+        };
+        let Some(removed) = novels.get(novel_id).map(entries) else {
+            return Ok(0);
+        };
+        if apply {
+            novels.remove(novel_id);
+            self.write(&root)?;
+        }
+        Ok(removed)
+    }
+
+    /// 删除不在 `keep` 中的章节记录，返回删除的条数；不按章节保存的文件不做处理
+    pub fn prune(&self, novel_id: &str, keep: &HashSet<String>, apply: bool) -> Result<usize> {
+        if !self.by_chapter {
+            return Ok(0);
+        }
+        let _lock = apply.then(|| FileLock::acquire(&self.path)).transpose()?;
+        let mut root = self.read()?;
+        let Some(chapters) = self
+            .novels(&mut root)
+            .and_then(|novels| novels.get_mut(novel_id))
+            .and_then(Value::as_object_mut)
+        else {
+            return Ok(0);
+        };
+        let before = chapters.len();
+        chapters.retain(|chapter, _| keep.contains(chapter));
+        let removed = before - chapters.len();
+        if apply && removed > 0 {
+            self.write(&root)?;
+        }
+        Ok(removed)
+    }
+
+    /// 写回整个文件
+    fn write(&self, root: &Value) -> Result<()> {
+        self.encoding
+            .write_blocking(&self.path, serde_json::to_string_pretty(root)?.as_bytes())
+    }
+}
+
+/// 一部小说在各存储中的数据量
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NovelUsage {
+    pub novel_id: String,
+    /// 已缓存的译文章节数
+    pub chapters: usize,
+    /// 各存储中占用的字节数之和
+    pub bytes: u64,
+    /// 存储名称 → 记录数与字节数
+    pub stores: BTreeMap<String, StoreUsage>,
+}
+
+/// 删除或清理的结果：存储名称 → 删除的条数，不含没有删除任何内容的存储
+pub type Removal = BTreeMap<String, usize>;
+
+/// 所有按小说保存数据的存储
+pub struct Library {
+    translations: Arc<dyn TranslationStore>,
+    files: Vec<NovelFile>,
+}
+
+impl Library {
+    /// 只含译文存储的空集合，其余文件由 [`Self::with_file`] 加入
+    pub fn new(translations: Arc<dyn TranslationStore>) -> Self {
+        Library {
+            translations,
+            files: Vec::new(),
+        }
+    }
+
+    /// 加入一个存储文件
+    pub fn with_file(mut self, file: NovelFile) -> Self {
+        self.files.push(file);
+        self
+    }
+
+    /// 把以其他方式（加密与否）保存的存储文件转换为各自当前的设置，返回转换的文件数；
+    /// 译文存储由其自身的 `migrate` 转换
+    pub fn migrate(&self) -> Result<usize> {
+        let mut converted = 0;
+        for file in &self.files {
+            let _lock = FileLock::acquire(&file.path)?;
+            if file.encoding.migrate_blocking(&file.path)? {
+                converted += 1;
+            }
+        }
+        Ok(converted)
+    }
+
+    /// 各小说的数据量，按占用的字节数从多到少排列
+    pub async fn usage(&self) -> Result<Vec<NovelUsage>> {
+        let mut novels: HashMap<String, NovelUsage> = HashMap::new();
+        let mut add = |store: String, usage: HashMap<String, StoreUsage>| {
+            for (novel_id, entry) in usage {
+                let novel = novels
+                    .entry(novel_id.clone())
+                    .or_insert_with(|| NovelUsage {
+                        novel_id,
+                        ..Default::default()
+                    });
+                novel.bytes += entry.bytes;
+                novel.stores.insert(store.clone(), entry);
+            }
+        };
+        add(TRANSLATIONS.to_string(), self.translations.usage().await?);
+        for file in &self.files {
+            add(file.name(), file.usage()?);
+        }
+        let mut novels: Vec<_> = novels
+            .into_values()
+            .map(|mut novel| {
+                novel.chapters = novel.stores.get(TRANSLATIONS).map_or(0, |t| t.entries);
+                novel
+            })
+            .collect();
+        novels.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.novel_id.cmp(&b.novel_id))
+        });
+        Ok(novels)
+    }
+
+    /// 删除小说在所有存储中的数据；`apply` 为假时只统计将被删除的条数
+    pub async fn remove(&self, novel_id: &str, apply: bool) -> Result<Removal> {
+        if novel_id == GLOBAL_GLOSSARY {
+            bail!("{GLOBAL_GLOSSARY} holds the global glossary, not a novel");
+        }
+        let chapters = self.translations.list(novel_id).await?;
+        let mut removal = Removal::new();
+        let removed = if apply {
+            self.translations.remove(novel_id, &chapters).await?
+        } else {
+            chapters.len()
+        };
+        record(&mut removal, TRANSLATIONS.to_string(), removed);
+        for file in &self.files {
+            record(&mut removal, file.name(), file.remove(novel_id, apply)?);
+        }
+        Ok(removal)
+    }
+
+    /// 删除目录中已不存在的章节的译文、原文、历史等记录，`chapters` 为目录中的全部章节路径；
+    /// `apply` 为假时只统计将被删除的条数
+    pub async fn prune(
+        &self,
+        novel_id: &str,
+        chapters: &HashSet<String>,
+        apply: bool,
+    ) -> Result<Removal> {
+        // 目录为空多半是抓取失败，此时清理会删掉全部缓存
+        if chapters.is_empty() {
+            bail!("the table of contents of {novel_id} is empty; nothing to compare against");
+        }
+        let orphans: Vec<String> = self
+            .translations
+            .list(novel_id)
+            .await?
+            .into_iter()
+            .filter(|chapter| !chapters.contains(chapter))
+            .collect();
+        let mut removal = Removal::new();
+        let removed = if apply {
+            self.translations.remove(novel_id, &orphans).await?
+        } else {
+            orphans.len()
+        };
+        record(&mut removal, TRANSLATIONS.to_string(), removed);
+        for file in &self.files {
+            record(
+                &mut removal,
+                file.name(),
+                file.prune(novel_id, chapters, apply)?,
+            );
+        }
+        Ok(removal)
+    }
+}
+
+/// 以 KiB、MiB 等单位显示字节数
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// 一部小说的记录条数：章节、词条或列表项的个数，摘要等单个值为 1
+fn entries(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => map.len(),
+        Value::Array(list) => list.len(),
+        _ => 1,
+    }
+}
+
+/// 记录删除了内容的存储
+fn record(removal: &mut Removal, store: String, removed: usize) {
+    if removed > 0 {
+        removal.insert(store, removed);
+    }
+}
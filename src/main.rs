@@ -1,67 +1,2455 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Result, anyhow, bail};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
 use env_logger::{Builder, Target};
-use log::{LevelFilter, error};
+use log::{LevelFilter, error, info, warn};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
 
-use crate::app::App;
-use crate::memory::{JsonStore, JsonTranslationStore};
-use crate::syosetu::{NcodeSite, NovelSite, OrgSite, Translator};
-
-mod app;
-mod memory;
-mod syosetu;
-mod ui;
+use syosetu_rs::app::{App, DEFAULT_CONCURRENCY};
+use syosetu_rs::backup::{Backup, BackupStores, NovelBackup};
+use syosetu_rs::bot::{Bot, BotConfig, FollowedNovel};
+use syosetu_rs::control::{self, ControlRequest};
+use syosetu_rs::cost::Pricing;
+use syosetu_rs::crawl::{CrawlPolicy, HostRule, load_crawl_config};
+use syosetu_rs::crypto::Cipher;
+use syosetu_rs::export::{self, ExportOptions};
+use syosetu_rs::http::{
+    CookieJar, HttpClient, HttpOptions, build_client, import_netscape_cookies, load_cookie_jar,
+    save_cookie_jar,
+};
+use syosetu_rs::kindle::{self, KindleConfig};
+use syosetu_rs::library::{Library, NovelFile, Removal, format_size};
+use syosetu_rs::memory::{
+    BlacklistStore, CategoryStore, CharacterStore, ConflictStore, DiffLine, DirTranslationStore,
+    DriftStore, EngineStore, Example, ExampleScope, ExampleStore, GLOBAL_GLOSSARY, HistoryStore,
+    IllustrationStore, JsonBlacklistStore, JsonCategoryStore, JsonCharacterStore,
+    JsonConflictStore, JsonDriftStore, JsonEngineStore, JsonExampleStore, JsonHistoryStore,
+    JsonIllustrationStore, JsonSettingsStore, JsonSourceHashStore, JsonSourceStore, JsonStampStore,
+    JsonStore, JsonSummaryStore, JsonSuspectStore, JsonTitleStore, JsonTranslationStore,
+    JsonUsageStore, KeywordStore, MergeStrategy, MsgpackTranslationStore, SettingsStore,
+    SourceHashStore, SourceStore, StampStore, SummaryStore, SuspectStore, TitleStore,
+    TranslationStore, UsageStore, blacklist_keyword, diff_lines, merge_glossaries, promote_keyword,
+};
+use syosetu_rs::metrics;
+use syosetu_rs::mining::{DEFAULT_MIN_COUNT, TermMiner};
+use syosetu_rs::paths::{APP_NAME, AppDirs};
+use syosetu_rs::pipeline::{
+    ChapterSource, Pipeline, check_consistency, known_terms, mine_candidates, queue_candidates,
+    replace_in_translations, retranslate_batch, rollback_translation, translate_batch,
+    translate_missing_titles, with_translated_titles,
+};
+use syosetu_rs::server::{self, ServedNovel};
+use syosetu_rs::syosetu::{
+    Chapter, ConfigSite, NOTE_LABELS_JA, NarouAccount, NovelSite, SiteOptions, SiteRegistry,
+    load_site_definitions,
+};
+use syosetu_rs::translate::{
+    CircuitBreaker, DEEPSEEK_API_BASE, DeepLBackend, DeepSeekTranslator, FallbackBackend,
+    GEMINI_API_BASE, GeminiBackend, Honorifics, OLLAMA_API_BASE, OllamaBackend, PromptTemplates,
+    Sampling, TargetLang, TranslationBackend, parse_max_tokens, parse_temperature, parse_top_p,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{Semaphore, mpsc};
+use tokio_util::sync::CancellationToken;
 
 /// 命令行参数定义
 #[derive(Parser, Debug)]
-#[command(author, version, about = "syosetu scraper")]
+#[command(
+    author,
+    version,
+    about = "syosetu scraper",
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    /// Novel index page url
+    #[arg(long, required = true)]
+    url: Option<String>,
+
+    /// API key of the translation service; not needed for Ollama or a local `--api-base` server.
+    /// Prefer `key set <backend>` or DEEPSEEK_API_KEY / GEMINI_API_KEY / DEEPL_AUTH_KEY, which are
+    /// used when this is omitted: keys on the command line end up in shell history and `ps`
+    #[arg(long)]
+    api_key: Option<String>,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Language to translate into (zh, en, ko); translations, titles and glossaries are kept in
+    /// separate files per language, e.g. translations.en.json
+    #[arg(long, global = true, default_value_t = TargetLang::Zh)]
+    target_lang: TargetLang,
+
+    /// Where chapter translations are kept: `json` (translations.json), `msgpack`
+    /// (translations.msgpack, faster to load for large libraries; see `convert-store`) or `dir`
+    /// (one text file per chapter under data/<novel_id>/, better for long novels)
+    #[arg(long, global = true, value_enum, default_value_t = TranslationStoreKind::Json)]
+    translation_store: TranslationStoreKind,
+
+    /// Compress stored translations with zstd (translations.json.zst, or .txt.zst files with
+    /// `--translation-store dir`); existing files are converted on start, and converted back
+    /// when the flag is dropped
+    #[arg(long, global = true, env = "SYOSETU_COMPRESS")]
+    compress: bool,
+
+    /// Encrypt stored translations, translation history, chapter titles, cached source text,
+    /// story summaries, character sheets and glossaries with a key derived from a passphrase,
+    /// read from SYOSETU_PASSPHRASE, the system keyring (`key set passphrase`) or a prompt;
+    /// existing files are converted on start. Other stores (stamps, usage counts, settings,
+    /// examples and the like) stay plain JSON
+    #[arg(long, global = true, env = "SYOSETU_ENCRYPT")]
+    encrypt: bool,
+
+    /// How Japanese honorifics (-san, -chan, -kun, -sama) are rendered: keep, transliterate or
+    /// localize; the rule is added to the prompt and enforced on the saved translation
+    #[arg(long, global = true)]
+    honorifics: Option<Honorifics>,
+
+    /// Do not keep a running story summary; by default it is updated after every chapter (one
+    /// extra request) and added to later prompts to keep names, pronouns and references consistent
+    #[arg(long, global = true)]
+    no_summary: bool,
+
+    /// Do not keep a character sheet (names, genders, speech styles, relationships); by default
+    /// it is updated after every chapter (one extra request) and added to later prompts
+    #[arg(long, global = true)]
+    no_characters: bool,
+
+    /// Paragraphs from the end of the previous chapter (source and cached translation) added to
+    /// the prompt so scenes continue smoothly across chapter breaks; 0 disables
+    #[arg(long, global = true, default_value_t = 3)]
+    context_paragraphs: usize,
+
+    /// Retries with a lower temperature when a translation looks truncated or untranslated (too
+    /// short, leftover kana, ends mid-sentence); chapters still suspect are marked `[?]`
+    #[arg(long, global = true, default_value_t = 1)]
+    suspect_retries: usize,
+
+    /// Have the model proofread each translated chapter against the source and fix omissions and
+    /// mistranslations (one extra request per chapter); `"review"` in settings.json overrides
+    /// this per novel
+    #[arg(long, global = true)]
+    review: bool,
+
+    /// Replace glossary terms in the source with placeholders before translating and put the
+    /// glossary translations back afterwards, so the model cannot rename them
+    #[arg(long, global = true)]
+    lock_terms: bool,
+
+    /// Keep newly extracted glossary terms in a pending list (pending_keywords.json) until they
+    /// are accepted, edited or rejected in the keyword review panel ('K')
+    #[arg(long, global = true)]
+    review_keywords: bool,
+
+    /// Directory of a compiled lindera dictionary (e.g. IPADIC) used to mine proper-noun
+    /// candidates locally; without it only katakana words are counted. Needs the `lindera` feature
+    #[arg(long, global = true)]
+    dictionary: Option<PathBuf>,
+
+    /// Number of chapters translated at the same time, in the TUI and by `serve`; further
+    /// chapters wait in the queue
+    #[arg(long, global = true, default_value_t = DEFAULT_CONCURRENCY)]
+    concurrency: usize,
+
+    /// Directory holding glossaries, translations, cookies, config and cache; defaults to the
+    /// platform directories (XDG data/config/cache dirs on Linux). Files left in the working
+    /// directory by older versions are copied there on first run
+    #[arg(long, global = true, env = "SYOSETU_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Path of the log file (defaults to app.log in the cache directory)
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Serve Prometheus metrics on this address (e.g. 127.0.0.1:9898) while the TUI, `serve` or
+    /// `bot` is running
+    #[arg(long, global = true)]
+    metrics_addr: Option<SocketAddr>,
+
+    /// Accept JSON-RPC control commands on this loopback address (e.g. 127.0.0.1:9899) while the
+    /// TUI or `serve` is running; every request must pass `--control-token` as `params.token`
+    #[arg(long, global = true, requires = "control_token")]
+    control_addr: Option<SocketAddr>,
+
+    /// Token that control socket requests must carry
+    #[arg(
+        long,
+        global = true,
+        env = "SYOSETU_CONTROL_TOKEN",
+        hide_env_values = true
+    )]
+    control_token: Option<String>,
+
+    /// Read Narou tables of contents from api.syosetu.com instead of the HTML pages
+    #[arg(long, global = true)]
+    narou_api: bool,
+
+    /// Attempts per HTTP request (pages and translation APIs) before giving up on network errors,
+    /// 5xx and 429 responses; 1 disables retries
+    #[arg(long, global = true, default_value_t = 3)]
+    retries: u32,
+
+    /// Initial delay in milliseconds before retrying a failed fetch; doubles each attempt
+    #[arg(long, global = true, default_value_t = 1000)]
+    retry_delay_ms: u64,
+
+    /// Minimum interval in milliseconds between requests to the same domain (0 disables)
+    #[arg(long, global = true, default_value_t = 1000)]
+    crawl_delay_ms: u64,
+
+    /// Requests allowed in a burst before the crawl delay applies
+    #[arg(long, global = true, default_value_t = 3)]
+    crawl_burst: u32,
+
+    /// Maximum concurrent requests to the same host (0 disables)
+    #[arg(long, global = true, default_value_t = 2)]
+    max_per_host: usize,
+
+    /// Skip pages that the site's robots.txt disallows
+    #[arg(long, global = true)]
+    respect_robots: bool,
+
+    /// TOML file overriding the crawl delay, burst, concurrency and robots settings per domain
+    /// (defaults to crawl.toml in the config directory)
+    #[arg(long, global = true)]
+    crawl_config: Option<PathBuf>,
+
+    /// Route all site and API requests through this proxy (http://, https:// or socks5://)
+    #[arg(long, global = true, env = "SYOSETU_PROXY")]
+    proxy: Option<String>,
+
+    /// User-Agent sent with every request (defaults to a desktop browser string)
+    #[arg(long, global = true)]
+    user_agent: Option<String>,
+
+    /// Give up on a request after this many seconds, including reading the response
+    #[arg(long, global = true, default_value_t = 300)]
+    timeout_secs: u64,
+
+    /// Give up connecting to a server after this many seconds
+    #[arg(long, global = true, default_value_t = 10)]
+    connect_timeout_secs: u64,
+
+    /// File where site cookies (logins, age confirmations) are kept between runs (defaults to
+    /// cookies.json in the data directory)
+    #[arg(long, global = true)]
+    cookies: Option<PathBuf>,
+
+    /// Directory where site pages are cached and revalidated with ETag/Last-Modified (defaults to
+    /// http_cache in the cache directory)
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Re-download pages instead of revalidating cached copies
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 翻译引擎的配置，界面与各子命令共用
+#[derive(ClapArgs, Debug)]
+struct BackendArgs {
+    /// Translation engine
+    #[arg(long, global = true, value_enum, default_value_t = Backend::Deepseek)]
+    backend: Backend,
+
+    /// Model name (defaults to deepseek-reasoner, qwen2.5 for Ollama, gemini-2.5-flash for Gemini)
+    #[arg(long, global = true)]
+    model: Option<String>,
+
+    /// Base URL of the API: any OpenAI-compatible chat-completions server (OpenRouter, vLLM,
+    /// LM Studio, ...) for the default backend, or the Ollama server (default localhost:11434)
+    #[arg(long, global = true)]
+    api_base: Option<String>,
+
+    /// Extra header sent with every translation request, as `Name: value`; repeatable
+    #[arg(long = "api-header", global = true)]
+    api_headers: Vec<String>,
+
+    /// Gemini safety-filter threshold applied to every category (BLOCK_NONE, BLOCK_ONLY_HIGH,
+    /// BLOCK_MEDIUM_AND_ABOVE, BLOCK_LOW_AND_ABOVE)
+    #[arg(long, global = true, default_value = "BLOCK_NONE")]
+    gemini_safety: String,
+
+    /// DeepSeek API key used to extract glossary terms when translating with DeepL; without it
+    /// DeepL translations add no new terms
+    #[arg(long, global = true, env = "DEEPSEEK_API_KEY", hide_env_values = true)]
+    keyword_api_key: Option<String>,
+
+    /// Backend to retry on when the previous one fails, as `backend[:model]` (e.g. `ollama:qwen2.5`);
+    /// repeatable, tried in order. Fallbacks use their default API base and read their keys from
+    /// DEEPSEEK_API_KEY, GEMINI_API_KEY or DEEPL_AUTH_KEY
+    #[arg(long = "fallback", global = true, value_parser = parse_fallback)]
+    fallbacks: Vec<(Backend, Option<String>)>,
+
+    /// Input price in USD per million tokens (per million characters for DeepL) used for cost
+    /// estimates; defaults to the public price of known models
+    #[arg(long, global = true)]
+    price_input: Option<f64>,
+
+    /// Output price in USD per million tokens used for cost estimates
+    #[arg(long, global = true)]
+    price_output: Option<f64>,
+
+    /// Sampling temperature for chapter and note translation, 0 to 2 (defaults to 1.3); other
+    /// requests keep their fixed temperatures. Per-novel settings take precedence
+    #[arg(long, global = true, value_parser = parse_temperature)]
+    temperature: Option<f64>,
+
+    /// Maximum tokens per reply (defaults to 8192, or the model default for Ollama)
+    #[arg(long, global = true, value_parser = parse_max_tokens)]
+    max_tokens: Option<u32>,
+
+    /// Nucleus sampling threshold, greater than 0 and at most 1; not sent unless set
+    #[arg(long, global = true, value_parser = parse_top_p)]
+    top_p: Option<f64>,
+
+    /// Directory with prompt templates (translate.txt, note.txt, keyword.txt) overriding the
+    /// built-in prompts; placeholders: {glossary}, {text}, {novel_title}, {target_lang},
+    /// {instructions}, and {translation} in keyword.txt. Missing files fall back to the
+    /// built-ins. Defaults to prompts in the config directory
+    #[arg(long, global = true)]
+    prompt_dir: Option<PathBuf>,
+}
+
+/// 解析 `--fallback` 的 `backend[:model]`
+fn parse_fallback(value: &str) -> Result<(Backend, Option<String>), String> {
+    let (name, model) = match value.split_once(':') {
+        Some((name, model)) => (name, Some(model.to_string())),
+        None => (value, None),
+    };
+    let backend = Backend::from_str(name, true)?;
+    Ok((backend, model))
+}
+
+/// 章节译文的存储方式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TranslationStoreKind {
+    /// All chapters in a single translations.json
+    Json,
+    /// All chapters in a single binary translations.msgpack (MessagePack)
+    Msgpack,
+    /// One file per chapter under data/<novel_id>/
+    Dir,
+}
+
+/// 可选的翻译引擎
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Backend {
+    /// DeepSeek or another OpenAI-compatible chat-completions API
+    #[value(alias = "openai")]
+    Deepseek,
+    /// Local Ollama server, no API key needed
+    Ollama,
+    /// Google Gemini (Generative Language API)
+    Gemini,
+    /// DeepL API (free keys ending in `:fx` use the free endpoint)
+    Deepl,
+}
+
+impl Backend {
+    /// 是否需要 API key；本地服务无需认证
+    fn needs_key(self, custom_base: bool) -> bool {
+        match self {
+            Backend::Deepseek => !custom_base,
+            Backend::Ollama => false,
+            Backend::Gemini | Backend::Deepl => true,
+        }
+    }
+
+    /// 未指定 `--model` 时使用的模型
+    fn default_model(self) -> &'static str {
+        match self {
+            Backend::Deepseek => "deepseek-reasoner",
+            Backend::Ollama => "qwen2.5",
+            Backend::Gemini => "gemini-2.5-flash",
+            Backend::Deepl => "deepl",
+        }
+    }
+
+    /// 未指定 `--api-key` 时读取 API key 的环境变量
+    fn key_env(self) -> Option<&'static str> {
+        match self {
+            Backend::Deepseek => Some("DEEPSEEK_API_KEY"),
+            Backend::Ollama => None,
+            Backend::Gemini => Some("GEMINI_API_KEY"),
+            Backend::Deepl => Some("DEEPL_AUTH_KEY"),
+        }
+    }
+
+    /// 系统钥匙串中保存 API key 的条目
+    fn key_name(self) -> Option<KeyName> {
+        match self {
+            Backend::Deepseek => Some(KeyName::Deepseek),
+            Backend::Ollama => None,
+            Backend::Gemini => Some(KeyName::Gemini),
+            Backend::Deepl => Some(KeyName::Deepl),
+        }
+    }
+
+    /// 未在命令行指定时使用的 API key：先取环境变量，再取钥匙串
+    fn stored_key(self) -> Option<String> {
+        self.key_env()
+            .and_then(|name| std::env::var(name).ok())
+            .filter(|key| !key.is_empty())
+            .or_else(|| self.key_name().and_then(keyring_get))
+    }
+}
+
+impl BackendArgs {
+    /// 主引擎的单价，命令行指定的价格优先
+    fn pricing(&self) -> Pricing {
+        let model = match self.backend {
+            Backend::Deepl => Backend::Deepl.default_model(),
+            kind => self.model.as_deref().unwrap_or(kind.default_model()),
+        };
+        let mut pricing = Pricing::for_model(model);
+        if let Some(input) = self.price_input {
+            pricing.input = input;
+        }
+        if let Some(output) = self.price_output {
+            pricing.output = output;
+        }
+        pricing
+    }
+}
+
+/// 不启动界面的子命令
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export cached translations as a static HTML site, updating it incrementally
+    Export(ExportArgs),
+    /// Export one or more novels' glossaries as a Yomitan dictionary
+    Dictionary(DictionaryArgs),
+    /// Export selected chapters as EPUB and email them to a Kindle address
+    Kindle(KindleArgs),
+    /// Run a Telegram bot that serves followed novels and reports new translations
+    Bot(BotArgs),
+    /// Serve a REST API for listing, reading and translating novels
+    Serve(ServeArgs),
+    /// Import cookies from a browser's Netscape cookies.txt export into the cookie file
+    ImportCookies(ImportCookiesArgs),
+    /// Log in to a Narou account or list its bookmarks (read-only: reading progress is not
+    /// written back to Narou)
+    Narou(NarouArgs),
+    /// List, add or remove few-shot translation examples added to prompts
+    Examples(ExamplesArgs),
+    /// Re-translate cached chapters with the current glossary, keeping the previous versions
+    Retranslate(RetranslateArgs),
+    /// List, diff or roll back the previous versions of a chapter translation
+    History(HistoryArgs),
+    /// Import or export a novel's or the global glossary as CSV (TSV for `.tsv`/`.txt` files), or
+    /// promote novel terms to the global glossary, or manage the blacklist of terms never added
+    /// to it
+    Glossary(GlossaryArgs),
+    /// Report cached chapters whose translation lacks the glossary translation of a term found in
+    /// the source, e.g. names translated before they were added to the glossary
+    Check(CheckArgs),
+    /// List frequent katakana words and proper nouns in the source text as glossary candidates,
+    /// without calling the translation API
+    Candidates(CandidatesArgs),
+    /// Convert stored translations between translations.json and translations.msgpack; use the
+    /// result with `--translation-store`
+    ConvertStore(ConvertStoreArgs),
+    /// Save API keys, the store passphrase and the Narou password in the system keyring instead
+    /// of passing them on the command line
+    Key(KeyArgs),
+    /// Write the glossary, translations, summary and settings of some novels, or all stored data
+    /// and configuration, to one compressed archive for moving to another machine
+    Backup(BackupArgs),
+    /// Restore an archive written by `backup`
+    Restore(RestoreArgs),
+    /// Report how much stored data each novel uses, delete a novel's data, or prune records of
+    /// chapters no longer in its table of contents
+    Library(LibraryArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct LibraryArgs {
+    #[command(subcommand)]
+    action: LibraryAction,
+}
+
+/// 按小说整理存储的数据
+#[derive(Subcommand, Debug)]
+enum LibraryAction {
+    /// List novels by the size of their stored data, largest first
+    Usage {
+        /// Also print the size in each store file
+        #[arg(long)]
+        detail: bool,
+    },
+    /// Delete every translation, glossary, summary, setting and cache entry of a novel; only
+    /// counts what would be deleted unless `--apply` is given
+    Remove {
+        novel: String,
+        /// Delete the data
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Delete translations, sources, history and other per-chapter records of chapters that are
+    /// no longer in the novel's table of contents; only counts them unless `--apply` is given
+    Prune {
+        /// Novel index page url
+        #[arg(long)]
+        url: String,
+        /// Delete the records
+        #[arg(long)]
+        apply: bool,
+    },
+}
+
+#[derive(ClapArgs, Debug)]
+struct KeyArgs {
+    #[command(subcommand)]
+    action: KeyAction,
+}
+
+/// 系统钥匙串中的密钥管理
+#[derive(Subcommand, Debug)]
+enum KeyAction {
+    /// Save a secret, typed at a hidden prompt or piped through stdin
+    Set { name: KeyName },
+    /// Remove a saved secret
+    Delete { name: KeyName },
+    /// Show which secrets are saved, without printing them
+    List,
+}
+
+/// 钥匙串中的条目
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyName {
+    /// API key of the DeepSeek backend
+    Deepseek,
+    /// API key of the Gemini backend
+    Gemini,
+    /// Auth key of the DeepL backend
+    Deepl,
+    /// Passphrase of the stores encrypted with `--encrypt`
+    Passphrase,
+    /// Password of the Narou account used by `narou login`
+    Narou,
+}
+
+impl KeyName {
+    /// 钥匙串条目的用户名
+    fn entry(self) -> &'static str {
+        match self {
+            KeyName::Deepseek => "deepseek",
+            KeyName::Gemini => "gemini",
+            KeyName::Deepl => "deepl",
+            KeyName::Passphrase => "passphrase",
+            KeyName::Narou => "narou",
+        }
+    }
+}
+
+/// 读取存储口令的环境变量，优先于钥匙串
+const PASSPHRASE_ENV: &str = "SYOSETU_PASSPHRASE";
+
+/// 读取なろう账号密码的环境变量，优先于钥匙串
+const NAROU_PASSWORD_ENV: &str = "NAROU_PASSWORD";
+
+/// 系统钥匙串中本程序的条目
+fn keyring_entry(name: KeyName) -> Result<keyring::Entry> {
+    Ok(keyring::Entry::new(APP_NAME, name.entry())?)
+}
+
+/// 读取钥匙串中的密钥；不存在或钥匙串不可用时返回 `None`
+fn keyring_get(name: KeyName) -> Option<String> {
+    match keyring_entry(name).map(|entry| entry.get_password()) {
+        Ok(Ok(secret)) => Some(secret),
+        Ok(Err(keyring::Error::NoEntry)) => None,
+        Ok(Err(e)) => {
+            warn!("Reading {} from the keyring failed: {e}", name.entry());
+            None
+        }
+        Err(e) => {
+            warn!("Opening the keyring failed: {e}");
+            None
+        }
+    }
+}
+
+/// 读取密钥：终端中以不回显的提示输入，否则从标准输入读取一行
+fn read_secret(prompt: &str) -> Result<String> {
+    let secret = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(prompt)?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line
+    };
+    let secret = secret.trim().to_string();
+    if secret.is_empty() {
+        bail!("empty secret");
+    }
+    Ok(secret)
+}
+
+/// 加密存储的口令：依次取环境变量、钥匙串，都没有时在终端中询问
+fn store_passphrase() -> Result<String> {
+    if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|v| !v.is_empty()) {
+        return Ok(passphrase);
+    }
+    if let Some(passphrase) = keyring_get(KeyName::Passphrase) {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("--encrypt needs a passphrase: set {PASSPHRASE_ENV} or run `key set passphrase`");
+    }
+    read_secret("Store passphrase: ")
+}
+
+/// なろう账号密码：依次取环境变量、钥匙串，都没有时询问或从标准输入读取
+fn narou_password() -> Result<String> {
+    if let Some(password) = std::env::var(NAROU_PASSWORD_ENV)
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        return Ok(password);
+    }
+    if let Some(password) = keyring_get(KeyName::Narou) {
+        return Ok(password);
+    }
+    read_secret("Narou password: ")
+}
+
+/// 管理钥匙串中的 API key、存储口令与なろう密码
+fn run_key(args: &KeyArgs) -> Result<()> {
+    match &args.action {
+        KeyAction::Set { name } => {
+            let secret = read_secret(&format!("{}: ", name.entry()))?;
+            keyring_entry(*name)?.set_password(&secret)?;
+            println!("Saved {} in the system keyring", name.entry());
+        }
+        KeyAction::Delete { name } => match keyring_entry(*name)?.delete_credential() {
+            Ok(()) => println!("Removed {} from the system keyring", name.entry()),
+            Err(keyring::Error::NoEntry) => println!("{} is not saved", name.entry()),
+            Err(e) => return Err(e.into()),
+        },
+        KeyAction::List => {
+            for name in KeyName::value_variants() {
+                let saved = keyring_get(*name).is_some();
+                println!(
+                    "{}\t{}",
+                    name.entry(),
+                    if saved { "saved" } else { "not saved" }
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(ClapArgs, Debug)]
+struct BackupArgs {
+    /// Output zip file
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Novel id to back up; repeat for several novels. Without it every file of the data and
+    /// config directories is archived, except cookies, locks and caches
+    #[arg(long = "novel")]
+    novels: Vec<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct RestoreArgs {
+    /// Archive written by `backup`
+    file: PathBuf,
+
+    /// Overwrite existing files when restoring a full backup; novel backups are always merged
+    /// into the current stores, keeping replaced translations in the history
+    #[arg(long)]
+    force: bool,
+}
+
+/// 按小说统计、删除或清理存储的数据
+async fn run_library(args: &LibraryArgs, registry: &SiteRegistry, stores: &Stores) -> Result<()> {
+    let print_removal = |removal: &Removal, apply: bool| {
+        for (store, count) in removal {
+            println!("{store}: {count}");
+        }
+        let total: usize = removal.values().sum();
+        if apply {
+            println!("Deleted {total} records");
+        } else {
+            println!("{total} records, pass --apply to delete them");
+        }
+    };
+    match &args.action {
+        LibraryAction::Usage { detail } => {
+            let novels = stores.library.usage().await?;
+            if novels.is_empty() {
+                println!("No stored novels");
+            }
+            for novel in &novels {
+                println!(
+                    "{}\t{} chapters\t{}",
+                    novel.novel_id,
+                    novel.chapters,
+                    format_size(novel.bytes)
+                );
+                if *detail {
+                    for (store, usage) in &novel.stores {
+                        println!(
+                            "  {store}: {} records, {}",
+                            usage.entries,
+                            format_size(usage.bytes)
+                        );
+                    }
+                }
+            }
+            let total: u64 = novels.iter().map(|novel| novel.bytes).sum();
+            println!("Total {}", format_size(total));
+        }
+        LibraryAction::Remove { novel, apply } => {
+            let removal = stores.library.remove(novel, *apply).await?;
+            if removal.is_empty() {
+                bail!("nothing is stored for novel {novel}");
+            }
+            print_removal(&removal, *apply);
+        }
+        LibraryAction::Prune { url, apply } => {
+            let site = registry.find(url)?;
+            let toc = site.fetch_directory(url, &CancellationToken::new()).await?;
+            let chapters: HashSet<String> = toc.into_iter().map(|c| c.path).collect();
+            let removal = stores
+                .library
+                .prune(&novel_id_from_url(url), &chapters, *apply)
+                .await?;
+            print_removal(&removal, *apply);
+        }
+    }
+    Ok(())
+}
+
+/// 将指定小说或全部数据写为备份文件
+async fn run_backup(args: &BackupArgs, stores: &Stores, dirs: &AppDirs) -> Result<()> {
+    let backup = if args.novels.is_empty() {
+        Backup::from_dirs(&dirs.data, &dirs.config)?
+    } else {
+        let mut backup = Backup::default();
+        for novel in &args.novels {
+            let novel = NovelBackup::collect(stores.backup(), novel).await?;
+            if novel.is_empty() {
+                bail!("nothing is stored for novel {}", novel.novel_id);
+            }
+            backup.novels.push(novel);
+        }
+        backup
+    };
+    backup.write(&args.out)?;
+    for novel in &backup.novels {
+        println!(
+            "{}: {} terms, {} chapters",
+            novel.novel_id,
+            novel.keywords.len(),
+            novel.translations.len()
+        );
+    }
+    if !backup.files.is_empty() {
+        println!("Archived {} files", backup.files.len());
+    }
+    println!("Wrote {}", args.out.display());
+    Ok(())
+}
+
+/// 恢复备份文件：小说条目并入存储，数据文件写回数据目录与配置目录
+async fn run_restore(args: &RestoreArgs, stores: &Stores, dirs: &AppDirs) -> Result<()> {
+    let backup = Backup::read(&args.file)?;
+    for novel in &backup.novels {
+        let chapters = novel.restore(stores.backup()).await?;
+        println!(
+            "{}: restored {} terms, {chapters} chapters",
+            novel.novel_id,
+            novel.keywords.len()
+        );
+    }
+    if !backup.files.is_empty() {
+        let (written, kept) = backup.restore_files(&dirs.data, &dirs.config, args.force)?;
+        println!("Restored {} files", written.len());
+        if !kept.is_empty() {
+            println!(
+                "Kept {} existing files; use --force to overwrite:",
+                kept.len()
+            );
+            for path in kept {
+                println!("  {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(ClapArgs, Debug)]
+struct ConvertStoreArgs {
+    /// Format to convert into (`json` or `msgpack`); the other single-file store is read and
+    /// left in place
+    #[arg(value_enum)]
+    to: TranslationStoreKind,
+}
+
+#[derive(ClapArgs, Debug)]
+struct CandidatesArgs {
+    /// Novel index page url
+    #[arg(long)]
+    url: String,
+
+    /// Chapters to scan, e.g. `1-10,15` (defaults to all cached chapters)
+    #[arg(long)]
+    chapters: Option<String>,
+
+    /// Only list words found at least this many times
+    #[arg(long, default_value_t = DEFAULT_MIN_COUNT)]
+    min_count: usize,
+
+    /// Add the candidates to the keyword review list ('K') with the original as placeholder
+    /// translation
+    #[arg(long)]
+    queue: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+struct CheckArgs {
+    /// Novel index page url
+    #[arg(long)]
+    url: String,
+
+    /// Chapters to check, e.g. `1-10,15` (defaults to all cached chapters)
+    #[arg(long)]
+    chapters: Option<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct GlossaryArgs {
+    /// Novel id whose glossary is managed; required unless `--global` is given or merging
+    #[arg(long)]
+    novel: Option<String>,
+
+    /// Manage the global glossary shared by all novels; a novel's own entries override it
+    #[arg(long, conflicts_with = "novel")]
+    global: bool,
+
+    #[command(subcommand)]
+    action: GlossaryAction,
+}
+
+/// 专有名词表的导入导出
+#[derive(Subcommand, Debug)]
+enum GlossaryAction {
+    /// Write the glossary with a `japanese,translated` header, for editing in a spreadsheet
+    Export { file: PathBuf },
+    /// Add terms from the first two columns of a file; other columns and a header row are
+    /// ignored
+    Import {
+        file: PathBuf,
+        /// Keep the current translation of terms that already exist instead of overwriting it
+        #[arg(long)]
+        keep_existing: bool,
+    },
+    /// Move terms from the novel's glossary to the global one
+    Promote {
+        #[arg(required = true)]
+        terms: Vec<String>,
+    },
+    /// List, add or remove terms that are never added to the glossary; global ones apply to all
+    /// novels
+    Blacklist {
+        #[command(subcommand)]
+        action: BlacklistAction,
+    },
+    /// Copy the glossary and term categories of one novel into another, e.g. from a novel into
+    /// its sequel or spin-off
+    Merge {
+        /// Novel id to copy terms from
+        from: String,
+        /// Novel id to copy terms into
+        into: String,
+        /// What to do with terms translated differently in both: keep the target's translation,
+        /// overwrite it, or keep it and list the term in the conflicts panel ('X')
+        #[arg(long, default_value = "review")]
+        strategy: MergeStrategy,
+    },
+    /// Replace an old translation of a term with a new one in all cached chapters; only lists
+    /// the affected chapters unless `--apply` is given
+    Replace {
+        old: String,
+        new: String,
+        /// Rewrite the chapters; the previous translations are kept in the history
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Print terms by how often they appear in the source of translated chapters, most frequent
+    /// first
+    Usage {
+        /// Print at most this many terms
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+}
+
+/// 屏蔽词的管理
+#[derive(Subcommand, Debug)]
+enum BlacklistAction {
+    /// Print the blacklisted terms
+    List,
+    /// Blacklist terms and remove them from the glossary
+    Add {
+        #[arg(required = true)]
+        terms: Vec<String>,
+    },
+    /// Allow terms to be extracted again
+    Remove {
+        #[arg(required = true)]
+        terms: Vec<String>,
+    },
+}
+
+#[derive(ClapArgs, Debug)]
+struct RetranslateArgs {
+    /// Novel index page url
+    #[arg(long)]
+    url: String,
+
+    /// Chapters to re-translate, e.g. `1-10,15` (defaults to all cached chapters); chapters that
+    /// were never translated are skipped
+    #[arg(long)]
+    chapters: Option<String>,
+
+    /// API key of the translation service; defaults to the key stored with `key set <backend>` or
+    /// the backend's environment variable (DEEPSEEK_API_KEY / GEMINI_API_KEY / DEEPL_AUTH_KEY)
+    #[arg(long)]
+    api_key: Option<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct HistoryArgs {
+    /// Novel index page url
+    #[arg(long)]
+    url: String,
+
+    /// Chapter number as shown in the directory (starting at 1)
+    #[arg(long)]
+    chapter: usize,
+
+    #[command(subcommand)]
+    action: HistoryAction,
+}
+
+/// 译文历史相关操作
+#[derive(Subcommand, Debug)]
+enum HistoryAction {
+    /// Print the previous versions with their numbers, oldest first
+    List,
+    /// Show the changes from a previous version to the current translation
+    Diff {
+        /// Version to compare (defaults to the most recent one)
+        version: Option<usize>,
+    },
+    /// Restore a previous version; the current translation is kept in the history
+    #[command(alias = "revert")]
+    Rollback { version: usize },
+}
+
+#[derive(ClapArgs, Debug)]
+struct ExamplesArgs {
+    /// Novel id whose own examples are managed
+    #[arg(long, global = true, conflicts_with = "genre")]
+    novel: Option<String>,
+
+    /// Genre whose examples are managed; novels pick one with `genre` in settings.json
+    #[arg(long, global = true)]
+    genre: Option<String>,
+
+    #[command(subcommand)]
+    action: ExamplesAction,
+}
+
+/// 翻译示例库相关操作
+#[derive(Subcommand, Debug)]
+enum ExamplesAction {
+    /// Print the examples with their numbers
+    List,
+    /// Add a source paragraph and its ideal translation
+    Add {
+        /// Japanese source paragraph
+        #[arg(long)]
+        source: String,
+        /// Translation to imitate
+        #[arg(long)]
+        translation: String,
+    },
+    /// Remove the example with this number (as shown by `list`)
+    Remove { number: usize },
+}
+
+#[derive(ClapArgs, Debug)]
+struct NarouArgs {
+    #[command(subcommand)]
+    action: NarouAction,
+}
+
+/// なろう账号相关操作
+#[derive(Subcommand, Debug)]
+enum NarouAction {
+    /// Log in and keep the session in the cookie file; the password is read from NAROU_PASSWORD,
+    /// the system keyring (`key set narou`) or a hidden prompt
+    Login {
+        /// Narou ID or email address
+        #[arg(long)]
+        id: String,
+    },
+    /// Print bookmarked novels as tab-separated url, title and bookmarked episode (しおり);
+    /// `bot --follow-bookmarks` follows the same list
+    Bookmarks,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ImportCookiesArgs {
+    /// Netscape-format cookies.txt exported from a browser
+    file: PathBuf,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: SocketAddr,
+
+    /// Novel index page url to serve; repeat for several novels
+    #[arg(long = "novel", required = true)]
+    novels: Vec<String>,
+
+    /// API key of the translation service; defaults to the key stored with `key set <backend>` or
+    /// the backend's environment variable (DEEPSEEK_API_KEY / GEMINI_API_KEY / DEEPL_AUTH_KEY)
+    #[arg(long)]
+    api_key: Option<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct BotArgs {
+    /// Telegram bot token
+    #[arg(long, env = "TELEGRAM_BOT_TOKEN", hide_env_values = true)]
+    token: String,
+
+    /// Chat id to answer and notify; messages from any other chat are ignored
+    #[arg(long, env = "TELEGRAM_CHAT_ID")]
+    chat_id: i64,
+
+    /// Novel index page url to follow; repeat for several novels
+    #[arg(long = "follow", required_unless_present = "follow_bookmarks")]
+    follow: Vec<String>,
+
+    /// Also follow the novels bookmarked in the Narou account (see `narou login`)
+    #[arg(long)]
+    follow_bookmarks: bool,
+
+    /// Seconds between checks for new translations
+    #[arg(long, default_value_t = 60)]
+    poll_secs: u64,
+}
+
+/// 导出格式
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    /// Static HTML site, updated incrementally
+    Html,
+    /// Single EPUB 3 file
+    Epub,
+    /// mdBook project, build it with `mdbook build`
+    Mdbook,
+}
+
+#[derive(ClapArgs, Debug)]
+struct KindleArgs {
     /// Novel index page url
     #[arg(long)]
     url: String,
 
-    /// DeepSeek API key
+    /// Chapters to send, e.g. `1-10,15` (defaults to all cached chapters)
+    #[arg(long)]
+    chapters: Option<String>,
+
+    /// Typeset vertically (tategaki) with right-to-left page progression
     #[arg(long)]
-    api_key: String,
+    vertical: bool,
+
+    /// Drop furigana instead of rendering it as ruby
+    #[arg(long)]
+    strip_furigana: bool,
+
+    /// Translate selected chapters (and chapter titles) that are not cached yet before sending;
+    /// without it only cached translations are sent and the translation API is never called
+    #[arg(long)]
+    translate_missing: bool,
+
+    /// API key used with `--translate-missing`; defaults to the key stored with
+    /// `key set <backend>` or the backend's environment variable
+    #[arg(long, requires = "translate_missing")]
+    api_key: Option<String>,
+
+    /// Kindle address (xxx@kindle.com)
+    #[arg(long, env = "KINDLE_TO")]
+    to: String,
+
+    /// Sender address approved in the Amazon account
+    #[arg(long, env = "SMTP_FROM")]
+    from: String,
+
+    /// SMTP server (STARTTLS)
+    #[arg(long, env = "SMTP_HOST")]
+    smtp_host: String,
 
-    /// Model name used when calling DeepSeek API
-    #[arg(long, default_value = "deepseek-reasoner")]
-    model: String,
+    /// SMTP port
+    #[arg(long, env = "SMTP_PORT", default_value_t = 587)]
+    smtp_port: u16,
+
+    /// SMTP user name
+    #[arg(long, env = "SMTP_USER")]
+    smtp_user: String,
+
+    /// SMTP password
+    #[arg(long, env = "SMTP_PASSWORD", hide_env_values = true)]
+    smtp_password: String,
+}
+
+#[derive(ClapArgs, Debug)]
+struct DictionaryArgs {
+    /// Novel id whose glossary is exported; repeat to merge a series
+    #[arg(long = "novel", required = true)]
+    novels: Vec<String>,
+
+    /// Output zip file
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Dictionary title shown in Yomitan
+    #[arg(long)]
+    title: Option<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ExportArgs {
+    /// Novel index page url
+    #[arg(long)]
+    url: String,
+
+    /// Output directory (html, mdbook) or file (epub)
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Html)]
+    format: ExportFormat,
+
+    /// Typeset vertically (tategaki) with right-to-left page progression
+    #[arg(long)]
+    vertical: bool,
+
+    /// Drop furigana instead of rendering it as ruby
+    #[arg(long)]
+    strip_furigana: bool,
+
+    /// Keep the original chapter titles instead of cached translations
+    #[arg(long)]
+    original_titles: bool,
+
+    /// Put the Japanese text next to the translation (mdbook only); `--vertical` also applies
+    /// to these pages
+    #[arg(long)]
+    bilingual: bool,
+}
+
+/// 从目录页地址推导小说 id，`show.php?id=123` 形式的地址取 `id` 参数
+fn novel_id_from_url(url: &str) -> String {
+    let last = url
+        .trim_end_matches('/')
+        .split('/')
+        .next_back()
+        .unwrap_or("novel");
+    match last.split_once("id=") {
+        Some((_, id)) if last.contains('?') => id.split('&').next().unwrap_or(id).to_string(),
+        _ => last.to_string(),
+    }
+}
+
+/// 构建站点注册表：配置目录下 `sites_dir`（`sites.d/`）中的自定义站点优先，其后为内置站点；
+/// pixiv 的登录 Cookie 从环境变量 `PIXIV_PHPSESSID` 读取；请求经过 `policy`，网络错误按 `http` 中的设置重试
+fn build_registry(
+    narou_api: bool,
+    sites_dir: &Path,
+    http: &HttpOptions,
+    client: &HttpClient,
+    policy: CrawlPolicy,
+) -> Result<SiteRegistry> {
+    let mut registry = SiteRegistry::new().with_crawl_policy(policy);
+    let definitions = load_site_definitions(sites_dir)?;
+    if !definitions.is_empty() {
+        info!("Loaded {} site definitions from sites.d", definitions.len());
+    }
+    for definition in definitions {
+        registry.register(Arc::new(
+            ConfigSite::new(definition)?.with_client(registry.crawl_client(client.clone())),
+        ));
+    }
+    registry.register_builtin(
+        &SiteOptions {
+            pixiv_session: std::env::var("PIXIV_PHPSESSID").ok(),
+            narou_api,
+            http: http.clone(),
+        },
+        client.clone(),
+    );
+    Ok(registry)
 }
 
+/// 按命令行配置使用共享的 HTTP 客户端创建翻译器，配置了 `--fallback` 时组成备用链
+fn build_translator(
+    api_key: Option<String>,
+    backend: &BackendArgs,
+    client: &HttpClient,
+) -> Result<Arc<dyn TranslationBackend>> {
+    let primary = build_backend(
+        backend.backend,
+        backend.model.clone(),
+        api_key,
+        backend.api_base.as_deref(),
+        &backend.api_headers,
+        backend,
+        client,
+    )?;
+    if backend.fallbacks.is_empty() {
+        return Ok(with_breaker(primary));
+    }
+    let mut chain = vec![primary];
+    for (kind, model) in &backend.fallbacks {
+        chain.push(build_backend(
+            *kind,
+            model.clone(),
+            None,
+            None,
+            &[],
+            backend,
+            client,
+        )?);
+    }
+    Ok(with_breaker(Arc::new(FallbackBackend::new(chain))))
+}
+
+/// 连续失败多少次后熔断
+const BREAKER_THRESHOLD: u32 = 5;
+
+/// 熔断后的冷却时间
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// 为翻译器加上熔断，服务持续失败时暂停发送请求
+fn with_breaker(translator: Arc<dyn TranslationBackend>) -> Arc<dyn TranslationBackend> {
+    Arc::new(CircuitBreaker::new(
+        translator,
+        BREAKER_THRESHOLD,
+        BREAKER_COOLDOWN,
+    ))
+}
+
+/// 创建单个翻译引擎
+fn build_backend(
+    kind: Backend,
+    model: Option<String>,
+    api_key: Option<String>,
+    api_base: Option<&str>,
+    api_headers: &[String],
+    backend: &BackendArgs,
+    client: &HttpClient,
+) -> Result<Arc<dyn TranslationBackend>> {
+    let templates = match &backend.prompt_dir {
+        Some(dir) => PromptTemplates::load(dir)?,
+        None => PromptTemplates::default(),
+    };
+    if kind == Backend::Ollama {
+        let model = model.unwrap_or(kind.default_model().to_string());
+        let base = api_base.unwrap_or(OLLAMA_API_BASE);
+        return Ok(Arc::new(
+            OllamaBackend::new(model)
+                .with_client(client.clone())
+                .with_api_base(base)
+                .with_templates(templates),
+        ));
+    }
+    let api_key = match api_key.or_else(|| kind.stored_key()) {
+        Some(key) => key,
+        None if !kind.needs_key(api_base.is_some()) => String::new(),
+        None => bail!(
+            "an API key is required for the {kind:?} backend; save it with `key set {}`",
+            kind.key_name().map_or("", KeyName::entry)
+        ),
+    };
+    if kind == Backend::Deepl {
+        let mut deepl = DeepLBackend::new(api_key).with_client(client.clone());
+        if let Some(base) = api_base {
+            deepl = deepl.with_api_base(base);
+        }
+        let keyword_key = backend
+            .keyword_api_key
+            .clone()
+            .or_else(|| keyring_get(KeyName::Deepseek));
+        if let Some(key) = keyword_key {
+            let model = model.unwrap_or("deepseek-chat".to_string());
+            let extractor = DeepSeekTranslator::new(key, model)
+                .with_client(client.clone())
+                .with_templates(templates);
+            deepl = deepl.with_keyword_extractor(Arc::new(extractor));
+        }
+        return Ok(Arc::new(deepl));
+    }
+    if kind == Backend::Gemini {
+        let model = model.unwrap_or(kind.default_model().to_string());
+        let base = api_base.unwrap_or(GEMINI_API_BASE);
+        return Ok(Arc::new(
+            GeminiBackend::new(api_key, model)
+                .with_client(client.clone())
+                .with_api_base(base)
+                .with_safety_threshold(&backend.gemini_safety)
+                .with_templates(templates),
+        ));
+    }
+    let model = model.unwrap_or(kind.default_model().to_string());
+    let base = api_base.unwrap_or(DEEPSEEK_API_BASE);
+    let mut translator = DeepSeekTranslator::new(api_key, model)
+        .with_client(client.clone())
+        .with_api_base(base)
+        .with_templates(templates);
+    for header in api_headers {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| anyhow!("invalid --api-header `{header}`, expected `Name: value`"))?;
+        translator = translator.with_header(name.trim(), value.trim());
+    }
+    Ok(Arc::new(translator))
+}
+
+/// 登录なろう账号或列出其ブックマーク
+async fn run_narou(args: &NarouArgs, client: &HttpClient, cookies: Arc<CookieJar>) -> Result<()> {
+    let account = NarouAccount::new(client.clone(), cookies);
+    let cancel = CancellationToken::new();
+    match &args.action {
+        NarouAction::Login { id } => {
+            account.login(id, &narou_password()?, &cancel).await?;
+            println!("Logged in to Narou");
+        }
+        NarouAction::Bookmarks => {
+            for bookmark in account.bookmarks(&cancel).await? {
+                let shiori = bookmark.shiori.map(|n| n.to_string()).unwrap_or_default();
+                println!("{}\t{}\t{shiori}", bookmark.url, bookmark.title);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 管理小说或题材的翻译示例
+fn run_examples(args: &ExamplesArgs, store: &dyn ExampleStore) -> Result<()> {
+    let scope = match (&args.novel, &args.genre) {
+        (Some(novel), _) => ExampleScope::Novel(novel.clone()),
+        (None, Some(genre)) => ExampleScope::Genre(genre.clone()),
+        (None, None) => bail!("either --novel or --genre is required"),
+    };
+    let mut examples = store.load(&scope)?;
+    match &args.action {
+        ExamplesAction::List => {
+            for (i, example) in examples.iter().enumerate() {
+                println!(
+                    "#{}\n{}\n---\n{}\n",
+                    i + 1,
+                    example.source,
+                    example.translation
+                );
+            }
+        }
+        ExamplesAction::Add {
+            source,
+            translation,
+        } => {
+            examples.push(Example {
+                source: source.trim().to_string(),
+                translation: translation.trim().to_string(),
+            });
+            store.save(&scope, &examples)?;
+            println!("Added example #{}", examples.len());
+        }
+        ExamplesAction::Remove { number } => {
+            if *number == 0 || *number > examples.len() {
+                bail!("no example #{number}, there are {}", examples.len());
+            }
+            examples.remove(number - 1);
+            store.save(&scope, &examples)?;
+            println!("Removed example #{number}");
+        }
+    }
+    Ok(())
+}
+
+/// 使用当前的专有名词表重新翻译已缓存的章节
+async fn run_retranslate(
+    args: &RetranslateArgs,
+    registry: &SiteRegistry,
+    backend: &BackendArgs,
+    client: &HttpClient,
+    stores: &Stores,
+) -> Result<()> {
+    let novel_id = novel_id_from_url(&args.url);
+    let site = registry.find(&args.url)?;
+    let cancel = CancellationToken::new();
+    let all = site.fetch_directory(&args.url, &cancel).await?;
+    let title = novel_title(site.as_ref(), &args.url, &novel_id).await;
+    let chapters: Vec<Chapter> = match &args.chapters {
+        Some(spec) => export::parse_chapter_ranges(spec, all.len())?
+            .into_iter()
+            .map(|i| all[i].clone())
+            .collect(),
+        None => all,
+    };
+    let translator = build_translator(args.api_key.clone(), backend, client)?;
+    let pipeline = stores.pipeline(site, translator);
+    let count = retranslate_batch(&pipeline, &novel_id, &title, &chapters, &cancel).await?;
+    println!("Re-translated {count} chapters");
+    Ok(())
+}
+
+/// 检查已缓存章节是否使用了专有名词表的译名，列出不一致的章节
+async fn run_check(args: &CheckArgs, registry: &SiteRegistry, stores: &Stores) -> Result<()> {
+    let novel_id = novel_id_from_url(&args.url);
+    let site = registry.find(&args.url)?;
+    let cancel = CancellationToken::new();
+    let all = site.fetch_directory(&args.url, &cancel).await?;
+    let numbered: Vec<(usize, Chapter)> = match &args.chapters {
+        Some(spec) => export::parse_chapter_ranges(spec, all.len())?
+            .into_iter()
+            .map(|i| (i, all[i].clone()))
+            .collect(),
+        None => all.into_iter().enumerate().collect(),
+    };
+    let chapters: Vec<Chapter> = numbered.iter().map(|(_, c)| c.clone()).collect();
+    let report = check_consistency(
+        ChapterSource {
+            site: site.as_ref(),
+            cache: stores.source.as_ref(),
+        },
+        stores.kw.as_ref(),
+        stores.trans.as_ref(),
+        stores.drift.as_ref(),
+        &novel_id,
+        &chapters,
+        &cancel,
+    )
+    .await?;
+    for item in &report {
+        let number = numbered
+            .iter()
+            .find(|(_, c)| c.path == item.path)
+            .map_or(0, |(i, _)| i + 1);
+        let terms: Vec<String> = item
+            .terms
+            .iter()
+            .map(|(jp, tr)| format!("{jp} → {tr}"))
+            .collect();
+        println!("#{number} {}: {}", item.title, terms.join(", "));
+    }
+    println!(
+        "{} chapters use a different translation for glossary terms",
+        report.len()
+    );
+    Ok(())
+}
+
+/// 在本地统计原文中的专有名词候选，可放入待审核列表
+async fn run_candidates(
+    args: &CandidatesArgs,
+    dictionary: Option<&Path>,
+    registry: &SiteRegistry,
+    stores: &Stores,
+) -> Result<()> {
+    let novel_id = novel_id_from_url(&args.url);
+    let site = registry.find(&args.url)?;
+    let cancel = CancellationToken::new();
+    let all = site.fetch_directory(&args.url, &cancel).await?;
+    let chapters: Vec<Chapter> = match &args.chapters {
+        Some(spec) => export::parse_chapter_ranges(spec, all.len())?
+            .into_iter()
+            .map(|i| all[i].clone())
+            .collect(),
+        None => {
+            let cached = stores.trans.list(&novel_id).await?;
+            all.into_iter()
+                .filter(|c| cached.contains(&c.path))
+                .collect()
+        }
+    };
+    let miner = TermMiner::new(dictionary)?;
+    let known = known_terms(
+        stores.kw.as_ref(),
+        stores.pending.as_ref(),
+        stores.blacklist.as_ref(),
+        &novel_id,
+    )
+    .await?;
+    let candidates = mine_candidates(
+        ChapterSource {
+            site: site.as_ref(),
+            cache: stores.source.as_ref(),
+        },
+        &novel_id,
+        &miner,
+        &chapters,
+        &known,
+        args.min_count,
+        &cancel,
+    )
+    .await?;
+    for candidate in &candidates {
+        println!("{}\t{}", candidate.count, candidate.term);
+    }
+    if args.queue {
+        let queued = queue_candidates(stores.pending.as_ref(), &novel_id, &candidates).await?;
+        println!("Queued {queued} candidates for review");
+    } else {
+        println!(
+            "{} candidates in {} chapters",
+            candidates.len(),
+            chapters.len()
+        );
+    }
+    Ok(())
+}
+
+/// 列出、比较或回滚章节的旧译文
+async fn run_history(args: &HistoryArgs, registry: &SiteRegistry, stores: &Stores) -> Result<()> {
+    let novel_id = novel_id_from_url(&args.url);
+    let site = registry.find(&args.url)?;
+    let chapters = site
+        .fetch_directory(&args.url, &CancellationToken::new())
+        .await?;
+    let Some(chapter) = args.chapter.checked_sub(1).and_then(|i| chapters.get(i)) else {
+        bail!("no chapter {}, there are {}", args.chapter, chapters.len());
+    };
+    let revisions = stores.history.load(&novel_id, &chapter.path)?;
+    match &args.action {
+        HistoryAction::List => {
+            if revisions.is_empty() {
+                println!("No previous versions of {}", chapter.title);
+            }
+            for (i, revision) in revisions.iter().enumerate() {
+                let saved_at = UNIX_EPOCH + Duration::from_secs(revision.saved_at);
+                println!(
+                    "#{}  {}  {} chars",
+                    i + 1,
+                    httpdate::fmt_http_date(saved_at),
+                    revision.text.chars().count()
+                );
+            }
+        }
+        HistoryAction::Diff { version } => {
+            let number = version.unwrap_or(revisions.len());
+            let Some(revision) = number.checked_sub(1).and_then(|i| revisions.get(i)) else {
+                bail!("no version #{number}, there are {}", revisions.len());
+            };
+            let current = stores
+                .trans
+                .load(&novel_id, &chapter.path)
+                .await?
+                .unwrap_or_default();
+            for line in diff_lines(&revision.text, &current) {
+                match line {
+                    DiffLine::Same(text) => println!("  {text}"),
+                    DiffLine::Removed(text) => println!("- {text}"),
+                    DiffLine::Added(text) => println!("+ {text}"),
+                }
+            }
+        }
+        HistoryAction::Rollback { version } => {
+            if *version == 0 {
+                bail!("versions start at 1");
+            }
+            rollback_translation(
+                stores.trans.as_ref(),
+                stores.history.as_ref(),
+                &novel_id,
+                &chapter.path,
+                version - 1,
+            )
+            .await?;
+            println!("Restored version #{version} of {}", chapter.title);
+        }
+    }
+    Ok(())
+}
+
+/// 在 JSON 与 MessagePack 两种单文件格式之间转换章节译文，原文件保留
+async fn run_convert_store(
+    args: &ConvertStoreArgs,
+    json: &JsonTranslationStore,
+    msgpack: &MsgpackTranslationStore,
+) -> Result<()> {
+    let all = match args.to {
+        TranslationStoreKind::Json => {
+            let all = msgpack.load_all().await?;
+            if all.is_empty() {
+                bail!("no translations in the msgpack store");
+            }
+            json.save_all(&all).await?;
+            all
+        }
+        TranslationStoreKind::Msgpack => {
+            let all = json.load_all().await?;
+            if all.is_empty() {
+                bail!("no translations in the json store");
+            }
+            msgpack.save_all(&all).await?;
+            all
+        }
+        TranslationStoreKind::Dir => bail!("only the json and msgpack stores can be converted"),
+    };
+    let chapters: usize = all.values().map(HashMap::len).sum();
+    println!(
+        "Converted {chapters} chapters of {} novels; run with `--translation-store {}`",
+        all.len(),
+        args.to
+            .to_possible_value()
+            .expect("no skipped variants")
+            .get_name()
+    );
+    Ok(())
+}
+
+/// 将浏览器导出的 cookies.txt 合并进 Cookie 文件
+fn run_import_cookies(args: &ImportCookiesArgs, jar: &CookieJar, path: &Path) -> Result<()> {
+    let text = std::fs::read_to_string(&args.file)?;
+    let imported = import_netscape_cookies(jar, &text)?;
+    save_cookie_jar(jar, path)?;
+    println!("Imported {imported} cookies into {}", path.display());
+    Ok(())
+}
+
+/// 合并指定小说的专有名词表并导出为 Yomitan 词典
+async fn run_dictionary(args: &DictionaryArgs, kw_store: &dyn KeywordStore) -> Result<()> {
+    let mut keywords = BTreeMap::new();
+    for novel in &args.novels {
+        for (jp, zh) in kw_store.load(novel).await? {
+            keywords.entry(jp).or_insert(zh);
+        }
+    }
+    let title = args.title.clone().unwrap_or_else(|| args.novels.join(", "));
+    export::export_yomitan(&args.out, &title, &keywords)?;
+    println!(
+        "Exported {} terms to {}",
+        keywords.len(),
+        args.out.display()
+    );
+    Ok(())
+}
+
+/// 导入或导出专有名词表，或管理屏蔽词
+async fn run_glossary(args: &GlossaryArgs, stores: &Stores) -> Result<()> {
+    let (kw_store, blacklist) = (stores.kw.as_ref(), stores.blacklist.as_ref());
+    let novel = match (&args.novel, args.global, &args.action) {
+        (Some(novel), ..) => novel.as_str(),
+        (None, true, _) => GLOBAL_GLOSSARY,
+        // 合并时两部小说都在参数中指定
+        (None, false, GlossaryAction::Merge { .. }) => "",
+        (None, false, _) => bail!("pass --novel or --global"),
+    };
+    match &args.action {
+        GlossaryAction::Export { file } => {
+            let count = export::export_glossary(kw_store, novel, file).await?;
+            println!("Exported {count} terms to {}", file.display());
+        }
+        GlossaryAction::Import {
+            file,
+            keep_existing,
+        } => {
+            let (added, changed) =
+                export::import_glossary(kw_store, novel, file, *keep_existing).await?;
+            println!("Imported {added} new terms and updated {changed}");
+        }
+        GlossaryAction::Promote { terms } => {
+            if args.global {
+                bail!("promote moves terms from a novel, pass --novel");
+            }
+            for term in terms {
+                if promote_keyword(kw_store, novel, term).await? {
+                    println!("Promoted {term} to the global glossary");
+                } else {
+                    println!("{term} is not in the glossary of {novel}");
+                }
+            }
+        }
+        GlossaryAction::Merge {
+            from,
+            into,
+            strategy,
+        } => {
+            let report = merge_glossaries(
+                kw_store,
+                stores.categories.as_ref(),
+                stores.conflicts.as_ref(),
+                from,
+                into,
+                *strategy,
+            )
+            .await?;
+            let (source, target) = (kw_store.load(from).await?, kw_store.load(into).await?);
+            for jp in &report.conflicts {
+                println!("{jp}: {} (kept) ↔ {}", target[jp], source[jp]);
+            }
+            println!(
+                "Added {} terms, overwrote {}, {} translated differently",
+                report.added,
+                report.overwritten,
+                report.conflicts.len()
+            );
+        }
+        GlossaryAction::Replace { old, new, apply } => {
+            if args.global {
+                bail!("cached translations belong to a novel, pass --novel");
+            }
+            let affected = replace_in_translations(
+                stores.trans.as_ref(),
+                stores.history.as_ref(),
+                kw_store,
+                novel,
+                old,
+                new,
+                *apply,
+            )
+            .await?;
+            for (path, count) in &affected {
+                println!("{path}: {count}");
+            }
+            let total: usize = affected.iter().map(|(_, count)| count).sum();
+            if *apply {
+                println!(
+                    "Replaced {total} occurrences in {} chapters",
+                    affected.len()
+                );
+            } else {
+                println!(
+                    "{total} occurrences in {} chapters, pass --apply to replace them",
+                    affected.len()
+                );
+            }
+        }
+        GlossaryAction::Usage { limit } => {
+            if args.global {
+                bail!("usage is counted per novel, pass --novel");
+            }
+            let usage = stores.usage.load(novel)?;
+            let mut terms: Vec<(usize, String, String)> = kw_store
+                .load(novel)
+                .await?
+                .into_iter()
+                .map(|(jp, zh)| (usage.get(&jp).copied().unwrap_or_default(), jp, zh))
+                .collect();
+            terms.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+            for (count, jp, zh) in terms.into_iter().take(limit.unwrap_or(usize::MAX)) {
+                println!("{count}\t{jp}\t{zh}");
+            }
+        }
+        GlossaryAction::Blacklist { action } => match action {
+            BlacklistAction::List => {
+                for term in blacklist.load(novel)? {
+                    println!("{term}");
+                }
+            }
+            BlacklistAction::Add { terms } => {
+                for term in terms {
+                    if blacklist_keyword(kw_store, blacklist, novel, term).await? {
+                        println!("Blacklisted {term} and removed it from the glossary");
+                    } else {
+                        println!("Blacklisted {term}");
+                    }
+                }
+            }
+            BlacklistAction::Remove { terms } => {
+                for term in terms {
+                    if !blacklist.remove(novel, term)? {
+                        println!("{term} is not blacklisted");
+                    }
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+/// 各命令共享的本地存储
+struct Stores {
+    kw: Arc<dyn KeywordStore>,
+    pending: Arc<dyn KeywordStore>,
+    categories: Arc<dyn CategoryStore>,
+    blacklist: Arc<dyn BlacklistStore>,
+    conflicts: Arc<dyn ConflictStore>,
+    usage: Arc<dyn UsageStore>,
+    review_keywords: bool,
+    trans: Arc<dyn TranslationStore>,
+    source: Arc<dyn SourceStore>,
+    hashes: Arc<dyn SourceHashStore>,
+    history: Arc<dyn HistoryStore>,
+    title: Arc<dyn TitleStore>,
+    stamp: Arc<dyn StampStore>,
+    illust: Arc<dyn IllustrationStore>,
+    engine: Arc<dyn EngineStore>,
+    drift: Arc<dyn DriftStore>,
+    suspect: Arc<dyn SuspectStore>,
+    suspect_retries: usize,
+    settings: Arc<dyn SettingsStore>,
+    examples: Arc<dyn ExampleStore>,
+    review: bool,
+    lock_terms: bool,
+    lang: TargetLang,
+    honorifics: Option<Honorifics>,
+    sampling: Sampling,
+    summary: Arc<dyn SummaryStore>,
+    rolling_summary: bool,
+    previous_paragraphs: usize,
+    characters: Arc<dyn CharacterStore>,
+    track_characters: bool,
+    library: Arc<Library>,
+}
+
+impl Stores {
+    /// 备份与恢复单部小说用到的存储
+    fn backup(&self) -> BackupStores<'_> {
+        BackupStores {
+            keywords: self.kw.as_ref(),
+            translations: self.trans.as_ref(),
+            history: self.history.as_ref(),
+            summaries: self.summary.as_ref(),
+            settings: self.settings.as_ref(),
+        }
+    }
+
+    /// 以指定站点与翻译器组装处理流水线
+    fn pipeline(
+        &self,
+        site: Arc<dyn NovelSite>,
+        translator: Arc<dyn TranslationBackend>,
+    ) -> Pipeline {
+        Pipeline {
+            site,
+            translator,
+            kw_store: self.kw.clone(),
+            pending_store: self.pending.clone(),
+            category_store: self.categories.clone(),
+            blacklist_store: self.blacklist.clone(),
+            conflict_store: self.conflicts.clone(),
+            usage_store: self.usage.clone(),
+            review_keywords: self.review_keywords,
+            trans_store: self.trans.clone(),
+            source_store: self.source.clone(),
+            hash_store: self.hashes.clone(),
+            history_store: self.history.clone(),
+            title_store: self.title.clone(),
+            stamp_store: self.stamp.clone(),
+            illust_store: self.illust.clone(),
+            engine_store: self.engine.clone(),
+            drift_store: self.drift.clone(),
+            suspect_store: self.suspect.clone(),
+            suspect_retries: self.suspect_retries,
+            settings_store: self.settings.clone(),
+            example_store: self.examples.clone(),
+            review: self.review,
+            lock_terms: self.lock_terms,
+            target_lang: self.lang,
+            honorifics: self.honorifics,
+            sampling: self.sampling,
+            summary_store: self.summary.clone(),
+            rolling_summary: self.rolling_summary,
+            previous_paragraphs: self.previous_paragraphs,
+            character_store: self.characters.clone(),
+            track_characters: self.track_characters,
+            library: self.library.clone(),
+        }
+    }
+}
+
+/// 导出时使用的作品名，站点未提供时使用小说 id
+async fn novel_title(site: &dyn NovelSite, url: &str, novel_id: &str) -> String {
+    match site.fetch_info(url, &CancellationToken::new()).await {
+        Ok(info) if !info.title.is_empty() => info.title,
+        Ok(_) => novel_id.to_string(),
+        Err(e) => {
+            error!("Failed to fetch novel info: {e:?}");
+            novel_id.to_string()
+        }
+    }
+}
+
+/// 抓取目录并将已缓存的译文导出到指定目录
+async fn run_export(args: &ExportArgs, registry: &SiteRegistry, stores: &Stores) -> Result<()> {
+    let trans_store = stores.trans.as_ref();
+    let novel_id = novel_id_from_url(&args.url);
+    let site = registry.find(&args.url)?;
+    let mut chapters = site
+        .fetch_directory(&args.url, &CancellationToken::new())
+        .await?;
+    let title = novel_title(site.as_ref(), &args.url, &novel_id).await;
+    if !args.original_titles {
+        chapters = with_translated_titles(&chapters, &stores.title.load(&novel_id)?);
+    }
+    let options = ExportOptions {
+        vertical: args.vertical,
+        strip_furigana: args.strip_furigana,
+        illustrations: stores.illust.load(&novel_id)?,
+        lang: stores.lang,
+    };
+    match args.format {
+        ExportFormat::Html => {
+            let report = export::export_html(
+                &args.out,
+                &title,
+                &novel_id,
+                &chapters,
+                trans_store,
+                &options,
+            )
+            .await?;
+            println!(
+                "Exported to {}: {} written, {} unchanged, {} removed",
+                args.out.display(),
+                report.written,
+                report.unchanged,
+                report.removed
+            );
+        }
+        ExportFormat::Epub => {
+            let count = export::export_epub(
+                &args.out,
+                &title,
+                &novel_id,
+                &chapters,
+                trans_store,
+                &options,
+            )
+            .await?;
+            println!("Exported {count} chapters to {}", args.out.display());
+        }
+        ExportFormat::Mdbook => {
+            let sources = if args.bilingual {
+                // 已翻译章节的原文优先取自缓存
+                let cancel = CancellationToken::new();
+                let source = ChapterSource {
+                    site: site.as_ref(),
+                    cache: stores.source.as_ref(),
+                };
+                let mut sources = HashMap::new();
+                for chapter in &chapters {
+                    if trans_store.load(&novel_id, &chapter.path).await?.is_some() {
+                        let sections = source.fetch(&novel_id, &chapter.path, &cancel).await?;
+                        sources.insert(chapter.path.clone(), sections.join(NOTE_LABELS_JA));
+                    }
+                }
+                Some(sources)
+            } else {
+                None
+            };
+            let count = export::export_mdbook(
+                &args.out,
+                &title,
+                &novel_id,
+                &chapters,
+                trans_store,
+                sources.as_ref(),
+                &options,
+            )
+            .await?;
+            println!("Exported {count} chapters to {}", args.out.display());
+        }
+    }
+    Ok(())
+}
+
+/// 按需翻译所选章节，导出 EPUB 并发送到 Kindle
+async fn run_kindle(
+    args: &KindleArgs,
+    registry: &SiteRegistry,
+    backend: &BackendArgs,
+    client: &HttpClient,
+    stores: &Stores,
+) -> Result<()> {
+    let novel_id = novel_id_from_url(&args.url);
+    let site = registry.find(&args.url)?;
+    let cancel = CancellationToken::new();
+    let all = site.fetch_directory(&args.url, &cancel).await?;
+    let title = novel_title(site.as_ref(), &args.url, &novel_id).await;
+    let mut chapters: Vec<Chapter> = match &args.chapters {
+        Some(spec) => export::parse_chapter_ranges(spec, all.len())?
+            .into_iter()
+            .map(|i| all[i].clone())
+            .collect(),
+        None => {
+            let cached = stores.trans.list(&novel_id).await?;
+            all.into_iter()
+                .filter(|c| cached.contains(&c.path))
+                .collect()
+        }
+    };
+    if args.translate_missing {
+        let translator = build_translator(args.api_key.clone(), backend, client)?;
+        let pipeline = stores.pipeline(site, translator);
+        let count = translate_batch(&pipeline, &novel_id, &title, &chapters, &cancel).await?;
+        println!("Translated {count} chapters");
+        let ctx = pipeline
+            .context(
+                stores.kw.load(&novel_id).await?.into_iter().collect(),
+                &title,
+            )
+            .await;
+        translate_missing_titles(&pipeline, &novel_id, &chapters, &ctx, &cancel).await?;
+    }
+    chapters = with_translated_titles(&chapters, &stores.title.load(&novel_id)?);
+    let book = std::env::temp_dir().join(format!("{novel_id}.epub"));
+    let count = export::export_epub(
+        &book,
+        &title,
+        &novel_id,
+        &chapters,
+        stores.trans.as_ref(),
+        &ExportOptions {
+            vertical: args.vertical,
+            strip_furigana: args.strip_furigana,
+            illustrations: stores.illust.load(&novel_id)?,
+            lang: stores.lang,
+        },
+    )
+    .await?;
+    let config = KindleConfig {
+        smtp_host: args.smtp_host.clone(),
+        smtp_port: args.smtp_port,
+        username: args.smtp_user.clone(),
+        password: args.smtp_password.clone(),
+        from: args.from.clone(),
+        to: args.to.clone(),
+    };
+    kindle::send_to_kindle(&config, &novel_id, &book).await?;
+    let _ = std::fs::remove_file(&book);
+    println!("Sent {count} chapters to {}", args.to);
+    Ok(())
+}
+
+/// 启动 REST API 服务，收到 Ctrl-C 时退出
+async fn run_serve(
+    args: &ServeArgs,
+    registry: &SiteRegistry,
+    backend: &BackendArgs,
+    client: &HttpClient,
+    stores: &Stores,
+    slots: Arc<Semaphore>,
+    control: Option<UnboundedReceiver<ControlRequest>>,
+) -> Result<()> {
+    let translator = build_translator(args.api_key.clone(), backend, client)?;
+    let novels = args
+        .novels
+        .iter()
+        .map(|url| {
+            Ok(ServedNovel {
+                url: url.clone(),
+                novel_id: novel_id_from_url(url),
+                pipeline: stores.pipeline(registry.find(url)?, translator.clone()),
+            })
+        })
+        .collect::<Result<_>>()?;
+    let cancel = CancellationToken::new();
+    let on_signal = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_signal.cancel();
+        }
+    });
+    server::serve(args.addr, novels, slots, control, cancel).await
+}
+
+/// 启动 Telegram 机器人，收到 Ctrl-C 时退出
+async fn run_bot(
+    args: &BotArgs,
+    registry: &SiteRegistry,
+    client: &HttpClient,
+    cookies: Arc<CookieJar>,
+    stores: &Stores,
+) -> Result<()> {
+    let mut urls = args.follow.clone();
+    if args.follow_bookmarks {
+        let account = NarouAccount::new(client.clone(), cookies);
+        for bookmark in account.bookmarks(&CancellationToken::new()).await? {
+            if !urls.contains(&bookmark.url) {
+                urls.push(bookmark.url);
+            }
+        }
+        info!("Following {} novels including Narou bookmarks", urls.len());
+    }
+    let novels = urls
+        .iter()
+        .map(|url| {
+            Ok(FollowedNovel {
+                url: url.clone(),
+                novel_id: novel_id_from_url(url),
+                site: registry.find(url)?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let config = BotConfig {
+        token: args.token.clone(),
+        allowed_chat: args.chat_id,
+        poll_interval: Duration::from_secs(args.poll_secs),
+        client: client.clone(),
+    };
+    let cancel = CancellationToken::new();
+    let on_signal = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_signal.cancel();
+        }
+    });
+    Bot::new(config, novels, stores.trans.clone(), stores.title.clone())?
+        .run(cancel)
+        .await
+}
+
+/// Windows 控制台默认使用本地代码页，切换到 UTF-8 以正确显示日文与中文
+#[cfg(windows)]
+fn enable_utf8_console() {
+    use windows_sys::Win32::System::Console::{SetConsoleCP, SetConsoleOutputCP};
+    const CP_UTF8: u32 = 65001;
+    unsafe {
+        SetConsoleCP(CP_UTF8);
+        SetConsoleOutputCP(CP_UTF8);
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_utf8_console() {}
+
 /// 解析参数并启动应用
 #[tokio::main]
 async fn main() -> Result<()> {
+    let mut args = Args::parse();
+    enable_utf8_console();
+    let dirs = match &args.data_dir {
+        Some(dir) => AppDirs::single(dir),
+        None => AppDirs::platform()?,
+    };
+    // 旧版本的文件在工作目录中，须在打开日志前复制过来
+    let migrated = dirs.prepare(Path::new("."))?;
+    let log_path = args
+        .log_file
+        .clone()
+        .unwrap_or_else(|| dirs.cache.join("app.log"));
+    if let Some(dir) = log_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
     let log_file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open("app.log")?;
+        .open(&log_path)?;
     Builder::from_default_env()
         .filter_level(LevelFilter::Info)
         .target(Target::Pipe(Box::new(log_file)))
         .init();
-    let args = Args::parse();
-    let novel_id = args
-        .url
-        .trim_end_matches('/')
-        .split('/')
-        .last()
-        .unwrap_or("novel")
-        .to_string();
-
-    let translator = Translator::new(args.api_key, args.model);
-    let site: Box<dyn NovelSite> = if args.url.contains("syosetu.org") {
-        Box::new(OrgSite::new())
+    for path in &migrated {
+        info!("Copied {} from the working directory", path.display());
+    }
+    if let Some(Command::Key(key_args)) = &args.command {
+        return run_key(key_args);
+    }
+    let cookie_path = args
+        .cookies
+        .clone()
+        .unwrap_or_else(|| dirs.data.join("cookies.json"));
+    args.backend
+        .prompt_dir
+        .get_or_insert_with(|| dirs.config.join("prompts"));
+    let cookies = Arc::new(load_cookie_jar(&cookie_path)?);
+    let defaults = HttpOptions::default();
+    let http = HttpOptions {
+        user_agent: args.user_agent.clone().unwrap_or(defaults.user_agent),
+        proxy: args.proxy.clone(),
+        timeout: Duration::from_secs(args.timeout_secs),
+        connect_timeout: Duration::from_secs(args.connect_timeout_secs),
+        cookies: Some(cookies.clone()),
+        cache_dir: Some(
+            args.cache_dir
+                .clone()
+                .unwrap_or_else(|| dirs.cache.join("http_cache")),
+        ),
+        refresh: args.refresh,
+        // 站点与翻译接口的请求只在客户端中间件里重试一层
+        max_retries: args.retries.max(1) - 1,
+        retry_delay: Duration::from_millis(args.retry_delay_ms),
+        ..defaults
+    };
+    // 站点、翻译器与机器人共用同一个客户端（连接池、Cookie 与缓存）
+    let client = build_client(&http)?;
+    let crawl_config = args
+        .crawl_config
+        .clone()
+        .unwrap_or_else(|| dirs.config.join("crawl.toml"));
+    let policy = CrawlPolicy::new(HostRule {
+        delay: Duration::from_millis(args.crawl_delay_ms),
+        burst: args.crawl_burst,
+        max_concurrent: args.max_per_host,
+        robots: args.respect_robots,
+    })
+    .with_overrides(&load_crawl_config(&crawl_config)?)
+    .with_robots_client(client.clone());
+    let registry = build_registry(
+        args.narou_api,
+        &dirs.config.join("sites.d"),
+        &http,
+        &client,
+        policy,
+    )?;
+    // 译文相关的存储按目标语言分开，插图地址来自原文，各语言共用
+    let lang = args.target_lang;
+    let data = |file: &str| dirs.data.join(lang.store_path(file));
+    let shared = |file: &str| dirs.data.join(file);
+    let cipher = if args.encrypt {
+        Some(Arc::new(Cipher::open(&dirs.data, &store_passphrase()?)?))
     } else {
-        Box::new(NcodeSite::new())
+        None
+    };
+    let converted = |count: usize| {
+        if count > 0 {
+            info!(
+                "Converted {count} translation files (compress: {}, encrypt: {})",
+                args.compress, args.encrypt
+            );
+        }
+    };
+    let trans: Arc<dyn TranslationStore> = match args.translation_store {
+        TranslationStoreKind::Json => {
+            let store = JsonTranslationStore::new(data("translations.json"))
+                .with_compression(args.compress)
+                .with_encryption(cipher.clone());
+            converted(store.migrate().await? as usize);
+            Arc::new(store)
+        }
+        TranslationStoreKind::Msgpack => {
+            let store = MsgpackTranslationStore::new(data("translations.msgpack"))
+                .with_compression(args.compress)
+                .with_encryption(cipher.clone());
+            converted(store.migrate().await? as usize);
+            Arc::new(store)
+        }
+        TranslationStoreKind::Dir => {
+            let store = DirTranslationStore::new(data("data"))
+                .with_compression(args.compress)
+                .with_encryption(cipher.clone());
+            converted(store.migrate().await?);
+            Arc::new(store)
+        }
+    };
+    // 按小说保存数据的存储文件，须与下面各存储使用的文件（及其加密设置）一致
+    let sealed = |path: PathBuf| NovelFile::new(path).with_encryption(cipher.clone());
+    let library = [
+        sealed(data("keywords.json")),
+        sealed(data("pending_keywords.json")),
+        NovelFile::new(shared("categories.json")),
+        NovelFile::new(shared("blacklist.json")),
+        sealed(data("conflicts.json")),
+        NovelFile::new(shared("usage.json")).by_chapter(),
+        sealed(shared("sources.json")).by_chapter(),
+        NovelFile::new(data("source_hashes.json")).by_chapter(),
+        sealed(data("history.json")).by_chapter(),
+        sealed(data("titles.json")).by_chapter(),
+        NovelFile::new(data("stamps.json")).by_chapter(),
+        NovelFile::new(shared("illustrations.json")).by_chapter(),
+        NovelFile::new(data("engines.json")).by_chapter(),
+        NovelFile::new(data("drift.json")).by_chapter(),
+        NovelFile::new(data("suspects.json")).by_chapter(),
+        NovelFile::new(shared("settings.json")),
+        NovelFile::new(data("examples.json")).in_section("novels"),
+        sealed(data("summaries.json")),
+        sealed(data("characters.json")),
+    ]
+    .into_iter()
+    .fold(Library::new(trans.clone()), Library::with_file);
+    let migrated = library.migrate()?;
+    if migrated > 0 {
+        info!(
+            "Converted {migrated} store files (encrypt: {})",
+            args.encrypt
+        );
+    }
+    let stores = Stores {
+        kw: Arc::new(JsonStore::new(data("keywords.json")).with_encryption(cipher.clone())),
+        pending: Arc::new(
+            JsonStore::new(data("pending_keywords.json")).with_encryption(cipher.clone()),
+        ),
+        categories: Arc::new(JsonCategoryStore::new(shared("categories.json"))),
+        blacklist: Arc::new(JsonBlacklistStore::new(shared("blacklist.json"))),
+        conflicts: Arc::new(
+            JsonConflictStore::new(data("conflicts.json")).with_encryption(cipher.clone()),
+        ),
+        usage: Arc::new(JsonUsageStore::new(shared("usage.json"))),
+        review_keywords: args.review_keywords,
+        trans,
+        source: Arc::new(
+            JsonSourceStore::new(shared("sources.json")).with_encryption(cipher.clone()),
+        ),
+        hashes: Arc::new(JsonSourceHashStore::new(data("source_hashes.json"))),
+        history: Arc::new(
+            JsonHistoryStore::new(data("history.json")).with_encryption(cipher.clone()),
+        ),
+        title: Arc::new(JsonTitleStore::new(data("titles.json")).with_encryption(cipher.clone())),
+        stamp: Arc::new(JsonStampStore::new(data("stamps.json"))),
+        illust: Arc::new(JsonIllustrationStore::new(shared("illustrations.json"))),
+        engine: Arc::new(JsonEngineStore::new(data("engines.json"))),
+        drift: Arc::new(JsonDriftStore::new(data("drift.json"))),
+        suspect: Arc::new(JsonSuspectStore::new(data("suspects.json"))),
+        suspect_retries: args.suspect_retries,
+        settings: Arc::new(JsonSettingsStore::new(shared("settings.json"))),
+        examples: Arc::new(JsonExampleStore::new(data("examples.json"))),
+        review: args.review,
+        lock_terms: args.lock_terms,
+        lang,
+        honorifics: args.honorifics,
+        sampling: Sampling {
+            temperature: args.backend.temperature,
+            max_tokens: args.backend.max_tokens,
+            top_p: args.backend.top_p,
+        },
+        summary: Arc::new(
+            JsonSummaryStore::new(data("summaries.json")).with_encryption(cipher.clone()),
+        ),
+        rolling_summary: !args.no_summary,
+        previous_paragraphs: args.context_paragraphs,
+        characters: Arc::new(
+            JsonCharacterStore::new(data("characters.json")).with_encryption(cipher.clone()),
+        ),
+        track_characters: !args.no_characters,
+        library: Arc::new(library),
     };
-    let store = JsonStore::new("keywords.json");
-    let trans_store = JsonTranslationStore::new("translations.json");
-    let app = App::new(novel_id);
+    // 界面与 `serve` 共用的翻译名额
+    let slots = Arc::new(Semaphore::new(args.concurrency.max(1)));
+    // 长时间运行的模式结束时关闭指标等后台服务
+    let shutdown = CancellationToken::new();
+    let long_running = matches!(
+        args.command,
+        None | Some(Command::Serve(_) | Command::Bot(_))
+    );
+    if let Some(addr) = args.metrics_addr.filter(|_| long_running) {
+        let cancel = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr, cancel).await {
+                error!("Metrics server error: {:?}", e);
+            }
+        });
+    }
+    let controlled = matches!(args.command, None | Some(Command::Serve(_)));
+    let mut control = None;
+    if let Some(addr) = args.control_addr.filter(|_| controlled) {
+        control::check_addr(addr)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let token = args.control_token.clone().unwrap_or_default();
+        let cancel = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(addr, token, tx, cancel).await {
+                error!("Control socket error: {:?}", e);
+            }
+        });
+        control = Some(rx);
+    }
+    if let Some(command) = &args.command {
+        let result = match command {
+            Command::Export(export_args) => run_export(export_args, &registry, &stores).await,
+            Command::Dictionary(dict_args) => run_dictionary(dict_args, stores.kw.as_ref()).await,
+            Command::Kindle(kindle_args) => {
+                run_kindle(kindle_args, &registry, &args.backend, &client, &stores).await
+            }
+            Command::Bot(bot_args) => {
+                run_bot(bot_args, &registry, &client, cookies.clone(), &stores).await
+            }
+            Command::Serve(serve_args) => {
+                run_serve(
+                    serve_args,
+                    &registry,
+                    &args.backend,
+                    &client,
+                    &stores,
+                    slots.clone(),
+                    control.take(),
+                )
+                .await
+            }
+            Command::ImportCookies(import_args) => {
+                run_import_cookies(import_args, &cookies, &cookie_path)
+            }
+            Command::Narou(narou_args) => run_narou(narou_args, &client, cookies.clone()).await,
+            Command::Examples(examples_args) => {
+                run_examples(examples_args, stores.examples.as_ref())
+            }
+            Command::Retranslate(retranslate_args) => {
+                run_retranslate(retranslate_args, &registry, &args.backend, &client, &stores).await
+            }
+            Command::History(history_args) => run_history(history_args, &registry, &stores).await,
+            Command::Check(check_args) => run_check(check_args, &registry, &stores).await,
+            Command::Candidates(candidates_args) => {
+                run_candidates(
+                    candidates_args,
+                    args.dictionary.as_deref(),
+                    &registry,
+                    &stores,
+                )
+                .await
+            }
+            Command::Glossary(glossary_args) => run_glossary(glossary_args, &stores).await,
+            Command::ConvertStore(convert_args) => {
+                let json = JsonTranslationStore::new(data("translations.json"))
+                    .with_compression(args.compress)
+                    .with_encryption(cipher.clone());
+                let msgpack = MsgpackTranslationStore::new(data("translations.msgpack"))
+                    .with_compression(args.compress)
+                    .with_encryption(cipher.clone());
+                run_convert_store(convert_args, &json, &msgpack).await
+            }
+            Command::Backup(backup_args) => run_backup(backup_args, &stores, &dirs).await,
+            Command::Restore(restore_args) => run_restore(restore_args, &stores, &dirs).await,
+            Command::Library(library_args) => run_library(library_args, &registry, &stores).await,
+            Command::Key(_) => unreachable!("key commands run before the stores are opened"),
+        };
+        // 保存站点在本次运行中设置的 Cookie
+        shutdown.cancel();
+        let result = result.and(save_cookie_jar(&cookies, &cookie_path));
+        if let Err(ref e) = result {
+            error!("Command error: {:?}", e);
+        }
+        return result;
+    }
+
+    // 未指定子命令时 clap 保证 url 已提供
+    let url = args.url.expect("url is required");
+    let api_key = args.api_key;
+    let novel_id = novel_id_from_url(&url);
+    let translator = build_translator(api_key, &args.backend, &client)?;
+    let pipeline = stores.pipeline(registry.find(&url)?, translator);
+    let mut app = App::new(novel_id)
+        .with_slots(slots)
+        .with_pricing(args.backend.pricing())
+        .with_term_miner(TermMiner::new(args.dictionary.as_deref())?);
+    if let Some(rx) = control {
+        app = app.with_control(rx);
+    }
     let result = app
-        .run(&args.url, site.as_ref(), &translator, &store, &trans_store)
-        .await;
+        .run(&url, pipeline)
+        .await
+        .and(save_cookie_jar(&cookies, &cookie_path));
+    shutdown.cancel();
     if let Err(ref e) = result {
         error!("Application error: {:?}", e);
     }
@@ -1,45 +1,201 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
 
-/// 用于持久化保存专有名词翻译表的抽象接口
+use crate::crypto::Cipher;
+use crate::syosetu::ChapterSections;
+use crate::translate::{
+    KeywordCategory, Sampling, StylePreset, parse_max_tokens, parse_temperature, parse_top_p,
+};
+
+/// 等待其他进程释放存储文件锁的最长时间
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 重试加锁的间隔
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// 跨进程的存储文件锁：在 `<path>.lock` 上加排他的建议锁并写入本进程的 PID。
+/// 界面与批量任务等多个实例同时运行时，据此串行化对同一存储文件的读改写，
+/// 避免一方的写入覆盖另一方；drop 时释放
+pub struct FileLock {
+    file: fs::File,
+}
+
+impl FileLock {
+    /// 尝试加锁，锁已被其他进程（或本进程的其他句柄）持有时返回 `None`
+    pub fn try_acquire(path: &Path) -> Result<Option<FileLock>> {
+        let lock_path = Self::lock_path(path);
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .with_context(|| format!("failed to open {}", lock_path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(fs::TryLockError::WouldBlock) => return Ok(None),
+            Err(fs::TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("failed to lock {}", lock_path.display()));
+            }
+        }
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(Some(FileLock { file }))
+    }
+
+    /// 加锁，锁被持有时等待，超过 [`LOCK_TIMEOUT`] 仍未释放则报错。同步存储的保存也会在
+    /// 异步任务中调用（如标题、历史与摘要），此时把等待移出执行器线程，以免其他任务停住
+    pub fn acquire(path: &Path) -> Result<FileLock> {
+        if let Some(lock) = Self::try_acquire(path)? {
+            return Ok(lock);
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| Self::wait(path))
+            }
+            _ => Self::wait(path),
+        }
+    }
+
+    /// 阻塞当前线程等待锁释放
+    fn wait(path: &Path) -> Result<FileLock> {
+        let start = Instant::now();
+        loop {
+            if let Some(lock) = Self::try_acquire(path)? {
+                return Ok(lock);
+            }
+            if start.elapsed() >= LOCK_TIMEOUT {
+                return Err(Self::busy(path));
+            }
+            std::thread::sleep(LOCK_RETRY);
+        }
+    }
+
+    /// 同 [`Self::acquire`]，等待时不阻塞执行器
+    pub async fn acquire_async(path: &Path) -> Result<FileLock> {
+        let start = Instant::now();
+        loop {
+            if let Some(lock) = Self::try_acquire(path)? {
+                return Ok(lock);
+            }
+            if start.elapsed() >= LOCK_TIMEOUT {
+                return Err(Self::busy(path));
+            }
+            tokio::time::sleep(LOCK_RETRY).await;
+        }
+    }
+
+    /// 锁文件的路径
+    fn lock_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".lock");
+        PathBuf::from(name)
+    }
+
+    /// 等待超时的错误，附上锁文件中记录的持有者 PID
+    fn busy(path: &Path) -> anyhow::Error {
+        let pid = fs::read_to_string(Self::lock_path(path))
+            .map(|pid| pid.trim().to_string())
+            .unwrap_or_default();
+        let holder = if pid.is_empty() {
+            "another syosetu-rs process".to_string()
+        } else {
+            format!("another syosetu-rs process (pid {pid})")
+        };
+        anyhow!(
+            "{} is locked by {holder}; wait for it to finish or close it and try again",
+            path.display()
+        )
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// 用于持久化保存专有名词翻译表的抽象接口；读写是异步的，大文件的写入不会阻塞界面与执行器
+#[async_trait]
 pub trait KeywordStore: Send + Sync {
     /// 读取指定小说的翻译表
-    fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
-    /// 保存翻译表
-    fn save(&self, novel_id: &str, keywords: &HashMap<String, String>) -> Result<()>;
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
+    /// 保存翻译表，已有词条保持不变
+    async fn save(&self, novel_id: &str, keywords: &HashMap<String, String>) -> Result<()>;
+    /// 新增或覆盖单个词条
+    async fn set(&self, novel_id: &str, jp: &str, zh: &str) -> Result<()>;
+    /// 删除单个词条，返回词条是否存在
+    async fn remove(&self, novel_id: &str, jp: &str) -> Result<bool>;
+    /// 底层文件的修改时间，界面据此发现外部编辑；不基于文件的实现返回 `None`
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
 }
 
-/// 将翻译表存储为 JSON 文件
-pub struct JsonStore {
-    path: PathBuf,
+/// 全局专有名词表在 [`KeywordStore`] 中使用的小说 id；翻译时全局词条垫在各小说的
+/// 词条之下，同一原文以小说自己的译名为准
+pub const GLOBAL_GLOSSARY: &str = "_global";
+
+/// 将小说中的词条移到全局专有名词表，词条不存在时返回 `false`
+pub async fn promote_keyword(
+    kw_store: &dyn KeywordStore,
+    novel_id: &str,
+    jp: &str,
+) -> Result<bool> {
+    let Some(zh) = kw_store.load(novel_id).await?.remove(jp) else {
+        return Ok(false);
+    };
+    kw_store.set(GLOBAL_GLOSSARY, jp, &zh).await?;
+    kw_store.remove(novel_id, jp).await
 }
 
-/// 缓存章节翻译内容的接口
-pub trait TranslationStore: Send + Sync {
-    /// 读取指定章节的翻译内容
-    fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<String>>;
-    /// 保存章节翻译
-    fn save(&self, novel_id: &str, chapter: &str, text: &str) -> Result<()>;
-    /// 列出所有已缓存章节路径
-    fn list(&self, novel_id: &str) -> Result<Vec<String>>;
+/// 将词条加入屏蔽列表并从对应的专有名词表中删除，返回词条原本是否在表中
+pub async fn blacklist_keyword(
+    kw_store: &dyn KeywordStore,
+    blacklist: &dyn BlacklistStore,
+    novel_id: &str,
+    jp: &str,
+) -> Result<bool> {
+    blacklist.add(novel_id, jp)?;
+    kw_store.remove(novel_id, jp).await
 }
 
-/// 简单的 JSON 文件实现，用于保存章节翻译
-pub struct JsonTranslationStore {
+/// 保存屏蔽词的接口：屏蔽词不会被加入专有名词表，与目标语言无关；
+/// 全局屏蔽词同样保存在小说 id 为 [`GLOBAL_GLOSSARY`] 的条目下
+pub trait BlacklistStore: Send + Sync {
+    /// 读取指定小说的屏蔽词，按原文排序
+    fn load(&self, novel_id: &str) -> Result<Vec<String>>;
+    /// 加入屏蔽词，返回是否新加入
+    fn add(&self, novel_id: &str, jp: &str) -> Result<bool>;
+    /// 移除屏蔽词，返回屏蔽词是否存在
+    fn remove(&self, novel_id: &str, jp: &str) -> Result<bool>;
+}
+
+/// 将屏蔽词存储为 JSON 文件
+pub struct JsonBlacklistStore {
     path: PathBuf,
 }
 
-impl JsonTranslationStore {
-    /// 创建一个新的 JSON 翻译存储
+impl JsonBlacklistStore {
+    /// 创建一个新的 JSON 屏蔽词存储
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        JsonTranslationStore { path: path.into() }
+        JsonBlacklistStore { path: path.into() }
     }
 
-    /// 读取整个文件并解析为嵌套的 HashMap
-    fn read_all(&self) -> HashMap<String, HashMap<String, String>> {
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> HashMap<String, Vec<String>> {
         if let Ok(content) = fs::read_to_string(&self.path) {
             serde_json::from_str(&content).unwrap_or_default()
         } else {
@@ -47,22 +203,238 @@ impl JsonTranslationStore {
         }
     }
 
-    /// 将内存中的数据写回文件
-    fn write_all(&self, data: &HashMap<String, HashMap<String, String>>) -> Result<()> {
-        let s = serde_json::to_string_pretty(data)?;
-        fs::write(&self.path, s)?;
+    /// 写回全部数据
+    fn write_all(&self, data: &HashMap<String, Vec<String>>) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(data)?)?;
         Ok(())
     }
 }
 
-impl JsonStore {
-    /// 创建一个新的 JSON 存储
+impl BlacklistStore for JsonBlacklistStore {
+    fn load(&self, novel_id: &str) -> Result<Vec<String>> {
+        Ok(self.read_all().remove(novel_id).unwrap_or_default())
+    }
+
+    fn add(&self, novel_id: &str, jp: &str) -> Result<bool> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all();
+        let terms = all.entry(novel_id.to_string()).or_default();
+        let Err(idx) = terms.binary_search_by(|t| t.as_str().cmp(jp)) else {
+            return Ok(false);
+        };
+        terms.insert(idx, jp.to_string());
+        self.write_all(&all)?;
+        Ok(true)
+    }
+
+    fn remove(&self, novel_id: &str, jp: &str) -> Result<bool> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all();
+        let Some(terms) = all.get_mut(novel_id) else {
+            return Ok(false);
+        };
+        let Some(idx) = terms.iter().position(|t| t == jp) else {
+            return Ok(false);
+        };
+        terms.remove(idx);
+        if terms.is_empty() {
+            all.remove(novel_id);
+        }
+        self.write_all(&all)?;
+        Ok(true)
+    }
+}
+
+/// 合并专有名词表时，来源译名与目标小说已有译名不同的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// 保留目标小说的译名
+    Keep,
+    /// 改用来源的译名
+    Overwrite,
+    /// 保留目标小说的译名，来源译名记为冲突，在冲突列表中选择
+    Review,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(MergeStrategy::Keep),
+            "overwrite" => Ok(MergeStrategy::Overwrite),
+            "review" => Ok(MergeStrategy::Review),
+            _ => bail!("unknown merge strategy `{s}`, expected keep, overwrite or review"),
+        }
+    }
+}
+
+/// 合并专有名词表的结果
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeReport {
+    /// 新加入目标小说的词条数
+    pub added: usize,
+    /// 改用来源译名的词条数
+    pub overwritten: usize,
+    /// 译名不同但未改动的原文，按原文排序
+    pub conflicts: Vec<String>,
+}
+
+/// 将小说 `from` 的专有名词表及词条类别合并到小说 `into`，供续作与外传沿用已有译名
+pub async fn merge_glossaries(
+    kw_store: &dyn KeywordStore,
+    category_store: &dyn CategoryStore,
+    conflict_store: &dyn ConflictStore,
+    from: &str,
+    into: &str,
+    strategy: MergeStrategy,
+) -> Result<MergeReport> {
+    if from == into {
+        bail!("cannot merge the glossary of {from} into itself");
+    }
+    let source = kw_store.load(from).await?;
+    let mut target = kw_store.load(into).await?;
+    let mut report = MergeReport::default();
+    let mut new = HashMap::new();
+    for (jp, zh) in source {
+        match target.get(&jp) {
+            None => {
+                report.added += 1;
+                new.insert(jp, zh);
+            }
+            Some(current) if *current == zh => {}
+            Some(_) if strategy == MergeStrategy::Overwrite => {
+                report.overwritten += 1;
+                target.insert(jp.clone(), zh.clone());
+                kw_store.set(into, &jp, &zh).await?;
+            }
+            Some(_) => {
+                if strategy == MergeStrategy::Review {
+                    conflict_store.record(into, &jp, &zh, "")?;
+                }
+                report.conflicts.push(jp);
+            }
+        }
+    }
+    if !new.is_empty() {
+        kw_store.save(into, &new).await?;
+    }
+    category_store.save(into, &category_store.load(from)?)?;
+    report.conflicts.sort();
+    Ok(report)
+}
+
+/// 模型提取出的译名与对照表不一致的词条
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// 模型最近一次给出的译名
+    pub proposed: String,
+    /// 提取出不一致译名的章节路径
+    pub chapters: Vec<String>,
+}
+
+/// 保存译名冲突的接口，键为原文
+pub trait ConflictStore: Send + Sync {
+    /// 读取指定小说的全部冲突
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, Conflict>>;
+    /// 记录一次冲突：更新为新的译名并追加章节，`chapter` 为空时只更新译名
+    fn record(&self, novel_id: &str, jp: &str, proposed: &str, chapter: &str) -> Result<()>;
+    /// 删除冲突并返回其记录
+    fn remove(&self, novel_id: &str, jp: &str) -> Result<Option<Conflict>>;
+}
+
+/// 将译名冲突存储为 JSON 文件
+pub struct JsonConflictStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+}
+
+impl JsonConflictStore {
+    /// 创建一个新的 JSON 冲突存储
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        JsonStore { path: path.into() }
+        JsonConflictStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// 加密保存冲突记录（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
     }
 
     /// 读取文件中的全部内容
-    fn read_all(&self) -> HashMap<String, HashMap<String, String>> {
+    fn read_all(&self) -> Result<HashMap<String, HashMap<String, Conflict>>> {
+        Ok(self
+            .encoding
+            .read_blocking(&self.path)?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default())
+    }
+
+    /// 写回全部数据
+    fn write_all(&self, data: &HashMap<String, HashMap<String, Conflict>>) -> Result<()> {
+        self.encoding
+            .write_blocking(&self.path, serde_json::to_string_pretty(data)?.as_bytes())
+    }
+}
+
+impl ConflictStore for JsonConflictStore {
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, Conflict>> {
+        Ok(self.read_all()?.remove(novel_id).unwrap_or_default())
+    }
+
+    fn record(&self, novel_id: &str, jp: &str, proposed: &str, chapter: &str) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all()?;
+        let conflict = all
+            .entry(novel_id.to_string())
+            .or_default()
+            .entry(jp.to_string())
+            .or_default();
+        conflict.proposed = proposed.to_string();
+        if !chapter.is_empty() && !conflict.chapters.iter().any(|c| c == chapter) {
+            conflict.chapters.push(chapter.to_string());
+        }
+        self.write_all(&all)
+    }
+
+    fn remove(&self, novel_id: &str, jp: &str) -> Result<Option<Conflict>> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all()?;
+        let Some(conflict) = all.get_mut(novel_id).and_then(|c| c.remove(jp)) else {
+            return Ok(None);
+        };
+        self.write_all(&all)?;
+        Ok(Some(conflict))
+    }
+}
+
+/// 保存专有名词类别的接口，类别与目标语言无关
+pub trait CategoryStore: Send + Sync {
+    /// 读取指定小说的词条类别，键为原文
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, KeywordCategory>>;
+    /// 保存类别，已有类别的词条保持不变
+    fn save(&self, novel_id: &str, categories: &HashMap<String, KeywordCategory>) -> Result<()>;
+    /// 设置单个词条的类别，为 `None` 时删除
+    fn set(&self, novel_id: &str, jp: &str, category: Option<KeywordCategory>) -> Result<()>;
+}
+
+/// 将专有名词类别存储为 JSON 文件
+pub struct JsonCategoryStore {
+    path: PathBuf,
+}
+
+impl JsonCategoryStore {
+    /// 创建一个新的 JSON 类别存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonCategoryStore { path: path.into() }
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> HashMap<String, HashMap<String, KeywordCategory>> {
         if let Ok(content) = fs::read_to_string(&self.path) {
             serde_json::from_str(&content).unwrap_or_default()
         } else {
@@ -71,49 +443,2091 @@ impl JsonStore {
     }
 
     /// 写回全部数据
-    fn write_all(&self, data: &HashMap<String, HashMap<String, String>>) -> Result<()> {
-        let s = serde_json::to_string_pretty(data)?;
-        fs::write(&self.path, s)?;
+    fn write_all(&self, data: &HashMap<String, HashMap<String, KeywordCategory>>) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(data)?)?;
         Ok(())
     }
 }
 
-impl KeywordStore for JsonStore {
-    fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
-        let all = self.read_all();
-        Ok(all.get(novel_id).cloned().unwrap_or_default())
+impl CategoryStore for JsonCategoryStore {
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, KeywordCategory>> {
+        Ok(self.read_all().remove(novel_id).unwrap_or_default())
     }
 
-    fn save(&self, novel_id: &str, keywords: &HashMap<String, String>) -> Result<()> {
+    fn save(&self, novel_id: &str, categories: &HashMap<String, KeywordCategory>) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
         let mut all = self.read_all();
         let entry = all.entry(novel_id.to_string()).or_default();
-        for (jp, zh) in keywords {
-            entry.entry(jp.clone()).or_insert(zh.clone());
+        for (jp, category) in categories {
+            entry.entry(jp.clone()).or_insert(*category);
         }
         self.write_all(&all)
     }
-}
-
-impl TranslationStore for JsonTranslationStore {
-    fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<String>> {
-        let all = self.read_all();
-        Ok(all
-            .get(novel_id)
-            .and_then(|m| m.get(chapter).cloned()))
-    }
 
-    fn save(&self, novel_id: &str, chapter: &str, text: &str) -> Result<()> {
+    fn set(&self, novel_id: &str, jp: &str, category: Option<KeywordCategory>) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
         let mut all = self.read_all();
         let entry = all.entry(novel_id.to_string()).or_default();
-        entry.insert(chapter.to_string(), text.to_string());
+        match category {
+            Some(category) => {
+                entry.insert(jp.to_string(), category);
+            }
+            None if entry.remove(jp).is_none() => return Ok(()),
+            None => {}
+        }
         self.write_all(&all)
     }
+}
 
-    fn list(&self, novel_id: &str) -> Result<Vec<String>> {
-        let all = self.read_all();
-        Ok(all
-            .get(novel_id)
-            .map(|m| m.keys().cloned().collect())
-            .unwrap_or_default())
+/// 将翻译表存储为 JSON 文件
+pub struct JsonStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+/// 缓存章节翻译内容的接口；读写是异步的，大文件的写入不会阻塞界面与执行器
+#[async_trait]
+pub trait TranslationStore: Send + Sync {
+    /// 读取指定章节的翻译内容
+    async fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<String>>;
+    /// 保存章节翻译
+    async fn save(&self, novel_id: &str, chapter: &str, text: &str) -> Result<()>;
+    /// 列出所有已缓存章节路径
+    async fn list(&self, novel_id: &str) -> Result<Vec<String>>;
+    /// 删除指定章节的译文，返回删除的章节数；小说不再有译文时一并删除其记录
+    async fn remove(&self, novel_id: &str, chapters: &[String]) -> Result<usize>;
+    /// 各小说已缓存的章节数与译文占用的字节数
+    async fn usage(&self) -> Result<HashMap<String, StoreUsage>>;
+}
+
+/// 一个存储中某部小说的记录数与占用的字节数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoreUsage {
+    /// 章节、词条等记录的条数
+    pub entries: usize,
+    /// 单文件存储中为记录序列化后的长度，每章一个文件时为文件大小
+    pub bytes: u64,
+}
+
+/// 译文等存储文件写入磁盘的方式：可选 zstd 压缩（文件名加 `.zst`）与加密（再加 `.enc`）。
+/// 切换设置前写下的其他格式的文件同样可以读取，保存时转换为当前格式
+#[derive(Clone, Default)]
+pub(crate) struct Encoding {
+    compress: bool,
+    cipher: Option<Arc<Cipher>>,
+}
+
+impl Encoding {
+    /// 只加密、不压缩，用于需要按小说读写的 JSON 存储
+    pub(crate) fn encrypted(cipher: Option<Arc<Cipher>>) -> Self {
+        Encoding {
+            compress: false,
+            cipher,
+        }
+    }
+
+    /// 当前格式的文件名
+    fn current(&self, path: &Path) -> PathBuf {
+        Self::variant(path, self.compress, self.cipher.is_some())
+    }
+
+    /// 以给定方式保存时的文件名
+    fn variant(path: &Path, compress: bool, encrypt: bool) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        if compress {
+            name.push(".zst");
+        }
+        if encrypt {
+            name.push(".enc");
+        }
+        PathBuf::from(name)
+    }
+
+    /// 各种方式下的文件名及其是否压缩、加密，当前设置在前
+    fn variants(&self, path: &Path) -> Vec<(PathBuf, bool, bool)> {
+        let current = (self.compress, self.cipher.is_some());
+        let others = [(false, false), (true, false), (false, true), (true, true)]
+            .into_iter()
+            .filter(|&v| v != current);
+        std::iter::once(current)
+            .chain(others)
+            .map(|(compress, encrypt)| (Self::variant(path, compress, encrypt), compress, encrypt))
+            .collect()
+    }
+
+    /// 是否存在当前格式以外的文件，即需要转换
+    async fn has_stale(&self, path: &Path) -> Result<bool> {
+        for (file, ..) in self.variants(path).into_iter().skip(1) {
+            if tokio::fs::try_exists(&file).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// 任一格式的文件是否存在
+    async fn exists(&self, path: &Path) -> bool {
+        for (file, ..) in self.variants(path) {
+            if tokio::fs::try_exists(&file).await.unwrap_or(false) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 还原以给定方式保存的文件内容
+    fn decode(
+        &self,
+        file: &Path,
+        mut bytes: Vec<u8>,
+        compress: bool,
+        encrypt: bool,
+    ) -> Result<Vec<u8>> {
+        if encrypt {
+            let Some(cipher) = &self.cipher else {
+                bail!("{} is encrypted; run with --encrypt", file.display());
+            };
+            bytes = cipher
+                .decrypt(&bytes)
+                .with_context(|| format!("failed to decrypt {}", file.display()))?;
+        }
+        if compress {
+            bytes = zstd::decode_all(bytes.as_slice())
+                .with_context(|| format!("failed to decompress {}", file.display()))?;
+        }
+        Ok(bytes)
+    }
+
+    /// 按当前设置压缩、加密
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut bytes = bytes.to_vec();
+        if self.compress {
+            bytes = zstd::encode_all(bytes.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        }
+        if let Some(cipher) = &self.cipher {
+            bytes = cipher.encrypt(&bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    /// 读取文件，优先读取当前格式；都不存在时返回 `None`
+    async fn read(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        for (file, compress, encrypt) in self.variants(path) {
+            let bytes = match tokio::fs::read(&file).await {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to read {}", file.display()));
+                }
+            };
+            return self.decode(&file, bytes, compress, encrypt).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// 同 [`Self::read`]，供同步的存储使用
+    pub(crate) fn read_blocking(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        for (file, compress, encrypt) in self.variants(path) {
+            let bytes = match fs::read(&file) {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to read {}", file.display()));
+                }
+            };
+            return self.decode(&file, bytes, compress, encrypt).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// 以 UTF-8 读取文本文件
+    async fn read_text(&self, path: &Path) -> Result<Option<String>> {
+        let Some(bytes) = self.read(path).await? else {
+            return Ok(None);
+        };
+        String::from_utf8(bytes)
+            .map(Some)
+            .with_context(|| format!("{} is not valid UTF-8", path.display()))
+    }
+
+    /// 以当前格式写入文件，随后删除其他格式的旧文件
+    async fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let bytes = self.encode(bytes)?;
+        let mut variants = self.variants(path).into_iter();
+        let (target, ..) = variants.next().expect("the current format comes first");
+        tokio::fs::write(&target, bytes).await?;
+        for (stale, ..) in variants {
+            match tokio::fs::remove_file(&stale).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(Self::stale_error(e, &target, &stale));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 同 [`Self::write`]，供同步的存储使用
+    pub(crate) fn write_blocking(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let bytes = self.encode(bytes)?;
+        let mut variants = self.variants(path).into_iter();
+        let (target, ..) = variants.next().expect("the current format comes first");
+        fs::write(&target, bytes)
+            .with_context(|| format!("failed to write {}", target.display()))?;
+        for (stale, ..) in variants {
+            match fs::remove_file(&stale) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(Self::stale_error(e, &target, &stale));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入新格式后删除旧文件失败的错误
+    fn stale_error(e: io::Error, target: &Path, stale: &Path) -> anyhow::Error {
+        anyhow::Error::new(e).context(format!(
+            "wrote {} but failed to remove {}",
+            target.display(),
+            stale.display()
+        ))
+    }
+
+    /// 把其他格式的文件转换为当前格式，返回是否转换过
+    pub(crate) fn migrate_blocking(&self, path: &Path) -> Result<bool> {
+        let stale = self
+            .variants(path)
+            .into_iter()
+            .skip(1)
+            .any(|(file, ..)| file.exists());
+        if !stale {
+            return Ok(false);
+        }
+        match self.read_blocking(path)? {
+            Some(bytes) => self.write_blocking(path, &bytes).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// 写入文本文件
+    async fn write_text(&self, path: &Path, text: &str) -> Result<()> {
+        self.write(path, text.as_bytes()).await
+    }
+
+    /// 删除所有格式的文件，返回是否存在过
+    async fn remove(&self, path: &Path) -> Result<bool> {
+        let mut removed = false;
+        for (file, ..) in self.variants(path) {
+            match tokio::fs::remove_file(&file).await {
+                Ok(()) => removed = true,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to remove {}", file.display()));
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// 单文件翻译存储中的全部译文：小说 id → 章节路径 → 译文
+pub type AllTranslations = HashMap<String, HashMap<String, String>>;
+
+/// 从单文件存储的全部译文中删除章节，返回删除的章节数与是否需要写回
+fn remove_chapters(
+    all: &mut AllTranslations,
+    novel_id: &str,
+    chapters: &[String],
+) -> (usize, bool) {
+    let Some(entry) = all.get_mut(novel_id) else {
+        return (0, false);
+    };
+    let removed = chapters
+        .iter()
+        .filter(|chapter| entry.remove(chapter.as_str()).is_some())
+        .count();
+    let emptied = entry.is_empty();
+    if emptied {
+        all.remove(novel_id);
+    }
+    (removed, removed > 0 || emptied)
+}
+
+/// 单文件存储中各小说的章节数与译文长度
+fn translation_usage(all: &AllTranslations) -> HashMap<String, StoreUsage> {
+    all.iter()
+        .map(|(novel_id, chapters)| {
+            let usage = StoreUsage {
+                entries: chapters.len(),
+                bytes: chapters
+                    .iter()
+                    .map(|(path, text)| (path.len() + text.len()) as u64)
+                    .sum(),
+            };
+            (novel_id.clone(), usage)
+        })
+        .collect()
+}
+
+/// 简单的 JSON 文件实现，用于保存章节翻译。
+///
+/// 全部译文在首次读取后保存在内存中，读取章节不再解析整个文件；保存单章时只把该章以一行
+/// JSON 追加到日志 `<path>.journal`，日志超过主文件大小时才合并写回主文件，
+/// 因此单章的读写与译文总量无关。其他进程追加的日志行在下次访问时增量读入，主文件被改写时
+/// 重新读取。压缩或加密时文件无法追加，仍整体改写
+pub struct JsonTranslationStore {
+    path: PathBuf,
+    /// 压缩与加密设置
+    encoding: Encoding,
+    /// 内存中的译文；同时串行化读改写，避免并发的保存互相覆盖
+    index: Mutex<TranslationIndex>,
+}
+
+/// 日志累积到此大小前不合并，避免主文件很小时频繁改写
+const JOURNAL_COMPACT_MIN: u64 = 1 << 20;
+
+/// [`JsonTranslationStore`] 日志中的一行：一章的译文
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    novel: String,
+    chapter: String,
+    text: String,
+}
+
+/// 文件的大小与修改时间，用于发现其他进程的改写；文件不存在时为 `None`
+type FileStamp = Option<(u64, Option<SystemTime>)>;
+
+/// [`JsonTranslationStore`] 在内存中的全部译文及读取时文件的状态
+#[derive(Default)]
+struct TranslationIndex {
+    data: AllTranslations,
+    /// 是否已读取过文件
+    loaded: bool,
+    /// 读取时主文件（各格式）的状态
+    files: Vec<FileStamp>,
+    /// 已读入的日志长度，之后的内容为其他进程新追加的行
+    journal: u64,
+}
+
+impl JsonTranslationStore {
+    /// 创建一个新的 JSON 翻译存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonTranslationStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+            index: Mutex::new(TranslationIndex::default()),
+        }
+    }
+
+    /// 以 zstd 压缩保存为 `<path>.zst`；未压缩的旧文件仍可读取，下次保存或
+    /// [`Self::migrate`] 时转换
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.encoding.compress = compress;
+        self
+    }
+
+    /// 以 `cipher` 加密保存为 `<path>.enc`（压缩时为 `<path>.zst.enc`），为 `None` 时不加密；
+    /// 转换方式同压缩
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding.cipher = cipher;
+        self
+    }
+
+    /// 把其他格式（压缩、加密与否）的旧文件转换为当前设置的格式，返回是否进行了转换；
+    /// 开启压缩或加密后，之前未压缩时留下的日志同样合并进主文件
+    pub async fn migrate(&self) -> Result<bool> {
+        let stale_journal = !self.journaled() && tokio::fs::try_exists(self.journal_path()).await?;
+        if !stale_journal && !self.encoding.has_stale(&self.path).await? {
+            return Ok(false);
+        }
+        let mut index = self.index.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        self.refresh(&mut index).await?;
+        self.compact(&mut index).await?;
+        Ok(true)
+    }
+
+    /// 读取所有小说的全部译文，用于转换存储格式；文件不存在时为空，格式错误时报错
+    pub async fn load_all(&self) -> Result<AllTranslations> {
+        let mut index = self.index.lock().await;
+        self.refresh(&mut index).await?;
+        Ok(index.data.clone())
+    }
+
+    /// 用 `data` 覆盖全部译文
+    pub async fn save_all(&self, data: &AllTranslations) -> Result<()> {
+        let mut index = self.index.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        index.data = data.clone();
+        self.compact(&mut index).await
+    }
+
+    /// 日志文件 `<path>.journal`
+    fn journal_path(&self) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(".journal");
+        PathBuf::from(name)
+    }
+
+    /// 是否以追加日志的方式保存；压缩与加密的文件无法追加
+    fn journaled(&self) -> bool {
+        !self.encoding.compress && self.encoding.cipher.is_none()
+    }
+
+    /// 主文件各格式的当前状态
+    async fn file_stamps(&self) -> Vec<FileStamp> {
+        let mut stamps = Vec::new();
+        for (file, ..) in self.encoding.variants(&self.path) {
+            let stamp = tokio::fs::metadata(&file)
+                .await
+                .ok()
+                .map(|m| (m.len(), m.modified().ok()));
+            stamps.push(stamp);
+        }
+        stamps
+    }
+
+    /// 使内存中的译文与文件一致：文件未变时不读取，日志变长时只读入新追加的行，
+    /// 主文件被改写或日志被合并时重新读取全部内容
+    async fn refresh(&self, index: &mut TranslationIndex) -> Result<()> {
+        let files = self.file_stamps().await;
+        let journal = self.journal_path();
+        let journal_len = tokio::fs::metadata(&journal).await.map_or(0, |m| m.len());
+        if index.loaded && index.files == files && journal_len >= index.journal {
+            if journal_len > index.journal {
+                index.journal = read_journal(&journal, index.journal, &mut index.data).await?;
+            }
+            return Ok(());
+        }
+        // 读取失败（如加密文件未提供口令）时不能当作空表，以免随后的保存覆盖文件
+        let mut data = match self.encoding.read_text(&self.path).await? {
+            Some(content) => serde_json::from_str(&content)
+                .with_context(|| format!("failed to parse {}", self.path.display()))?,
+            None => AllTranslations::new(),
+        };
+        let consumed = read_journal(&journal, 0, &mut data).await?;
+        *index = TranslationIndex {
+            data,
+            loaded: true,
+            files,
+            journal: consumed,
+        };
+        Ok(())
+    }
+
+    /// 把内存中的全部译文写回主文件并删除已合并的日志
+    async fn compact(&self, index: &mut TranslationIndex) -> Result<()> {
+        let s = serde_json::to_string_pretty(&index.data)?;
+        self.encoding.write_text(&self.path, &s).await?;
+        match tokio::fs::remove_file(self.journal_path()).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        index.files = self.file_stamps().await;
+        index.journal = 0;
+        Ok(())
+    }
+}
+
+/// 从 `offset` 起读取日志并应用到 `data`，返回读入部分的结尾位置；
+/// 最后一行尚未写完（没有换行符）时留到下次读取
+async fn read_journal(path: &Path, offset: u64, data: &mut AllTranslations) -> Result<u64> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    file.seek(io::SeekFrom::Start(offset)).await?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).await?;
+    let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    for line in bytes[..complete].split(|&b| b == b'\n') {
+        if line.is_empty() {
+            continue;
+        }
+        let entry: JournalEntry = serde_json::from_slice(line)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        data.entry(entry.novel)
+            .or_default()
+            .insert(entry.chapter, entry.text);
+    }
+    Ok(offset + complete as u64)
+}
+
+/// 以 MessagePack 二进制格式保存章节翻译，内容与 [`JsonTranslationStore`] 相同；
+/// 章节很多时启动读取比解析 JSON 快，但无法直接用文本编辑器查看
+pub struct MsgpackTranslationStore {
+    path: PathBuf,
+    /// 压缩与加密设置
+    encoding: Encoding,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+impl MsgpackTranslationStore {
+    /// 创建一个新的 MessagePack 翻译存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        MsgpackTranslationStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 以 zstd 压缩保存，同 [`JsonTranslationStore::with_compression`]
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.encoding.compress = compress;
+        self
+    }
+
+    /// 加密保存，同 [`JsonTranslationStore::with_encryption`]
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding.cipher = cipher;
+        self
+    }
+
+    /// 把其他格式（压缩、加密与否）的旧文件转换为当前设置的格式，返回是否进行了转换
+    pub async fn migrate(&self) -> Result<bool> {
+        if !self.encoding.has_stale(&self.path).await? {
+            return Ok(false);
+        }
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let all = self.load_all().await?;
+        self.write_all(&all).await?;
+        Ok(true)
+    }
+
+    /// 读取所有小说的全部译文；文件不存在时为空，格式错误时报错
+    pub async fn load_all(&self) -> Result<AllTranslations> {
+        let Some(bytes) = self.encoding.read(&self.path).await? else {
+            return Ok(HashMap::new());
+        };
+        rmp_serde::from_slice(&bytes)
+            .with_context(|| format!("failed to parse {}", self.path.display()))
+    }
+
+    /// 用 `data` 覆盖全部译文
+    pub async fn save_all(&self, data: &AllTranslations) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        self.write_all(data).await
+    }
+
+    /// 将内存中的数据写回文件
+    async fn write_all(&self, data: &AllTranslations) -> Result<()> {
+        let bytes = rmp_serde::to_vec(data)?;
+        self.encoding.write(&self.path, &bytes).await
+    }
+}
+
+impl JsonStore {
+    /// 创建一个新的 JSON 存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 加密保存词条（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 读取文件中的全部内容；文件可能正被用户手动编辑，格式错误时报错而不是当作空表，
+    /// 以免随后的写入清空文件
+    async fn read_all(&self) -> Result<HashMap<String, HashMap<String, String>>> {
+        let Some(content) = self.encoding.read(&self.path).await? else {
+            return Ok(HashMap::new());
+        };
+        serde_json::from_slice(&content)
+            .with_context(|| format!("failed to parse {}", self.path.display()))
+    }
+
+    /// 写回全部数据
+    async fn write_all(&self, data: &HashMap<String, HashMap<String, String>>) -> Result<()> {
+        let s = serde_json::to_string_pretty(data)?;
+        self.encoding.write(&self.path, s.as_bytes()).await
+    }
+}
+
+#[async_trait]
+impl KeywordStore for JsonStore {
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
+        let all = self.read_all().await?;
+        Ok(all.get(novel_id).cloned().unwrap_or_default())
+    }
+
+    async fn save(&self, novel_id: &str, keywords: &HashMap<String, String>) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await?;
+        let entry = all.entry(novel_id.to_string()).or_default();
+        for (jp, zh) in keywords {
+            entry.entry(jp.clone()).or_insert(zh.clone());
+        }
+        self.write_all(&all).await
+    }
+
+    async fn set(&self, novel_id: &str, jp: &str, zh: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await?;
+        all.entry(novel_id.to_string())
+            .or_default()
+            .insert(jp.to_string(), zh.to_string());
+        self.write_all(&all).await
+    }
+
+    async fn remove(&self, novel_id: &str, jp: &str) -> Result<bool> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await?;
+        let removed = all
+            .get_mut(novel_id)
+            .is_some_and(|m| m.remove(jp).is_some());
+        if removed {
+            self.write_all(&all).await?;
+        }
+        Ok(removed)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(self.encoding.current(&self.path))
+            .and_then(|m| m.modified())
+            .ok()
+    }
+}
+
+#[async_trait]
+impl TranslationStore for JsonTranslationStore {
+    async fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<String>> {
+        let mut index = self.index.lock().await;
+        self.refresh(&mut index).await?;
+        Ok(index
+            .data
+            .get(novel_id)
+            .and_then(|m| m.get(chapter).cloned()))
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, text: &str) -> Result<()> {
+        let mut index = self.index.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        self.refresh(&mut index).await?;
+        index
+            .data
+            .entry(novel_id.to_string())
+            .or_default()
+            .insert(chapter.to_string(), text.to_string());
+        if !self.journaled() {
+            return self.compact(&mut index).await;
+        }
+        let mut line = serde_json::to_vec(&JournalEntry {
+            novel: novel_id.to_string(),
+            chapter: chapter.to_string(),
+            text: text.to_string(),
+        })?;
+        line.push(b'\n');
+        let journal = self.journal_path();
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal)
+            .await
+            .with_context(|| format!("failed to open {}", journal.display()))?;
+        file.write_all(&line).await?;
+        // tokio 的文件在后台线程写入，须等写完再释放锁，其他进程才能读到这一行
+        file.flush().await?;
+        index.journal += line.len() as u64;
+        // 日志比主文件大时合并，改写主文件的开销分摊到此前追加的各章；
+        // 主文件还不存在时直接写出，便于查看
+        match index.files.first().copied().flatten() {
+            Some((main, _)) if index.journal <= main.max(JOURNAL_COMPACT_MIN) => {}
+            _ => self.compact(&mut index).await?,
+        }
+        Ok(())
+    }
+
+    async fn list(&self, novel_id: &str) -> Result<Vec<String>> {
+        let mut index = self.index.lock().await;
+        self.refresh(&mut index).await?;
+        Ok(index
+            .data
+            .get(novel_id)
+            .map(|m| m.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn remove(&self, novel_id: &str, chapters: &[String]) -> Result<usize> {
+        let mut index = self.index.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        self.refresh(&mut index).await?;
+        let (removed, changed) = remove_chapters(&mut index.data, novel_id, chapters);
+        if changed {
+            self.compact(&mut index).await?;
+        }
+        Ok(removed)
+    }
+
+    async fn usage(&self) -> Result<HashMap<String, StoreUsage>> {
+        let mut index = self.index.lock().await;
+        self.refresh(&mut index).await?;
+        Ok(translation_usage(&index.data))
+    }
+}
+
+#[async_trait]
+impl TranslationStore for MsgpackTranslationStore {
+    async fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<String>> {
+        let mut all = self.load_all().await?;
+        Ok(all
+            .get_mut(novel_id)
+            .and_then(|chapters| chapters.remove(chapter)))
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, text: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.load_all().await?;
+        all.entry(novel_id.to_string())
+            .or_default()
+            .insert(chapter.to_string(), text.to_string());
+        self.write_all(&all).await
+    }
+
+    async fn list(&self, novel_id: &str) -> Result<Vec<String>> {
+        let all = self.load_all().await?;
+        Ok(all
+            .get(novel_id)
+            .map(|chapters| chapters.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn remove(&self, novel_id: &str, chapters: &[String]) -> Result<usize> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.load_all().await?;
+        let (removed, changed) = remove_chapters(&mut all, novel_id, chapters);
+        if changed {
+            self.write_all(&all).await?;
+        }
+        Ok(removed)
+    }
+
+    async fn usage(&self) -> Result<HashMap<String, StoreUsage>> {
+        Ok(translation_usage(&self.load_all().await?))
+    }
+}
+
+/// 每章一个文件的翻译存储：译文保存为 `<root>/<novel_id>/<chapter_hash>.txt`，
+/// 章节路径与文件名的对应关系记录在同一目录的 `index.json` 中。
+/// 章节很多时不必每次读写整个 `translations.json`，也便于比较与备份
+pub struct DirTranslationStore {
+    root: PathBuf,
+    /// 章节文件的压缩与加密设置
+    encoding: Encoding,
+    /// 串行化索引的读改写
+    lock: Mutex<()>,
+}
+
+impl DirTranslationStore {
+    /// 创建一个以 `root` 为根目录的翻译存储
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirTranslationStore {
+            root: root.into(),
+            encoding: Encoding::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 以 zstd 压缩保存章节译文；索引 `index.json` 不压缩。未压缩的旧文件仍可读取，
+    /// 下次保存该章或 [`Self::migrate`] 时转换
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.encoding.compress = compress;
+        self
+    }
+
+    /// 加密保存章节译文（`.txt.enc`），索引不加密
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding.cipher = cipher;
+        self
+    }
+
+    /// 把所有小说中其他格式（压缩、加密与否）的章节文件转换为当前设置的格式，
+    /// 返回转换的章节数
+    pub async fn migrate(&self) -> Result<usize> {
+        let mut novels = match tokio::fs::read_dir(&self.root).await {
+            Ok(novels) => novels,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let mut converted = 0;
+        while let Some(novel) = novels.next_entry().await? {
+            if !novel.file_type().await?.is_dir() {
+                continue;
+            }
+            // 同一章可能有多个格式的文件，按去掉扩展名后的文件名只转换一次
+            let mut chapters = BTreeSet::new();
+            let mut files = tokio::fs::read_dir(novel.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let name = file.file_name();
+                let Some(name) = name.to_str() else {
+                    continue;
+                };
+                let name = name.strip_suffix(".enc").unwrap_or(name);
+                let name = name.strip_suffix(".zst").unwrap_or(name);
+                if name.ends_with(".txt") {
+                    chapters.insert(file.path().with_file_name(name));
+                }
+            }
+            for path in chapters {
+                if !self.encoding.has_stale(&path).await? {
+                    continue;
+                }
+                if let Some(text) = self.encoding.read_text(&path).await? {
+                    self.encoding.write_text(&path, &text).await?;
+                    converted += 1;
+                }
+            }
+        }
+        Ok(converted)
+    }
+
+    /// 小说的目录，小说 id 中不适合作为文件名的字符替换为 `_`
+    fn novel_dir(&self, novel_id: &str) -> PathBuf {
+        let name: String = novel_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.root.join(name)
+    }
+
+    /// 章节译文的文件名：章节路径 SHA-256 摘要的前 16 位
+    fn file_name(chapter: &str) -> String {
+        let hash: String = Sha256::digest(chapter.as_bytes())
+            .iter()
+            .take(8)
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        format!("{hash}.txt")
+    }
+
+    /// 读取小说的章节索引，键为章节路径，值为文件名；索引不存在时为空，损坏时报错，
+    /// 以免随后的写入或删除把它当作没有章节
+    async fn read_index(&self, novel_id: &str) -> Result<BTreeMap<String, String>> {
+        let path = self.novel_dir(novel_id).join("index.json");
+        match tokio::fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("failed to parse {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    /// 写入章节索引：先写临时文件再改名，中途退出也不会留下写了一半的索引
+    async fn write_index(dir: &Path, index: &BTreeMap<String, String>) -> Result<()> {
+        let path = dir.join("index.json");
+        let temp = dir.join("index.json.tmp");
+        tokio::fs::write(&temp, serde_json::to_string_pretty(index)?)
+            .await
+            .with_context(|| format!("failed to write {}", temp.display()))?;
+        tokio::fs::rename(&temp, &path)
+            .await
+            .with_context(|| format!("failed to replace {}", path.display()))
+    }
+
+    /// 目录中是否还有（任一格式的）章节文件，包括索引中没有记录的
+    async fn has_chapter_files(dir: &Path) -> Result<bool> {
+        let mut files = match tokio::fs::read_dir(dir).await {
+            Ok(files) => files,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(file) = files.next_entry().await? {
+            if file.file_name().to_string_lossy().contains(".txt") {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl TranslationStore for DirTranslationStore {
+    async fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<String>> {
+        let path = self.novel_dir(novel_id).join(Self::file_name(chapter));
+        self.encoding.read_text(&path).await
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, text: &str) -> Result<()> {
+        let dir = self.novel_dir(novel_id);
+        tokio::fs::create_dir_all(&dir).await?;
+        let file = Self::file_name(chapter);
+        self.encoding.write_text(&dir.join(&file), text).await?;
+        let _guard = self.lock.lock().await;
+        // 锁文件 `<novel_id>.lock` 放在小说目录旁边，不混入章节文件
+        let _lock = FileLock::acquire_async(&dir).await?;
+        let mut index = self.read_index(novel_id).await?;
+        if index.get(chapter) != Some(&file) {
+            index.insert(chapter.to_string(), file);
+            Self::write_index(&dir, &index).await?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, novel_id: &str) -> Result<Vec<String>> {
+        let dir = self.novel_dir(novel_id);
+        let mut chapters = Vec::new();
+        // 只列出文件（任一格式）仍然存在的章节，手动删除的译文视为未缓存
+        for (chapter, file) in self.read_index(novel_id).await? {
+            if self.encoding.exists(&dir.join(file)).await {
+                chapters.push(chapter);
+            }
+        }
+        Ok(chapters)
+    }
+
+    async fn remove(&self, novel_id: &str, chapters: &[String]) -> Result<usize> {
+        let dir = self.novel_dir(novel_id);
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&dir).await?;
+        let mut index = self.read_index(novel_id).await?;
+        let mut removed = 0;
+        for chapter in chapters {
+            let file = index
+                .remove(chapter)
+                .unwrap_or_else(|| Self::file_name(chapter));
+            if self.encoding.remove(&dir.join(file)).await? {
+                removed += 1;
+            }
+        }
+        // 只在没有任何章节文件时删除整个目录，索引之外的文件（如手动放入的）不受影响
+        if index.is_empty() && !Self::has_chapter_files(&dir).await? {
+            match tokio::fs::remove_dir_all(&dir).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(e).with_context(|| format!("failed to remove {}", dir.display()));
+                }
+                _ => {}
+            }
+        } else if removed > 0 {
+            Self::write_index(&dir, &index).await?;
+        }
+        Ok(removed)
+    }
+
+    /// 按目录统计，小说 id 中替换过的字符无法还原，报告的是目录名
+    async fn usage(&self) -> Result<HashMap<String, StoreUsage>> {
+        let mut novels = match tokio::fs::read_dir(&self.root).await {
+            Ok(novels) => novels,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut usage = HashMap::new();
+        while let Some(novel) = novels.next_entry().await? {
+            if !novel.file_type().await?.is_dir() {
+                continue;
+            }
+            let Some(name) = novel.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let mut entry = StoreUsage {
+                entries: self.list(&name).await?.len(),
+                bytes: 0,
+            };
+            let mut files = tokio::fs::read_dir(novel.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                entry.bytes += file.metadata().await?.len();
+            }
+            usage.insert(name, entry);
+        }
+        Ok(usage)
+    }
+}
+
+/// 缓存抓取到的章节原文（含前書き、後書き与插图地址）的接口，重新翻译、对照阅读与
+/// 统计术语时无需重新下载；原文与目标语言无关
+#[async_trait]
+pub trait SourceStore: Send + Sync {
+    /// 读取章节原文，未缓存时返回 `None`
+    async fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<ChapterSections>>;
+    /// 保存章节原文，已有的会被覆盖
+    async fn save(&self, novel_id: &str, chapter: &str, sections: &ChapterSections) -> Result<()>;
+    /// 删除章节原文（如章节被改稿），返回原文是否存在
+    async fn remove(&self, novel_id: &str, chapter: &str) -> Result<bool>;
+}
+
+/// 将章节原文存储为 JSON 文件
+pub struct JsonSourceStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+impl JsonSourceStore {
+    /// 创建一个新的 JSON 原文存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonSourceStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 加密保存原文（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 读取文件中的全部内容
+    async fn read_all(&self) -> Result<HashMap<String, HashMap<String, ChapterSections>>> {
+        Ok(self
+            .encoding
+            .read(&self.path)
+            .await?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default())
+    }
+
+    /// 写回全部数据
+    async fn write_all(
+        &self,
+        data: &HashMap<String, HashMap<String, ChapterSections>>,
+    ) -> Result<()> {
+        let s = serde_json::to_string_pretty(data)?;
+        self.encoding.write(&self.path, s.as_bytes()).await
+    }
+}
+
+#[async_trait]
+impl SourceStore for JsonSourceStore {
+    async fn load(&self, novel_id: &str, chapter: &str) -> Result<Option<ChapterSections>> {
+        let all = self.read_all().await?;
+        Ok(all.get(novel_id).and_then(|m| m.get(chapter).cloned()))
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, sections: &ChapterSections) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await?;
+        all.entry(novel_id.to_string())
+            .or_default()
+            .insert(chapter.to_string(), sections.clone());
+        self.write_all(&all).await
+    }
+
+    async fn remove(&self, novel_id: &str, chapter: &str) -> Result<bool> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await?;
+        let removed = all
+            .get_mut(novel_id)
+            .is_some_and(|m| m.remove(chapter).is_some());
+        if removed {
+            self.write_all(&all).await?;
+        }
+        Ok(removed)
+    }
+}
+
+/// 每章最多保留的旧译文数量，超出时丢弃最旧的版本
+pub const MAX_REVISIONS: usize = 10;
+
+/// 被重新翻译或回滚覆盖的一版章节译文
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    /// 译文全文
+    pub text: String,
+    /// 被覆盖时的 Unix 时间戳（秒）
+    pub saved_at: u64,
+}
+
+impl Revision {
+    /// 以当前时间记录一版译文
+    pub fn now(text: &str) -> Self {
+        let saved_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Revision {
+            text: text.to_string(),
+            saved_at,
+        }
+    }
+}
+
+/// 保存章节旧译文的接口，用于比较与回滚
+pub trait HistoryStore: Send + Sync {
+    /// 读取章节的旧译文，按时间从旧到新排列
+    fn load(&self, novel_id: &str, chapter: &str) -> Result<Vec<Revision>>;
+    /// 覆盖章节的旧译文，只保留最新的 [`MAX_REVISIONS`] 版
+    fn save(&self, novel_id: &str, chapter: &str, revisions: &[Revision]) -> Result<()>;
+}
+
+/// 将旧译文存储为 JSON 文件
+pub struct JsonHistoryStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+}
+
+impl JsonHistoryStore {
+    /// 创建一个新的 JSON 译文历史存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonHistoryStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// 加密保存旧译文（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> Result<HashMap<String, HashMap<String, Vec<Revision>>>> {
+        Ok(self
+            .encoding
+            .read_blocking(&self.path)?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default())
+    }
+}
+
+impl HistoryStore for JsonHistoryStore {
+    fn load(&self, novel_id: &str, chapter: &str) -> Result<Vec<Revision>> {
+        Ok(self
+            .read_all()?
+            .get_mut(novel_id)
+            .and_then(|m| m.remove(chapter))
+            .unwrap_or_default())
+    }
+
+    fn save(&self, novel_id: &str, chapter: &str, revisions: &[Revision]) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all()?;
+        let entry = all.entry(novel_id.to_string()).or_default();
+        let start = revisions.len().saturating_sub(MAX_REVISIONS);
+        if revisions.is_empty() {
+            entry.remove(chapter);
+        } else {
+            entry.insert(chapter.to_string(), revisions[start..].to_vec());
+        }
+        self.encoding
+            .write_blocking(&self.path, serde_json::to_string_pretty(&all)?.as_bytes())
+    }
+}
+
+/// 逐行比较两版译文的结果
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffLine {
+    /// 两版都有的行
+    Same(String),
+    /// 只在旧版中的行
+    Removed(String),
+    /// 只在新版中的行
+    Added(String),
+}
+
+/// 按最长公共子序列逐行比较两版译文
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lcs[i][j] 为 old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// 缓存章节标题等短文本译文的接口
+pub trait TitleStore: Send + Sync {
+    /// 读取指定小说的标题译文，键为章节路径
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
+    /// 保存标题译文，已有条目会被覆盖
+    fn save(&self, novel_id: &str, titles: &HashMap<String, String>) -> Result<()>;
+}
+
+/// 将标题译文存储为 JSON 文件
+pub struct JsonTitleStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+}
+
+impl JsonTitleStore {
+    /// 创建一个新的 JSON 标题存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonTitleStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// 加密保存标题译文（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> Result<HashMap<String, HashMap<String, String>>> {
+        Ok(self
+            .encoding
+            .read_blocking(&self.path)?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default())
+    }
+
+    /// 写回全部数据
+    fn write_all(&self, data: &HashMap<String, HashMap<String, String>>) -> Result<()> {
+        let s = serde_json::to_string_pretty(data)?;
+        self.encoding.write_blocking(&self.path, s.as_bytes())
+    }
+}
+
+impl TitleStore for JsonTitleStore {
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
+        let all = self.read_all()?;
+        Ok(all.get(novel_id).cloned().unwrap_or_default())
+    }
+
+    fn save(&self, novel_id: &str, titles: &HashMap<String, String>) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all()?;
+        let entry = all.entry(novel_id.to_string()).or_default();
+        for (path, title) in titles {
+            entry.insert(path.clone(), title.clone());
+        }
+        self.write_all(&all)
+    }
+}
+
+/// 记录章节翻译时目录中的版本标记（改稿或发布时间），用于发现缓存后被改稿的章节
+pub trait StampStore: Send + Sync {
+    /// 读取指定小说的版本标记，键为章节路径
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
+    /// 保存版本标记，已有条目会被覆盖
+    fn save(&self, novel_id: &str, stamps: &HashMap<String, String>) -> Result<()>;
+}
+
+/// 将版本标记存储为 JSON 文件
+pub struct JsonStampStore {
+    path: PathBuf,
+}
+
+impl JsonStampStore {
+    /// 创建一个新的 JSON 版本标记存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonStampStore { path: path.into() }
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> HashMap<String, HashMap<String, String>> {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+
+    /// 写回全部数据
+    fn write_all(&self, data: &HashMap<String, HashMap<String, String>>) -> Result<()> {
+        let s = serde_json::to_string_pretty(data)?;
+        fs::write(&self.path, s)?;
+        Ok(())
+    }
+}
+
+impl StampStore for JsonStampStore {
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
+        let all = self.read_all();
+        Ok(all.get(novel_id).cloned().unwrap_or_default())
+    }
+
+    fn save(&self, novel_id: &str, stamps: &HashMap<String, String>) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all();
+        let entry = all.entry(novel_id.to_string()).or_default();
+        for (path, stamp) in stamps {
+            entry.insert(path.clone(), stamp.clone());
+        }
+        self.write_all(&all)
+    }
+}
+
+/// 记录翻译时各章节原文的摘要；作者改稿后原文摘要不同，据此发现过时的译文
+#[async_trait]
+pub trait SourceHashStore: Send + Sync {
+    /// 读取指定小说各章节的原文摘要，键为章节路径
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
+    /// 记录单章的原文摘要，覆盖已有记录
+    async fn save(&self, novel_id: &str, chapter: &str, hash: &str) -> Result<()>;
+}
+
+/// 将原文摘要存储为 JSON 文件
+pub struct JsonSourceHashStore {
+    path: PathBuf,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+impl JsonSourceHashStore {
+    /// 创建一个新的 JSON 原文摘要存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonSourceHashStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 读取文件中的全部内容
+    async fn read_all(&self) -> HashMap<String, HashMap<String, String>> {
+        if let Ok(content) = tokio::fs::read_to_string(&self.path).await {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+}
+
+#[async_trait]
+impl SourceHashStore for JsonSourceHashStore {
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
+        Ok(self.read_all().await.remove(novel_id).unwrap_or_default())
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, hash: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await;
+        all.entry(novel_id.to_string())
+            .or_default()
+            .insert(chapter.to_string(), hash.to_string());
+        tokio::fs::write(&self.path, serde_json::to_string_pretty(&all)?).await?;
+        Ok(())
+    }
+}
+
+/// 记录各章节缓存译文由哪个翻译引擎产出，便于发现备用引擎翻译的章节
+#[async_trait]
+pub trait EngineStore: Send + Sync {
+    /// 读取指定小说各章节的翻译引擎，键为章节路径
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
+    /// 记录单章的翻译引擎，覆盖已有记录
+    async fn save(&self, novel_id: &str, chapter: &str, engine: &str) -> Result<()>;
+}
+
+/// 将翻译引擎记录存储为 JSON 文件
+pub struct JsonEngineStore {
+    path: PathBuf,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+impl JsonEngineStore {
+    /// 创建一个新的 JSON 翻译引擎记录
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonEngineStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 读取文件中的全部内容
+    async fn read_all(&self) -> HashMap<String, HashMap<String, String>> {
+        if let Ok(content) = tokio::fs::read_to_string(&self.path).await {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+
+    /// 写回全部数据
+    async fn write_all(&self, data: &HashMap<String, HashMap<String, String>>) -> Result<()> {
+        let s = serde_json::to_string_pretty(data)?;
+        tokio::fs::write(&self.path, s).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EngineStore for JsonEngineStore {
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
+        let all = self.read_all().await;
+        Ok(all.get(novel_id).cloned().unwrap_or_default())
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, engine: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await;
+        let entry = all.entry(novel_id.to_string()).or_default();
+        entry.insert(chapter.to_string(), engine.to_string());
+        self.write_all(&all).await
+    }
+}
+
+/// 记录各章节译文中未沿用对照表译名的原文术语，目录中以 `[!]` 标出
+#[async_trait]
+pub trait DriftStore: Send + Sync {
+    /// 读取指定小说各章节的漂移术语，键为章节路径
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, Vec<String>>>;
+    /// 记录单章的漂移术语，覆盖已有记录；为空时删除记录
+    async fn save(&self, novel_id: &str, chapter: &str, terms: &[String]) -> Result<()>;
+}
+
+/// 将漂移术语记录存储为 JSON 文件
+pub struct JsonDriftStore {
+    path: PathBuf,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+impl JsonDriftStore {
+    /// 创建一个新的 JSON 漂移术语记录
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonDriftStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 读取文件中的全部内容
+    async fn read_all(&self) -> HashMap<String, HashMap<String, Vec<String>>> {
+        if let Ok(content) = tokio::fs::read_to_string(&self.path).await {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+}
+
+#[async_trait]
+impl DriftStore for JsonDriftStore {
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, Vec<String>>> {
+        Ok(self.read_all().await.remove(novel_id).unwrap_or_default())
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, terms: &[String]) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await;
+        let entry = all.entry(novel_id.to_string()).or_default();
+        if terms.is_empty() {
+            // 没有记录也不必创建文件
+            if entry.remove(chapter).is_none() {
+                return Ok(());
+            }
+        } else {
+            entry.insert(chapter.to_string(), terms.to_vec());
+        }
+        tokio::fs::write(&self.path, serde_json::to_string_pretty(&all)?).await?;
+        Ok(())
+    }
+}
+
+/// 记录重试后仍然可疑的章节译文及原因，目录中以 `[?]` 标出
+#[async_trait]
+pub trait SuspectStore: Send + Sync {
+    /// 读取指定小说各章节的可疑原因，键为章节路径
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>>;
+    /// 记录单章的可疑原因，覆盖已有记录；为 `None` 时删除记录
+    async fn save(&self, novel_id: &str, chapter: &str, reason: Option<&str>) -> Result<()>;
+}
+
+/// 将可疑章节记录存储为 JSON 文件
+pub struct JsonSuspectStore {
+    path: PathBuf,
+    /// 串行化读改写，避免并发的保存互相覆盖
+    lock: Mutex<()>,
+}
+
+impl JsonSuspectStore {
+    /// 创建一个新的 JSON 可疑章节记录
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonSuspectStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 读取文件中的全部内容
+    async fn read_all(&self) -> HashMap<String, HashMap<String, String>> {
+        if let Ok(content) = tokio::fs::read_to_string(&self.path).await {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+}
+
+#[async_trait]
+impl SuspectStore for JsonSuspectStore {
+    async fn load(&self, novel_id: &str) -> Result<HashMap<String, String>> {
+        Ok(self.read_all().await.remove(novel_id).unwrap_or_default())
+    }
+
+    async fn save(&self, novel_id: &str, chapter: &str, reason: Option<&str>) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let _lock = FileLock::acquire_async(&self.path).await?;
+        let mut all = self.read_all().await;
+        let entry = all.entry(novel_id.to_string()).or_default();
+        match reason {
+            Some(reason) => {
+                entry.insert(chapter.to_string(), reason.to_string());
+            }
+            // 没有记录也不必创建文件
+            None if entry.remove(chapter).is_none() => return Ok(()),
+            None => {}
+        }
+        tokio::fs::write(&self.path, serde_json::to_string_pretty(&all)?).await?;
+        Ok(())
+    }
+}
+
+/// 记录各章节原文中专有名词出现次数的接口，与目标语言无关
+pub trait UsageStore: Send + Sync {
+    /// 读取指定小说各词条在全部章节中出现的总次数，键为原文
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, usize>>;
+    /// 记录单章各词条的出现次数，覆盖该章已有记录；为空时删除记录
+    fn save(&self, novel_id: &str, chapter: &str, counts: &HashMap<String, usize>) -> Result<()>;
+}
+
+/// 将专有名词出现次数存储为 JSON 文件，按章节分别保存以便重新翻译时只更新一章
+pub struct JsonUsageStore {
+    path: PathBuf,
+}
+
+impl JsonUsageStore {
+    /// 创建一个新的 JSON 出现次数记录
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonUsageStore { path: path.into() }
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> HashMap<String, HashMap<String, HashMap<String, usize>>> {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+}
+
+impl UsageStore for JsonUsageStore {
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, usize>> {
+        let mut totals = HashMap::new();
+        for counts in self
+            .read_all()
+            .remove(novel_id)
+            .unwrap_or_default()
+            .into_values()
+        {
+            for (term, count) in counts {
+                *totals.entry(term).or_default() += count;
+            }
+        }
+        Ok(totals)
+    }
+
+    fn save(&self, novel_id: &str, chapter: &str, counts: &HashMap<String, usize>) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all();
+        let entry = all.entry(novel_id.to_string()).or_default();
+        if counts.is_empty() {
+            if entry.remove(chapter).is_none() {
+                return Ok(());
+            }
+        } else {
+            entry.insert(chapter.to_string(), counts.clone());
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&all)?)?;
+        Ok(())
+    }
+}
+
+/// 按小说覆盖的设置，未设置的项沿用命令行参数
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NovelSettings {
+    /// 翻译后是否让模型对照原文审校译文
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<bool>,
+    /// 对话式引擎使用的模型，备用引擎也会使用同一模型名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 温度、最大 token 数等采样参数，已设置的项覆盖命令行的值
+    #[serde(flatten)]
+    pub sampling: Sampling,
+    /// 译文风格预设
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<StylePreset>,
+    /// 题材，如 `isekai`，用于选取示例库中该题材的翻译示例
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    /// 附加在提示词中的作品说明，如“这是一部搞笑的异世界作品”
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// 设置界面中可编辑的字段名，顺序与 [`NovelSettings::field`] 一致
+pub const SETTINGS_FIELDS: [&str; 8] = [
+    "review",
+    "model",
+    "temperature",
+    "max_tokens",
+    "top_p",
+    "style",
+    "genre",
+    "notes",
+];
+
+impl NovelSettings {
+    /// 按 [`SETTINGS_FIELDS`] 中的下标显示字段，未设置时为空
+    pub fn field(&self, index: usize) -> String {
+        match index {
+            0 => self
+                .review
+                .map(|r| if r { "on" } else { "off" }.to_string()),
+            1 => self.model.clone(),
+            2 => self.sampling.temperature.map(|t| t.to_string()),
+            3 => self.sampling.max_tokens.map(|n| n.to_string()),
+            4 => self.sampling.top_p.map(|p| p.to_string()),
+            5 => self.style.map(|s| s.to_string()),
+            6 => self.genre.clone(),
+            _ => self.notes.clone(),
+        }
+        .unwrap_or_default()
+    }
+
+    /// 按 [`SETTINGS_FIELDS`] 中的下标设置字段，空字符串表示沿用全局设置
+    pub fn set_field(&mut self, index: usize, value: &str) -> Result<()> {
+        let value = value.trim();
+        let text = (!value.is_empty()).then(|| value.to_string());
+        match index {
+            0 => {
+                self.review = match value {
+                    "" => None,
+                    "on" | "true" | "yes" => Some(true),
+                    "off" | "false" | "no" => Some(false),
+                    _ => bail!("expected on or off, got `{value}`"),
+                }
+            }
+            1 => self.model = text,
+            2 => self.sampling.temperature = text.as_deref().map(parse_temperature).transpose()?,
+            3 => self.sampling.max_tokens = text.as_deref().map(parse_max_tokens).transpose()?,
+            4 => self.sampling.top_p = text.as_deref().map(parse_top_p).transpose()?,
+            5 => self.style = text.map(|s| s.parse()).transpose()?,
+            6 => self.genre = text,
+            _ => self.notes = text,
+        }
+        Ok(())
+    }
+}
+
+/// 保存各小说设置的接口
+pub trait SettingsStore: Send + Sync {
+    /// 读取指定小说的设置，没有时返回默认值
+    fn load(&self, novel_id: &str) -> Result<NovelSettings>;
+    /// 保存设置，覆盖已有内容
+    fn save(&self, novel_id: &str, settings: &NovelSettings) -> Result<()>;
+}
+
+/// 将各小说设置存储为 JSON 文件
+pub struct JsonSettingsStore {
+    path: PathBuf,
+}
+
+impl JsonSettingsStore {
+    /// 创建一个新的 JSON 设置存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonSettingsStore { path: path.into() }
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> HashMap<String, NovelSettings> {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+}
+
+impl SettingsStore for JsonSettingsStore {
+    fn load(&self, novel_id: &str) -> Result<NovelSettings> {
+        Ok(self.read_all().remove(novel_id).unwrap_or_default())
+    }
+
+    fn save(&self, novel_id: &str, settings: &NovelSettings) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all();
+        all.insert(novel_id.to_string(), settings.clone());
+        fs::write(&self.path, serde_json::to_string_pretty(&all)?)?;
+        Ok(())
+    }
+}
+
+/// 少样本翻译示例：一段原文及其理想的译文
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Example {
+    pub source: String,
+    pub translation: String,
+}
+
+/// 示例所属的范围
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExampleScope {
+    /// 只用于指定小说
+    Novel(String),
+    /// 用于设置中题材相同的所有小说
+    Genre(String),
+}
+
+/// 保存少样本翻译示例的接口，示例按小说或题材分组
+pub trait ExampleStore: Send + Sync {
+    /// 读取指定范围的示例
+    fn load(&self, scope: &ExampleScope) -> Result<Vec<Example>>;
+    /// 保存指定范围的示例，覆盖已有内容
+    fn save(&self, scope: &ExampleScope, examples: &[Example]) -> Result<()>;
+}
+
+/// `examples.json` 的内容
+#[derive(Default, Serialize, Deserialize)]
+struct ExampleBank {
+    #[serde(default)]
+    novels: HashMap<String, Vec<Example>>,
+    #[serde(default)]
+    genres: HashMap<String, Vec<Example>>,
+}
+
+impl ExampleBank {
+    /// 范围对应的分组及键
+    fn group(&mut self, scope: &ExampleScope) -> (&mut HashMap<String, Vec<Example>>, String) {
+        match scope {
+            ExampleScope::Novel(id) => (&mut self.novels, id.clone()),
+            ExampleScope::Genre(genre) => (&mut self.genres, genre.clone()),
+        }
+    }
+}
+
+/// 将示例库存储为 JSON 文件，可直接手工编辑
+pub struct JsonExampleStore {
+    path: PathBuf,
+}
+
+impl JsonExampleStore {
+    /// 创建一个新的 JSON 示例库
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonExampleStore { path: path.into() }
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> ExampleBank {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            ExampleBank::default()
+        }
+    }
+}
+
+impl ExampleStore for JsonExampleStore {
+    fn load(&self, scope: &ExampleScope) -> Result<Vec<Example>> {
+        let mut bank = self.read_all();
+        let (group, key) = bank.group(scope);
+        Ok(group.remove(&key).unwrap_or_default())
+    }
+
+    fn save(&self, scope: &ExampleScope, examples: &[Example]) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut bank = self.read_all();
+        let (group, key) = bank.group(scope);
+        if examples.is_empty() {
+            group.remove(&key);
+        } else {
+            group.insert(key, examples.to_vec());
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&bank)?)?;
+        Ok(())
+    }
+}
+
+/// 保存各小说剧情摘要的接口，摘要在每章翻译后更新
+pub trait SummaryStore: Send + Sync {
+    /// 读取指定小说的剧情摘要，没有时返回 `None`
+    fn load(&self, novel_id: &str) -> Result<Option<String>>;
+    /// 保存剧情摘要，覆盖已有内容
+    fn save(&self, novel_id: &str, summary: &str) -> Result<()>;
+}
+
+/// 将剧情摘要存储为 JSON 文件
+pub struct JsonSummaryStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+}
+
+impl JsonSummaryStore {
+    /// 创建一个新的 JSON 剧情摘要存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonSummaryStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// 加密保存剧情摘要（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> Result<HashMap<String, String>> {
+        Ok(self
+            .encoding
+            .read_blocking(&self.path)?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default())
+    }
+}
+
+impl SummaryStore for JsonSummaryStore {
+    fn load(&self, novel_id: &str) -> Result<Option<String>> {
+        Ok(self.read_all()?.remove(novel_id))
+    }
+
+    fn save(&self, novel_id: &str, summary: &str) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all()?;
+        all.insert(novel_id.to_string(), summary.to_string());
+        self.encoding
+            .write_blocking(&self.path, serde_json::to_string_pretty(&all)?.as_bytes())
+    }
+}
+
+/// 人物设定中可在界面中编辑的字段名，顺序与 [`Character::field_mut`] 一致
+pub const CHARACTER_FIELDS: [&str; 5] = ["name", "translated", "gender", "speech", "relationships"];
+
+/// 人物设定中的一个角色，由模型在翻译过程中逐章补充，也可在界面中修改
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Character {
+    /// 原文中的名字
+    pub name: String,
+    /// 译名
+    #[serde(default)]
+    pub translated: String,
+    /// 性别，不确定时为空
+    #[serde(default)]
+    pub gender: String,
+    /// 说话方式，如自称、口癖与语气
+    #[serde(default)]
+    pub speech: String,
+    /// 与其他角色的关系
+    #[serde(default)]
+    pub relationships: String,
+}
+
+impl Character {
+    /// 用 `other` 中非空的字段覆盖当前设定
+    pub fn update(&mut self, other: &Character) {
+        for (field, value) in [
+            (&mut self.translated, &other.translated),
+            (&mut self.gender, &other.gender),
+            (&mut self.speech, &other.speech),
+            (&mut self.relationships, &other.relationships),
+        ] {
+            if !value.trim().is_empty() {
+                *field = value.trim().to_string();
+            }
+        }
+    }
+
+    /// 按 [`CHARACTER_FIELDS`] 的顺序列出各字段
+    pub fn fields(&self) -> [&str; 5] {
+        [
+            &self.name,
+            &self.translated,
+            &self.gender,
+            &self.speech,
+            &self.relationships,
+        ]
+    }
+
+    /// 按 [`CHARACTER_FIELDS`] 中的下标取得字段，越界时取最后一个
+    pub fn field_mut(&mut self, index: usize) -> &mut String {
+        match index {
+            0 => &mut self.name,
+            1 => &mut self.translated,
+            2 => &mut self.gender,
+            3 => &mut self.speech,
+            _ => &mut self.relationships,
+        }
+    }
+}
+
+/// 按名字合并人物设定：已有的人物更新非空字段，新人物追加在末尾
+pub fn merge_characters(characters: &mut Vec<Character>, updates: Vec<Character>) {
+    for update in updates {
+        let name = update.name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        match characters.iter_mut().find(|c| c.name == name) {
+            Some(existing) => existing.update(&update),
+            None => {
+                let mut added = Character {
+                    name: name.to_string(),
+                    ..Default::default()
+                };
+                added.update(&update);
+                characters.push(added);
+            }
+        }
+    }
+}
+
+/// 保存各小说人物设定的接口
+pub trait CharacterStore: Send + Sync {
+    /// 读取指定小说的人物设定，按首次出场顺序排列
+    fn load(&self, novel_id: &str) -> Result<Vec<Character>>;
+    /// 保存人物设定，覆盖已有内容
+    fn save(&self, novel_id: &str, characters: &[Character]) -> Result<()>;
+}
+
+/// 将人物设定存储为 JSON 文件
+pub struct JsonCharacterStore {
+    path: PathBuf,
+    /// 加密设置
+    encoding: Encoding,
+}
+
+impl JsonCharacterStore {
+    /// 创建一个新的 JSON 人物设定存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonCharacterStore {
+            path: path.into(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// 加密保存人物设定（文件名加 `.enc`）；未加密的旧文件仍可读取，下次保存时转换
+    pub fn with_encryption(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.encoding = Encoding::encrypted(cipher);
+        self
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> Result<HashMap<String, Vec<Character>>> {
+        Ok(self
+            .encoding
+            .read_blocking(&self.path)?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default())
+    }
+}
+
+impl CharacterStore for JsonCharacterStore {
+    fn load(&self, novel_id: &str) -> Result<Vec<Character>> {
+        Ok(self.read_all()?.remove(novel_id).unwrap_or_default())
+    }
+
+    fn save(&self, novel_id: &str, characters: &[Character]) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all()?;
+        all.insert(novel_id.to_string(), characters.to_vec());
+        self.encoding
+            .write_blocking(&self.path, serde_json::to_string_pretty(&all)?.as_bytes())
+    }
+}
+
+/// 保存章节插图（挿絵）地址的接口，译文中的 `[挿絵 #n]` 对应列表中第 n 项
+pub trait IllustrationStore: Send + Sync {
+    /// 读取指定小说各章节的插图地址，键为章节路径
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, Vec<String>>>;
+    /// 保存单章的插图地址，覆盖已有列表
+    fn save(&self, novel_id: &str, chapter: &str, urls: &[String]) -> Result<()>;
+}
+
+/// 将插图地址存储为 JSON 文件
+pub struct JsonIllustrationStore {
+    path: PathBuf,
+}
+
+impl JsonIllustrationStore {
+    /// 创建一个新的 JSON 插图地址存储
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonIllustrationStore { path: path.into() }
+    }
+
+    /// 读取文件中的全部内容
+    fn read_all(&self) -> HashMap<String, HashMap<String, Vec<String>>> {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        }
+    }
+
+    /// 写回全部数据
+    fn write_all(&self, data: &HashMap<String, HashMap<String, Vec<String>>>) -> Result<()> {
+        let s = serde_json::to_string_pretty(data)?;
+        fs::write(&self.path, s)?;
+        Ok(())
+    }
+}
+
+impl IllustrationStore for JsonIllustrationStore {
+    fn load(&self, novel_id: &str) -> Result<HashMap<String, Vec<String>>> {
+        let all = self.read_all();
+        Ok(all.get(novel_id).cloned().unwrap_or_default())
+    }
+
+    fn save(&self, novel_id: &str, chapter: &str, urls: &[String]) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut all = self.read_all();
+        let entry = all.entry(novel_id.to_string()).or_default();
+        entry.insert(chapter.to_string(), urls.to_vec());
+        self.write_all(&all)
     }
 }
@@ -0,0 +1,122 @@
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use anyhow::Result;
+use axum::Router;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::syosetu::is_cancelled;
+
+/// 本程序所有指标注册到的 registry
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+/// 已完成翻译的章节数
+pub static CHAPTERS_TRANSLATED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(IntCounter::new(
+        "syosetu_chapters_translated_total",
+        "Chapters fetched, translated and cached",
+    ))
+});
+
+/// 翻译接口请求耗时，按调用类型区分
+pub static API_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "syosetu_api_request_duration_seconds",
+            "Latency of translation API requests",
+        )
+        .buckets(vec![1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0]),
+        &["call"],
+    ))
+});
+
+/// 接口返回的 token 用量，按 prompt/completion 区分
+pub static API_TOKENS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "syosetu_api_tokens_total",
+            "Tokens reported by the translation API",
+        ),
+        &["type"],
+    ))
+});
+
+/// 按错误类型统计的失败次数
+pub static ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("syosetu_errors_total", "Failed chapter tasks by kind"),
+        &["kind"],
+    ))
+});
+
+/// 正在处理中的章节任务数
+pub static QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(IntGauge::new(
+        "syosetu_queue_depth",
+        "Chapter tasks currently in flight",
+    ))
+});
+
+/// 将指标注册到全局 registry
+fn register<M>(metric: prometheus::Result<M>) -> M
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    let metric = metric.expect("invalid metric definition");
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("metric registered twice");
+    metric
+}
+
+/// 记录一次失败，被取消的任务单独归为 `cancelled`
+pub fn record_failure(kind: &str, err: &anyhow::Error) {
+    let kind = if is_cancelled(err) { "cancelled" } else { kind };
+    ERRORS.with_label_values(&[kind]).inc();
+}
+
+/// 记录一次接口调用的耗时
+pub fn observe_latency(call: &str, started: Instant) {
+    API_LATENCY
+        .with_label_values(&[call])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// 以 Prometheus 文本格式导出全部指标
+pub fn render() -> String {
+    // 确保尚未使用过的指标也出现在输出中
+    LazyLock::force(&CHAPTERS_TRANSLATED);
+    LazyLock::force(&API_LATENCY);
+    LazyLock::force(&API_TOKENS);
+    LazyLock::force(&ERRORS);
+    LazyLock::force(&QUEUE_DEPTH);
+    let mut buf = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&REGISTRY.gather(), &mut buf) {
+        log::error!("failed to encode metrics: {e}");
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    ([(CONTENT_TYPE, prometheus::TEXT_FORMAT)], render())
+}
+
+/// 在指定地址提供 `/metrics` 接口，直到令牌被取消
+pub async fn serve(addr: SocketAddr, cancel: CancellationToken) -> Result<()> {
+    let router = Router::new().route("/metrics", get(metrics_handler));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("Serving metrics on http://{addr}/metrics");
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { cancel.cancelled().await })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! 不调用翻译接口、在本地从原文中挖掘专有名词候选。
+//!
+//! 默认只统计连续的片假名词；启用 `lindera` 特性并提供词典目录（如编译好的 IPADIC）时
+//! 改用 lindera 分词，另外收集词典标为固有名词的词。
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Result;
+
+/// 本地挖掘出的专有名词候选
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub term: String,
+    /// 在所统计的原文中出现的次数
+    pub count: usize,
+}
+
+/// 候选词默认的最少出现次数
+pub const DEFAULT_MIN_COUNT: usize = 3;
+
+/// 统计原文中候选词出现次数的分词器
+pub struct TermMiner {
+    #[cfg(feature = "lindera")]
+    segmenter: Option<lindera::segmenter::Segmenter>,
+}
+
+impl TermMiner {
+    /// 只统计片假名词的挖掘器，不需要词典
+    pub fn katakana() -> Self {
+        TermMiner {
+            #[cfg(feature = "lindera")]
+            segmenter: None,
+        }
+    }
+
+    /// 提供词典目录时使用 lindera 分词，否则只统计片假名词
+    pub fn new(dictionary: Option<&Path>) -> Result<Self> {
+        let Some(path) = dictionary else {
+            return Ok(Self::katakana());
+        };
+        #[cfg(feature = "lindera")]
+        {
+            use lindera::dictionary::load_fs_dictionary;
+            use lindera::mode::Mode;
+            use lindera::segmenter::Segmenter;
+
+            let dictionary = load_fs_dictionary(path).map_err(|e| {
+                anyhow::anyhow!("failed to load dictionary {}: {e}", path.display())
+            })?;
+            Ok(TermMiner {
+                segmenter: Some(Segmenter::new(Mode::Normal, dictionary, None)),
+            })
+        }
+        #[cfg(not(feature = "lindera"))]
+        anyhow::bail!(
+            "cannot load dictionary {}: built without the `lindera` feature",
+            path.display()
+        )
+    }
+
+    /// 把文本中各候选词的出现次数累加到 `counts`
+    pub fn count(&self, text: &str, counts: &mut HashMap<String, usize>) -> Result<()> {
+        #[cfg(feature = "lindera")]
+        if let Some(segmenter) = &self.segmenter {
+            let tokens = segmenter
+                .segment(std::borrow::Cow::Borrowed(text))
+                .map_err(|e| anyhow::anyhow!("failed to tokenize text: {e}"))?;
+            for mut token in tokens {
+                let surface = token.surface.to_string();
+                let details = token.details();
+                let proper =
+                    details.first() == Some(&"名詞") && details.get(1) == Some(&"固有名詞");
+                // 词典里没有的片假名词多为人名或造词
+                let katakana = is_katakana_word(&surface)
+                    && matches!(details.first(), Some(&"名詞") | Some(&"UNK"));
+                if (proper || katakana) && surface.chars().count() >= 2 {
+                    *counts.entry(surface).or_default() += 1;
+                }
+            }
+            return Ok(());
+        }
+        for word in katakana_words(text) {
+            *counts.entry(word.to_string()).or_default() += 1;
+        }
+        Ok(())
+    }
+}
+
+/// 片假名（含长音符与中点），不含平假名
+fn is_katakana(c: char) -> bool {
+    ('\u{30A1}'..='\u{30FC}').contains(&c)
+}
+
+/// 由两个以上片假名组成且不以长音符或中点开头的词
+fn is_katakana_word(word: &str) -> bool {
+    word.chars().count() >= 2 && word.chars().all(is_katakana) && !word.starts_with(['ー', '・'])
+}
+
+/// 文本中的连续片假名词，去掉首尾的中点
+pub fn katakana_words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !is_katakana(c))
+        .map(|word| word.trim_matches('・').trim_start_matches('ー'))
+        .filter(|word| is_katakana_word(word))
+        .collect()
+}
+
+/// 按出现次数从多到少排列候选词，去掉出现次数不足 `min_count` 的词及 `known` 中已有的词
+pub fn rank_candidates(
+    counts: HashMap<String, usize>,
+    min_count: usize,
+    known: &HashSet<String>,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = counts
+        .into_iter()
+        .filter(|(term, count)| *count >= min_count && !known.contains(term))
+        .map(|(term, count)| Candidate { term, count })
+        .collect();
+    candidates.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    candidates
+}
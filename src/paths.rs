@@ -0,0 +1,215 @@
+//! 数据、配置与缓存目录的定位
+//!
+//! 默认使用各平台的标准目录（Linux 为 XDG 目录，macOS 为 `~/Library`，Windows 为
+//! `%APPDATA%`/`%LOCALAPPDATA%`），`--data-dir` 时所有文件都放在指定目录下。
+//! 旧版本把文件写在工作目录中，首次使用数据目录时由 [`AppDirs::prepare`] 复制过来。
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+
+/// 各平台目录下的子目录名
+pub const APP_NAME: &str = "syosetu-rs";
+
+/// 工作目录中有这些存储文件（可带语言代码）时才认为是旧版本的数据目录；
+/// `data/`、`settings.json` 等名字太常见，单凭它们不做迁移
+const LEGACY_MARKERS: &[&str] = &["keywords", "translations"];
+
+/// 按目标语言区分的 JSON 存储文件名（不含扩展名与语言代码）
+const DATA_FILES: &[&str] = &[
+    "keywords",
+    "pending_keywords",
+    "categories",
+    "blacklist",
+    "conflicts",
+    "usage",
+    "translations",
+    "history",
+    "titles",
+    "stamps",
+    "illustrations",
+    "sources",
+    "source_hashes",
+    "engines",
+    "drift",
+    "suspects",
+    "settings",
+    "examples",
+    "summaries",
+    "characters",
+    "cookies",
+];
+
+/// 数据、配置与缓存目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppDirs {
+    /// 对照表、译文等存储文件及 Cookie
+    pub data: PathBuf,
+    /// `crawl.toml`、自定义站点目录 `sites.d/` 与提示词模板目录 `prompts/`
+    pub config: PathBuf,
+    /// 页面缓存 `http_cache/` 与日志 `app.log`
+    pub cache: PathBuf,
+}
+
+impl AppDirs {
+    /// 所有文件都放在同一目录下
+    pub fn single(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        AppDirs {
+            data: dir.clone(),
+            config: dir.clone(),
+            cache: dir,
+        }
+    }
+
+    /// 当前平台的标准目录
+    pub fn platform() -> Result<Self> {
+        Self::from_env(|name| std::env::var_os(name))
+    }
+
+    /// 根据环境变量确定标准目录，`env` 为查询环境变量的函数
+    pub fn from_env(env: impl Fn(&str) -> Option<OsString>) -> Result<Self> {
+        // 空值与未设置相同
+        let var = |name: &str| env(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+        let home =
+            || var("HOME").ok_or_else(|| anyhow!("cannot find the home directory; use --data-dir"));
+        let (data, config, cache) = if cfg!(windows) {
+            let roaming =
+                var("APPDATA").ok_or_else(|| anyhow!("APPDATA is not set; use --data-dir"))?;
+            let local = var("LOCALAPPDATA").unwrap_or_else(|| roaming.clone());
+            (roaming.clone(), roaming, local)
+        } else if cfg!(target_os = "macos") {
+            let library = home()?.join("Library");
+            let support = library.join("Application Support");
+            (support.clone(), support, library.join("Caches"))
+        } else {
+            let data = match var("XDG_DATA_HOME") {
+                Some(dir) => dir,
+                None => home()?.join(".local/share"),
+            };
+            let config = match var("XDG_CONFIG_HOME") {
+                Some(dir) => dir,
+                None => home()?.join(".config"),
+            };
+            let cache = match var("XDG_CACHE_HOME") {
+                Some(dir) => dir,
+                None => home()?.join(".cache"),
+            };
+            (data, config, cache)
+        };
+        Ok(AppDirs {
+            data: data.join(APP_NAME),
+            config: config.join(APP_NAME),
+            cache: cache.join(APP_NAME),
+        })
+    }
+
+    /// 创建各目录；数据目录此前不存在（首次运行）且 `legacy` 目录中有旧版本的
+    /// `keywords.json` 或 `translations.json` 时，把其中本程序的文件复制到对应的目录，
+    /// 返回复制后的路径。原文件保留在原处，目标已存在的文件保持不动
+    pub fn prepare(&self, legacy: &Path) -> Result<Vec<PathBuf>> {
+        let first_run = !self.data.exists();
+        for dir in [&self.data, &self.config, &self.cache] {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        if !first_run {
+            return Ok(Vec::new());
+        }
+        let mut copied = Vec::new();
+        let entries = match fs::read_dir(legacy) {
+            Ok(entries) => entries.collect::<io::Result<Vec<_>>>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(copied),
+            Err(e) => return Err(e.into()),
+        };
+        let is_legacy = entries.iter().any(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(data_stem)
+                .is_some_and(|stem| LEGACY_MARKERS.contains(&stem))
+        });
+        if !is_legacy {
+            return Ok(copied);
+        }
+        for entry in entries {
+            let name = entry.file_name();
+            let Some(dir) = name.to_str().and_then(|name| self.legacy_dir(name)) else {
+                continue;
+            };
+            let target = dir.join(&name);
+            if target.exists() {
+                continue;
+            }
+            copy_path(&entry.path(), &target).with_context(|| {
+                format!(
+                    "failed to copy {} to {}",
+                    name.to_string_lossy(),
+                    target.display()
+                )
+            })?;
+            copied.push(target);
+        }
+        copied.sort();
+        Ok(copied)
+    }
+
+    /// 旧版本写在工作目录中的文件应复制到的目录，不是本程序的文件时返回 `None`
+    fn legacy_dir(&self, name: &str) -> Option<&Path> {
+        match name {
+            "crawl.toml" | "prompts" | "sites.d" => return Some(&self.config),
+            "http_cache" | "app.log" => return Some(&self.cache),
+            _ => {}
+        }
+        // 不带扩展名的只有 `--translation-store dir` 的章节目录
+        let known = match data_stem(name) {
+            Some(stem) => DATA_FILES.contains(&stem),
+            None => language_stem(name) == Some("data"),
+        };
+        known.then_some(self.data.as_path())
+    }
+}
+
+/// JSON 存储文件名去掉扩展名与语言代码后的部分，如 `translations.en.json` → `translations`
+fn data_stem(name: &str) -> Option<&str> {
+    language_stem(name.strip_suffix(".json")?)
+}
+
+/// 去掉其他目标语言的文件带有的语言代码，如 `data.en` → `data`；后缀不是语言代码时
+/// 返回 `None`
+fn language_stem(name: &str) -> Option<&str> {
+    match name.split_once('.') {
+        Some((stem, code)) if code.len() == 2 && code.bytes().all(|b| b.is_ascii_lowercase()) => {
+            Some(stem)
+        }
+        Some(_) => None,
+        None => Some(name),
+    }
+}
+
+/// 复制文件或目录
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        copy_dir(from, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// 递归复制目录
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
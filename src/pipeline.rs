@@ -0,0 +1,1157 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio_util::sync::CancellationToken;
+
+use crate::library::Library;
+use crate::memory::{
+    BlacklistStore, CategoryStore, CharacterStore, Conflict, ConflictStore, DriftStore,
+    EngineStore, Example, ExampleScope, ExampleStore, GLOBAL_GLOSSARY, HistoryStore,
+    IllustrationStore, KeywordStore, NovelSettings, Revision, SettingsStore, SourceHashStore,
+    SourceStore, StampStore, SummaryStore, SuspectStore, TitleStore, TranslationStore, UsageStore,
+    merge_characters,
+};
+use crate::metrics;
+use crate::mining::{Candidate, TermMiner, rank_candidates};
+use crate::syosetu::{Chapter, ChapterSections, NOTE_LABELS_JA, NovelSite, joined_body};
+use crate::translate::{
+    Honorifics, Keyword, KeywordCategory, Sampling, StreamEvent, StreamSender, Suspect, TargetLang,
+    TermLock, TranslationBackend, TranslationContext, suspect_translation, verify_glossary,
+};
+
+/// 后台任务共享的站点、翻译器与存储
+#[derive(Clone)]
+pub struct Pipeline {
+    pub site: Arc<dyn NovelSite>,
+    pub translator: Arc<dyn TranslationBackend>,
+    pub kw_store: Arc<dyn KeywordStore>,
+    /// 等待审核的新专有名词，仅在开启 `review_keywords` 时使用
+    pub pending_store: Arc<dyn KeywordStore>,
+    /// 专有名词的类别，全局词条的类别保存在 `GLOBAL_GLOSSARY` 下
+    pub category_store: Arc<dyn CategoryStore>,
+    /// 不会加入对照表的屏蔽词，提取结果中的屏蔽词直接丢弃
+    pub blacklist_store: Arc<dyn BlacklistStore>,
+    /// 提取出的译名与对照表不一致的词条，等待选择保留哪个译名
+    pub conflict_store: Arc<dyn ConflictStore>,
+    /// 各章节原文中专有名词的出现次数，翻译时更新
+    pub usage_store: Arc<dyn UsageStore>,
+    /// 新提取的专有名词先放入待审核列表，接受后才加入对照表
+    pub review_keywords: bool,
+    pub trans_store: Arc<dyn TranslationStore>,
+    /// 抓取到的章节原文，重新翻译与统计时优先使用
+    pub source_store: Arc<dyn SourceStore>,
+    /// 翻译时章节原文的摘要，打开章节时据此发现作者改稿
+    pub hash_store: Arc<dyn SourceHashStore>,
+    /// 被重新翻译或回滚覆盖的旧译文
+    pub history_store: Arc<dyn HistoryStore>,
+    pub title_store: Arc<dyn TitleStore>,
+    pub stamp_store: Arc<dyn StampStore>,
+    pub illust_store: Arc<dyn IllustrationStore>,
+    pub engine_store: Arc<dyn EngineStore>,
+    pub drift_store: Arc<dyn DriftStore>,
+    pub suspect_store: Arc<dyn SuspectStore>,
+    /// 正文译文可疑（过短、残留假名或结尾中断）时的重试次数
+    pub suspect_retries: usize,
+    pub settings_store: Arc<dyn SettingsStore>,
+    pub example_store: Arc<dyn ExampleStore>,
+    /// 翻译后让模型对照原文审校正文，可按小说在设置中覆盖
+    pub review: bool,
+    /// 翻译前把原文中的对照表术语换成占位符，翻译后换回固定的译名
+    pub lock_terms: bool,
+    /// 译文的目标语言，存储已按语言区分
+    pub target_lang: TargetLang,
+    /// 敬称的处理方式，译文保存前按此检查
+    pub honorifics: Option<Honorifics>,
+    /// 命令行设置的采样参数，各小说的设置可逐项覆盖
+    pub sampling: Sampling,
+    pub summary_store: Arc<dyn SummaryStore>,
+    /// 每章翻译后更新剧情摘要，并附在之后章节的提示词中
+    pub rolling_summary: bool,
+    /// 附在提示词中的上一章结尾段落数，0 表示不附带
+    pub previous_paragraphs: usize,
+    pub character_store: Arc<dyn CharacterStore>,
+    /// 每章翻译后更新人物设定，并附在之后章节的提示词中
+    pub track_characters: bool,
+    /// 上述存储按小说的统计、删除与清理
+    pub library: Arc<Library>,
+}
+
+impl Pipeline {
+    /// 读取全局专有名词表，读取失败时只记录日志
+    pub async fn global_keywords(&self) -> HashMap<String, String> {
+        self.kw_store
+            .load(GLOBAL_GLOSSARY)
+            .await
+            .inspect_err(|e| warn!("Failed to load the global glossary: {e:?}"))
+            .unwrap_or_default()
+    }
+
+    /// 章节原文的来源，缓存取自 `source_store`
+    pub fn source(&self) -> ChapterSource<'_> {
+        ChapterSource {
+            site: self.site.as_ref(),
+            cache: self.source_store.as_ref(),
+        }
+    }
+
+    /// 以专有名词表与作品名构造翻译上下文，全局词条垫在 `keywords` 之下
+    pub async fn context(
+        &self,
+        keywords: Vec<(String, String)>,
+        novel_title: &str,
+    ) -> TranslationContext {
+        let mut glossary = self.global_keywords().await;
+        glossary.extend(keywords);
+        TranslationContext::new(glossary.into_iter().collect())
+            .with_novel_title(novel_title)
+            .with_target_lang(self.target_lang)
+            .with_honorifics(self.honorifics)
+            .with_sampling(self.sampling)
+            .with_term_lock(self.lock_terms)
+    }
+}
+
+/// 抓取、翻译章节并提取新的专有名词，返回新提取的词条；
+/// 提供 `partial` 时正文译文边生成边回传。启用剧情摘要时翻译前读取摘要，保存译文后更新
+pub async fn process_chapter(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    path: &str,
+    ctx: &TranslationContext,
+    partial: Option<&StreamSender>,
+    cancel: &CancellationToken,
+) -> Result<Vec<Keyword>> {
+    let summary = if pipeline.rolling_summary {
+        pipeline.summary_store.load(novel_id)?.unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let characters = if pipeline.track_characters {
+        pipeline.character_store.load(novel_id)?
+    } else {
+        Vec::new()
+    };
+    let settings = pipeline.settings_store.load(novel_id)?;
+    let examples = load_examples(pipeline, novel_id, &settings)?;
+    let mut ctx = ctx
+        .clone()
+        .with_settings(&settings)
+        .with_examples(examples)
+        .with_categories(load_categories(pipeline, novel_id)?)
+        .with_summary(&summary)
+        .with_characters(characters);
+    if pipeline.previous_paragraphs > 0
+        && let Some(previous) = &ctx.previous_chapter
+    {
+        ctx.previous_tail = previous_tail(pipeline, novel_id, previous, cancel).await;
+    }
+    let ctx = &ctx;
+    let sections = pipeline.source().fetch(novel_id, path, cancel).await?;
+    let (mut body, engine, suspect) =
+        translate_body(pipeline, path, &sections.body, ctx, partial, cancel).await?;
+    let review = settings.review.unwrap_or(pipeline.review);
+    // 可疑的译文审校意义不大，保留原样以便在目录中标出
+    if review && suspect.is_none() {
+        body = review_body(pipeline, path, &sections.body, body, ctx, cancel).await;
+    }
+    // 前書き与後書き使用单独的提示词翻译
+    let preface = translate_note(pipeline, sections.preface.as_deref(), ctx, cancel).await?;
+    let afterword = translate_note(pipeline, sections.afterword.as_deref(), ctx, cancel).await?;
+    let translation = ChapterSections {
+        preface,
+        body,
+        afterword,
+        ..Default::default()
+    }
+    .join(ctx.target_lang.note_labels());
+    let translation = enforce_honorifics(ctx, path, translation);
+    let content = sections.join(NOTE_LABELS_JA);
+    let (translation, drifted) = verify_glossary(&content, &translation, &ctx.keywords);
+    if !drifted.is_empty() {
+        warn!(
+            "{path} does not use the glossary for {}",
+            drifted.join(", ")
+        );
+    }
+    let new_keywords = pipeline
+        .translator
+        .extract_keywords(&translation, &content, ctx, cancel)
+        .await
+        .inspect_err(|e| metrics::record_failure("extract", e))?;
+    let terms = ctx
+        .keywords
+        .iter()
+        .map(|(jp, _)| jp.clone())
+        .chain(new_keywords.iter().map(|k| k.clone().normalized().japanese));
+    pipeline
+        .usage_store
+        .save(novel_id, path, &term_usage(&content, terms))
+        .inspect_err(|e| metrics::record_failure("store", e))?;
+    save_translation(
+        pipeline.trans_store.as_ref(),
+        pipeline.history_store.as_ref(),
+        novel_id,
+        path,
+        &translation,
+    )
+    .await
+    .inspect_err(|e| metrics::record_failure("store", e))?;
+    pipeline
+        .engine_store
+        .save(novel_id, path, &engine)
+        .await
+        .inspect_err(|e| metrics::record_failure("store", e))?;
+    pipeline
+        .hash_store
+        .save(novel_id, path, &source_hash(&sections))
+        .await
+        .inspect_err(|e| metrics::record_failure("store", e))?;
+    pipeline
+        .drift_store
+        .save(novel_id, path, &drifted)
+        .await
+        .inspect_err(|e| metrics::record_failure("store", e))?;
+    pipeline
+        .suspect_store
+        .save(novel_id, path, suspect.map(|s| s.to_string()).as_deref())
+        .await
+        .inspect_err(|e| metrics::record_failure("store", e))?;
+    if !sections.images.is_empty() {
+        pipeline
+            .illust_store
+            .save(novel_id, path, &sections.images)
+            .inspect_err(|e| metrics::record_failure("store", e))?;
+    }
+    metrics::CHAPTERS_TRANSLATED.inc();
+    if pipeline.rolling_summary {
+        update_summary(pipeline, novel_id, &summary, &translation, ctx, cancel).await;
+    }
+    if pipeline.track_characters {
+        update_characters(pipeline, novel_id, &translation, &content, ctx, cancel).await;
+    }
+    Ok(new_keywords)
+}
+
+/// 用新翻译的章节更新人物设定；失败时保留原设定，不影响已保存的译文
+async fn update_characters(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    translation: &str,
+    content: &str,
+    ctx: &TranslationContext,
+    cancel: &CancellationToken,
+) {
+    let result = match pipeline
+        .translator
+        .update_characters(translation, content, ctx, cancel)
+        .await
+    {
+        Ok(updates) if updates.is_empty() => Ok(()),
+        // 重新读取，以免覆盖翻译期间在界面中所做的修改
+        Ok(updates) => pipeline
+            .character_store
+            .load(novel_id)
+            .and_then(|mut sheet| {
+                merge_characters(&mut sheet, updates);
+                pipeline.character_store.save(novel_id, &sheet)
+            }),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        metrics::record_failure("characters", &e);
+        warn!("Failed to update the characters of {novel_id}: {e:?}");
+    }
+}
+
+/// 章节原文的来源：优先读取缓存，未缓存时从站点抓取并存入缓存
+#[derive(Clone, Copy)]
+pub struct ChapterSource<'a> {
+    pub site: &'a dyn NovelSite,
+    pub cache: &'a dyn SourceStore,
+}
+
+impl ChapterSource<'_> {
+    /// 读取章节原文
+    pub async fn fetch(
+        &self,
+        novel_id: &str,
+        path: &str,
+        cancel: &CancellationToken,
+    ) -> Result<ChapterSections> {
+        if let Some(sections) = self.cache.load(novel_id, path).await? {
+            return Ok(sections);
+        }
+        let sections = self
+            .site
+            .fetch_sections(path, cancel)
+            .await
+            .inspect_err(|e| metrics::record_failure("fetch", e))?;
+        self.cache
+            .save(novel_id, path, &sections)
+            .await
+            .inspect_err(|e| metrics::record_failure("store", e))?;
+        Ok(sections)
+    }
+}
+
+/// 章节原文的摘要：合并各部分后 SHA-256 摘要的前 16 位
+pub fn source_hash(sections: &ChapterSections) -> String {
+    Sha256::digest(sections.join(NOTE_LABELS_JA).as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 重新抓取章节原文并与翻译时的摘要比较，原文已被作者改动时返回 `true`。
+/// 翻译时没有记录摘要的章节以缓存的原文（没有缓存时以当前原文）为准补记
+pub async fn source_changed(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    path: &str,
+    cancel: &CancellationToken,
+) -> Result<bool> {
+    let sections = pipeline
+        .site
+        .fetch_sections(path, cancel)
+        .await
+        .inspect_err(|e| metrics::record_failure("fetch", e))?;
+    let current = source_hash(&sections);
+    let recorded = match pipeline.hash_store.load(novel_id).await?.remove(path) {
+        Some(hash) => hash,
+        None => {
+            let hash = match pipeline.source_store.load(novel_id, path).await? {
+                Some(cached) => source_hash(&cached),
+                None => current.clone(),
+            };
+            pipeline.hash_store.save(novel_id, path, &hash).await?;
+            hash
+        }
+    };
+    Ok(recorded != current)
+}
+
+/// 上一章结尾的原文与译文，各取 `previous_paragraphs` 段；
+/// 上一章尚未翻译或原文抓取失败时返回 `None`
+async fn previous_tail(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    path: &str,
+    cancel: &CancellationToken,
+) -> Option<(String, String)> {
+    let translation = pipeline.trans_store.load(novel_id, path).await.ok()??;
+    let sections = match pipeline.source().fetch(novel_id, path, cancel).await {
+        Ok(sections) => sections,
+        Err(e) => {
+            warn!("Failed to fetch the previous chapter {path}: {e:?}");
+            return None;
+        }
+    };
+    let n = pipeline.previous_paragraphs;
+    let translation = joined_body(&translation, pipeline.target_lang.note_labels());
+    Some((
+        last_paragraphs(&sections.body, n),
+        last_paragraphs(translation, n),
+    ))
+}
+
+/// 文本中最后 `n` 个非空段落
+fn last_paragraphs(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+/// 用新翻译的章节更新剧情摘要；失败时保留原摘要，不影响已保存的译文
+async fn update_summary(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    summary: &str,
+    translation: &str,
+    ctx: &TranslationContext,
+    cancel: &CancellationToken,
+) {
+    let result = match pipeline
+        .translator
+        .summarize(summary, translation, ctx, cancel)
+        .await
+    {
+        Ok(updated) if updated.is_empty() || updated == summary => Ok(()),
+        Ok(updated) => pipeline.summary_store.save(novel_id, &updated),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        metrics::record_failure("summary", &e);
+        warn!("Failed to update the story summary of {novel_id}: {e:?}");
+    }
+}
+
+/// 翻译正文；译文可疑时降低温度重试，返回译文、引擎名以及重试后仍存在的问题
+async fn translate_body(
+    pipeline: &Pipeline,
+    path: &str,
+    body: &str,
+    ctx: &TranslationContext,
+    partial: Option<&StreamSender>,
+    cancel: &CancellationToken,
+) -> Result<(String, String, Option<Suspect>)> {
+    let (lock, locked) = if ctx.lock_terms {
+        TermLock::lock(body, &ctx.keywords)
+    } else {
+        (TermLock::default(), body.to_string())
+    };
+    let mut attempt = 0;
+    loop {
+        let retry_ctx;
+        let ctx = if attempt == 0 {
+            ctx
+        } else {
+            retry_ctx = ctx.clone().with_retry(true);
+            &retry_ctx
+        };
+        let result = match partial.filter(|_| !lock.is_empty()) {
+            // 锁定了术语时片段先换回译名再转发
+            Some(tx) => {
+                let (locked_tx, locked_rx) = mpsc::unbounded_channel();
+                let translate = async {
+                    let locked_tx = locked_tx;
+                    pipeline
+                        .translator
+                        .translate_streaming(&locked, ctx, Some(&locked_tx), cancel)
+                        .await
+                };
+                tokio::join!(translate, forward_unlocked(&lock, locked_rx, tx)).0
+            }
+            None => {
+                pipeline
+                    .translator
+                    .translate_streaming(&locked, ctx, partial, cancel)
+                    .await
+            }
+        };
+        let (translation, engine) =
+            result.inspect_err(|e| metrics::record_failure("translate", e))?;
+        let translation = unlock_terms(&lock, path, translation);
+        let suspect = suspect_translation(body, &translation, ctx.target_lang);
+        let Some(reason) = suspect.filter(|_| attempt < pipeline.suspect_retries) else {
+            if let Some(reason) = suspect {
+                warn!("Translation of {path} is still suspect: {reason}");
+            }
+            return Ok((translation, engine, suspect));
+        };
+        attempt += 1;
+        warn!(
+            "Translation of {path} looks suspect ({reason}), retrying ({attempt}/{})",
+            pipeline.suspect_retries
+        );
+        // 界面中已显示的片段作废
+        if let Some(tx) = partial {
+            let _ = tx.send(StreamEvent::Restart);
+        }
+    }
+}
+
+/// 读取全局与小说自己的词条类别，同一词条以小说的类别为准
+pub fn load_categories(
+    pipeline: &Pipeline,
+    novel_id: &str,
+) -> Result<HashMap<String, KeywordCategory>> {
+    let mut categories = pipeline.category_store.load(GLOBAL_GLOSSARY)?;
+    categories.extend(pipeline.category_store.load(novel_id)?);
+    Ok(categories)
+}
+
+/// 读取全局与小说自己的屏蔽词
+pub fn load_blacklist(pipeline: &Pipeline, novel_id: &str) -> Result<HashSet<String>> {
+    let mut blacklist: HashSet<String> = pipeline
+        .blacklist_store
+        .load(GLOBAL_GLOSSARY)?
+        .into_iter()
+        .collect();
+    blacklist.extend(pipeline.blacklist_store.load(novel_id)?);
+    Ok(blacklist)
+}
+
+/// 读取小说自己的翻译示例，之后是设置中所属题材的示例
+fn load_examples(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    settings: &NovelSettings,
+) -> Result<Vec<Example>> {
+    let mut examples = pipeline
+        .example_store
+        .load(&ExampleScope::Novel(novel_id.to_string()))?;
+    if let Some(genre) = &settings.genre {
+        examples.extend(
+            pipeline
+                .example_store
+                .load(&ExampleScope::Genre(genre.clone()))?,
+        );
+    }
+    Ok(examples)
+}
+
+/// 让模型审校正文译文；审校失败或结果可疑时沿用原译文
+async fn review_body(
+    pipeline: &Pipeline,
+    path: &str,
+    source: &str,
+    translation: String,
+    ctx: &TranslationContext,
+    cancel: &CancellationToken,
+) -> String {
+    match pipeline
+        .translator
+        .review(&translation, source, ctx, cancel)
+        .await
+    {
+        Ok(reviewed) => match suspect_translation(source, &reviewed, ctx.target_lang) {
+            Some(reason) => {
+                warn!("Discarded the review of {path}: {reason}");
+                translation
+            }
+            None => reviewed,
+        },
+        Err(e) => {
+            metrics::record_failure("review", &e);
+            warn!("Failed to review {path}: {e:?}");
+            translation
+        }
+    }
+}
+
+/// 改写译文中不符合设置的敬称
+fn enforce_honorifics(ctx: &TranslationContext, path: &str, text: String) -> String {
+    let Some(honorifics) = ctx.honorifics else {
+        return text;
+    };
+    let (fixed, count) = honorifics.enforce(&text);
+    if count > 0 {
+        warn!("Rewrote {count} honorifics in {path} to match {honorifics:?}");
+    }
+    fixed
+}
+
+/// 翻译作者的前書き或後書き，没有时返回 `None`
+async fn translate_note(
+    pipeline: &Pipeline,
+    note: Option<&str>,
+    ctx: &TranslationContext,
+    cancel: &CancellationToken,
+) -> Result<Option<String>> {
+    let Some(text) = note else {
+        return Ok(None);
+    };
+    let (lock, locked) = if ctx.lock_terms {
+        TermLock::lock(text, &ctx.keywords)
+    } else {
+        (TermLock::default(), text.to_string())
+    };
+    let translated = pipeline
+        .translator
+        .translate_note(&locked, ctx, cancel)
+        .await
+        .inspect_err(|e| metrics::record_failure("notes", e))?;
+    Ok(Some(unlock_terms(&lock, "note", translated)))
+}
+
+/// 把流式片段中的占位符换回译名后转发给 `tx`，直到引擎不再发送；
+/// 被拆到两个片段中的占位符等到闭合后再转发
+async fn forward_unlocked(
+    lock: &TermLock,
+    mut rx: UnboundedReceiver<StreamEvent>,
+    tx: &StreamSender,
+) {
+    let mut pending = String::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            StreamEvent::Delta(delta) => {
+                pending.push_str(&delta);
+                let open = match pending.rfind('⟦') {
+                    Some(i) if !pending[i..].contains('⟧') => i,
+                    _ => pending.len(),
+                };
+                let rest = pending.split_off(open);
+                if !pending.is_empty() {
+                    let _ = tx.send(StreamEvent::Delta(lock.unlock(&pending).0));
+                }
+                pending = rest;
+            }
+            StreamEvent::Restart => {
+                pending.clear();
+                let _ = tx.send(StreamEvent::Restart);
+            }
+        }
+    }
+    if !pending.is_empty() {
+        let _ = tx.send(StreamEvent::Delta(lock.unlock(&pending).0));
+    }
+}
+
+/// 把译文中的占位符换回译名，模型丢掉的占位符只记录日志，之后由 `verify_glossary` 记为漂移
+fn unlock_terms(lock: &TermLock, path: &str, translation: String) -> String {
+    if lock.is_empty() {
+        return translation;
+    }
+    let (translation, lost) = lock.unlock(&translation);
+    if !lost.is_empty() {
+        warn!("{path} lost the locked terms {}", lost.join(", "));
+    }
+    translation
+}
+
+/// 一致性检查中译文没有使用对照译名的章节
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inconsistency {
+    pub path: String,
+    pub title: String,
+    /// 原文中出现但译文中找不到对照译名的术语及其译名
+    pub terms: Vec<(String, String)>,
+}
+
+/// 以当前的专有名词表（含全局词条）检查已缓存章节的译文，找出原文出现术语而译文没有使用
+/// 对照译名的章节；原文优先取自缓存，未缓存的章节跳过。检查结果同时覆盖各章的漂移记录。
+/// 不需要翻译引擎，因此直接接收所用的站点与存储
+pub async fn check_consistency(
+    source: ChapterSource<'_>,
+    kw_store: &dyn KeywordStore,
+    trans_store: &dyn TranslationStore,
+    drift_store: &dyn DriftStore,
+    novel_id: &str,
+    chapters: &[Chapter],
+    cancel: &CancellationToken,
+) -> Result<Vec<Inconsistency>> {
+    let mut glossary = kw_store.load(GLOBAL_GLOSSARY).await?;
+    glossary.extend(kw_store.load(novel_id).await?);
+    let keywords: Vec<(String, String)> = glossary.clone().into_iter().collect();
+    let mut report = Vec::new();
+    for chapter in chapters {
+        let Some(translation) = trans_store.load(novel_id, &chapter.path).await? else {
+            continue;
+        };
+        let sections = source.fetch(novel_id, &chapter.path, cancel).await?;
+        let content = sections.join(NOTE_LABELS_JA);
+        let (_, mut missing) = verify_glossary(&content, &translation, &keywords);
+        missing.sort();
+        drift_store.save(novel_id, &chapter.path, &missing).await?;
+        if missing.is_empty() {
+            continue;
+        }
+        let terms = missing
+            .into_iter()
+            .map(|jp| {
+                let tr = glossary[&jp].clone();
+                (jp, tr)
+            })
+            .collect();
+        report.push(Inconsistency {
+            path: chapter.path.clone(),
+            title: chapter.title.clone(),
+            terms,
+        });
+    }
+    Ok(report)
+}
+
+/// 统计原文中各术语的出现次数，没有出现的术语不计入
+pub fn term_usage(
+    content: &str,
+    terms: impl IntoIterator<Item = String>,
+) -> HashMap<String, usize> {
+    terms
+        .into_iter()
+        .filter(|term| !term.is_empty())
+        .filter_map(|term| {
+            let count = content.matches(term.as_str()).count();
+            (count > 0).then_some((term, count))
+        })
+        .collect()
+}
+
+/// 以当前的专有名词表（含全局词条）重新统计已缓存章节原文中各术语的出现次数并保存，
+/// 返回各术语的总次数；原文优先取自缓存，未缓存的章节跳过
+pub async fn count_usage(
+    source: ChapterSource<'_>,
+    kw_store: &dyn KeywordStore,
+    trans_store: &dyn TranslationStore,
+    usage_store: &dyn UsageStore,
+    novel_id: &str,
+    chapters: &[Chapter],
+    cancel: &CancellationToken,
+) -> Result<HashMap<String, usize>> {
+    let mut glossary = kw_store.load(GLOBAL_GLOSSARY).await?;
+    glossary.extend(kw_store.load(novel_id).await?);
+    let cached: HashSet<String> = trans_store.list(novel_id).await?.into_iter().collect();
+    for chapter in chapters.iter().filter(|c| cached.contains(&c.path)) {
+        let sections = source.fetch(novel_id, &chapter.path, cancel).await?;
+        let content = sections.join(NOTE_LABELS_JA);
+        let counts = term_usage(&content, glossary.keys().cloned());
+        usage_store.save(novel_id, &chapter.path, &counts)?;
+    }
+    usage_store.load(novel_id)
+}
+
+/// 对照表（含全局词条）、待审核列表与屏蔽词中已有的原文，挖掘候选时跳过
+pub async fn known_terms(
+    kw_store: &dyn KeywordStore,
+    pending_store: &dyn KeywordStore,
+    blacklist_store: &dyn BlacklistStore,
+    novel_id: &str,
+) -> Result<HashSet<String>> {
+    let mut known = HashSet::new();
+    for id in [GLOBAL_GLOSSARY, novel_id] {
+        known.extend(kw_store.load(id).await?.into_keys());
+        known.extend(pending_store.load(id).await?.into_keys());
+        known.extend(blacklist_store.load(id)?);
+    }
+    Ok(known)
+}
+
+/// 读取章节原文（优先取自缓存）并用 `miner` 在本地统计专有名词候选，不调用翻译接口；
+/// 出现次数不足 `min_count` 或已在 `known` 中的词不列出
+pub async fn mine_candidates(
+    source: ChapterSource<'_>,
+    novel_id: &str,
+    miner: &TermMiner,
+    chapters: &[Chapter],
+    known: &HashSet<String>,
+    min_count: usize,
+    cancel: &CancellationToken,
+) -> Result<Vec<Candidate>> {
+    let mut counts = HashMap::new();
+    for chapter in chapters {
+        let sections = source.fetch(novel_id, &chapter.path, cancel).await?;
+        miner.count(&sections.join(NOTE_LABELS_JA), &mut counts)?;
+    }
+    Ok(rank_candidates(counts, min_count, known))
+}
+
+/// 把候选词放入待审核列表，译名暂填原文，审核时修改；返回新加入的个数
+pub async fn queue_candidates(
+    pending_store: &dyn KeywordStore,
+    novel_id: &str,
+    candidates: &[Candidate],
+) -> Result<usize> {
+    let pending = pending_store.load(novel_id).await?;
+    let new: HashMap<String, String> = candidates
+        .iter()
+        .filter(|c| !pending.contains_key(&c.term))
+        .map(|c| (c.term.clone(), c.term.clone()))
+        .collect();
+    if !new.is_empty() {
+        pending_store.save(novel_id, &new).await?;
+    }
+    Ok(new.len())
+}
+
+/// 读取仍待处理的译名冲突，按原文排序，每项为原文、对照表中的译名与冲突记录；
+/// 词条已从对照表删除或已改为新译名的冲突同时清除
+pub async fn load_conflicts(
+    pipeline: &Pipeline,
+    novel_id: &str,
+) -> Result<Vec<(String, String, Conflict)>> {
+    let keywords = pipeline.kw_store.load(novel_id).await?;
+    let mut conflicts = Vec::new();
+    for (jp, conflict) in pipeline.conflict_store.load(novel_id)? {
+        match keywords.get(&jp) {
+            Some(current) if *current != conflict.proposed => {
+                conflicts.push((jp, current.clone(), conflict));
+            }
+            _ => {
+                pipeline.conflict_store.remove(novel_id, &jp)?;
+            }
+        }
+    }
+    conflicts.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(conflicts)
+}
+
+/// 解决译名冲突：`accept` 为真时改用模型给出的译名，否则保留对照表中的译名。返回译文与
+/// 结果不一致、可以重新翻译的已缓存章节：保留原译名时为提取出新译名的章节，改用新译名时
+/// 为译文中仍使用原译名的章节
+pub async fn resolve_conflict(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    jp: &str,
+    accept: bool,
+) -> Result<Vec<String>> {
+    let Some(conflict) = pipeline.conflict_store.remove(novel_id, jp)? else {
+        return Ok(Vec::new());
+    };
+    if !accept {
+        return Ok(conflict.chapters);
+    }
+    let Some(current) = pipeline.kw_store.load(novel_id).await?.remove(jp) else {
+        return Ok(Vec::new());
+    };
+    pipeline
+        .kw_store
+        .set(novel_id, jp, &conflict.proposed)
+        .await?;
+    let proposed = conflict.proposed.as_str();
+    let mut affected = Vec::new();
+    for path in pipeline.trans_store.list(novel_id).await? {
+        let Some(text) = pipeline.trans_store.load(novel_id, &path).await? else {
+            continue;
+        };
+        // 新译名包含原译名时（如「托莉」→「托莉丝」），原译名出现得更多才算仍在使用
+        let inside_proposed = if proposed.contains(current.as_str()) {
+            text.matches(proposed).count()
+        } else {
+            0
+        };
+        if text.matches(current.as_str()).count() > inside_proposed {
+            affected.push(path);
+        }
+    }
+    Ok(affected)
+}
+
+/// 记录章节当前的版本标记，之后目录中的标记不同即表示缓存后被改稿
+pub fn record_stamps(pipeline: &Pipeline, novel_id: &str, chapters: &[Chapter]) -> Result<()> {
+    let stamps: HashMap<String, String> = chapters
+        .iter()
+        .filter_map(|c| Some((c.path.clone(), c.stamp()?.to_string())))
+        .collect();
+    if stamps.is_empty() {
+        return Ok(());
+    }
+    pipeline.stamp_store.save(novel_id, &stamps)
+}
+
+/// 保存章节译文，已有不同的译文时先将其存入历史
+pub async fn save_translation(
+    trans_store: &dyn TranslationStore,
+    history_store: &dyn HistoryStore,
+    novel_id: &str,
+    path: &str,
+    text: &str,
+) -> Result<()> {
+    if let Some(old) = trans_store.load(novel_id, path).await?
+        && old != text
+    {
+        let mut revisions = history_store.load(novel_id, path)?;
+        revisions.push(Revision::now(&old));
+        history_store.save(novel_id, path, &revisions)?;
+    }
+    trans_store.save(novel_id, path, text).await
+}
+
+/// 将章节译文回滚到历史中的第 `index` 版（从 0 开始，按时间从旧到新），
+/// 当前译文存入历史以便再次回滚，返回恢复的译文
+pub async fn rollback_translation(
+    trans_store: &dyn TranslationStore,
+    history_store: &dyn HistoryStore,
+    novel_id: &str,
+    path: &str,
+    index: usize,
+) -> Result<String> {
+    let mut revisions = history_store.load(novel_id, path)?;
+    if index >= revisions.len() {
+        bail!(
+            "{path} has {} previous versions, not {}",
+            revisions.len(),
+            index + 1
+        );
+    }
+    let revision = revisions.remove(index);
+    if let Some(current) = trans_store.load(novel_id, path).await? {
+        revisions.push(Revision::now(&current));
+    }
+    history_store.save(novel_id, path, &revisions)?;
+    trans_store.save(novel_id, path, &revision.text).await?;
+    Ok(revision.text)
+}
+
+/// 把译文中的 `old` 替换为 `new`，返回替换后的文本与替换次数；位于 `protected` 中任一词
+/// 之内的 `old` 保持不变，例如改写「纱耶」时不动「纱耶香」
+pub fn replace_term(text: &str, old: &str, new: &str, protected: &[&str]) -> (String, usize) {
+    if old.is_empty() {
+        return (text.to_string(), 0);
+    }
+    let guarded: Vec<(usize, usize)> = protected
+        .iter()
+        .filter(|word| word.len() > old.len() && word.contains(old))
+        .flat_map(|word| text.match_indices(word).map(|(i, w)| (i, i + w.len())))
+        .collect();
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut count = 0;
+    for (i, _) in text.match_indices(old) {
+        let end = i + old.len();
+        if guarded
+            .iter()
+            .any(|&(start, stop)| start <= i && end <= stop)
+        {
+            continue;
+        }
+        out.push_str(&text[last..i]);
+        out.push_str(new);
+        last = end;
+        count += 1;
+    }
+    out.push_str(&text[last..]);
+    (out, count)
+}
+
+/// 修改译名后把已缓存章节译文中的旧译名改为新译名，返回受影响的章节及各章替换次数；
+/// `write` 为 `false` 时只预览不写入。对照表（含全局词条）中包含旧译名的其他译名及新译名
+/// 本身不会被改写，被改写的译文先存入历史，可以回滚
+pub async fn replace_in_translations(
+    trans_store: &dyn TranslationStore,
+    history_store: &dyn HistoryStore,
+    kw_store: &dyn KeywordStore,
+    novel_id: &str,
+    old: &str,
+    new: &str,
+    write: bool,
+) -> Result<Vec<(String, usize)>> {
+    if old.is_empty() || old == new {
+        return Ok(Vec::new());
+    }
+    let mut glossary = kw_store.load(GLOBAL_GLOSSARY).await?;
+    glossary.extend(kw_store.load(novel_id).await?);
+    let mut protected: Vec<&str> = glossary.values().map(String::as_str).collect();
+    protected.push(new);
+    let mut affected = Vec::new();
+    for path in trans_store.list(novel_id).await? {
+        let Some(text) = trans_store.load(novel_id, &path).await? else {
+            continue;
+        };
+        let (replaced, count) = replace_term(&text, old, new, &protected);
+        if count == 0 {
+            continue;
+        }
+        if write {
+            save_translation(trans_store, history_store, novel_id, &path, &replaced).await?;
+        }
+        affected.push((path, count));
+    }
+    affected.sort();
+    Ok(affected)
+}
+
+/// 在无界面模式下依次翻译章节并保存新增的专有名词，已缓存的章节会被跳过，
+/// 返回本次新翻译的章节数；`novel_title` 用于提示词模板中的 `{novel_title}`
+pub async fn translate_batch(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    novel_title: &str,
+    chapters: &[Chapter],
+    cancel: &CancellationToken,
+) -> Result<usize> {
+    run_batch(pipeline, novel_id, novel_title, chapters, false, cancel).await
+}
+
+/// 使用当前的专有名词表重新翻译已缓存的章节，未缓存的章节会被跳过，
+/// 旧译文存入历史；返回重新翻译的章节数
+pub async fn retranslate_batch(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    novel_title: &str,
+    chapters: &[Chapter],
+    cancel: &CancellationToken,
+) -> Result<usize> {
+    run_batch(pipeline, novel_id, novel_title, chapters, true, cancel).await
+}
+
+/// 依次处理缓存状态与 `cached` 一致的章节
+async fn run_batch(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    novel_title: &str,
+    chapters: &[Chapter],
+    cached: bool,
+    cancel: &CancellationToken,
+) -> Result<usize> {
+    let mut keywords = pipeline.kw_store.load(novel_id).await?;
+    let mut translated = 0;
+    for (i, chapter) in chapters.iter().enumerate() {
+        if pipeline
+            .trans_store
+            .load(novel_id, &chapter.path)
+            .await?
+            .is_some()
+            != cached
+        {
+            continue;
+        }
+        info!("Translating {}", chapter.title);
+        let ctx = pipeline
+            .context(
+                keywords
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+                novel_title,
+            )
+            .await
+            .with_previous_chapter(i.checked_sub(1).map(|p| chapters[p].path.clone()));
+        let new_keywords =
+            process_chapter(pipeline, novel_id, &chapter.path, &ctx, None, cancel).await?;
+        commit_keywords(
+            pipeline,
+            novel_id,
+            &chapter.path,
+            &mut keywords,
+            new_keywords,
+        )
+        .await?;
+        record_stamps(pipeline, novel_id, std::slice::from_ref(chapter))?;
+        translated += 1;
+    }
+    Ok(translated)
+}
+
+/// 保存章节 `chapter` 中新提取的专有名词，先经 [`Keyword::normalized`] 规范化并去重，
+/// 屏蔽词直接丢弃；译名与对照表不同的词条记为冲突。开启审核时放入待审核列表
+/// （已在对照表中的词条除外），否则加入 `keywords` 并写入对照表
+pub async fn commit_keywords(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    chapter: &str,
+    keywords: &mut HashMap<String, String>,
+    found: Vec<Keyword>,
+) -> Result<()> {
+    let blacklist = load_blacklist(pipeline, novel_id)?;
+    // 规范化后同一原文只保留第一个译名，避免「トウリ」「トウリさん」分别加入对照表
+    let mut seen = HashSet::new();
+    let found: Vec<Keyword> = found
+        .into_iter()
+        .map(Keyword::normalized)
+        .filter(|k| {
+            !k.japanese.is_empty()
+                && !k.translated.is_empty()
+                && !blacklist.contains(&k.japanese)
+                && seen.insert(k.japanese.clone())
+        })
+        .collect();
+    for k in &found {
+        if keywords
+            .get(&k.japanese)
+            .is_some_and(|current| *current != k.translated)
+        {
+            pipeline
+                .conflict_store
+                .record(novel_id, &k.japanese, &k.translated, chapter)?;
+        }
+    }
+    // 类别与译名分开保存，待审核的词条接受后沿用提取时的类别
+    let categories: HashMap<String, KeywordCategory> = found
+        .iter()
+        .filter_map(|k| Some((k.japanese.clone(), k.category?)))
+        .collect();
+    if !categories.is_empty() {
+        pipeline.category_store.save(novel_id, &categories)?;
+    }
+    if pipeline.review_keywords {
+        let global = pipeline.global_keywords().await;
+        let pending: HashMap<String, String> = found
+            .into_iter()
+            .filter(|k| !keywords.contains_key(&k.japanese) && !global.contains_key(&k.japanese))
+            .map(|k| (k.japanese, k.translated))
+            .collect();
+        if pending.is_empty() {
+            return Ok(());
+        }
+        return pipeline.pending_store.save(novel_id, &pending).await;
+    }
+    for k in found {
+        keywords.entry(k.japanese).or_insert(k.translated);
+    }
+    pipeline.kw_store.save(novel_id, keywords).await
+}
+
+/// 翻译尚未缓存译名的章节标题并保存，返回本次新增的译名；模型漏译的标题不保存，
+/// 下次调用时重试
+pub async fn translate_missing_titles(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    chapters: &[Chapter],
+    ctx: &TranslationContext,
+    cancel: &CancellationToken,
+) -> Result<HashMap<String, String>> {
+    let cached = pipeline.title_store.load(novel_id)?;
+    let missing: Vec<&Chapter> = chapters
+        .iter()
+        .filter(|c| !cached.contains_key(&c.path))
+        .collect();
+    if missing.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let originals: Vec<String> = missing.iter().map(|c| c.title.clone()).collect();
+    let ctx = &ctx
+        .clone()
+        .with_settings(&pipeline.settings_store.load(novel_id)?);
+    let translated = pipeline
+        .translator
+        .translate_titles(&originals, ctx, cancel)
+        .await
+        .inspect_err(|e| metrics::record_failure("titles", e))?;
+    let titles: HashMap<String, String> = missing
+        .iter()
+        .map(|c| c.path.clone())
+        .zip(translated)
+        .filter_map(|(path, title)| {
+            let title = enforce_honorifics(ctx, &path, title?);
+            Some((path, title))
+        })
+        .collect();
+    if !titles.is_empty() {
+        pipeline.title_store.save(novel_id, &titles)?;
+    }
+    Ok(titles)
+}
+
+/// 作品简介译文在标题缓存中使用的键；章节路径都是地址，不会与之冲突
+const SYNOPSIS_KEY: &str = "#synopsis";
+
+/// 翻译作品简介并缓存，已有译文时直接返回
+pub async fn translate_synopsis(
+    pipeline: &Pipeline,
+    novel_id: &str,
+    synopsis: &str,
+    ctx: &TranslationContext,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    if let Some(cached) = pipeline.title_store.load(novel_id)?.remove(SYNOPSIS_KEY) {
+        return Ok(cached);
+    }
+    let ctx = ctx
+        .clone()
+        .with_settings(&pipeline.settings_store.load(novel_id)?);
+    let translated = pipeline
+        .translator
+        .translate_text(synopsis, &ctx, cancel)
+        .await
+        .inspect_err(|e| metrics::record_failure("synopsis", e))?;
+    pipeline.title_store.save(
+        novel_id,
+        &HashMap::from([(SYNOPSIS_KEY.to_string(), translated.clone())]),
+    )?;
+    Ok(translated)
+}
+
+/// 用已缓存的标题译文替换章节标题
+pub fn with_translated_titles(
+    chapters: &[Chapter],
+    titles: &HashMap<String, String>,
+) -> Vec<Chapter> {
+    chapters
+        .iter()
+        .map(|c| Chapter {
+            title: titles
+                .get(&c.path)
+                .cloned()
+                .unwrap_or_else(|| c.title.clone()),
+            ..c.clone()
+        })
+        .collect()
+}
@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{Semaphore, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::control::{ControlCommand, ControlRequest};
+use crate::metrics;
+use crate::pipeline::{Pipeline, commit_keywords, process_chapter, record_stamps};
+use crate::syosetu::{Chapter, cancellable, is_cancelled};
+
+/// `serve` 模式下提供的小说
+pub struct ServedNovel {
+    /// 目录页地址
+    pub url: String,
+    /// 小说 id，同时用作 API 路径
+    pub novel_id: String,
+    /// 该小说使用的站点与存储
+    pub pipeline: Pipeline,
+}
+
+/// 接口之间共享的状态
+struct ServerState {
+    novels: Vec<ServedNovel>,
+    /// 各小说的目录缓存
+    directories: Mutex<HashMap<String, Vec<Chapter>>>,
+    /// 各小说的标题缓存，用于提示词中的 `{novel_title}`
+    titles: Mutex<HashMap<String, String>>,
+    /// 已排队的章节，键为 (小说 id, 章节路径)
+    jobs: Mutex<HashMap<(String, String), Job>>,
+    /// 最近一次翻译失败的原因，重新排队或翻译成功后清除
+    failures: Mutex<HashMap<(String, String), String>>,
+    /// 控制 socket 的 pause 命令设置，暂停期间不再开始新的翻译
+    paused: watch::Sender<bool>,
+    /// 同时翻译的章节数上限，与界面共用 `--concurrency`
+    slots: Arc<Semaphore>,
+    /// 根取消令牌，关闭服务时取消所有翻译任务
+    cancel: CancellationToken,
+}
+
+/// 已排队的翻译任务
+struct Job {
+    token: CancellationToken,
+    /// 是否已取得翻译名额
+    running: bool,
+}
+
+/// 接口错误，以 `{"error": "..."}` 形式返回
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({"error": self.1}))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        error!("API error: {e:?}");
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+fn not_found(what: String) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, format!("{what} not found"))
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+impl ServerState {
+    fn novel(&self, id: &str) -> ApiResult<&ServedNovel> {
+        self.novels
+            .iter()
+            .find(|n| n.novel_id == id)
+            .ok_or_else(|| not_found(format!("novel {id}")))
+    }
+
+    /// 取得目录，首次访问时抓取
+    async fn directory(&self, novel: &ServedNovel) -> Result<Vec<Chapter>> {
+        if let Some(chapters) = self.directories.lock().unwrap().get(&novel.novel_id) {
+            return Ok(chapters.clone());
+        }
+        let chapters = novel
+            .pipeline
+            .site
+            .fetch_directory(&novel.url, &self.cancel)
+            .await?;
+        self.directories
+            .lock()
+            .unwrap()
+            .insert(novel.novel_id.clone(), chapters.clone());
+        Ok(chapters)
+    }
+
+    /// 取得小说标题，首次访问时抓取；抓取失败时退回小说 id 且不缓存
+    async fn title(&self, novel: &ServedNovel) -> String {
+        if let Some(title) = self.titles.lock().unwrap().get(&novel.novel_id) {
+            return title.clone();
+        }
+        match novel
+            .pipeline
+            .site
+            .fetch_info(&novel.url, &self.cancel)
+            .await
+        {
+            Ok(info) => {
+                let title = if info.title.is_empty() {
+                    novel.novel_id.clone()
+                } else {
+                    info.title
+                };
+                self.titles
+                    .lock()
+                    .unwrap()
+                    .insert(novel.novel_id.clone(), title.clone());
+                title
+            }
+            Err(e) => {
+                error!("Failed to fetch novel info for {}: {e:?}", novel.novel_id);
+                novel.novel_id.clone()
+            }
+        }
+    }
+
+    /// 取消已排队的任务，返回是否有任务被取消
+    fn cancel_job(&self, id: &str, path: &str) -> bool {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&(id.to_string(), path.to_string()));
+        job.inspect(|job| job.token.cancel()).is_some()
+    }
+
+    /// 按从 1 开始的序号取得章节
+    async fn chapter(&self, novel: &ServedNovel, n: usize) -> ApiResult<Chapter> {
+        self.directory(novel)
+            .await?
+            .get(n.wrapping_sub(1))
+            .cloned()
+            .ok_or_else(|| not_found(format!("chapter {n}")))
+    }
+}
+
+async fn list_novels(State(state): State<Arc<ServerState>>) -> ApiResult<Json<Value>> {
+    let mut novels = Vec::new();
+    for novel in &state.novels {
+        let cached = novel
+            .pipeline
+            .trans_store
+            .list(&novel.novel_id)
+            .await?
+            .len();
+        novels.push(json!({"id": novel.novel_id, "url": novel.url, "cached": cached}));
+    }
+    Ok(Json(Value::Array(novels)))
+}
+
+async fn list_chapters(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Value>> {
+    let novel = state.novel(&id)?;
+    let chapters = state.directory(novel).await?;
+    let cached = novel.pipeline.trans_store.list(&id).await?;
+    let titles = novel.pipeline.title_store.load(&id)?;
+    let jobs = state.jobs.lock().unwrap();
+    let failures = state.failures.lock().unwrap();
+    let list = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let key = (id.clone(), c.path.clone());
+            let error = failures.get(&key);
+            let status = match jobs.get(&key) {
+                _ if cached.contains(&c.path) => "cached",
+                Some(job) if job.running => "processing",
+                Some(_) => "waiting",
+                None if error.is_some() => "failed",
+                None => "pending",
+            };
+            json!({
+                "index": i + 1,
+                "path": c.path,
+                "title": c.title,
+                "translated_title": titles.get(&c.path),
+                "status": status,
+                "error": error,
+            })
+        })
+        .collect();
+    Ok(Json(Value::Array(list)))
+}
+
+async fn get_chapter(
+    State(state): State<Arc<ServerState>>,
+    Path((id, n)): Path<(String, usize)>,
+) -> ApiResult<Json<Value>> {
+    let novel = state.novel(&id)?;
+    let chapter = state.chapter(novel, n).await?;
+    let translation = novel
+        .pipeline
+        .trans_store
+        .load(&id, &chapter.path)
+        .await?
+        .ok_or_else(|| not_found(format!("translation of chapter {n}")))?;
+    Ok(Json(json!({
+        "index": n,
+        "path": chapter.path,
+        "title": chapter.title,
+        "translation": translation,
+    })))
+}
+
+async fn queue_chapter(
+    State(state): State<Arc<ServerState>>,
+    Path((id, n)): Path<(String, usize)>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let status = queue(&state, &id, n).await?;
+    let code = match status {
+        "cached" => StatusCode::OK,
+        _ => StatusCode::ACCEPTED,
+    };
+    Ok((code, Json(json!({"status": status}))))
+}
+
+/// 将第 n 章加入翻译，返回 `cached` 或 `processing`
+async fn queue(state: &Arc<ServerState>, id: &str, n: usize) -> ApiResult<&'static str> {
+    let novel = state.novel(id)?;
+    let chapter = state.chapter(novel, n).await?;
+    if novel
+        .pipeline
+        .trans_store
+        .load(id, &chapter.path)
+        .await?
+        .is_some()
+    {
+        return Ok("cached");
+    }
+    let key = (id.to_string(), chapter.path.clone());
+    if state.jobs.lock().unwrap().contains_key(&key) {
+        return Ok("processing");
+    }
+    // 先准备好上下文再登记任务，失败时不会留下永远“处理中”的任务
+    let pipeline = novel.pipeline.clone();
+    let previous = match n {
+        0 | 1 => None,
+        _ => state.chapter(novel, n - 1).await.ok().map(|c| c.path),
+    };
+    let title = state.title(novel).await;
+    let ctx = pipeline
+        .context(
+            pipeline.kw_store.load(id).await?.into_iter().collect(),
+            &title,
+        )
+        .await
+        .with_previous_chapter(previous);
+    let token = {
+        let mut jobs = state.jobs.lock().unwrap();
+        if jobs.contains_key(&key) {
+            return Ok("processing");
+        }
+        let token = state.cancel.child_token();
+        let job = Job {
+            token: token.clone(),
+            running: false,
+        };
+        jobs.insert(key.clone(), job);
+        metrics::QUEUE_DEPTH.set(jobs.len() as i64);
+        state.failures.lock().unwrap().remove(&key);
+        token
+    };
+    let state = state.clone();
+    tokio::spawn(async move {
+        let (novel_id, path) = &key;
+        let slots = state.slots.clone();
+        let mut paused = state.paused.subscribe();
+        // 等待恢复与名额期间也可取消
+        let waited = async {
+            paused.wait_for(|paused| !paused).await?;
+            Ok(slots.acquire_owned().await?)
+        };
+        let result = match cancellable(&token, waited).await {
+            Ok(_permit) => {
+                if let Some(job) = state.jobs.lock().unwrap().get_mut(&key) {
+                    job.running = true;
+                }
+                process_chapter(&pipeline, novel_id, path, &ctx, None, &token).await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(found) => {
+                let saved = match pipeline.kw_store.load(novel_id).await {
+                    Ok(mut keywords) => {
+                        commit_keywords(&pipeline, novel_id, path, &mut keywords, found).await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = saved {
+                    error!("Failed to save keywords for {novel_id}: {e:?}");
+                }
+                if let Err(e) = record_stamps(&pipeline, novel_id, &[chapter]) {
+                    error!("Failed to save stamp for {path}: {e:?}");
+                }
+                info!("Translated {path}");
+            }
+            Err(e) if is_cancelled(&e) => info!("Cancelled {path}"),
+            Err(e) => {
+                error!("Chapter {path} failed: {e:?}");
+                state
+                    .failures
+                    .lock()
+                    .unwrap()
+                    .insert(key.clone(), format!("{e:#}"));
+            }
+        }
+        let mut jobs = state.jobs.lock().unwrap();
+        jobs.remove(&key);
+        metrics::QUEUE_DEPTH.set(jobs.len() as i64);
+    });
+    Ok("processing")
+}
+
+async fn cancel_chapter(
+    State(state): State<Arc<ServerState>>,
+    Path((id, n)): Path<(String, usize)>,
+) -> ApiResult<Json<Value>> {
+    let novel = state.novel(&id)?;
+    let chapter = state.chapter(novel, n).await?;
+    let cancelled = state.cancel_job(&id, &chapter.path);
+    Ok(Json(json!({"cancelled": cancelled})))
+}
+
+/// 执行控制 socket 发来的命令；章节可用路径或从 1 开始的序号指定，
+/// 只提供一部小说时可省略 `novel`
+async fn handle_control(
+    state: &Arc<ServerState>,
+    novel: Option<String>,
+    command: ControlCommand,
+) -> ApiResult<Value> {
+    let chapter = |reference: String| async move {
+        let novel = match novel {
+            Some(id) => state.novel(&id)?,
+            None if state.novels.len() == 1 => &state.novels[0],
+            None => {
+                let message = "missing `novel` param".to_string();
+                return Err(ApiError(StatusCode::BAD_REQUEST, message));
+            }
+        };
+        let n = match reference.parse::<usize>() {
+            Ok(n) => n,
+            Err(_) => {
+                let chapters = state.directory(novel).await?;
+                let i = chapters.iter().position(|c| c.path == reference);
+                i.map(|i| i + 1)
+                    .ok_or_else(|| not_found(format!("chapter {reference}")))?
+            }
+        };
+        let chapter = state.chapter(novel, n).await?;
+        Ok((novel.novel_id.clone(), n, chapter.path))
+    };
+    match command {
+        ControlCommand::Status => {
+            let jobs = state.jobs.lock().unwrap();
+            let listed = |running: bool| {
+                jobs.iter()
+                    .filter(|(_, job)| job.running == running)
+                    .map(|((novel, path), _)| json!({"novel": novel, "path": path}))
+                    .collect::<Vec<_>>()
+            };
+            let failed: Vec<_> = state
+                .failures
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|((novel, path), error)| json!({"novel": novel, "path": path, "error": error}))
+                .collect();
+            Ok(json!({
+                "novels": state.novels.iter().map(|n| &n.novel_id).collect::<Vec<_>>(),
+                "processing": listed(true),
+                "waiting": listed(false),
+                "failed": failed,
+                "paused": *state.paused.borrow(),
+            }))
+        }
+        ControlCommand::Queue(reference) => {
+            let (id, n, path) = chapter(reference).await?;
+            let status = queue(state, &id, n).await?;
+            Ok(json!({"novel": id, "path": path, "status": status}))
+        }
+        ControlCommand::Cancel(reference) => {
+            let (id, _, path) = chapter(reference).await?;
+            let cancelled = state.cancel_job(&id, &path);
+            Ok(json!({"novel": id, "path": path, "cancelled": cancelled}))
+        }
+        ControlCommand::Pause | ControlCommand::Resume => {
+            let paused = matches!(command, ControlCommand::Pause);
+            state.paused.send_replace(paused);
+            Ok(json!({"paused": paused}))
+        }
+    }
+}
+
+async fn get_glossary(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<HashMap<String, String>>> {
+    let novel = state.novel(&id)?;
+    Ok(Json(novel.pipeline.kw_store.load(&id).await?))
+}
+
+/// `PUT /api/novels/{id}/glossary/{term}` 的请求体
+#[derive(Deserialize)]
+struct TermBody {
+    translation: String,
+}
+
+async fn put_term(
+    State(state): State<Arc<ServerState>>,
+    Path((id, term)): Path<(String, String)>,
+    Json(body): Json<TermBody>,
+) -> ApiResult<Json<Value>> {
+    let novel = state.novel(&id)?;
+    novel
+        .pipeline
+        .kw_store
+        .set(&id, &term, &body.translation)
+        .await?;
+    Ok(Json(json!({"term": term, "translation": body.translation})))
+}
+
+async fn delete_term(
+    State(state): State<Arc<ServerState>>,
+    Path((id, term)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    let novel = state.novel(&id)?;
+    if novel.pipeline.kw_store.remove(&id, &term).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found(format!("term {term}")))
+    }
+}
+
+/// REST API 路由，所有响应均为 JSON，错误形如 `{"error": "..."}`：
+///
+/// | 方法与路径 | 说明 |
+/// |---|---|
+/// | `GET /api/novels` | 小说列表：`id`、`url`、已缓存章节数 `cached` |
+/// | `GET /api/novels/{id}/chapters` | 章节列表：`index`、`path`、`title`、`translated_title`、`status`（`cached`/`processing`/`waiting`/`pending`/`failed`），失败时 `error` 为最近一次的原因 |
+/// | `GET /api/novels/{id}/chapters/{n}` | 第 n 章（从 1 开始）的译文，未翻译时 404 |
+/// | `POST /api/novels/{id}/chapters/{n}/translate` | 将章节加入翻译，返回 202；已缓存时返回 200；同时翻译的章节数受 `slots` 限制，其余章节为 `waiting` |
+/// | `DELETE /api/novels/{id}/chapters/{n}/translate` | 取消正在进行的翻译 |
+/// | `GET /api/novels/{id}/glossary` | 专有名词表 `{原文: 译文}` |
+/// | `PUT /api/novels/{id}/glossary/{term}` | 新增或修改词条，请求体 `{"translation": "..."}` |
+/// | `DELETE /api/novels/{id}/glossary/{term}` | 删除词条，返回 204 |
+///
+/// 给出 `control` 时在后台执行控制 socket 的命令（须在 tokio 运行时中调用）；
+/// 取消 `cancel` 时停止所有翻译任务
+pub fn router(
+    novels: Vec<ServedNovel>,
+    slots: Arc<Semaphore>,
+    control: Option<UnboundedReceiver<ControlRequest>>,
+    cancel: CancellationToken,
+) -> Router {
+    let state = Arc::new(ServerState {
+        novels,
+        directories: Mutex::new(HashMap::new()),
+        titles: Mutex::new(HashMap::new()),
+        jobs: Mutex::new(HashMap::new()),
+        failures: Mutex::new(HashMap::new()),
+        paused: watch::Sender::new(false),
+        slots,
+        cancel,
+    });
+    if let Some(mut rx) = control {
+        let state = state.clone();
+        tokio::spawn(async move {
+            while let Some(req) = rx.recv().await {
+                let reply = handle_control(&state, req.novel, req.command).await;
+                let _ = req.reply.send(reply.map_err(|e| e.1));
+            }
+        });
+    }
+    Router::new()
+        .route("/api/novels", get(list_novels))
+        .route("/api/novels/{id}/chapters", get(list_chapters))
+        .route("/api/novels/{id}/chapters/{n}", get(get_chapter))
+        .route(
+            "/api/novels/{id}/chapters/{n}/translate",
+            post(queue_chapter).delete(cancel_chapter),
+        )
+        .route("/api/novels/{id}/glossary", get(get_glossary))
+        .route(
+            "/api/novels/{id}/glossary/{term}",
+            put(put_term).delete(delete_term),
+        )
+        .with_state(state)
+}
+
+/// 在指定地址提供 REST API，直到令牌被取消
+pub async fn serve(
+    addr: SocketAddr,
+    novels: Vec<ServedNovel>,
+    slots: Arc<Semaphore>,
+    control: Option<UnboundedReceiver<ControlRequest>>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Serving REST API on http://{addr}/api");
+    axum::serve(listener, router(novels, slots, control, cancel.clone()))
+        .with_graceful_shutdown(async move { cancel.cancelled().await })
+        .await?;
+    Ok(())
+}
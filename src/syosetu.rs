@@ -1,336 +1,2694 @@
-use std::sync::Arc;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, LazyLock, Mutex};
 
-use anyhow::{anyhow, Result};
-use reqwest::Client;
-use curl::easy::{Easy2, Handler, HttpVersion, List, WriteError};
-use scraper::{Html, Selector};
+use anyhow::{Result, anyhow, bail};
 use async_trait::async_trait;
+use curl::easy::{Easy2, Handler, HttpVersion, List, WriteError};
+use encoding_rs::{Encoding, SHIFT_JIS};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::crawl::CrawlPolicy;
+use crate::http::{CookieJar, HttpClient, HttpOptions, build_client, cookie_header};
+
+struct Sink {
+    data: Vec<u8>,
+    cancel: CancellationToken,
+}
+
+impl Handler for Sink {
+    fn write(&mut self, data: &[u8]) -> std::result::Result<usize, WriteError> {
+        self.data.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    /// 返回 false 时 curl 会中止传输
+    fn progress(&mut self, _dltotal: f64, _dlnow: f64, _ultotal: f64, _ulnow: f64) -> bool {
+        !self.cancel.is_cancelled()
+    }
+}
+
+/// 任务被取消时返回的错误
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// 判断错误是否由取消引起
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.is::<Cancelled>()
+}
+
+/// 执行 future，若令牌先被取消则立即返回 `Cancelled`
+pub async fn cancellable<T, F>(cancel: &CancellationToken, fut: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(Cancelled.into()),
+        res = fut => res,
+    }
+}
+
+/// 是否为可能自行恢复的网络错误；中间件拒绝的请求（如被 robots.txt 禁止）与
+/// 4xx 状态码不算
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    if let Some(status) = err.chain().find_map(|e| e.downcast_ref::<StatusError>()) {
+        return status.is_transient();
+    }
+    err.chain().any(|e| {
+        e.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+            e.status()
+                .is_none_or(|s| s.is_server_error() || s.as_u16() == 429)
+        }) || matches!(
+            e.downcast_ref::<reqwest_middleware::Error>(),
+            Some(reqwest_middleware::Error::Reqwest(_))
+        ) || e.is::<curl::Error>()
+            || e.is::<std::io::Error>()
+    })
+}
+
+/// 目录中每个章节的基本信息
+#[derive(Clone, Default)]
+pub struct Chapter {
+    /// 章节的完整网址
+    pub path: String,
+    /// 章节标题
+    pub title: String,
+    /// 目录中显示的发布时间
+    pub published: Option<String>,
+    /// 目录中显示的改稿时间
+    pub updated: Option<String>,
+    /// 所属的章（卷）标题，目录没有分组时为 `None`
+    pub arc: Option<String>,
+}
+
+impl Chapter {
+    /// 章节在目录中的版本标记：有改稿时间时为改稿时间，否则为发布时间
+    pub fn stamp(&self) -> Option<&str> {
+        self.updated.as_deref().or(self.published.as_deref())
+    }
+}
+
+/// 原文中前書き与後書き的标题
+pub const NOTE_LABELS_JA: (&str, &str) = ("【前書き】", "【後書き】");
+
+/// 译文中前言与后记的标题
+pub const NOTE_LABELS_ZH: (&str, &str) = ("【作者前言】", "【作者后记】");
+
+/// 英文译文中前言与后记的标题
+pub const NOTE_LABELS_EN: (&str, &str) = ("[Author's Foreword]", "[Author's Afterword]");
+
+/// 韩文译文中前言与后记的标题
+pub const NOTE_LABELS_KO: (&str, &str) = ("【작가의 말】", "【후기】");
+
+/// 前言、后记与正文之间的分隔线
+const NOTE_SEPARATOR: &str = "――――――――";
+
+/// 作品信息，未知的项目为空
+#[derive(Clone, Debug, Default)]
+pub struct NovelInfo {
+    pub title: String,
+    pub author: String,
+    /// 作品简介（あらすじ）
+    pub synopsis: String,
+    pub tags: Vec<String>,
+}
+
+/// 一章的正文及作者写在正文前后的前書き、後書き
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ChapterSections {
+    pub preface: Option<String>,
+    pub body: String,
+    pub afterword: Option<String>,
+    /// 插图地址，文本中的 `[挿絵 #n]` 对应第 n 项
+    pub images: Vec<String>,
+}
+
+impl ChapterSections {
+    /// 将解析时留下的插图标记替换为 `[挿絵 #n]`，并把相对地址按 `base` 补全后收集到 `images`
+    pub fn with_illustrations(mut self, base: &str) -> Self {
+        let base = reqwest::Url::parse(base).ok();
+        let mut images = std::mem::take(&mut self.images);
+        let mut replace = |text: &str| {
+            ILLUSTRATION_MARK
+                .replace_all(text, |caps: &regex::Captures| {
+                    let src = &caps[1];
+                    images.push(
+                        base.as_ref()
+                            .and_then(|b| b.join(src).ok())
+                            .map_or_else(|| src.to_string(), String::from),
+                    );
+                    format!("[挿絵 #{}]", images.len())
+                })
+                .into_owned()
+        };
+        self.preface = self.preface.as_deref().map(&mut replace);
+        self.body = replace(&self.body);
+        self.afterword = self.afterword.as_deref().map(&mut replace);
+        self.images = images;
+        self
+    }
+
+    /// 合并为一段文本，前言与后记加上 `labels` 中的标题并用分隔线与正文隔开
+    pub fn join(&self, labels: (&str, &str)) -> String {
+        let mut text = String::new();
+        if let Some(preface) = &self.preface {
+            text.push_str(&format!("{}\n{preface}\n{NOTE_SEPARATOR}\n", labels.0));
+        }
+        text.push_str(&self.body);
+        if let Some(afterword) = &self.afterword {
+            text.push_str(&format!("\n{NOTE_SEPARATOR}\n{}\n{afterword}", labels.1));
+        }
+        text
+    }
+}
+
+/// 从 `ChapterSections::join` 合并的文本中取出正文部分
+pub fn joined_body<'a>(text: &'a str, labels: (&str, &str)) -> &'a str {
+    let mut body = text;
+    if body.starts_with(labels.0)
+        && let Some((_, rest)) = body.split_once(&format!("\n{NOTE_SEPARATOR}\n"))
+    {
+        body = rest;
+    }
+    if let Some((rest, _)) = body.rsplit_once(&format!("\n{NOTE_SEPARATOR}\n{}\n", labels.1)) {
+        body = rest;
+    }
+    body
+}
+
+/// 解析页面内容时可能出现的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+    /// 选择器语法错误
+    Selector(String),
+    /// 页面中找不到正文容器
+    BodyNotFound,
+    /// 正文容器存在但没有任何文本
+    EmptyBody,
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Selector(e) => write!(f, "selector parse error: {e}"),
+            ExtractError::BodyNotFound => f.write_str("body not found"),
+            ExtractError::EmptyBody => f.write_str("body is empty"),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// 页面返回了非成功的状态码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusError {
+    pub url: String,
+    pub status: u16,
+}
+
+impl StatusError {
+    /// 5xx 与 429 可能自行恢复，其余状态码重试也不会成功
+    pub fn is_transient(&self) -> bool {
+        self.status >= 500 || self.status == 429
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} returned HTTP {}", self.url, self.status)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+fn selector(css: &str) -> Result<Selector, ExtractError> {
+    Selector::parse(css).map_err(|e| ExtractError::Selector(e.to_string()))
+}
+
+/// 取出匹配选择器的第一个元素的文本，每个文本节点一行
+fn extract_body(html: &str, css: &str) -> Result<String, ExtractError> {
+    let document = Html::parse_document(html);
+    let body_selector = selector(css)?;
+    let element = document
+        .select(&body_selector)
+        .next()
+        .ok_or(ExtractError::BodyNotFound)?;
+    let mut lines = Vec::new();
+    collect_lines(element, &mut lines, &mut None);
+    let content = lines.join("\n");
+    if content.is_empty() {
+        return Err(ExtractError::EmptyBody);
+    }
+    Ok(content)
+}
+
+/// 收集元素中的文本，每个文本节点一行；`<ruby>` 转为 `｜親文字《読み》`，
+/// 并与同一父元素下相邻的文本连成一行。`last` 记录上一段文本的父元素及是否为注音
+fn collect_lines<'a>(
+    element: ElementRef<'a>,
+    lines: &mut Vec<String>,
+    last: &mut Option<(ElementRef<'a>, bool)>,
+) {
+    for child in element.children() {
+        let (piece, is_ruby) = match child.value() {
+            Node::Text(t) => (t.trim().to_string(), false),
+            Node::Element(e) if e.name() == "ruby" => match ElementRef::wrap(child) {
+                Some(ruby) => (ruby_markup(ruby), true),
+                None => continue,
+            },
+            // 插图单独占一行，地址留在标记中，由 `ChapterSections::with_illustrations` 编号
+            Node::Element(e) if e.name() == "img" => {
+                if let Some(src) = e.attr("data-src").or(e.attr("src")) {
+                    lines.push(format!("［＃挿絵（{}）入る］", src.trim()));
+                    *last = None;
+                }
+                continue;
+            }
+            Node::Element(_) => {
+                if let Some(el) = ElementRef::wrap(child) {
+                    collect_lines(el, lines, last);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if piece.is_empty() {
+            continue;
+        }
+        let glue = matches!(*last, Some((parent, prev_ruby)) if parent == element && (prev_ruby || is_ruby));
+        match lines.last_mut() {
+            Some(line) if glue => line.push_str(&piece),
+            _ => lines.push(piece),
+        }
+        *last = Some((element, is_ruby));
+    }
+}
+
+/// 将 `<ruby>` 元素转为 `｜親文字《読み》`，没有读音时只保留親文字
+fn ruby_markup(ruby: ElementRef) -> String {
+    let mut base = String::new();
+    let mut reading = String::new();
+    for node in ruby.descendants() {
+        let Node::Text(t) = node.value() else {
+            continue;
+        };
+        let parent = node.ancestors().filter_map(ElementRef::wrap).next();
+        match parent.map(|p| p.value().name()) {
+            Some("rt") => reading.push_str(t.trim()),
+            Some("rp") => {}
+            _ => base.push_str(t.trim()),
+        }
+    }
+    if reading.is_empty() || base.is_empty() {
+        base
+    } else {
+        format!("｜{base}《{reading}》")
+    }
+}
+
+/// `｜親文字《読み》` 形式的注音；只识别带 `｜` 的写法，以免与中文书名号混淆
+static RUBY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"｜([^｜《》\n]+)《([^《》\n]+)》").unwrap());
+
+/// 去掉注音，只保留親文字
+pub fn strip_ruby(text: &str) -> String {
+    RUBY.replace_all(text, "$1").into_owned()
+}
+
+/// 将注音显示为 `親文字（読み）`，用于终端等纯文本场合
+pub fn ruby_to_parens(text: &str) -> String {
+    RUBY.replace_all(text, "$1（$2）").into_owned()
+}
+
+/// 将注音转换为 HTML `<ruby>` 标签，输入应已转义
+pub fn ruby_to_html(text: &str) -> String {
+    RUBY.replace_all(text, "<ruby>$1<rt>$2</rt></ruby>")
+        .into_owned()
+}
+
+/// 解析正文时插入的插图标记（青空文庫的注記格式），其中保存原始地址
+static ILLUSTRATION_MARK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"［＃挿絵（([^）\n]+)）入る］").unwrap());
+
+/// 单独成行的插图占位符；译文中可能被译为「插图」
+static ILLUSTRATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[(?:挿絵|插图) #(\d+)\]$").unwrap());
+
+/// 若该行是插图占位符 `[挿絵 #n]`，返回从 0 开始的插图序号
+pub fn illustration_index(line: &str) -> Option<usize> {
+    let caps = ILLUSTRATION.captures(line.trim())?;
+    caps[1].parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// 在插图占位符后附上地址，用于终端等纯文本场合
+pub fn illustrations_to_links(text: &str, images: &[String]) -> String {
+    text.lines()
+        .map(
+            |line| match illustration_index(line).and_then(|i| images.get(i)) {
+                Some(url) => format!("{} {url}", line.trim()),
+                None => line.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 小説家になろう（全年龄）的域名
+pub const NCODE_ORIGIN: &str = "https://ncode.syosetu.com";
+
+/// ノクターン／ムーンライトノベルズ（R18）的域名
+pub const NOVEL18_ORIGIN: &str = "https://novel18.syosetu.com";
+
+/// 解析 ncode.syosetu.com 与 novel18.syosetu.com 目录页中的章节列表，
+/// 相对链接基于 `origin` 补全，章节归入其前面最近的章标题
+pub fn parse_ncode_directory(html: &str, origin: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let item_selector = selector(".p-eplist__chapter-title, a.p-eplist__subtitle")?;
+    let update_selector = selector(".p-eplist__update")?;
+    let mut arc = None;
+    let mut links = Vec::new();
+    for el in document.select(&item_selector) {
+        let text = el
+            .text()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("");
+        if el.value().name() != "a" {
+            arc = Some(text).filter(|t| !t.is_empty());
+            continue;
+        }
+        let Some(href) = el.value().attr("href").filter(|h| !h.is_empty()) else {
+            continue;
+        };
+        let full = if href.starts_with("http") {
+            href.to_string()
+        } else {
+            format!("{origin}{href}")
+        };
+        let (published, updated) = el
+            .parent()
+            .and_then(ElementRef::wrap)
+            .map(|sublist| ncode_update_dates(sublist, &update_selector))
+            .unwrap_or_default();
+        links.push(Chapter {
+            path: full,
+            title: text,
+            published,
+            updated,
+            arc: arc.clone(),
+        });
+    }
+    Ok(links)
+}
+
+/// 取出目录条目中的发布时间与改稿时间，
+/// 改稿时间位于 `<span title="2020/02/02 12:00 改稿">` 中
+fn ncode_update_dates(
+    sublist: ElementRef,
+    update_selector: &Selector,
+) -> (Option<String>, Option<String>) {
+    let Some(update) = sublist.select(update_selector).next() else {
+        return (None, None);
+    };
+    let published = update
+        .text()
+        .map(str::trim)
+        .find(|t| !t.is_empty())
+        .map(str::to_string);
+    let updated = update
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find_map(|el| el.value().attr("title"))
+        .map(|t| t.trim_end_matches("改稿").trim().to_string())
+        .filter(|t| !t.is_empty());
+    (published, updated)
+}
+
+/// 短編没有目录，目录地址即正文页；页面包含正文时返回指向该页的单个章节
+pub fn parse_ncode_short_story(html: &str, url: &str) -> Option<Chapter> {
+    let document = Html::parse_document(html);
+    let body = selector("div.p-novel__body").ok()?;
+    document.select(&body).next()?;
+    let title =
+        first_text(&document, &["h1.p-novel__title", "title"]).unwrap_or_else(|| url.to_string());
+    Some(Chapter {
+        path: url.to_string(),
+        title,
+        ..Default::default()
+    })
+}
+
+/// 按顺序尝试选择器，返回第一个非空元素的文本
+fn first_text(document: &Html, selectors: &[&str]) -> Option<String> {
+    selectors
+        .iter()
+        .filter_map(|s| selector(s).ok())
+        .find_map(|sel| {
+            let text = document.select(&sel).next()?.text().collect::<String>();
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        })
+}
+
+/// 解析 ncode 目录页（或短編页面）顶部的作品信息；目录页没有标签
+pub fn parse_ncode_info(html: &str) -> Result<NovelInfo, ExtractError> {
+    let document = Html::parse_document(html);
+    let title = first_text(&document, &["h1.p-novel__title", "p.novel_title", "title"])
+        .ok_or(ExtractError::BodyNotFound)?;
+    let author = first_text(
+        &document,
+        &[
+            ".p-novel__author a",
+            ".p-novel__author",
+            "div.novel_writername a",
+        ],
+    )
+    .unwrap_or_default();
+    let synopsis = match extract_body(html, "div#novel_ex, div.p-novel__summary") {
+        Ok(text) => text,
+        Err(ExtractError::BodyNotFound | ExtractError::EmptyBody) => String::new(),
+        Err(e) => return Err(e),
+    };
+    Ok(NovelInfo {
+        title,
+        author: author.trim_start_matches("作者：").trim().to_string(),
+        synopsis,
+        ..Default::default()
+    })
+}
+
+/// なろう小説 API（全年龄）
+const NAROU_API: &str = "https://api.syosetu.com/novelapi/api/";
+
+/// なろう小説 API（R18）
+const NAROU18_API: &str = "https://api.syosetu.com/novel18api/api/";
+
+/// なろう小説 API 返回的作品信息
+#[derive(Clone, Debug)]
+pub struct NarouInfo {
+    /// 小写的作品代码，如 `n1234ab`
+    pub ncode: String,
+    pub title: String,
+    pub writer: String,
+    /// 已发布的话数
+    pub chapter_count: usize,
+    /// 是否为短編
+    pub short_story: bool,
+    /// 最后一次发布章节的时间
+    pub last_update: String,
+    /// 作品信息最后更新的时间
+    pub updated_at: String,
+    /// あらすじ
+    pub story: String,
+    /// 作者设置的关键词
+    pub keywords: Vec<String>,
+}
+
+impl From<NarouInfo> for NovelInfo {
+    fn from(info: NarouInfo) -> Self {
+        NovelInfo {
+            title: info.title,
+            author: info.writer,
+            synopsis: info.story,
+            tags: info.keywords,
+        }
+    }
+}
+
+/// 从地址中取出作品代码（`n` 开头，后接数字与字母）
+pub fn ncode_from_url(url: &str) -> Option<String> {
+    static NCODE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i)^n\d{4}[a-z]{1,3}$").unwrap());
+    url.split(['/', '?', '#'])
+        .find(|segment| NCODE.is_match(segment))
+        .map(str::to_ascii_lowercase)
+}
+
+/// 解析なろう小説 API（`out=json`）的响应，第一个元素为命中数，第二个为作品
+pub fn parse_narou_api(json: &str) -> Result<NarouInfo, ExtractError> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|_| ExtractError::BodyNotFound)?;
+    let novel = value.get(1).ok_or(ExtractError::BodyNotFound)?;
+    let text = |key: &str| novel[key].as_str().unwrap_or_default().trim().to_string();
+    let ncode = text("ncode").to_ascii_lowercase();
+    if ncode.is_empty() {
+        return Err(ExtractError::BodyNotFound);
+    }
+    Ok(NarouInfo {
+        ncode,
+        title: text("title"),
+        writer: text("writer"),
+        chapter_count: novel["general_all_no"].as_u64().unwrap_or(0) as usize,
+        short_story: novel["novel_type"].as_u64() == Some(2),
+        last_update: text("general_lastup"),
+        updated_at: text("novelupdated_at"),
+        story: text("story"),
+        keywords: text("keyword")
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// 根据 API 信息生成章节列表；API 不提供各话标题，以「第N話」代替
+pub fn narou_api_chapters(info: &NarouInfo, origin: &str) -> Vec<Chapter> {
+    if info.short_story {
+        return vec![Chapter {
+            path: format!("{origin}/{}/", info.ncode),
+            title: info.title.clone(),
+            ..Default::default()
+        }];
+    }
+    (1..=info.chapter_count)
+        .map(|n| Chapter {
+            path: format!("{origin}/{}/{n}/", info.ncode),
+            title: format!("第{n}話"),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// なろう账号的登录表单提交地址
+const NAROU_LOGIN: &str = "https://ssl.syosetu.com/login/login/";
+
+/// なろう账号的ブックマーク列表
+const NAROU_BOOKMARKS: &str = "https://syosetu.com/favnovelmain/list/";
+
+/// ブックマーク列表最多分页数
+const NAROU_BOOKMARK_MAX_PAGES: usize = 50;
+
+/// ブックマーク中的一部作品
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NarouBookmark {
+    /// 作品目录页地址
+    pub url: String,
+    pub title: String,
+    /// しおり所在的话数，未设置时为 `None`
+    pub shiori: Option<usize>,
+}
+
+/// 解析ブックマーク列表页：指向作品目录的链接为标题，文字为しおり的单话链接为阅读进度
+pub fn parse_narou_bookmarks(html: &str) -> Vec<NarouBookmark> {
+    static EPISODE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i)^/(n\d{4}[a-z]{1,3})/(\d+)/?$").unwrap());
+    let document = Html::parse_document(html);
+    let sel = Selector::parse("a[href]").unwrap();
+    let mut bookmarks: Vec<NarouBookmark> = Vec::new();
+    for link in document.select(&sel) {
+        let href = link.value().attr("href").unwrap_or_default();
+        let Some(path) = href
+            .strip_prefix(NCODE_ORIGIN)
+            .or_else(|| href.strip_prefix(NOVEL18_ORIGIN))
+        else {
+            continue;
+        };
+        let origin = &href[..href.len() - path.len()];
+        let Some(ncode) = ncode_from_url(path) else {
+            continue;
+        };
+        let url = format!("{origin}/{ncode}/");
+        let index = match bookmarks.iter().position(|b| b.url == url) {
+            Some(index) => index,
+            None => {
+                bookmarks.push(NarouBookmark {
+                    url,
+                    ..Default::default()
+                });
+                bookmarks.len() - 1
+            }
+        };
+        let bookmark = &mut bookmarks[index];
+        let text = link.text().collect::<String>().trim().to_string();
+        // 指向某一话的链接还有「最新」等，只取文字为しおり的链接
+        if let Some(caps) = EPISODE.captures(path) {
+            if text.contains("しおり") {
+                bookmark.shiori = caps[2].parse().ok();
+            }
+        } else if bookmark.title.is_empty() {
+            bookmark.title = text;
+        }
+    }
+    bookmarks.retain(|b| !b.title.is_empty());
+    bookmarks
+}
+
+/// 目录最多分页数，防止异常页面导致请求过多
+const NCODE_MAX_PAGES: usize = 200;
+
+/// 从目录页的分页栏（`?p=N`）解析总页数，没有分页时为 1
+pub fn parse_ncode_page_count(html: &str) -> usize {
+    static PAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[?&]p=(\d+)").unwrap());
+    let document = Html::parse_document(html);
+    let Ok(pager) = selector(".c-pager a[href]") else {
+        return 1;
+    };
+    document
+        .select(&pager)
+        .filter_map(|el| PAGE.captures(el.value().attr("href")?))
+        .filter_map(|c| c[1].parse::<usize>().ok())
+        .max()
+        .unwrap_or(1)
+        .clamp(1, NCODE_MAX_PAGES)
+}
+
+/// 正文容器，按顺序尝试；新版页面中前書き与後書き是同级的 `p-novel__text`，需要排除
+const NCODE_BODY: [&str; 3] = [
+    "div.p-novel__text:not(.p-novel__text--preface):not(.p-novel__text--afterword)",
+    "div#novel_honbun",
+    "div.p-novel__body",
+];
+
+/// 解析 ncode.syosetu.com 章节页的正文，不含前書き与後書き
+pub fn parse_ncode_chapter(html: &str) -> Result<String, ExtractError> {
+    let mut result = Err(ExtractError::BodyNotFound);
+    for css in NCODE_BODY {
+        result = extract_body(html, css);
+        if !matches!(result, Err(ExtractError::BodyNotFound)) {
+            break;
+        }
+    }
+    result
+}
+
+/// 解析 ncode.syosetu.com 章节页的正文及前書き（`--preface`）、後書き（`--afterword`）
+pub fn parse_ncode_sections(html: &str) -> Result<ChapterSections, ExtractError> {
+    let note = |css: &str| match extract_body(html, css) {
+        Ok(text) => Ok(Some(text)),
+        Err(ExtractError::BodyNotFound | ExtractError::EmptyBody) => Ok(None),
+        Err(e) => Err(e),
+    };
+    Ok(ChapterSections {
+        preface: note("div.p-novel__text--preface, div#novel_p")?,
+        body: parse_ncode_chapter(html)?,
+        afterword: note("div.p-novel__text--afterword, div#novel_a")?,
+        ..Default::default()
+    })
+}
+
+/// 解析 syosetu.org 目录页中的章节列表，相对链接基于 `url` 补全
+pub fn parse_org_directory(html: &str, url: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let link_selector = selector("div.ss table a[href$='.html']")?;
+    let base = format!("{}/", url.trim_end_matches('/'));
+    let links = document
+        .select(&link_selector)
+        .filter_map(|el| {
+            let href = el.value().attr("href")?;
+            let title = el.text().collect::<Vec<_>>().join("");
+            let full = if href.starts_with("http") {
+                href.to_string()
+            } else {
+                format!("{}{}", base, href.trim_start_matches("./"))
+            };
+            Some(Chapter {
+                path: full,
+                title: title.trim().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(links)
+}
+
+/// 解析 syosetu.org 章节页的正文
+pub fn parse_org_chapter(html: &str) -> Result<String, ExtractError> {
+    extract_body(html, "div#honbun")
+}
+
+/// 从 kakuyomu.jp 的地址中取出作品 id
+fn kakuyomu_work_id(url: &str) -> Option<&str> {
+    let rest = &url[url.find("/works/")? + "/works/".len()..];
+    rest.split(['/', '?', '#'])
+        .next()
+        .filter(|id| !id.is_empty())
+}
+
+/// 从 `__NEXT_DATA__` 中的 Apollo 缓存读取目录，各对象之间通过 `__ref` 引用
+fn parse_kakuyomu_next_data(json: &str, work_id: &str) -> Option<Vec<Chapter>> {
+    let data: serde_json::Value = serde_json::from_str(json).ok()?;
+    let state = data.pointer("/props/pageProps/__APOLLO_STATE__")?;
+    let deref = |v: &serde_json::Value| {
+        v.get("__ref")
+            .and_then(|r| r.as_str())
+            .and_then(|r| state.get(r))
+    };
+    let work = state.get(format!("Work:{work_id}"))?;
+    let mut chapters = Vec::new();
+    for toc in work.get("tableOfContents")?.as_array()? {
+        let Some(toc) = deref(toc) else { continue };
+        let arc = toc
+            .get("chapter")
+            .and_then(deref)
+            .and_then(|c| c["title"].as_str())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+        for episode in toc["episodeUnions"].as_array().into_iter().flatten() {
+            let Some(episode) = deref(episode) else {
+                continue;
+            };
+            let (Some(id), Some(title)) = (episode["id"].as_str(), episode["title"].as_str())
+            else {
+                continue;
+            };
+            chapters.push(Chapter {
+                path: format!("https://kakuyomu.jp/works/{work_id}/episodes/{id}"),
+                title: title.trim().to_string(),
+                arc: arc.clone(),
+                ..Default::default()
+            });
+        }
+    }
+    Some(chapters)
+}
+
+/// 解析 kakuyomu.jp 作品页 `__NEXT_DATA__` 中的作品信息
+pub fn parse_kakuyomu_info(html: &str, url: &str) -> Result<NovelInfo, ExtractError> {
+    let document = Html::parse_document(html);
+    let work_id = kakuyomu_work_id(url).ok_or(ExtractError::BodyNotFound)?;
+    let script = document
+        .select(&selector("script#__NEXT_DATA__")?)
+        .next()
+        .ok_or(ExtractError::BodyNotFound)?;
+    let data: serde_json::Value = serde_json::from_str(&script.text().collect::<String>())
+        .map_err(|_| ExtractError::BodyNotFound)?;
+    let state = data
+        .pointer("/props/pageProps/__APOLLO_STATE__")
+        .ok_or(ExtractError::BodyNotFound)?;
+    let work = state
+        .get(format!("Work:{work_id}"))
+        .ok_or(ExtractError::BodyNotFound)?;
+    let text = |v: &serde_json::Value| v.as_str().unwrap_or_default().trim().to_string();
+    let author = work["author"]["__ref"]
+        .as_str()
+        .and_then(|r| state.get(r))
+        .map(|a| text(&a["activityName"]))
+        .unwrap_or_default();
+    Ok(NovelInfo {
+        title: text(&work["title"]),
+        author,
+        synopsis: text(&work["introduction"]),
+        tags: work["tagLabels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.as_str())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// 解析 kakuyomu.jp 作品页中的话数列表
+///
+/// 新版页面的目录只存在于内嵌的 `__NEXT_DATA__` JSON 中，旧版页面则是普通链接，
+/// 两者都找不到时返回空列表
+pub fn parse_kakuyomu_directory(html: &str, url: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    if let Some(work_id) = kakuyomu_work_id(url)
+        && let Some(script) = document.select(&selector("script#__NEXT_DATA__")?).next()
+        && let Some(chapters) =
+            parse_kakuyomu_next_data(&script.text().collect::<String>(), work_id)
+        && !chapters.is_empty()
+    {
+        return Ok(chapters);
+    }
+    let link_selector = selector("a.widget-toc-episode-episodeTitle")?;
+    let title_selector = selector(".widget-toc-episode-titleLabel")?;
+    let links = document
+        .select(&link_selector)
+        .filter_map(|el| {
+            let href = el.value().attr("href").filter(|h| !h.is_empty())?;
+            let title = match el.select(&title_selector).next() {
+                Some(label) => label.text().collect::<String>(),
+                None => el.text().collect::<String>(),
+            };
+            let full = if href.starts_with("http") {
+                href.to_string()
+            } else {
+                format!("https://kakuyomu.jp{href}")
+            };
+            Some(Chapter {
+                path: full,
+                title: title.trim().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(links)
+}
+
+/// 解析 kakuyomu.jp 话页面的正文
+pub fn parse_kakuyomu_chapter(html: &str) -> Result<String, ExtractError> {
+    extract_body(html, "div.widget-episodeBody")
+}
+
+/// 解析 alphapolis.co.jp 目录页中的章节列表
+pub fn parse_alphapolis_directory(html: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let link_selector = selector("div.episode a[href*='/episode/']")?;
+    let title_selector = selector("span.title")?;
+    let links = document
+        .select(&link_selector)
+        .filter_map(|el| {
+            let href = el.value().attr("href")?;
+            let title = match el.select(&title_selector).next() {
+                Some(label) => label.text().collect::<String>(),
+                None => el.text().collect::<String>(),
+            };
+            let full = if href.starts_with("http") {
+                href.to_string()
+            } else {
+                format!("https://www.alphapolis.co.jp{href}")
+            };
+            Some(Chapter {
+                path: full,
+                title: title.trim().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(links)
+}
+
+/// 解析 alphapolis.co.jp 章节页的正文
+pub fn parse_alphapolis_chapter(html: &str) -> Result<String, ExtractError> {
+    extract_body(html, "div#novelBody")
+}
+
+/// 纯文本小说中的章节标题行：`第N話/章` 等、Markdown 标题或プロローグ／エピローグ
+static TEXT_HEADING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:#{1,3}\s*\S.*|第[0-9０-９一二三四五六七八九十百千万〇零]+[話章部節回幕](?:\s.*|　.*)?|(?:プロローグ|エピローグ|序章|終章)(?:\s.*|　.*)?)$",
+    )
+    .unwrap()
+});
+
+/// 按章节标题行拆分纯文本，返回 `(标题, 正文)` 列表；
+/// 第一个标题之前的内容归入第一章，没有标题时返回空列表
+pub fn split_text_chapters(text: &str) -> Vec<(String, String)> {
+    let mut chapters: Vec<(String, String)> = Vec::new();
+    let mut preface = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.chars().count() <= 50 && TEXT_HEADING.is_match(trimmed) {
+            let title = trimmed.trim_start_matches('#').trim().to_string();
+            let body = if chapters.is_empty() {
+                std::mem::take(&mut preface)
+            } else {
+                String::new()
+            };
+            chapters.push((title, body));
+            continue;
+        }
+        let body = match chapters.last_mut() {
+            Some((_, body)) => body,
+            None => &mut preface,
+        };
+        body.push_str(line);
+        body.push('\n');
+    }
+    chapters
+}
+
+/// `sites.d/*.toml` 中定义的站点，无需重新编译即可支持新站点
+///
+/// ```toml
+/// name = "example"
+/// url_pattern = '^https://example\.com/novel/\d+'
+/// directory_selector = "ul.toc a"
+/// title_selector = "span.title"
+/// body_selector = "div#novel_body"
+/// base_url = "https://example.com"
+/// ```
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct SiteDefinition {
+    /// 站点名称，用于日志与错误信息
+    pub name: String,
+    /// 匹配目录页地址的正则表达式
+    pub url_pattern: String,
+    /// 目录页中章节链接的选择器
+    pub directory_selector: String,
+    /// 在章节链接内查找标题的选择器，缺省时使用链接文本
+    #[serde(default)]
+    pub title_selector: Option<String>,
+    /// 章节页中正文容器的选择器
+    pub body_selector: String,
+    /// 补全相对链接的前缀，缺省时相对目录页地址解析
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// 读取目录下所有 `.toml` 站点定义，目录不存在时返回空列表
+pub fn load_site_definitions(dir: &std::path::Path) -> Result<Vec<SiteDefinition>> {
+    let mut definitions = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(definitions);
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    files.sort();
+    for file in files {
+        let text = std::fs::read_to_string(&file)?;
+        let definition: SiteDefinition = toml::from_str(&text)
+            .map_err(|e| anyhow!("invalid site definition {}: {e}", file.display()))?;
+        Regex::new(&definition.url_pattern)
+            .map_err(|e| anyhow!("invalid url_pattern in {}: {e}", file.display()))?;
+        definitions.push(definition);
+    }
+    Ok(definitions)
+}
+
+/// 按站点定义解析目录页中的章节列表
+pub fn parse_config_directory(
+    definition: &SiteDefinition,
+    html: &str,
+    url: &str,
+) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let link_selector = selector(&definition.directory_selector)?;
+    let title_selector = definition
+        .title_selector
+        .as_deref()
+        .map(selector)
+        .transpose()?;
+    let page = reqwest::Url::parse(url).ok();
+    let links = document
+        .select(&link_selector)
+        .filter_map(|el| {
+            let href = el.value().attr("href").filter(|h| !h.is_empty())?;
+            let title = match title_selector.as_ref().and_then(|s| el.select(s).next()) {
+                Some(label) => label.text().collect::<String>(),
+                None => el.text().collect::<String>(),
+            };
+            let full = if href.starts_with("http") {
+                href.to_string()
+            } else if let Some(base) = &definition.base_url {
+                format!(
+                    "{}/{}",
+                    base.trim_end_matches('/'),
+                    href.trim_start_matches('/')
+                )
+            } else {
+                page.as_ref()?.join(href).ok()?.to_string()
+            };
+            Some(Chapter {
+                path: full,
+                title: title.trim().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(links)
+}
+
+/// 读取 EPUB 中的文件内容
+fn read_zip_entry(zip: &mut zip::ZipArchive<std::fs::File>, name: &str) -> Result<String> {
+    let mut entry = zip.by_name(name)?;
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut entry, &mut bytes)?;
+    Ok(decode_japanese(&bytes))
+}
+
+/// 相对 `base`（容器内文件路径）解析 EPUB 内的链接
+fn epub_join(base: &str, href: &str) -> String {
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop();
+    for seg in href.split('#').next().unwrap_or(href).split('/') {
+        match seg {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(seg),
+        }
+    }
+    parts.join("/")
+}
+
+/// 列出 EPUB 阅读顺序中的 `(容器内路径, 标题)`，标题取自各文件的标题或首个见出し
+fn epub_spine(file: &std::path::Path) -> Result<Vec<(String, String)>> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(file)?)?;
+    let container = Html::parse_document(&read_zip_entry(&mut zip, "META-INF/container.xml")?);
+    let opf_path = container
+        .select(&selector("rootfile[full-path]")?)
+        .find_map(|el| el.value().attr("full-path"))
+        .ok_or_else(|| anyhow!("container.xml has no rootfile"))?
+        .to_string();
+    let opf = Html::parse_document(&read_zip_entry(&mut zip, &opf_path)?);
+    let manifest: std::collections::HashMap<&str, &str> = opf
+        .select(&selector("item[id][href]")?)
+        .filter_map(|el| Some((el.value().attr("id")?, el.value().attr("href")?)))
+        .collect();
+    let heading = selector("h1, h2, h3, title")?;
+    let mut spine = Vec::new();
+    for itemref in opf.select(&selector("itemref[idref]")?) {
+        if itemref.value().attr("linear") == Some("no") {
+            continue;
+        }
+        let Some(href) = itemref
+            .value()
+            .attr("idref")
+            .and_then(|id| manifest.get(id))
+        else {
+            continue;
+        };
+        let path = epub_join(&opf_path, href);
+        let page = Html::parse_document(&read_zip_entry(&mut zip, &path)?);
+        let title = page
+            .select(&heading)
+            .map(|el| el.text().collect::<String>().trim().to_string())
+            .find(|t| !t.is_empty())
+            .unwrap_or_else(|| path.rsplit('/').next().unwrap_or(&path).to_string());
+        spine.push((path, title));
+    }
+    Ok(spine)
+}
+
+/// 提取 XHTML 页面 body 中的文本，每个文本节点一行；振り仮名同网页章节一样转为
+/// `｜親文字《読み》`，并与同一父元素下相邻的文本连成一行
+pub fn parse_xhtml_text(html: &str) -> Result<String, ExtractError> {
+    let document = Html::parse_document(html);
+    let body = document
+        .select(&selector("body")?)
+        .next()
+        .ok_or(ExtractError::BodyNotFound)?;
+    let mut lines: Vec<String> = Vec::new();
+    let mut last = None;
+    for node in body.descendants() {
+        let (piece, is_ruby) = match node.value() {
+            Node::Element(e) if e.name() == "ruby" => match ElementRef::wrap(node) {
+                Some(ruby) => (ruby_markup(ruby), true),
+                None => continue,
+            },
+            Node::Text(t) => (t.trim().to_string(), false),
+            _ => continue,
+        };
+        // 注音内的文本已由 `ruby_markup` 处理
+        let skipped = node.ancestors().filter_map(ElementRef::wrap).any(|a| {
+            let name = a.value().name();
+            is_boilerplate(name) || name == "ruby"
+        });
+        let parent = node.parent().and_then(ElementRef::wrap);
+        if skipped || piece.is_empty() || parent.is_none() {
+            continue;
+        }
+        let glue = matches!(last, Some((prev, prev_ruby)) if Some(prev) == parent && (prev_ruby || is_ruby));
+        match lines.last_mut() {
+            Some(line) if glue => line.push_str(&piece),
+            _ => lines.push(piece),
+        }
+        last = parent.map(|p| (p, is_ruby));
+    }
+    let content = lines.join("\n");
+    if content.is_empty() {
+        return Err(ExtractError::EmptyBody);
+    }
+    Ok(content)
+}
+
+/// 正文提取时忽略的元素
+fn is_boilerplate(name: &str) -> bool {
+    matches!(
+        name,
+        "script"
+            | "style"
+            | "noscript"
+            | "nav"
+            | "header"
+            | "footer"
+            | "aside"
+            | "form"
+            | "rt"
+            | "rp"
+    )
+}
+
+/// 元素及其祖先中是否有需要忽略的元素
+fn in_boilerplate(el: ElementRef) -> bool {
+    std::iter::once(el)
+        .chain(el.ancestors().filter_map(ElementRef::wrap))
+        .any(|e| is_boilerplate(e.value().name()))
+}
+
+/// 统计元素内文本的字符数，以及其中位于链接内的字符数
+fn text_and_link_len(el: ElementRef) -> (usize, usize) {
+    let mut text = 0;
+    let mut link = 0;
+    for node in el.descendants() {
+        if let Node::Text(t) = node.value() {
+            let ancestors: Vec<ElementRef> = node
+                .ancestors()
+                .map_while(ElementRef::wrap)
+                .take_while(|a| a.id() != el.id())
+                .collect();
+            if ancestors.iter().any(|a| is_boilerplate(a.value().name())) {
+                continue;
+            }
+            let len = t.trim().chars().count();
+            text += len;
+            if ancestors.iter().any(|a| a.value().name() == "a") {
+                link += len;
+            }
+        }
+    }
+    (text, link)
+}
+
+/// 元素直接包含的正文长度：直接的文本节点加上段落等行内子元素，扣除链接文本
+fn own_text_score(el: ElementRef) -> usize {
+    let mut score = 0;
+    for child in el.children() {
+        match child.value() {
+            Node::Text(t) => score += t.trim().chars().count(),
+            Node::Element(e)
+                if matches!(
+                    e.name(),
+                    "p" | "span" | "ruby" | "em" | "strong" | "b" | "i" | "font" | "blockquote"
+                ) =>
+            {
+                if let Some(child) = ElementRef::wrap(child) {
+                    let (text, link) = text_and_link_len(child);
+                    score += text - link;
+                }
+            }
+            _ => {}
+        }
+    }
+    score
+}
+
+/// 启发式提取任意页面的正文：选出直接包含最多非链接文本的元素
+pub fn parse_generic_chapter(html: &str) -> Result<String, ExtractError> {
+    let document = Html::parse_document(html);
+    let best = document
+        .select(&selector("body, body *")?)
+        .filter(|el| !in_boilerplate(*el) && el.value().name() != "a")
+        .map(|el| (own_text_score(el), el))
+        .filter(|(score, _)| *score > 0)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, el)| el)
+        .ok_or(ExtractError::BodyNotFound)?;
+    let content = best
+        .descendants()
+        .filter_map(|node| match node.value() {
+            Node::Text(t)
+                if !node
+                    .ancestors()
+                    .filter_map(ElementRef::wrap)
+                    .any(|a| is_boilerplate(a.value().name())) =>
+            {
+                Some(t.trim())
+            }
+            _ => None,
+        })
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if content.is_empty() {
+        return Err(ExtractError::EmptyBody);
+    }
+    Ok(content)
+}
+
+/// 启发式解析任意页面的目录：地址位于当前页面路径之下的链接视为章节；
+/// 少于两个时把页面本身当作唯一的章节
+pub fn parse_generic_directory(html: &str, url: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let base = reqwest::Url::parse(url).map_err(|_| ExtractError::BodyNotFound)?;
+    let mut prefix = base.clone();
+    prefix.set_query(None);
+    prefix.set_fragment(None);
+    let mut prefix = prefix.to_string();
+    if !prefix.ends_with('/') {
+        prefix.push('/');
+    }
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for el in document.select(&selector("a[href]")?) {
+        if in_boilerplate(el) {
+            continue;
+        }
+        let Some(mut link) = el.value().attr("href").and_then(|h| base.join(h).ok()) else {
+            continue;
+        };
+        link.set_fragment(None);
+        let link = link.to_string();
+        let title = el.text().collect::<String>().trim().to_string();
+        if link.starts_with(&prefix)
+            && link != prefix
+            && !title.is_empty()
+            && title.chars().count() <= 100
+            && !chapters.iter().any(|c| c.path == link)
+        {
+            chapters.push(Chapter {
+                path: link,
+                title,
+                ..Default::default()
+            });
+        }
+    }
+    if chapters.len() >= 2 {
+        return Ok(chapters);
+    }
+    let title = ["h1", "title"]
+        .iter()
+        .filter_map(|css| selector(css).ok())
+        .find_map(|sel| {
+            let text = document.select(&sel).next()?.text().collect::<String>();
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        })
+        .unwrap_or_else(|| url.to_string());
+    Ok(vec![Chapter {
+        path: url.to_string(),
+        title,
+        ..Default::default()
+    }])
+}
+
+/// NovelUp+ 目录最多翻页数，防止异常页面导致无限循环
+const NOVELUP_MAX_PAGES: usize = 100;
+
+/// 解析 novelup.plus 目录页（单页）中的章节列表
+pub fn parse_novelup_directory(html: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let link_selector = selector(".episode_link a[href], .episodeList .episodeTitle a[href]")?;
+    let links = document
+        .select(&link_selector)
+        .filter_map(|el| {
+            let href = el.value().attr("href").filter(|h| !h.is_empty())?;
+            let full = if href.starts_with("http") {
+                href.to_string()
+            } else {
+                format!("https://novelup.plus{href}")
+            };
+            Some(Chapter {
+                path: full,
+                title: el.text().collect::<String>().trim().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(links)
+}
+
+/// 取得 novelup.plus 目录的下一页地址，没有下一页时返回 `None`
+pub fn parse_novelup_next_page(html: &str, url: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let next = selector("a[rel='next'], .pagination .next a").ok()?;
+    let href = document
+        .select(&next)
+        .find_map(|el| el.value().attr("href"))?;
+    if href.starts_with("http") {
+        Some(href.to_string())
+    } else if href.starts_with('?') {
+        let base = url.split('?').next().unwrap_or(url);
+        Some(format!("{base}{href}"))
+    } else {
+        Some(format!("https://novelup.plus{href}"))
+    }
+}
+
+/// 解析 novelup.plus 章节页的正文
+pub fn parse_novelup_chapter(html: &str) -> Result<String, ExtractError> {
+    extract_body(html, "div#js-scroll-area .content, div.novel_body")
+}
+
+/// pixiv 站点地址，ajax 接口要求以此作为 Referer
+const PIXIV_ORIGIN: &str = "https://www.pixiv.net";
+
+/// 从 pixiv 小说地址中取出 `(是否为系列, id)`
+fn pixiv_target(url: &str) -> Option<(bool, &str)> {
+    let digits = |s: &str| -> Option<usize> {
+        let len = s.chars().take_while(char::is_ascii_digit).count();
+        (len > 0).then_some(len)
+    };
+    if let Some(pos) = url.find("/novel/series/") {
+        let rest = &url[pos + "/novel/series/".len()..];
+        return Some((true, &rest[..digits(rest)?]));
+    }
+    let pos = url.find("id=")?;
+    let rest = &url[pos + "id=".len()..];
+    Some((false, &rest[..digits(rest)?]))
+}
+
+/// 取出 ajax 响应的 `body`，接口报错时返回错误信息
+fn pixiv_body(json: &str) -> Result<serde_json::Value> {
+    let mut resp: serde_json::Value = serde_json::from_str(json)?;
+    if resp["error"].as_bool() == Some(true) {
+        return Err(anyhow!(
+            "pixiv error: {}",
+            resp["message"].as_str().unwrap_or("unknown error")
+        ));
+    }
+    Ok(resp["body"].take())
+}
+
+/// 解析系列的 `content_titles` 接口，跳过当前不可阅读的话
+pub fn parse_pixiv_series(json: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let body = pixiv_body(json).map_err(|_| ExtractError::BodyNotFound)?;
+    let entries = body.as_array().ok_or(ExtractError::BodyNotFound)?;
+    Ok(entries
+        .iter()
+        .filter(|e| e["available"].as_bool() != Some(false))
+        .filter_map(|e| {
+            let id = e["id"].as_str()?;
+            Some(Chapter {
+                path: format!("{PIXIV_ORIGIN}/novel/show.php?id={id}"),
+                title: e["title"].as_str().unwrap_or(id).trim().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect())
+}
+
+static PIXIV_RUBY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[rb:\s*(.+?)\s*>\s*(.+?)\s*\]\]").unwrap());
+static PIXIV_JUMPURI: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[jumpuri:\s*(.+?)\s*>\s*.+?\]\]").unwrap());
+static PIXIV_CHAPTER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[chapter:\s*(.*?)\]").unwrap());
+static PIXIV_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(?:newpage|pixivimage:[^\]]*|uploadedimage:[^\]]*|jump:[^\]]*)\]").unwrap()
+});
+
+/// 将 pixiv 小说的特殊标记转换为纯文本：振り仮名转为 `｜親文字《読み》`，
+/// 章节标记保留标题，分页与插图标记被移除
+pub fn pixiv_markup_to_text(content: &str) -> String {
+    let text = PIXIV_RUBY.replace_all(content, "｜$1《$2》");
+    let text = PIXIV_JUMPURI.replace_all(&text, "$1");
+    let text = PIXIV_CHAPTER.replace_all(&text, "\n$1\n");
+    let text = PIXIV_TAG.replace_all(&text, "\n");
+    text.lines()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 解析单篇小说的 ajax 响应，返回转换后的正文
+pub fn parse_pixiv_novel(json: &str) -> Result<String, ExtractError> {
+    let body = pixiv_body(json).map_err(|_| ExtractError::BodyNotFound)?;
+    let content = body["content"].as_str().ok_or(ExtractError::BodyNotFound)?;
+    let text = pixiv_markup_to_text(content);
+    if text.is_empty() {
+        return Err(ExtractError::EmptyBody);
+    }
+    Ok(text)
+}
+
+/// 解析青空文库图书卡片页，返回 XHTML 正文文件的地址
+pub fn parse_aozora_card(html: &str, url: &str) -> Result<String, ExtractError> {
+    let document = Html::parse_document(html);
+    let link_selector = selector("table.download a[href$='.html']")?;
+    let href = document
+        .select(&link_selector)
+        .find_map(|el| el.value().attr("href"))
+        .ok_or(ExtractError::BodyNotFound)?;
+    if href.starts_with("http") {
+        return Ok(href.to_string());
+    }
+    // 卡片页与 files/ 目录位于同一层级
+    let base = &url[..url.rfind('/').map_or(url.len(), |i| i + 1)];
+    Ok(format!("{base}{}", href.trim_start_matches("./")))
+}
+
+/// 青空文库正文中用作分章的见出し锚点，优先使用大見出し
+fn aozora_sections(document: &Html) -> Result<Vec<(String, String)>, ExtractError> {
+    for css in [
+        ".o-midashi a.midashi_anchor",
+        ".naka-midashi a.midashi_anchor",
+    ] {
+        let sections: Vec<(String, String)> = document
+            .select(&selector(css)?)
+            .filter_map(|el| {
+                let id = el.value().attr("id")?;
+                let title = el.text().collect::<String>();
+                Some((id.to_string(), title.trim().to_string()))
+            })
+            .collect();
+        if !sections.is_empty() {
+            return Ok(sections);
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// 解析青空文库 XHTML 正文，按大見出し（没有时按中見出し）拆分为章节；
+/// 章节地址为 `url#锚点`，没有见出し时整部作品作为一章
+pub fn parse_aozora_directory(html: &str, url: &str) -> Result<Vec<Chapter>, ExtractError> {
+    let document = Html::parse_document(html);
+    let sections = aozora_sections(&document)?;
+    if sections.is_empty() {
+        let title = document
+            .select(&selector("h1.title")?)
+            .next()
+            .map(|el| el.text().collect::<String>().trim().to_string())
+            .unwrap_or_else(|| url.to_string());
+        return Ok(vec![Chapter {
+            path: url.to_string(),
+            title,
+            ..Default::default()
+        }]);
+    }
+    Ok(sections
+        .into_iter()
+        .map(|(id, title)| Chapter {
+            path: format!("{url}#{id}"),
+            title,
+            ..Default::default()
+        })
+        .collect())
+}
+
+/// 将外字图片的说明转换为文本：带 `U+XXXX` 的直接换成对应字符，
+/// 否则保留「…」中的字形描述
+fn aozora_gaiji(alt: &str) -> String {
+    if let Some(pos) = alt.find("U+") {
+        let hex: String = alt[pos + 2..]
+            .chars()
+            .take_while(char::is_ascii_hexdigit)
+            .collect();
+        if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+            return c.to_string();
+        }
+    }
+    match (alt.find('「'), alt.rfind('」')) {
+        (Some(start), Some(end)) if start < end => {
+            format!("〔{}〕", &alt[start + '「'.len_utf8()..end])
+        }
+        _ => "※".to_string(),
+    }
+}
+
+/// 解析青空文库 XHTML 中指定见出し锚点的章节正文，`anchor` 为空时返回全文。
+/// 振り仮名转为 `｜親文字《読み》`，入力者注被丢弃，外字替换为文字或描述
+pub fn parse_aozora_chapter(html: &str, anchor: Option<&str>) -> Result<String, ExtractError> {
+    let document = Html::parse_document(html);
+    let main = document
+        .select(&selector("div.main_text")?)
+        .next()
+        .ok_or(ExtractError::BodyNotFound)?;
+    let boundaries: Vec<String> = match anchor {
+        Some(_) => aozora_sections(&document)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect(),
+        None => Vec::new(),
+    };
+    // 第一个见出し之前的内容归入第一章
+    let mut inside = anchor.is_none() || anchor == boundaries.first().map(String::as_str);
+    let mut text = String::new();
+    for node in main.descendants() {
+        if let Some(el) = ElementRef::wrap(node) {
+            let value = el.value();
+            if let Some(id) = value.attr("id")
+                && boundaries.iter().any(|b| b == id)
+            {
+                if Some(id) == anchor {
+                    inside = true;
+                } else if inside {
+                    break;
+                }
+            }
+            if !inside {
+                continue;
+            }
+            match value.name() {
+                "br" => text.push('\n'),
+                "ruby" => text.push('｜'),
+                "img" if value.classes().any(|c| c == "gaiji") => {
+                    text.push_str(&aozora_gaiji(value.attr("alt").unwrap_or("")));
+                }
+                _ => {}
+            }
+        } else if let Node::Text(t) = node.value() {
+            if !inside {
+                continue;
+            }
+            let mut ancestors = node.ancestors().filter_map(ElementRef::wrap);
+            let hidden = ancestors
+                .clone()
+                .any(|a| a.value().name() == "rp" || a.value().classes().any(|c| c == "notes"));
+            if ancestors.any(|a| a.value().name() == "rt") {
+                text.push_str(&format!("《{}》", t.trim()));
+            } else if !hidden {
+                text.push_str(t);
+            }
+        }
+    }
+    if anchor.is_some() && !inside {
+        return Err(ExtractError::BodyNotFound);
+    }
+    let content = text
+        .lines()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if content.is_empty() {
+        return Err(ExtractError::EmptyBody);
+    }
+    Ok(content)
+}
+
+/// 读取页面开头的 XML 声明或 `<meta>` 中声明的编码
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_ascii_lowercase();
+    let pos = head
+        .find("charset=")
+        .map(|p| p + "charset=".len())
+        .or_else(|| head.find("encoding=").map(|p| p + "encoding=".len()))?;
+    let label: String = head[pos..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    Encoding::for_label(label.as_bytes())
+}
+
+/// 将页面字节解码为文本：优先使用页面声明的编码，
+/// 没有声明时按 UTF-8 解析，失败则按 Shift_JIS 处理
+pub fn decode_japanese(bytes: &[u8]) -> String {
+    if let Some(encoding) = declared_encoding(bytes) {
+        return encoding.decode(bytes).0.into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => SHIFT_JIS.decode(bytes).0.into_owned(),
+    }
+}
+
+/// 使用统一的请求头下载页面 HTML
+async fn get_html(client: &HttpClient, url: &str) -> Result<String> {
+    get_html_with_cookie(client, url, None).await
+}
+
+/// 下载页面 HTML，可附带额外的 Cookie（例如年龄确认）；非 2xx 响应返回 [`StatusError`]，
+/// 而不是把错误页当作正文解析
+async fn get_html_with_cookie(
+    client: &HttpClient,
+    url: &str,
+    cookie: Option<&str>,
+) -> Result<String> {
+    let mut req = client
+        .get(url)
+        .header("Accept-Language", "en-US,en;q=0.9,ja;q=0.8");
+    if let Some(cookie) = cookie {
+        req = req.header("Cookie", cookie);
+    }
+    let resp = req.send().await?;
+    if !resp.status().is_success() {
+        return Err(StatusError {
+            url: url.to_string(),
+            status: resp.status().as_u16(),
+        }
+        .into());
+    }
+    Ok(resp.text().await?)
+}
+
+/// 抽象小说站点需要实现的接口
+#[async_trait::async_trait]
+pub trait NovelSite: Send + Sync {
+    /// 站点名称，用于提示信息
+    fn name(&self) -> &str;
+    /// 该站点处理的地址模式（通常为域名），用于匹配与列出支持的站点
+    fn url_patterns(&self) -> Vec<String>;
+    /// 是否处理该地址，默认检查地址是否包含任一模式
+    fn matches(&self, url: &str) -> bool {
+        self.url_patterns().iter().any(|p| url.contains(p.as_str()))
+    }
+    /// 根据目录页地址抓取章节列表
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>>;
+    /// 下载并解析单章正文，插图保留为 `［＃挿絵（地址）入る］` 标记
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String>;
+    /// 抓取作品信息（标题、作者、简介、标签），默认不提供任何信息
+    async fn fetch_info(&self, _url: &str, _cancel: &CancellationToken) -> Result<NovelInfo> {
+        Ok(NovelInfo::default())
+    }
+    /// 下载单章并分出作者的前書き与後書き，默认整章都是正文；插图标记会被编号为 `[挿絵 #n]`
+    async fn fetch_sections(
+        &self,
+        url: &str,
+        cancel: &CancellationToken,
+    ) -> Result<ChapterSections> {
+        Ok(ChapterSections {
+            body: self.fetch_chapter(url, cancel).await?,
+            ..Default::default()
+        }
+        .with_illustrations(url))
+    }
+}
+
+/// 内置站点的可选配置
+#[derive(Clone, Debug, Default)]
+pub struct SiteOptions {
+    /// pixiv 的 `PHPSESSID` Cookie
+    pub pixiv_session: Option<String>,
+    /// なろう系站点通过官方 API 获取目录，而不是解析目录页
+    pub narou_api: bool,
+    /// 内置站点的 HTTP 配置；reqwest 客户端另行传入，这里只供使用 curl 的站点读取代理等设置
+    pub http: HttpOptions,
+}
+
+/// 按地址选择站点实现，先注册的站点优先匹配
+#[derive(Default)]
+pub struct SiteRegistry {
+    sites: Vec<Arc<dyn NovelSite>>,
+    policy: Arc<CrawlPolicy>,
+}
+
+impl SiteRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置所有站点共用的抓取策略（按域名限速、并发上限与 robots.txt）；须在注册站点前调用，
+    /// 站点使用 [`Self::crawl_client`] 得到的客户端时每个请求都经过该策略
+    pub fn with_crawl_policy(self, policy: CrawlPolicy) -> Self {
+        SiteRegistry {
+            policy: Arc::new(policy),
+            ..self
+        }
+    }
+
+    /// 经过抓取策略的客户端，供注册的站点抓取页面
+    pub fn crawl_client(&self, client: HttpClient) -> HttpClient {
+        self.policy.guard(client)
+    }
+
+    /// 注册一个站点实现
+    pub fn register(&mut self, site: Arc<dyn NovelSite>) {
+        self.sites.push(site);
+    }
+
+    /// 注册内置站点；本地文件优先，通用站点兜底处理其余的 http(s) 地址
+    pub fn register_builtin(&mut self, options: &SiteOptions, client: HttpClient) {
+        let client = self.crawl_client(client);
+        self.register(Arc::new(EpubSite::new()));
+        self.register(Arc::new(LocalSite::new()));
+        self.register(Arc::new(
+            NcodeSite::new()
+                .with_client(client.clone())
+                .with_api(options.narou_api),
+        ));
+        self.register(Arc::new(
+            NcodeSite::r18()
+                .with_client(client.clone())
+                .with_api(options.narou_api),
+        ));
+        self.register(Arc::new(
+            OrgSite::new()
+                .with_client(client.clone())
+                .with_http_options(options.http.clone())
+                .with_crawl_policy(self.policy.clone()),
+        ));
+        self.register(Arc::new(KakuyomuSite::new().with_client(client.clone())));
+        self.register(Arc::new(AlphapolisSite::new().with_client(client.clone())));
+        self.register(Arc::new(NovelupSite::new().with_client(client.clone())));
+        self.register(Arc::new(
+            PixivNovelSite::new(options.pixiv_session.clone()).with_client(client.clone()),
+        ));
+        self.register(Arc::new(AozoraSite::new().with_client(client.clone())));
+        self.register(Arc::new(GenericSite::new().with_client(client)));
+    }
+
+    /// 查找处理该地址的站点，找不到时列出所有支持的站点
+    pub fn find(&self, url: &str) -> Result<Arc<dyn NovelSite>> {
+        if let Some(site) = self.sites.iter().find(|s| s.matches(url)) {
+            return Ok(site.clone());
+        }
+        let supported = self
+            .sites
+            .iter()
+            .map(|s| format!("  {}: {}", s.name(), s.url_patterns().join(", ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(anyhow!(
+            "no site handles `{url}`; supported sites:\n{supported}"
+        ))
+    }
+}
+
+/// ncode.syosetu.com 的实现，`r18()` 用于 novel18.syosetu.com
+pub struct NcodeSite {
+    client: HttpClient,
+    /// 补全相对链接使用的域名
+    origin: &'static str,
+    /// 是否发送 `over18=yes` 以跳过年龄确认页
+    over18: bool,
+    /// 是否通过なろう小説 API 获取目录
+    use_api: bool,
+}
+
+impl NcodeSite {
+    pub fn new() -> Self {
+        NcodeSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            origin: NCODE_ORIGIN,
+            over18: false,
+            use_api: false,
+        }
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 通过官方 API 而不是目录页获取章节列表
+    pub fn with_api(self, use_api: bool) -> Self {
+        NcodeSite { use_api, ..self }
+    }
+
+    /// ノクターン／ムーンライト等 R18 站点，请求时携带年龄确认 Cookie
+    pub fn r18() -> Self {
+        NcodeSite {
+            origin: NOVEL18_ORIGIN,
+            over18: true,
+            ..Self::new()
+        }
+    }
+
+    async fn get(&self, url: &str) -> Result<String> {
+        let cookie = self.over18.then_some("over18=yes");
+        get_html_with_cookie(&self.client, url, cookie).await
+    }
+
+    /// 通过なろう小説 API 查询作品信息
+    pub async fn fetch_api_info(&self, url: &str, cancel: &CancellationToken) -> Result<NarouInfo> {
+        let ncode = ncode_from_url(url).ok_or_else(|| anyhow!("no ncode in `{url}`"))?;
+        let api = if self.over18 { NAROU18_API } else { NAROU_API };
+        let api_url = format!("{api}?out=json&of=t-n-w-s-k-ga-nt-gl-nu&ncode={ncode}");
+        let json = cancellable(cancel, self.get(&api_url)).await?;
+        Ok(parse_narou_api(&json)?)
+    }
+}
+
+impl Default for NcodeSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 小説家になろう账号；登录会话保存在共用的 Cookie 存储中，之后访问站点时自动携带
+pub struct NarouAccount {
+    client: HttpClient,
+    cookies: Arc<CookieJar>,
+}
+
+impl NarouAccount {
+    /// `client` 需使用 `cookies` 作为 Cookie 存储构建
+    pub fn new(client: HttpClient, cookies: Arc<CookieJar>) -> Self {
+        NarouAccount { client, cookies }
+    }
+
+    /// 是否已有登录会话
+    pub fn logged_in(&self) -> bool {
+        cookie_header(&self.cookies, NAROU_BOOKMARKS).is_some_and(|c| c.contains("userl="))
+    }
+
+    /// 使用なろう ID（或邮箱）与密码登录
+    pub async fn login(&self, id: &str, password: &str, cancel: &CancellationToken) -> Result<()> {
+        let request = self
+            .client
+            .post(NAROU_LOGIN)
+            .form(&[("narouid", id), ("pass", password)])
+            .send();
+        cancellable(cancel, async { Ok(request.await?.error_for_status()?) }).await?;
+        if !self.logged_in() {
+            bail!("Narou login failed: check the ID and password");
+        }
+        Ok(())
+    }
+
+    /// 读取账号的ブックマーク列表（默认分类），需先登录
+    pub async fn bookmarks(&self, cancel: &CancellationToken) -> Result<Vec<NarouBookmark>> {
+        if !self.logged_in() {
+            bail!("not logged in to Narou; run `narou login` first");
+        }
+        let mut bookmarks: Vec<NarouBookmark> = Vec::new();
+        for page in 1..=NAROU_BOOKMARK_MAX_PAGES {
+            let url = format!("{NAROU_BOOKMARKS}?p={page}");
+            let html = cancellable(cancel, get_html(&self.client, &url)).await?;
+            let found: Vec<NarouBookmark> = parse_narou_bookmarks(&html)
+                .into_iter()
+                .filter(|b| !bookmarks.iter().any(|seen| seen.url == b.url))
+                .collect();
+            // 超出最后一页时网站会重复显示最后一页或显示空列表
+            if found.is_empty() {
+                break;
+            }
+            bookmarks.extend(found);
+        }
+        Ok(bookmarks)
+    }
+}
+
+#[async_trait]
+impl NovelSite for NcodeSite {
+    fn name(&self) -> &str {
+        if self.over18 { "novel18" } else { "ncode" }
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec![self.origin.trim_start_matches("https://").to_string()]
+    }
+
+    /// 每页目录最多 100 话，其余分页并发抓取（并发数与间隔由客户端上的抓取策略限制），
+    /// 按页码合并
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        if self.use_api {
+            let info = self.fetch_api_info(url, cancel).await?;
+            return Ok(narou_api_chapters(&info, self.origin));
+        }
+        let base = url.split('?').next().unwrap_or(url);
+        let directory_html = cancellable(cancel, self.get(base)).await?;
+        let mut chapters = parse_ncode_directory(&directory_html, self.origin)?;
+        if chapters.is_empty()
+            && let Some(short) = parse_ncode_short_story(&directory_html, base)
+        {
+            return Ok(vec![short]);
+        }
+        let pages = parse_ncode_page_count(&directory_html);
+        let mut tasks = JoinSet::new();
+        for page in 2..=pages {
+            let client = self.client.clone();
+            let cookie = self.over18.then_some("over18=yes");
+            let page_url = format!("{base}?p={page}");
+            let origin = self.origin;
+            let cancel = cancel.clone();
+            tasks.spawn(async move {
+                let fetch = async {
+                    let html = get_html_with_cookie(&client, &page_url, cookie).await?;
+                    Ok(parse_ncode_directory(&html, origin)?)
+                };
+                (page, cancellable(&cancel, fetch).await)
+            });
+        }
+        let mut rest: Vec<(usize, Vec<Chapter>)> = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            let (page, result) = joined?;
+            rest.push((page, result?));
+        }
+        rest.sort_by_key(|(page, _)| *page);
+        for (_, page_chapters) in rest {
+            for chapter in page_chapters {
+                if !chapters.iter().any(|c| c.path == chapter.path) {
+                    chapters.push(chapter);
+                }
+            }
+        }
+        Ok(chapters)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let content_html = cancellable(cancel, self.get(url)).await?;
+        Ok(parse_ncode_chapter(&content_html)?)
+    }
+
+    async fn fetch_sections(
+        &self,
+        url: &str,
+        cancel: &CancellationToken,
+    ) -> Result<ChapterSections> {
+        let content_html = cancellable(cancel, self.get(url)).await?;
+        Ok(parse_ncode_sections(&content_html)?.with_illustrations(url))
+    }
+
+    async fn fetch_info(&self, url: &str, cancel: &CancellationToken) -> Result<NovelInfo> {
+        if self.use_api {
+            return Ok(self.fetch_api_info(url, cancel).await?.into());
+        }
+        let base = url.split('?').next().unwrap_or(url);
+        let directory_html = cancellable(cancel, self.get(base)).await?;
+        Ok(parse_ncode_info(&directory_html)?)
+    }
+}
+
+/// syosetu.org 的实现
+pub struct OrgSite {
+    client: HttpClient,
+    /// 章节页通过 curl 下载，代理、UA 与超时需要单独设置
+    http: HttpOptions,
+    /// curl 的请求不经过客户端，需单独经过抓取策略
+    policy: Option<Arc<CrawlPolicy>>,
+}
+
+impl OrgSite {
+    pub fn new() -> Self {
+        OrgSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            http: HttpOptions::default(),
+            policy: None,
+        }
+    }
+
+    /// 设置 curl 下载章节时使用的代理、UA 与超时
+    pub fn with_http_options(self, http: HttpOptions) -> Self {
+        OrgSite { http, ..self }
+    }
+
+    /// curl 下载章节前经过的抓取策略
+    pub fn with_crawl_policy(self, policy: Arc<CrawlPolicy>) -> Self {
+        OrgSite {
+            policy: Some(policy),
+            ..self
+        }
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl Default for OrgSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NovelSite for OrgSite {
+    fn name(&self) -> &str {
+        "hameln"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["syosetu.org".to_string()]
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let directory_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_org_directory(&directory_html, url)?)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        // curl 不经过客户端的重试中间件，按与中间件相同的策略重试
+        let content_html = self
+            .http
+            .retry_policy()
+            .run(url, cancel, || self.download(url, cancel))
+            .await?;
+        Ok(parse_org_chapter(&content_html)?)
+    }
+}
+
+impl OrgSite {
+    /// 经过抓取策略后用 curl 下载章节页
+    async fn download(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let _permit = match &self.policy {
+            Some(policy) => Some(policy.acquire(url, cancel).await?),
+            None => None,
+        };
+        let url = url.to_string();
+        let http = self.http.clone();
+        let sink = Sink {
+            data: Vec::new(),
+            cancel: cancel.clone(),
+        };
+        let fetch = tokio::task::spawn_blocking(move || -> Result<String> {
+            let mut easy = Easy2::new(sink);
+            easy.url(&url)?;
+            easy.progress(true)?;
+            easy.http_version(HttpVersion::V2TLS)?;
+            easy.useragent(&http.user_agent)?;
+            easy.timeout(http.timeout)?;
+            easy.connect_timeout(http.connect_timeout)?;
+            if let Some(proxy) = &http.proxy {
+                easy.proxy(proxy)?;
+            }
+            if let Some(cookie) = http
+                .cookies
+                .as_ref()
+                .and_then(|jar| cookie_header(jar, &url))
+            {
+                easy.cookie(&cookie)?;
+            }
+            let mut headers = List::new();
+            headers.append(
+                "Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+            )?;
+            headers.append("Accept-Language: ja,en-US;q=0.9,en;q=0.8")?;
+            headers.append("Sec-Fetch-Dest: document")?;
+            headers.append("Sec-Fetch-Mode: navigate")?;
+            headers.append("Sec-Fetch-Site: none")?;
+            headers.append("Upgrade-Insecure-Requests: 1")?;
+            easy.http_headers(headers)?;
+            easy.perform()?;
+            let status = easy.response_code()?;
+            if status != 200 {
+                return Err(StatusError {
+                    url,
+                    status: status as u16,
+                }
+                .into());
+            }
+            Ok(String::from_utf8_lossy(&easy.get_ref().data).to_string())
+        });
+        cancellable(cancel, async { fetch.await? }).await
+    }
+}
 
-struct Sink(Vec<u8>);
+/// alphapolis.co.jp 的实现
+pub struct AlphapolisSite {
+    client: HttpClient,
+}
 
-impl Handler for Sink {
-    fn write(&mut self, data: &[u8]) -> std::result::Result<usize, WriteError> {
-        self.0.extend_from_slice(data);
-        Ok(data.len())
+impl AlphapolisSite {
+    pub fn new() -> Self {
+        AlphapolisSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+        }
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
     }
 }
 
-/// 发送请求时使用的 UA 字符串
-const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36 Edg/136.0.0.0";
+impl Default for AlphapolisSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-const TRANSLATE_PROMPT: &str = r##"请将以下日文内容完整、准确地翻译成中文。
-要求：
-1. 保持原文段落结构；
-2. 不要添加任何解释、注释或额外信息；
-3. **仅输出译文，不要输出原文或其他解释；**
-4. 注重文章原本的表达，特别是对话需要准确反映语气与人物特点。
+#[async_trait]
+impl NovelSite for AlphapolisSite {
+    fn name(&self) -> &str {
+        "alphapolis"
+    }
 
-{}"##;
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["alphapolis.co.jp".to_string()]
+    }
 
-const KEYWORD_PROMPT: &str = r##"请根据以下已提取的翻译列表、日文原文和中文译文，
-从中找出新的专有名词（日文原文中的人名、地名、招式名、非常见物品名等），以及它们
-在译文中的对应中文译名。
-要求：
-1. 仅输出新的翻译对照，不要重复已提取条目；
-2. 输出格式为 JSONL，每行一个，例如:{\"japanese\":\"トウリ\",\"chinese\":\"托莉\"}；
-3. **不要添加任何说明、注释或其他额外内容。不要使用markdown格式或使用三引号将json包裹**
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let directory_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_alphapolis_directory(&directory_html)?)
+    }
 
-已提取的翻译列表:
-{existing_pairs}
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let content_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_alphapolis_chapter(&content_html)?)
+    }
+}
 
-日文原文:
-{japanese_text}
+/// 由 [`SiteDefinition`] 驱动的站点实现
+pub struct ConfigSite {
+    client: HttpClient,
+    definition: SiteDefinition,
+    pattern: Regex,
+}
 
-中文译文:
-{chinese_text}"##;
+impl ConfigSite {
+    pub fn new(definition: SiteDefinition) -> Result<Self> {
+        Ok(ConfigSite {
+            client: build_client(&HttpOptions::default())?,
+            pattern: Regex::new(&definition.url_pattern)?,
+            definition,
+        })
+    }
 
-const DEEPSEEK_API_BASE: &str = "https://api.deepseek.com/chat/completions";
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
 
-/// 目录中每个章节的基本信息
-#[derive(Clone)]
-pub struct Chapter {
-    /// 章节的完整网址
-    pub path: String,
-    /// 章节标题
-    pub title: String,
+    /// 地址是否由该站点定义处理
+    pub fn matches(&self, url: &str) -> bool {
+        self.pattern.is_match(url)
+    }
+}
+
+#[async_trait]
+impl NovelSite for ConfigSite {
+    fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec![self.definition.url_pattern.clone()]
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        self.pattern.is_match(url)
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let directory_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_config_directory(
+            &self.definition,
+            &directory_html,
+            url,
+        )?)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let content_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(extract_body(&content_html, &self.definition.body_selector)?)
+    }
 }
 
-/// 提供翻译服务的客户端
-pub struct Translator {
-    client: Arc<Client>,
-    api_key: String,
-    model: String,
+/// 未知站点的通用实现，依靠启发式规则提取目录与正文
+pub struct GenericSite {
+    client: HttpClient,
 }
 
-impl Translator {
-    /// 创建新的翻译客户端
-    pub fn new(api_key: String, model: String) -> Self {
-        Translator {
-            client: Arc::new(Client::new()),
-            api_key,
-            model,
+impl GenericSite {
+    pub fn new() -> Self {
+        GenericSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
         }
     }
 
-    /// 调用 DeepSeek 接口翻译文本
-    pub async fn translate_text(
-        &self,
-        input: &str,
-        keywords: &[(String, String)],
-    ) -> Result<String> {
-        let known = if keywords.is_empty() {
-            String::new()
-        } else {
-            let pairs = keywords
-                .iter()
-                .map(|(jp, zh)| format!("{jp}:{zh}"))
-                .collect::<Vec<_>>()
-                .join(", ");
-            format!("已知翻译对照：{pairs}\n")
-        };
-        let content = format!("{known}{input}");
-        let req = serde_json::json!({
-           "model": self.model,
-           "messages": [
-               {"role": "user", "content": TRANSLATE_PROMPT.replace("{}", &content)}
-           ],
-           "max_tokens": 8192,
-           "temperature": 1.3,
-           "stream": false,
-        });
-        let resp = self
-            .client
-            .post(DEEPSEEK_API_BASE)
-            .json(&req)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await?;
-        let output = resp
-            .json::<serde_json::Value>()
-            .await?
-            .pointer("/choices/0/message/content")
-            .ok_or(anyhow!("deepseek api response api error"))?
-            .as_str()
-            .unwrap_or("")
-            .to_string();
-        Ok(output)
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
     }
 
-    /// 从翻译结果中进一步提取新的专有名词对照
-    pub async fn extract_keywords(
-        &self,
-        zh: &str,
-        jp: &str,
-        keywords: Vec<String>,
-    ) -> Result<Vec<String>> {
-        let req = serde_json::json!({
-           "model": self.model,
-           "messages": [
-               {"role": "user", "content": KEYWORD_PROMPT.replace("{existing_pairs}", &format!("{keywords:?}")).replace("{japanese_text}", jp).replace("{chinese_text}", zh)}
-           ],
-           "max_tokens": 8192,
-           "temperature": 1.3,
-           "stream": false,
-        });
-        let resp = self
+    /// 下载页面并按声明的编码解码，小型站点常用 Shift_JIS 或 EUC-JP
+    async fn get(&self, url: &str) -> Result<String> {
+        let bytes = self
             .client
-            .post(DEEPSEEK_API_BASE)
-            .json(&req)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .get(url)
+            .header("Accept-Language", "ja,en-US;q=0.9,en;q=0.8")
             .send()
-            .await?;
-        let output = resp
-            .json::<serde_json::Value>()
             .await?
-            .pointer("/choices/0/message/content")
-            .ok_or(anyhow!("deepseek api response api error"))?
-            .as_str()
-            .unwrap_or("")
-            .to_string();
-        Ok(output.split('\n').map(|s| s.to_string()).collect())
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(decode_japanese(&bytes))
     }
 }
 
-/// 抽象小说站点需要实现的接口
-#[async_trait::async_trait]
-pub trait NovelSite: Send + Sync {
-    /// 根据目录页地址抓取章节列表
-    async fn fetch_directory(&self, url: &str) -> Result<Vec<Chapter>>;
-    /// 下载并解析单章正文
-    async fn fetch_chapter(&self, url: &str) -> Result<String>;
+impl Default for GenericSite {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// ncode.syosetu.com 的实现
-pub struct NcodeSite {
-    client: Arc<Client>,
+#[async_trait]
+impl NovelSite for GenericSite {
+    fn name(&self) -> &str {
+        "generic"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["http://".to_string(), "https://".to_string()]
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        url.starts_with("http://") || url.starts_with("https://")
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let html = cancellable(cancel, self.get(url)).await?;
+        Ok(parse_generic_directory(&html, url)?)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let html = cancellable(cancel, self.get(url)).await?;
+        Ok(parse_generic_chapter(&html)?)
+    }
 }
 
-impl NcodeSite {
+/// 本地文件的实现：目录中的每个 `.txt` 文件为一章，
+/// 单个文本文件则按章节标题拆分，章节路径为 `文件#序号`
+pub struct LocalSite;
+
+impl LocalSite {
     pub fn new() -> Self {
-        let client = Client::builder()
-            .redirect(reqwest::redirect::Policy::limited(10))
-            .cookie_store(true)
-            .build()
-            .expect("failed to build reqwest client");
-        NcodeSite {
-            client: Arc::new(client),
+        LocalSite
+    }
+
+    /// 读取文本文件，非 UTF-8 时按 Shift_JIS 解码
+    async fn read_text(path: &std::path::Path) -> Result<String> {
+        let bytes = tokio::fs::read(path).await?;
+        Ok(decode_japanese(&bytes))
+    }
+}
+
+impl Default for LocalSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 去掉 `file://` 前缀得到本地路径
+fn local_path(url: &str) -> &std::path::Path {
+    std::path::Path::new(url.strip_prefix("file://").unwrap_or(url))
+}
+
+#[async_trait]
+impl NovelSite for LocalSite {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["file://".to_string(), "<existing path>".to_string()]
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        url.starts_with("file://") || std::path::Path::new(url).exists()
+    }
+
+    async fn fetch_directory(
+        &self,
+        url: &str,
+        _cancel: &CancellationToken,
+    ) -> Result<Vec<Chapter>> {
+        let path = local_path(url);
+        if tokio::fs::metadata(path).await?.is_dir() {
+            let mut files = Vec::new();
+            let mut entries = tokio::fs::read_dir(path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file = entry.path();
+                if file
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("txt"))
+                {
+                    files.push(file);
+                }
+            }
+            files.sort();
+            return Ok(files
+                .into_iter()
+                .map(|file| Chapter {
+                    title: file
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    path: file.to_string_lossy().to_string(),
+                    ..Default::default()
+                })
+                .collect());
+        }
+        let text = Self::read_text(path).await?;
+        let sections = split_text_chapters(&text);
+        if sections.is_empty() {
+            let title = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| url.to_string());
+            return Ok(vec![Chapter {
+                path: path.to_string_lossy().to_string(),
+                title,
+                ..Default::default()
+            }]);
+        }
+        Ok(sections
+            .into_iter()
+            .enumerate()
+            .map(|(i, (title, _))| Chapter {
+                path: format!("{}#{}", path.display(), i + 1),
+                title,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn fetch_chapter(&self, url: &str, _cancel: &CancellationToken) -> Result<String> {
+        let (file, section) = match url.rsplit_once('#') {
+            Some((file, n)) if n.parse::<usize>().is_ok() => (file, n.parse::<usize>().ok()),
+            _ => (url, None),
+        };
+        let text = Self::read_text(local_path(file)).await?;
+        let body = match section {
+            Some(n) => split_text_chapters(&text)
+                .into_iter()
+                .nth(n.wrapping_sub(1))
+                .map(|(_, body)| body)
+                .ok_or_else(|| anyhow!("section {n} not found in {file}"))?,
+            None => text,
+        };
+        let content = body
+            .lines()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if content.is_empty() {
+            return Err(ExtractError::EmptyBody.into());
         }
+        Ok(content)
+    }
+}
+
+/// EPUB 文件的实现：阅读顺序（spine）中的每个文件为一章，
+/// 章节路径为 `文件.epub!/容器内路径`
+pub struct EpubSite;
+
+impl EpubSite {
+    pub fn new() -> Self {
+        EpubSite
+    }
+}
+
+impl Default for EpubSite {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[async_trait]
-impl NovelSite for NcodeSite {
-    async fn fetch_directory(&self, url: &str) -> Result<Vec<Chapter>> {
-        let directory_html = self
-            .client
-            .get(url)
-            .header("User-Agent", USER_AGENT)
-            .header("Accept-Language", "en-US,en;q=0.9,ja;q=0.8")
-            .send()
-            .await?
-            .text()
-            .await?;
-        let document = Html::parse_document(&directory_html);
-        let link_selector = Selector::parse("a.p-eplist__subtitle")
-            .map_err(|e| anyhow!("selector parse error: {e}"))?;
-        let links: Vec<Chapter> = document
-            .select(&link_selector)
-            .filter_map(|el| {
-                let href = el.value().attr("href")?;
-                let text = el
-                    .text()
-                    .map(str::trim)
-                    .filter(|t| !t.is_empty())
-                    .collect::<Vec<_>>()
-                    .join("");
-                let full = if href.starts_with("http") {
-                    href.to_string()
-                } else {
-                    format!("https://ncode.syosetu.com{href}")
-                };
-                Some(Chapter { path: full, title: text })
+impl NovelSite for EpubSite {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["<path>.epub".to_string()]
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        url.to_ascii_lowercase().ends_with(".epub") && local_path(url).is_file()
+    }
+
+    async fn fetch_directory(
+        &self,
+        url: &str,
+        _cancel: &CancellationToken,
+    ) -> Result<Vec<Chapter>> {
+        let file = local_path(url).to_path_buf();
+        let spine = tokio::task::spawn_blocking(move || epub_spine(&file)).await??;
+        Ok(spine
+            .into_iter()
+            .map(|(path, title)| Chapter {
+                path: format!("{}!/{path}", local_path(url).display()),
+                title,
+                ..Default::default()
             })
-            .collect();
-        Ok(links)
+            .collect())
     }
 
-    async fn fetch_chapter(&self, url: &str) -> Result<String> {
-        let content_html = self
+    async fn fetch_chapter(&self, url: &str, _cancel: &CancellationToken) -> Result<String> {
+        let (file, entry) = url
+            .rsplit_once("!/")
+            .ok_or_else(|| anyhow!("not an EPUB chapter path: {url}"))?;
+        let file = local_path(file).to_path_buf();
+        let entry = entry.to_string();
+        let html = tokio::task::spawn_blocking(move || -> Result<String> {
+            let mut zip = zip::ZipArchive::new(std::fs::File::open(&file)?)?;
+            read_zip_entry(&mut zip, &entry)
+        })
+        .await??;
+        Ok(parse_xhtml_text(&html)?)
+    }
+}
+
+/// novelup.plus 的实现，目录按 `?p=` 分页
+pub struct NovelupSite {
+    client: HttpClient,
+}
+
+impl NovelupSite {
+    pub fn new() -> Self {
+        NovelupSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+        }
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl Default for NovelupSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NovelSite for NovelupSite {
+    fn name(&self) -> &str {
+        "novelup"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["novelup.plus".to_string()]
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let mut chapters: Vec<Chapter> = Vec::new();
+        let mut page_url = url.to_string();
+        for _ in 0..NOVELUP_MAX_PAGES {
+            let html = cancellable(cancel, get_html(&self.client, &page_url)).await?;
+            for chapter in parse_novelup_directory(&html)? {
+                if !chapters.iter().any(|c| c.path == chapter.path) {
+                    chapters.push(chapter);
+                }
+            }
+            match parse_novelup_next_page(&html, &page_url) {
+                Some(next) if next != page_url => page_url = next,
+                _ => break,
+            }
+        }
+        Ok(chapters)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let content_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_novelup_chapter(&content_html)?)
+    }
+}
+
+/// pixiv 小说的实现，目录地址可以是系列页或单篇小说页
+///
+/// 仅登录可见或 R18 作品需要提供浏览器中的 `PHPSESSID` Cookie
+pub struct PixivNovelSite {
+    client: HttpClient,
+    session: Option<String>,
+}
+
+impl PixivNovelSite {
+    pub fn new(session: Option<String>) -> Self {
+        PixivNovelSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            session,
+        }
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 调用 ajax 接口，返回原始 JSON 文本
+    async fn ajax(&self, path: &str) -> Result<String> {
+        let mut req = self
             .client
-            .get(url)
-            .header("User-Agent", USER_AGENT)
-            .header("Accept-Language", "en-US,en;q=0.9,ja;q=0.8")
-            .send()
-            .await?
-            .text()
+            .get(format!("{PIXIV_ORIGIN}{path}"))
+            .header("Referer", format!("{PIXIV_ORIGIN}/"))
+            .header("Accept", "application/json")
+            .header("Accept-Language", "ja,en-US;q=0.9,en;q=0.8");
+        if let Some(session) = &self.session {
+            req = req.header("Cookie", format!("PHPSESSID={session}"));
+        }
+        let json = req.send().await?.text().await?;
+        // 先检查接口错误，以便保留服务端给出的原因
+        pixiv_body(&json)?;
+        Ok(json)
+    }
+}
+
+#[async_trait]
+impl NovelSite for PixivNovelSite {
+    fn name(&self) -> &str {
+        "pixiv"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["pixiv.net".to_string()]
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let (series, id) =
+            pixiv_target(url).ok_or_else(|| anyhow!("unrecognized pixiv url: {url}"))?;
+        if series {
+            let json = cancellable(
+                cancel,
+                self.ajax(&format!("/ajax/novel/series/{id}/content_titles")),
+            )
             .await?;
-        let document = Html::parse_document(&content_html);
-        let body_selector = Selector::parse("div.p-novel__body")
-            .map_err(|e| anyhow!("selector parse error: {e}"))?;
-        if let Some(element) = document.select(&body_selector).next() {
-            let content = element
-                .text()
-                .map(str::trim)
-                .filter(|t| !t.is_empty())
-                .collect::<Vec<_>>()
-                .join("\n");
-            Ok(content)
-        } else {
-            Err(anyhow!("body not found"))
+            return Ok(parse_pixiv_series(&json)?);
         }
+        let json = cancellable(cancel, self.ajax(&format!("/ajax/novel/{id}"))).await?;
+        let body = pixiv_body(&json)?;
+        Ok(vec![Chapter {
+            path: format!("{PIXIV_ORIGIN}/novel/show.php?id={id}"),
+            title: body["title"].as_str().unwrap_or(id).to_string(),
+            ..Default::default()
+        }])
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let id = match pixiv_target(url) {
+            Some((false, id)) => id,
+            _ => return Err(anyhow!("unrecognized pixiv novel url: {url}")),
+        };
+        let json = cancellable(cancel, self.ajax(&format!("/ajax/novel/{id}"))).await?;
+        Ok(parse_pixiv_novel(&json)?)
     }
 }
 
-/// syosetu.org 的实现
-pub struct OrgSite {
-    client: Arc<Client>,
+/// kakuyomu.jp 的实现
+pub struct KakuyomuSite {
+    client: HttpClient,
 }
 
-impl OrgSite {
+impl KakuyomuSite {
     pub fn new() -> Self {
-        let client = Client::builder()
-            .redirect(reqwest::redirect::Policy::limited(10))
-            .cookie_store(true)
-            .build()
-            .expect("failed to build reqwest client");
-        OrgSite {
-            client: Arc::new(client),
+        KakuyomuSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
         }
     }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl Default for KakuyomuSite {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[async_trait]
-impl NovelSite for OrgSite {
-    async fn fetch_directory(&self, url: &str) -> Result<Vec<Chapter>> {
-        let directory_html = self
+impl NovelSite for KakuyomuSite {
+    fn name(&self) -> &str {
+        "kakuyomu"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["kakuyomu.jp".to_string()]
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let directory_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_kakuyomu_directory(&directory_html, url)?)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let content_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_kakuyomu_chapter(&content_html)?)
+    }
+
+    async fn fetch_info(&self, url: &str, cancel: &CancellationToken) -> Result<NovelInfo> {
+        let directory_html = cancellable(cancel, get_html(&self.client, url)).await?;
+        Ok(parse_kakuyomu_info(&directory_html, url)?)
+    }
+}
+
+/// 青空文库 (aozora.gr.jp) 的实现，目录页地址为图书卡片页或 XHTML 正文文件
+pub struct AozoraSite {
+    client: HttpClient,
+    /// 最近下载的正文文件，同一作品的各章节共用
+    last_text: Mutex<Option<(String, String)>>,
+}
+
+impl AozoraSite {
+    pub fn new() -> Self {
+        AozoraSite {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            last_text: Mutex::new(None),
+        }
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 下载并解码页面，青空文库的正文文件多为 Shift_JIS
+    async fn get_decoded(&self, url: &str) -> Result<String> {
+        let bytes = self
             .client
             .get(url)
-            .header("User-Agent", USER_AGENT)
-            .header("Accept-Language", "en-US,en;q=0.9,ja;q=0.8")
+            .header("Accept-Language", "ja,en-US;q=0.9,en;q=0.8")
             .send()
             .await?
-            .text()
+            .error_for_status()?
+            .bytes()
             .await?;
-        let document = Html::parse_document(&directory_html);
-        let selector = Selector::parse("div.ss table a[href$='.html']")
-            .map_err(|e| anyhow!("selector parse error: {e}"))?;
-        let base = url.trim_end_matches('/');
-        let base = format!("{}/", base);
-        let links: Vec<Chapter> = document
-            .select(&selector)
-            .filter_map(|el| {
-                let href = el.value().attr("href")?;
-                let title = el.text().collect::<Vec<_>>().join("");
-                let full = if href.starts_with("http") {
-                    href.to_string()
-                } else {
-                    format!("{}{}", base, href.trim_start_matches("./"))
-                };
-                Some(Chapter {
-                    path: full,
-                    title: title.trim().to_string(),
-                })
-            })
-            .collect();
-        Ok(links)
+        Ok(decode_japanese(&bytes))
     }
 
-    async fn fetch_chapter(&self, url: &str) -> Result<String> {
-        let url = url.to_string();
-        let content_html = tokio::task::spawn_blocking(move || -> Result<String> {
-            let mut easy = Easy2::new(Sink(Vec::new()));
-            easy.url(&url)?;
-            easy.http_version(HttpVersion::V2TLS)?;
-            easy.useragent(USER_AGENT)?;
-            let mut headers = List::new();
-            headers.append("Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")?;
-            headers.append("Accept-Language: ja,en-US;q=0.9,en;q=0.8")?;
-            headers.append("Sec-Fetch-Dest: document")?;
-            headers.append("Sec-Fetch-Mode: navigate")?;
-            headers.append("Sec-Fetch-Site: none")?;
-            headers.append("Upgrade-Insecure-Requests: 1")?;
-            easy.http_headers(headers)?;
-            easy.perform()?;
-            let status = easy.response_code()?;
-            if status != 200 {
-                return Err(anyhow!(format!("unexpected status {status}")));
-            }
-            Ok(String::from_utf8_lossy(&easy.get_ref().0).to_string())
-        })
-        .await??;
-        let document = Html::parse_document(&content_html);
-        let body_selector = Selector::parse("div#honbun")
-            .map_err(|e| anyhow!("selector parse error: {e}"))?;
-        if let Some(element) = document.select(&body_selector).next() {
-            let content = element
-                .text()
-                .map(str::trim)
-                .filter(|t| !t.is_empty())
-                .collect::<Vec<_>>()
-                .join("\n");
-            Ok(content)
-        } else {
-            Err(anyhow!("body not found"))
+    /// 取得正文文件内容，命中缓存时不再下载
+    async fn get_text(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        if let Some((cached_url, html)) = self.last_text.lock().unwrap().as_ref()
+            && cached_url == url
+        {
+            return Ok(html.clone());
         }
+        let html = cancellable(cancel, self.get_decoded(url)).await?;
+        *self.last_text.lock().unwrap() = Some((url.to_string(), html.clone()));
+        Ok(html)
+    }
+}
+
+impl Default for AozoraSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NovelSite for AozoraSite {
+    fn name(&self) -> &str {
+        "aozora"
+    }
+
+    fn url_patterns(&self) -> Vec<String> {
+        vec!["aozora.gr.jp".to_string()]
+    }
+
+    async fn fetch_directory(&self, url: &str, cancel: &CancellationToken) -> Result<Vec<Chapter>> {
+        let text_url = if url.contains("/files/") {
+            url.to_string()
+        } else {
+            let card = cancellable(cancel, self.get_decoded(url)).await?;
+            parse_aozora_card(&card, url)?
+        };
+        let html = self.get_text(&text_url, cancel).await?;
+        Ok(parse_aozora_directory(&html, &text_url)?)
+    }
+
+    async fn fetch_chapter(&self, url: &str, cancel: &CancellationToken) -> Result<String> {
+        let (text_url, anchor) = match url.split_once('#') {
+            Some((text_url, anchor)) => (text_url, Some(anchor)),
+            None => (url, None),
+        };
+        let html = self.get_text(text_url, cancel).await?;
+        Ok(parse_aozora_chapter(&html, anchor)?)
     }
 }
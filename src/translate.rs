@@ -0,0 +1,2893 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+use crate::http::{HttpClient, HttpOptions, build_client};
+use crate::memory::{Character, Example, NovelSettings};
+use crate::metrics;
+use crate::syosetu::{
+    NOTE_LABELS_EN, NOTE_LABELS_KO, NOTE_LABELS_ZH, cancellable, is_transient, strip_ruby,
+};
+
+const TRANSLATE_PROMPT: &str = r##"请将以下日文内容完整、准确地翻译成{target_lang}。
+要求：
+1. 保持原文段落结构；
+2. 不要添加任何解释、注释或额外信息；
+3. **仅输出译文，不要输出原文或其他解释；**
+4. 注重文章原本的表达，特别是对话需要准确反映语气与人物特点；
+5. 原文中 `｜文字《读音》` 形式的注音请保留该格式：翻译文字部分，读音保持原文；
+6. 单独成行的插图占位符 `[挿絵 #n]` 请原样保留，不要翻译或删除。
+
+{instructions}{glossary}{text}"##;
+
+const NOTE_PROMPT: &str = r##"以下是网络小说作者写在章节正文前后的附言（前書き／後書き），
+内容多为对读者的问候、更新说明或设定补充。请将其翻译成{target_lang}。
+要求：
+1. 保持原文段落结构；
+2. 语气口语化，保留作者与读者交流的感觉；
+3. **仅输出译文，不要输出原文或其他解释。**
+
+{instructions}{glossary}{text}"##;
+
+const KEYWORD_PROMPT: &str = r##"请根据以下已提取的翻译列表、日文原文和{target_lang}译文，
+从中找出新的专有名词（日文原文中的人名、地名、招式名、非常见物品名等），以及它们
+在译文中的对应{target_lang}译名。
+要求：
+1. 仅输出新的翻译对照，不要重复已提取条目；
+2. 输出一个 JSON 对象，格式为 {"keywords":[{"japanese":"トウリ","translated":"托莉","category":"person"}]}，没有新条目时输出 {"keywords":[]}；
+3. category 为类别：person（人名）、place（地名）、skill（招式、技能、魔法）、item（物品）、organization（组织、势力），无法判断时省略；
+4. **不要添加任何说明、注释或其他额外内容。不要使用markdown格式或使用三引号将json包裹**
+
+已提取的翻译列表:
+{glossary}
+
+日文原文:
+{text}
+
+{target_lang}译文:
+{translation}"##;
+
+const TITLE_PROMPT: &str = r##"请将以下编号的日文短文本（章节标题或简介）逐条翻译成{target_lang}。
+要求：
+1. 每行输出一条，格式为“编号<TAB>译文”，编号与输入一致，不要遗漏；
+2. 专有名词参考已知翻译对照；
+3. **不要添加任何说明、注释或其他额外内容。**
+
+{known}{items}"##;
+
+const SUMMARY_PROMPT: &str = r##"以下是日文网络小说《{novel_title}》到目前为止的剧情摘要，以及最新一章的{target_lang}译文。
+请结合最新一章更新剧情摘要，供之后翻译时参考。
+要求：
+1. 使用{target_lang}书写，总长度不超过 800 字；
+2. 重点记录主要人物的身份、性别、彼此的关系与称呼，以及仍在进行中的情节；
+3. 已经结束且不再重要的情节可以压缩或删去；
+4. **仅输出更新后的摘要，不要添加任何说明。**
+
+已有摘要:
+{summary}
+
+最新一章译文:
+{text}"##;
+
+const CHARACTER_PROMPT: &str = r##"请根据以下已有的人物设定、日文原文和{target_lang}译文，更新本章出场人物的设定。
+要求：
+1. 只输出本章中新出场、或设定有新信息的人物，没有变化的人物不要输出；
+2. 每个人物包含 name（日文原名）、translated（译名）、gender（性别，无法确定时留空）、speech（说话方式，如自称、口癖、语气）、relationships（与其他人物的关系）；
+3. 输出一个 JSON 对象，格式为 {"characters":[{"name":"トウリ","translated":"托莉","gender":"女","speech":"自称“咱”，语气直率","relationships":"主角的青梅竹马"}]}，没有需要更新的人物时输出 {"characters":[]}；
+4. **不要添加任何说明、注释或其他额外内容。**
+
+已有的人物设定:
+{characters}
+
+日文原文:
+{text}
+
+{target_lang}译文:
+{translation}"##;
+
+const REVIEW_PROMPT: &str = r##"你是日文网络小说《{novel_title}》的{target_lang}译文审校。请逐段对照以下日文原文与{target_lang}译文，修正其中的漏译、误译以及与已知翻译对照不一致的译名。
+要求：
+1. 只修改有问题的地方，其余译文保持原样，不要润色或改写风格；
+2. 保留译文中的分段与 [挿絵 #n] 等占位标记；
+3. **仅输出修正后的完整译文，不要添加任何说明、注释或其他额外内容。**
+
+{instructions}{glossary}日文原文:
+{text}
+
+{target_lang}译文:
+{translation}"##;
+
+/// 译文的目标语言
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TargetLang {
+    #[default]
+    Zh,
+    En,
+    Ko,
+}
+
+impl TargetLang {
+    /// 语言代码，如 `zh`、`en`
+    pub fn code(self) -> &'static str {
+        match self {
+            TargetLang::Zh => "zh",
+            TargetLang::En => "en",
+            TargetLang::Ko => "ko",
+        }
+    }
+
+    /// 提示词中的语言名称，对应模板中的 `{target_lang}`
+    pub fn prompt_name(self) -> &'static str {
+        match self {
+            TargetLang::Zh => "中文",
+            TargetLang::En => "英文",
+            TargetLang::Ko => "韩文",
+        }
+    }
+
+    /// DeepL 的目标语言代码
+    fn deepl_code(self) -> &'static str {
+        match self {
+            TargetLang::Zh => "ZH",
+            TargetLang::En => "EN-US",
+            TargetLang::Ko => "KO",
+        }
+    }
+
+    /// 译文中前言与后记的标题
+    pub fn note_labels(self) -> (&'static str, &'static str) {
+        match self {
+            TargetLang::Zh => NOTE_LABELS_ZH,
+            TargetLang::En => NOTE_LABELS_EN,
+            TargetLang::Ko => NOTE_LABELS_KO,
+        }
+    }
+
+    /// 正文译文与原文（不计空白）字数之比的下限，低于此值视为译文过短
+    fn min_length_ratio(self) -> f64 {
+        match self {
+            // 英文按字母计，通常比日文长得多
+            TargetLang::En => 0.8,
+            TargetLang::Zh | TargetLang::Ko => 0.3,
+        }
+    }
+
+    /// 按目标语言区分的存储文件：中文沿用原文件名，其他语言在扩展名前加上语言代码，
+    /// 如 `translations.en.json`
+    pub fn store_path(self, file: &str) -> String {
+        match (self, file.rsplit_once('.')) {
+            (TargetLang::Zh, _) => file.to_string(),
+            (_, Some((stem, ext))) => format!("{stem}.{}.{ext}", self.code()),
+            (_, None) => format!("{file}.{}", self.code()),
+        }
+    }
+}
+
+impl fmt::Display for TargetLang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for TargetLang {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "zh" | "zh-cn" | "chinese" => Ok(TargetLang::Zh),
+            "en" | "english" => Ok(TargetLang::En),
+            "ko" | "korean" => Ok(TargetLang::Ko),
+            _ => bail!("unsupported target language `{s}`, expected zh, en or ko"),
+        }
+    }
+}
+
+/// 翻译时附带的已知信息，随每次调用传入
+#[derive(Clone, Debug, Default)]
+pub struct TranslationContext {
+    /// 已知的专有名词对照
+    pub keywords: Vec<(String, String)>,
+    /// 作品名，对应提示词模板中的 `{novel_title}`
+    pub novel_title: String,
+    /// 译文的目标语言，对应提示词模板中的 `{target_lang}`
+    pub target_lang: TargetLang,
+    /// 敬称的处理方式，未设置时由模型自行决定
+    pub honorifics: Option<Honorifics>,
+    /// 到上一章为止的剧情摘要
+    pub summary: String,
+    /// 上一章的路径，用于读取其结尾作为衔接参考
+    pub previous_chapter: Option<String>,
+    /// 上一章结尾的原文与译文
+    pub previous_tail: Option<(String, String)>,
+    /// 已知的人物设定
+    pub characters: Vec<Character>,
+    /// 上次译文可疑后的重试：降低温度并要求完整翻译
+    pub retry: bool,
+    /// 覆盖对话式引擎默认使用的模型
+    pub model: Option<String>,
+    /// 采样参数，来自命令行并可被各小说的设置覆盖
+    pub sampling: Sampling,
+    /// 译文风格预设
+    pub style: Option<StylePreset>,
+    /// 附加在提示词中的作品说明，如“这是一部搞笑的异世界作品”
+    pub notes: String,
+    /// 少样本翻译示例
+    pub examples: Vec<Example>,
+    /// 专有名词的类别，键为原文；人名会在补充要求中强调保持一致
+    pub categories: HashMap<String, KeywordCategory>,
+    /// 原文中的对照表术语已换成占位符，见 [`TermLock`]
+    pub lock_terms: bool,
+}
+
+impl TranslationContext {
+    pub fn new(keywords: Vec<(String, String)>) -> Self {
+        TranslationContext {
+            keywords,
+            ..Default::default()
+        }
+    }
+
+    /// 设置作品名
+    pub fn with_novel_title(mut self, title: &str) -> Self {
+        self.novel_title = title.to_string();
+        self
+    }
+
+    /// 设置目标语言
+    pub fn with_target_lang(mut self, lang: TargetLang) -> Self {
+        self.target_lang = lang;
+        self
+    }
+
+    /// 设置敬称的处理方式
+    pub fn with_honorifics(mut self, honorifics: Option<Honorifics>) -> Self {
+        self.honorifics = honorifics;
+        self
+    }
+
+    /// 设置剧情摘要
+    pub fn with_summary(mut self, summary: &str) -> Self {
+        self.summary = summary.trim().to_string();
+        self
+    }
+
+    /// 设置上一章的路径
+    pub fn with_previous_chapter(mut self, path: Option<String>) -> Self {
+        self.previous_chapter = path;
+        self
+    }
+
+    /// 设置人物设定
+    pub fn with_characters(mut self, characters: Vec<Character>) -> Self {
+        self.characters = characters;
+        self
+    }
+
+    /// 标记为可疑译文的重试
+    pub fn with_retry(mut self, retry: bool) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置全局的采样参数
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// 应用按小说覆盖的模型、采样参数、风格与作品说明
+    pub fn with_settings(mut self, settings: &NovelSettings) -> Self {
+        self.model = settings.model.clone();
+        self.sampling = settings.sampling.or(self.sampling);
+        self.style = settings.style;
+        self.notes = settings.notes.clone().unwrap_or_default();
+        self
+    }
+
+    /// 设置少样本翻译示例，最多保留 `MAX_EXAMPLES` 条
+    pub fn with_examples(mut self, mut examples: Vec<Example>) -> Self {
+        examples.truncate(MAX_EXAMPLES);
+        self.examples = examples;
+        self
+    }
+
+    /// 设置专有名词的类别
+    pub fn with_categories(mut self, categories: HashMap<String, KeywordCategory>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    /// 设置是否锁定术语
+    pub fn with_term_lock(mut self, lock_terms: bool) -> Self {
+        self.lock_terms = lock_terms;
+        self
+    }
+
+    /// 对照表中类别为人名的原文，按原文排序
+    fn person_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .keywords
+            .iter()
+            .map(|(jp, _)| jp.as_str())
+            .filter(|jp| self.categories.get(*jp) == Some(&KeywordCategory::Person))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// 使用指定温度的请求参数，并带上按小说覆盖的模型
+    fn chat_params(&self, temperature: f64) -> ChatParams {
+        ChatParams {
+            temperature,
+            model: self.model.clone(),
+            max_tokens: self.sampling.max_tokens,
+            top_p: self.sampling.top_p,
+        }
+    }
+
+    /// 附加在提示词中的剧情摘要、人物设定、上一章结尾与补充要求，对应模板中的 `{instructions}`
+    fn instructions(&self) -> String {
+        let mut out = String::new();
+        if !self.notes.is_empty() {
+            out.push_str(&format!("作品说明：\n{}\n\n", self.notes));
+        }
+        if !self.examples.is_empty() {
+            let examples: String = self
+                .examples
+                .iter()
+                .map(|e| format!("原文：\n{}\n译文：\n{}\n\n", e.source, e.translation))
+                .collect();
+            out.push_str(&format!(
+                "翻译示例（参考其文风与用词，不要翻译或输出）：\n{examples}"
+            ));
+        }
+        if !self.summary.is_empty() {
+            out.push_str(&format!(
+                "前情提要（用于保持人称、人物关系与情节指代一致，不要翻译或输出）：\n{}\n\n",
+                self.summary
+            ));
+        }
+        if !self.characters.is_empty() {
+            let sheet: String = self
+                .characters
+                .iter()
+                .map(|c| format!("- {}\n", describe_character(c)))
+                .collect();
+            out.push_str(&format!(
+                "人物设定（翻译时保持性别、人称与说话方式一致）：\n{sheet}\n"
+            ));
+        }
+        if let Some((source, translation)) = &self.previous_tail {
+            out.push_str(&format!(
+                "上一章结尾（仅供衔接参考，不要翻译或输出）：\n原文：\n{source}\n译文：\n{translation}\n\n"
+            ));
+        }
+        let mut rules: Vec<String> = self
+            .honorifics
+            .map(|h| h.instruction(self.target_lang))
+            .into_iter()
+            .collect();
+        let names = self.person_names();
+        if !names.is_empty() {
+            rules.push(format!(
+                "以下人名必须始终使用已知翻译对照中的译名，不要改写或换用其他译法：{}",
+                names.join("、")
+            ));
+        }
+        if self.lock_terms {
+            rules.push(
+                "原文中 ⟦T1⟧ 形式的占位符代表已确定译名的专有名词，在译文的相应位置原样保留，不要翻译、改写或删除"
+                    .to_string(),
+            );
+        }
+        if let Some(style) = self.style {
+            rules.push(style.instruction().to_string());
+        }
+        if self.retry {
+            rules.push(
+                "上一次的译文不完整或残留日文，请完整翻译全部内容，不要省略、截断或保留日文原文"
+                    .to_string(),
+            );
+        }
+        if !rules.is_empty() {
+            let rules: String = rules.iter().map(|r| format!("- {r}\n")).collect();
+            out.push_str(&format!("补充要求：\n{rules}\n"));
+        }
+        out
+    }
+}
+
+/// 附在提示词中的翻译示例上限，示例过多会占用大量上下文
+pub const MAX_EXAMPLES: usize = 4;
+
+/// 人物设定在提示词中的一行，如 `トウリ（托莉）：女；自称“咱”；主角的青梅竹马`
+fn describe_character(character: &Character) -> String {
+    let mut line = character.name.clone();
+    if !character.translated.is_empty() {
+        line.push_str(&format!("（{}）", character.translated));
+    }
+    let details: Vec<&str> = [
+        &character.gender,
+        &character.speech,
+        &character.relationships,
+    ]
+    .into_iter()
+    .map(|s| s.as_str())
+    .filter(|s| !s.is_empty())
+    .collect();
+    if !details.is_empty() {
+        line.push_str(&format!("：{}", details.join("；")));
+    }
+    line
+}
+
+/// 人名后日文敬称（さん、ちゃん、くん、様）的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Honorifics {
+    /// 保留日文写法，如「托莉さん」
+    Keep,
+    /// 罗马字音译，如「托莉-san」
+    Transliterate,
+    /// 按目标语言习惯意译或省略
+    Localize,
+}
+
+/// 日文敬称及其罗马字写法
+const HONORIFICS: [(&str, &str); 5] = [
+    ("さん", "san"),
+    ("ちゃん", "chan"),
+    ("くん", "kun"),
+    ("さま", "sama"),
+    ("様", "sama"),
+];
+
+/// 译文中的日文敬称；注音《》整体匹配以便跳过，后接平假名或「々」的不是敬称（如「ちゃんと」「様々」）
+static KANA_HONORIFIC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"《[^《》\n]*》|(さん|ちゃん|くん|さま|様)([\p{Hiragana}々])?").unwrap()
+});
+
+/// 译文中罗马字写法的敬称
+static ROMAJI_HONORIFIC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"-(san|chan|kun|sama)([^A-Za-z]|$)").unwrap());
+
+impl Honorifics {
+    /// 写入提示词的要求
+    fn instruction(self, lang: TargetLang) -> String {
+        match self {
+            Honorifics::Keep => {
+                "人名后的日文敬称（さん、ちゃん、くん、様）原样保留日文写法，不要翻译。".to_string()
+            }
+            Honorifics::Transliterate => "人名后的日文敬称按罗马字音译，用连字符接在名字后：さん→-san、ちゃん→-chan、くん→-kun、様→-sama。".to_string(),
+            Honorifics::Localize => format!(
+                "人名后的日文敬称按{}的习惯意译或省略，不要保留日文或罗马字写法。",
+                lang.prompt_name()
+            ),
+        }
+    }
+
+    /// 检查译文并改写不符合设置的敬称，返回改写后的译文与改写处数
+    pub fn enforce(self, text: &str) -> (String, usize) {
+        let mut fixed = 0;
+        let text = KANA_HONORIFIC.replace_all(text, |caps: &regex::Captures| {
+            let (Some(kana), None) = (caps.get(1), caps.get(2)) else {
+                return caps[0].to_string();
+            };
+            let romaji = HONORIFICS
+                .iter()
+                .find(|(k, _)| *k == kana.as_str())
+                .map_or("", |(_, r)| *r);
+            match self {
+                Honorifics::Keep => caps[0].to_string(),
+                Honorifics::Transliterate => {
+                    fixed += 1;
+                    format!("-{romaji}")
+                }
+                Honorifics::Localize => {
+                    fixed += 1;
+                    String::new()
+                }
+            }
+        });
+        let text = ROMAJI_HONORIFIC.replace_all(&text, |caps: &regex::Captures| {
+            let rest = &caps[2];
+            match self {
+                Honorifics::Transliterate => caps[0].to_string(),
+                Honorifics::Keep => {
+                    fixed += 1;
+                    let kana = HONORIFICS
+                        .iter()
+                        .rev()
+                        .find(|(_, r)| *r == &caps[1])
+                        .map_or("", |(k, _)| *k);
+                    format!("{kana}{rest}")
+                }
+                Honorifics::Localize => {
+                    fixed += 1;
+                    rest.to_string()
+                }
+            }
+        });
+        (text.into_owned(), fixed)
+    }
+}
+
+/// 检查译文是否沿用了对照表中的译名。原文中出现的术语若原样残留在译文里，替换为对照的译名；
+/// 译名本身包含原文术语时无法区分，不做替换。返回修正后的译文，以及替换后仍找不到译名的原文术语
+pub fn verify_glossary(
+    source: &str,
+    translation: &str,
+    keywords: &[(String, String)],
+) -> (String, Vec<String>) {
+    let mut terms: Vec<&(String, String)> = keywords
+        .iter()
+        .filter(|(jp, tr)| {
+            !jp.is_empty() && !tr.is_empty() && jp != tr && source.contains(jp.as_str())
+        })
+        .collect();
+    // 先替换较长的术语，避免只替换了长术语中的一部分
+    terms.sort_by_key(|(jp, _)| std::cmp::Reverse(jp.chars().count()));
+    let mut text = translation.to_string();
+    for (jp, tr) in &terms {
+        if !tr.contains(jp.as_str()) && text.contains(jp.as_str()) {
+            text = text.replace(jp.as_str(), tr);
+        }
+    }
+    let drifted = terms
+        .iter()
+        .filter(|(_, tr)| !text.contains(tr.as_str()))
+        .map(|(jp, _)| jp.clone())
+        .collect();
+    (text, drifted)
+}
+
+/// 模型返回的占位符，容许其中多出空白
+static LOCK_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"⟦\s*T\s*(\d+)\s*⟧").unwrap());
+
+/// 锁定术语：翻译前把原文中的对照表术语换成 `⟦T1⟧` 形式的占位符，翻译后换回对照的译名，
+/// 即使模型不遵守对照表，译名也不会改变
+#[derive(Clone, Debug, Default)]
+pub struct TermLock {
+    /// 按占位符编号排列的原文术语与译名
+    terms: Vec<(String, String)>,
+}
+
+impl TermLock {
+    /// 把 `text` 中出现的术语换成占位符，返回锁定后的文本；同一位置优先匹配较长的术语
+    pub fn lock(text: &str, keywords: &[(String, String)]) -> (Self, String) {
+        let mut candidates: Vec<&(String, String)> = keywords
+            .iter()
+            .filter(|(jp, tr)| !jp.is_empty() && !tr.is_empty() && text.contains(jp.as_str()))
+            .collect();
+        candidates.sort_by_key(|(jp, _)| std::cmp::Reverse(jp.len()));
+        let mut lock = TermLock::default();
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            match candidates
+                .iter()
+                .find(|(jp, _)| rest.starts_with(jp.as_str()))
+            {
+                Some((jp, tr)) => {
+                    let idx = match lock.terms.iter().position(|(term, _)| term == jp) {
+                        Some(idx) => idx,
+                        None => {
+                            lock.terms.push((jp.clone(), tr.clone()));
+                            lock.terms.len() - 1
+                        }
+                    };
+                    out.push_str(&format!("⟦T{}⟧", idx + 1));
+                    rest = &rest[jp.len()..];
+                }
+                None => {
+                    out.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        (lock, out)
+    }
+
+    /// 是否锁定了任何术语
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// 把译文中的占位符换回译名，返回换回后的译文与译文中找不到占位符的原文术语；
+    /// 模型编造的编号原样保留
+    pub fn unlock(&self, translation: &str) -> (String, Vec<String>) {
+        let mut seen = vec![false; self.terms.len()];
+        let text = LOCK_PLACEHOLDER.replace_all(translation, |caps: &regex::Captures| {
+            let term = caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .filter(|&idx| idx < self.terms.len());
+            match term {
+                Some(idx) => {
+                    seen[idx] = true;
+                    self.terms[idx].1.clone()
+                }
+                None => caps[0].to_string(),
+            }
+        });
+        let lost = self
+            .terms
+            .iter()
+            .zip(seen)
+            .filter(|(_, seen)| !seen)
+            .map(|((jp, _), _)| jp.clone())
+            .collect();
+        (text.into_owned(), lost)
+    }
+}
+
+/// 译文风格预设，作为补充要求附在提示词中
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StylePreset {
+    /// 贴近原文的直译
+    Faithful,
+    /// 流畅、有文采的意译
+    Literary,
+    /// 轻松口语化，适合轻小说与喜剧
+    Casual,
+}
+
+impl StylePreset {
+    /// 全部预设，按界面中切换的顺序排列
+    pub const ALL: [StylePreset; 3] = [
+        StylePreset::Faithful,
+        StylePreset::Literary,
+        StylePreset::Casual,
+    ];
+
+    /// 预设名称，如 `literary`
+    pub fn name(self) -> &'static str {
+        match self {
+            StylePreset::Faithful => "faithful",
+            StylePreset::Literary => "literary",
+            StylePreset::Casual => "casual",
+        }
+    }
+
+    /// 附加在提示词补充要求中的说明
+    fn instruction(self) -> &'static str {
+        match self {
+            StylePreset::Faithful => "译文尽量贴近原文的用词与句式，不要意译或增删内容",
+            StylePreset::Literary => "译文在忠实原意的前提下追求流畅自然、富有文采，可适当调整语序",
+            StylePreset::Casual => "译文使用轻松口语化的表达，保留吐槽与玩笑的节奏感",
+        }
+    }
+}
+
+impl fmt::Display for StylePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StylePreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        StylePreset::ALL
+            .into_iter()
+            .find(|p| p.name() == s.to_ascii_lowercase())
+            .ok_or_else(|| {
+                anyhow!("unknown style preset `{s}`, expected faithful, literary or casual")
+            })
+    }
+}
+
+/// 专有名词的类别，由提取时的模型给出，可在对照表界面中修改
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCategory {
+    /// 人名
+    Person,
+    /// 地名
+    Place,
+    /// 招式、技能与魔法
+    Skill,
+    /// 物品
+    Item,
+    /// 组织与势力
+    Organization,
+}
+
+impl KeywordCategory {
+    /// 全部类别，按界面中切换的顺序排列
+    pub const ALL: [KeywordCategory; 5] = [
+        KeywordCategory::Person,
+        KeywordCategory::Place,
+        KeywordCategory::Skill,
+        KeywordCategory::Item,
+        KeywordCategory::Organization,
+    ];
+
+    /// 类别名称，如 `person`
+    pub fn name(self) -> &'static str {
+        match self {
+            KeywordCategory::Person => "person",
+            KeywordCategory::Place => "place",
+            KeywordCategory::Skill => "skill",
+            KeywordCategory::Item => "item",
+            KeywordCategory::Organization => "organization",
+        }
+    }
+}
+
+impl fmt::Display for KeywordCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for KeywordCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        KeywordCategory::ALL
+            .into_iter()
+            .find(|c| c.name() == s.trim().to_ascii_lowercase())
+            .ok_or_else(|| anyhow!("unknown keyword category `{s}`"))
+    }
+}
+
+/// 模型提取出的专有名词
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyword {
+    pub japanese: String,
+    pub translated: String,
+    /// 模型未给出或给出无法识别的类别时为 `None`
+    pub category: Option<KeywordCategory>,
+}
+
+impl Keyword {
+    /// 没有类别的词条
+    pub fn new(japanese: &str, translated: &str) -> Self {
+        Keyword {
+            japanese: japanese.to_string(),
+            translated: translated.to_string(),
+            category: None,
+        }
+    }
+
+    /// 规范化后的词条：统一全角与半角，去掉首尾的空白与标点，原文末尾有人名敬称时
+    /// 去掉敬称，译文中对应的日文或罗马字敬称一并去掉
+    pub fn normalized(self) -> Self {
+        let japanese = normalize_term(&self.japanese);
+        let mut translated = normalize_term(&self.translated);
+        let japanese = match strip_honorific(&japanese) {
+            Some(name) => {
+                for (kana, romaji) in HONORIFICS {
+                    if let Some(rest) = translated
+                        .strip_suffix(kana)
+                        .or_else(|| translated.strip_suffix(&format!("-{romaji}")))
+                        .filter(|rest| !rest.is_empty())
+                    {
+                        translated = rest.to_string();
+                        break;
+                    }
+                }
+                name.to_string()
+            }
+            None => japanese,
+        };
+        Keyword {
+            japanese,
+            translated,
+            ..self
+        }
+    }
+}
+
+/// 半角片假名及标点（U+FF61 起）对应的全角字符
+const HALFWIDTH_KANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+/// 统一全角与半角并去掉首尾的空白与标点：全角英数字与符号转为半角，
+/// 半角片假名转为全角并与后面的浊点、半浊点合并
+pub fn normalize_term(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        let c = match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{FF61}'..='\u{FF9F}' => HALFWIDTH_KANA
+                .chars()
+                .nth((c as u32 - 0xFF61) as usize)
+                .unwrap_or(c),
+            _ => c,
+        };
+        // 浊点、半浊点与前一个假名合成一个字符
+        let composed = match (c, out.chars().last()) {
+            ('゛', Some('ウ')) => Some('ヴ'),
+            ('゛', Some(p @ ('カ'..='ト' | 'ハ'..='ホ'))) if can_voice(p) => {
+                char::from_u32(p as u32 + 1)
+            }
+            ('゜', Some(p @ 'ハ'..='ホ')) if can_voice(p) => char::from_u32(p as u32 + 2),
+            _ => None,
+        };
+        match composed {
+            Some(voiced) => {
+                out.pop();
+                out.push(voiced);
+            }
+            None => out.push(c),
+        }
+    }
+    let is_edge = |c: char| {
+        c.is_whitespace()
+            || c.is_ascii_punctuation()
+            || "「」『』（）【】〈〉《》、。・…“”‘’〜".contains(c)
+    };
+    out.trim_matches(is_edge).to_string()
+}
+
+/// 可加浊点的清音假名：カ行至タ行的大写假名及ハ行（与浊音相邻排列）
+fn can_voice(c: char) -> bool {
+    match c {
+        'カ'..='ヂ' => (c as u32 - 'カ' as u32).is_multiple_of(2),
+        'ツ' | 'テ' | 'ト' => true,
+        'ハ'..='ホ' => (c as u32 - 'ハ' as u32).is_multiple_of(3),
+        _ => false,
+    }
+}
+
+/// 去掉人名末尾的敬称，返回去掉后的名字；以「お」「ご」开头的称呼（如「お姉さん」）
+/// 及去掉后不足两个字的词（如「皆さん」）不是人名加敬称，返回 `None`
+fn strip_honorific(term: &str) -> Option<&str> {
+    if term.starts_with(['お', 'ご']) {
+        return None;
+    }
+    HONORIFICS
+        .iter()
+        .map(|(kana, _)| *kana)
+        .chain(["殿"])
+        .find_map(|kana| term.strip_suffix(kana))
+        .filter(|name| name.chars().count() >= 2)
+}
+
+/// 正文译文可疑的原因
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Suspect {
+    /// 译文相对原文过短，可能漏译或被截断
+    TooShort,
+    /// 译文中残留大量假名，可能没有翻译
+    Untranslated,
+    /// 原文以完整的句子结束而译文没有，可能输出中断
+    Truncated,
+}
+
+impl fmt::Display for Suspect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Suspect::TooShort => "output too short",
+            Suspect::Untranslated => "kana left untranslated",
+            Suspect::Truncated => "ends mid-sentence",
+        })
+    }
+}
+
+/// 原文少于此字数时不检查长度比例，短章节的比例波动太大
+const SUSPECT_MIN_CHARS: usize = 200;
+
+/// 译文中假名占比的上限，保留的敬称与拟声词通常远低于此值
+const MAX_KANA_RATIO: f64 = 0.1;
+
+/// 视为句子已结束的字符
+const SENTENCE_ENDS: &str = "。！？!?.…」』）)”’\"'～~—♪☆★";
+
+/// 按译文长度、残留假名与结尾检查正文译文，没有问题时返回 `None`
+pub fn suspect_translation(source: &str, translation: &str, lang: TargetLang) -> Option<Suspect> {
+    let source_chars = source.chars().filter(|c| !c.is_whitespace()).count();
+    let text = strip_ruby(translation);
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if source_chars >= SUSPECT_MIN_CHARS
+        && (chars.len() as f64) < source_chars as f64 * lang.min_length_ratio()
+    {
+        return Some(Suspect::TooShort);
+    }
+    // 不计长音符与中点，中文译文中也会出现
+    let kana = chars
+        .iter()
+        .filter(|c| matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}'))
+        .count();
+    if kana as f64 > chars.len() as f64 * MAX_KANA_RATIO {
+        return Some(Suspect::Untranslated);
+    }
+    let ends_sentence = |s: &str| {
+        s.trim_end()
+            .chars()
+            .last()
+            .is_some_and(|c| SENTENCE_ENDS.contains(c))
+    };
+    if ends_sentence(source) && !ends_sentence(&text) {
+        return Some(Suspect::Truncated);
+    }
+    None
+}
+
+impl FromStr for Honorifics {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(Honorifics::Keep),
+            "transliterate" => Ok(Honorifics::Transliterate),
+            "localize" => Ok(Honorifics::Localize),
+            _ => bail!("unknown honorifics mode `{s}`, expected keep, transliterate or localize"),
+        }
+    }
+}
+
+/// 对话式模型使用的提示词模板，可从 `--prompt-dir` 中的文件加载。
+/// 正文与附言模板可用 `{glossary}`（已知对照）、`{text}`（原文）、`{novel_title}`、`{target_lang}`、
+/// `{instructions}`（剧情摘要、人物设定、上一章结尾与敬称等补充要求）；
+/// 专有名词模板另有 `{translation}`（译文），其中 `{glossary}` 为已提取的条目
+#[derive(Clone, Debug)]
+pub struct PromptTemplates {
+    pub translate: String,
+    pub note: String,
+    pub keyword: String,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        PromptTemplates {
+            translate: TRANSLATE_PROMPT.to_string(),
+            note: NOTE_PROMPT.to_string(),
+            keyword: KEYWORD_PROMPT.to_string(),
+        }
+    }
+}
+
+impl PromptTemplates {
+    /// 读取目录中的 `translate.txt`、`note.txt` 与 `keyword.txt`，缺少的文件使用内置模板
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut templates = PromptTemplates::default();
+        for (name, slot) in [
+            ("translate.txt", &mut templates.translate),
+            ("note.txt", &mut templates.note),
+            ("keyword.txt", &mut templates.keyword),
+        ] {
+            match fs::read_to_string(dir.join(name)) {
+                Ok(text) => *slot = text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(anyhow!("failed to read {}: {e}", dir.join(name).display())),
+            }
+        }
+        Ok(templates)
+    }
+}
+
+/// 内置模板，未配置自定义模板的引擎共用
+static BUILTIN_TEMPLATES: LazyLock<PromptTemplates> = LazyLock::new(PromptTemplates::default);
+
+/// 替换模板中的占位符；依次替换，已替换进去的原文中出现的占位符不会被再次替换
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    'scan: while let Some(start) = rest.find('{') {
+        for (name, value) in values {
+            if let Some(tail) = rest[start + 1..]
+                .strip_prefix(*name)
+                .and_then(|t| t.strip_prefix('}'))
+            {
+                out.push_str(&rest[..start]);
+                out.push_str(value);
+                rest = tail;
+                continue 'scan;
+            }
+        }
+        out.push_str(&rest[..=start]);
+        rest = &rest[start + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 单次请求中翻译的标题数量上限
+const TITLE_BATCH: usize = 200;
+
+/// DeepSeek API 的地址，也是 `--api-base` 的默认值
+pub const DEEPSEEK_API_BASE: &str = "https://api.deepseek.com";
+
+/// OpenRouter 用于标识调用方的请求头，缺少时请求仍可成功，但不会显示应用名称
+const OPENROUTER_HEADERS: [(&str, &str); 2] = [
+    ("HTTP-Referer", "https://github.com/ForSeason/syosetu-rs"),
+    ("X-Title", "syosetu-rs"),
+];
+
+/// 由 API 根地址得到对话补全接口的地址，已包含 `/chat/completions` 时原样返回
+pub fn chat_completions_url(base: &str) -> String {
+    let base = base.trim_end_matches('/');
+    if base.ends_with("/chat/completions") {
+        base.to_string()
+    } else {
+        format!("{base}/chat/completions")
+    }
+}
+
+/// 流式翻译时回传的进度
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamEvent {
+    /// 新生成的一段译文
+    Delta(String),
+    /// 引擎失败后改用下一个引擎重新翻译，此前收到的片段作废
+    Restart,
+}
+
+/// 接收流式翻译进度的通道
+pub type StreamSender = UnboundedSender<StreamEvent>;
+
+/// 翻译引擎需要实现的接口；界面与处理流程只通过它调用翻译，更换引擎无需改动其他逻辑
+#[async_trait]
+pub trait TranslationBackend: Send + Sync {
+    /// 引擎名称，用于日志
+    fn name(&self) -> &str;
+
+    /// 翻译章节正文，`ctx` 提供已知的专有名词对照与作品信息
+    async fn translate_text(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String>;
+
+    /// 翻译章节正文，生成过程中把新产出的片段发给 `partial`，返回译文与实际产出译文的引擎名称；
+    /// 默认在翻译完成后一次性发送全文
+    async fn translate_streaming(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        partial: Option<&StreamSender>,
+        cancel: &CancellationToken,
+    ) -> Result<(String, String)> {
+        let text = self.translate_text(input, ctx, cancel).await?;
+        if let Some(tx) = partial {
+            let _ = tx.send(StreamEvent::Delta(text.clone()));
+        }
+        Ok((text, self.name().to_string()))
+    }
+
+    /// 翻译作者的前書き或後書き，默认与正文相同
+    async fn translate_note(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        self.translate_text(input, ctx, cancel).await
+    }
+
+    /// 对照原文与译文提取新的专有名词及其类别
+    async fn extract_keywords(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Keyword>>;
+
+    /// 根据最新一章的译文更新剧情摘要；不支持时原样返回已有摘要
+    async fn summarize(
+        &self,
+        summary: &str,
+        _chapter: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> Result<String> {
+        Ok(summary.to_string())
+    }
+
+    /// 对照原文与译文更新人物设定，返回新出场或有新信息的人物；不支持时返回空列表
+    async fn update_characters(
+        &self,
+        _zh: &str,
+        _jp: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> Result<Vec<Character>> {
+        Ok(Vec::new())
+    }
+
+    /// 对照原文审校正文译文，修正漏译与误译并返回修正后的译文；不支持时原样返回
+    async fn review(
+        &self,
+        zh: &str,
+        _jp: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> Result<String> {
+        Ok(zh.to_string())
+    }
+
+    /// 批量翻译章节标题等短文本，返回与输入一一对应的译文，模型漏译的条目为 `None`；
+    /// 默认逐条调用 `translate_text`
+    async fn translate_titles(
+        &self,
+        titles: &[String],
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Option<String>>> {
+        let mut result = Vec::with_capacity(titles.len());
+        for title in titles {
+            result.push(Some(self.translate_text(title, ctx, cancel).await?));
+        }
+        Ok(result)
+    }
+}
+
+/// 按顺序尝试多个引擎：前一个失败（额度用尽、服务中断等）时自动改用下一个
+pub struct FallbackBackend {
+    backends: Vec<Arc<dyn TranslationBackend>>,
+    name: String,
+}
+
+impl FallbackBackend {
+    /// 以主引擎在前的顺序创建，列表不能为空
+    pub fn new(backends: Vec<Arc<dyn TranslationBackend>>) -> Self {
+        assert!(
+            !backends.is_empty(),
+            "fallback chain needs at least one backend"
+        );
+        let name = backends
+            .iter()
+            .map(|b| b.name())
+            .collect::<Vec<_>>()
+            .join(" > ");
+        FallbackBackend { backends, name }
+    }
+
+    /// 依次调用各引擎直到成功，返回结果与成功的引擎；任务被取消时不再尝试后续引擎
+    async fn first_success<T, F, Fut>(
+        &self,
+        cancel: &CancellationToken,
+        call: F,
+    ) -> Result<(T, &str)>
+    where
+        F: Fn(Arc<dyn TranslationBackend>) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T>> + Send,
+        T: Send,
+    {
+        let mut last_err = None;
+        for backend in &self.backends {
+            match call(backend.clone()).await {
+                Ok(value) => return Ok((value, backend.name())),
+                Err(e) if cancel.is_cancelled() => return Err(e),
+                Err(e) => {
+                    warn!("{} failed, trying the next backend: {e:#}", backend.name());
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.expect("fallback chain is not empty"))
+    }
+}
+
+#[async_trait]
+impl TranslationBackend for FallbackBackend {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn translate_text(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let (text, _) = self.translate_streaming(input, ctx, None, cancel).await?;
+        Ok(text)
+    }
+
+    async fn translate_streaming(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        partial: Option<&StreamSender>,
+        cancel: &CancellationToken,
+    ) -> Result<(String, String)> {
+        // 嵌套的链条也报告最终产出译文的引擎
+        let (attributed, _) = self
+            .first_success(cancel, |b| async move {
+                let result = b.translate_streaming(input, ctx, partial, cancel).await;
+                if result.is_err()
+                    && let Some(tx) = partial
+                {
+                    let _ = tx.send(StreamEvent::Restart);
+                }
+                result
+            })
+            .await?;
+        Ok(attributed)
+    }
+
+    async fn translate_note(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let (text, _) = self
+            .first_success(cancel, |b| async move {
+                b.translate_note(input, ctx, cancel).await
+            })
+            .await?;
+        Ok(text)
+    }
+
+    async fn extract_keywords(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Keyword>> {
+        let (lines, _) = self
+            .first_success(cancel, |b| async move {
+                b.extract_keywords(zh, jp, ctx, cancel).await
+            })
+            .await?;
+        Ok(lines)
+    }
+
+    async fn summarize(
+        &self,
+        summary: &str,
+        chapter: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let (summary, _) = self
+            .first_success(cancel, |b| async move {
+                b.summarize(summary, chapter, ctx, cancel).await
+            })
+            .await?;
+        Ok(summary)
+    }
+
+    async fn update_characters(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Character>> {
+        let (characters, _) = self
+            .first_success(cancel, |b| async move {
+                b.update_characters(zh, jp, ctx, cancel).await
+            })
+            .await?;
+        Ok(characters)
+    }
+
+    async fn review(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let (reviewed, _) = self
+            .first_success(
+                cancel,
+                |b| async move { b.review(zh, jp, ctx, cancel).await },
+            )
+            .await?;
+        Ok(reviewed)
+    }
+
+    async fn translate_titles(
+        &self,
+        titles: &[String],
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Option<String>>> {
+        let (translated, _) = self
+            .first_success(cancel, |b| async move {
+                b.translate_titles(titles, ctx, cancel).await
+            })
+            .await?;
+        Ok(translated)
+    }
+}
+
+/// 翻译服务返回的错误，按原因分类以便给出可操作的提示
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiError {
+    /// API key 无效或没有权限
+    InvalidKey(String),
+    /// 账户余额或额度不足
+    InsufficientBalance(String),
+    /// 输入超过模型的上下文长度
+    ContextOverflow(String),
+    /// 请求过于频繁
+    RateLimited(String),
+    /// 其他错误
+    Other { status: u16, message: String },
+}
+
+impl ApiError {
+    /// 根据 HTTP 状态码与错误响应分类；兼容 OpenAI（`error.message`）、Gemini（`error.status`）、
+    /// DeepL（`message`）与 Ollama（`error` 为字符串）的格式
+    pub fn from_response(status: u16, body: &serde_json::Value) -> ApiError {
+        let message = body
+            .pointer("/error/message")
+            .or_else(|| body.get("message"))
+            .or_else(|| body.get("error"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let code = body
+            .pointer("/error/code")
+            .or_else(|| body.pointer("/error/status"))
+            .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+            .unwrap_or_default();
+        let lower = format!("{message} {code}").to_lowercase();
+        if lower.contains("context length")
+            || lower.contains("context_length")
+            || lower.contains("maximum context")
+        {
+            ApiError::ContextOverflow(message)
+        } else if status == 402 || status == 456 || lower.contains("insufficient balance") {
+            ApiError::InsufficientBalance(message)
+        } else if status == 401
+            || status == 403
+            || lower.contains("api key not valid")
+            || lower.contains("invalid api key")
+            || lower.contains("authentication")
+        {
+            ApiError::InvalidKey(message)
+        } else if status == 429 || lower.contains("rate limit") {
+            ApiError::RateLimited(message)
+        } else {
+            ApiError::Other { status, message }
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::InvalidKey(m) => {
+                write!(f, "API key was rejected, check --api-key ({m})")
+            }
+            ApiError::InsufficientBalance(m) => write!(
+                f,
+                "account balance is insufficient, top up with the provider or use --fallback ({m})"
+            ),
+            ApiError::ContextOverflow(m) => write!(
+                f,
+                "input exceeds the model's context length, try a model with a longer context ({m})"
+            ),
+            ApiError::RateLimited(m) => {
+                write!(
+                    f,
+                    "rate limited by the API, lower --concurrency or wait ({m})"
+                )
+            }
+            ApiError::Other { status, message } => write!(f, "API error {status}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// 熔断后返回的错误：翻译服务持续失败，冷却期内不再发送请求
+#[derive(Debug)]
+pub struct CircuitOpen {
+    /// 距离允许再次尝试的时间
+    pub retry_in: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "translation API keeps failing, retrying allowed in {}s",
+            self.retry_in.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// 错误是否由熔断引起；此时界面应暂停队列而不是继续提交任务
+pub fn is_circuit_open(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<CircuitOpen>().is_some()
+}
+
+/// 错误是否说明翻译服务暂时不可用：网络错误、5xx 与限流；请求本身被拒绝
+/// （400、内容被安全过滤拦截等）不算
+fn is_outage(err: &anyhow::Error) -> bool {
+    is_transient(err)
+        || err.chain().any(|e| {
+            matches!(
+                e.downcast_ref::<ApiError>(),
+                Some(ApiError::RateLimited(_) | ApiError::Other { status: 500.., .. })
+            )
+        })
+}
+
+/// 连续不可用次数达到上限时熔断：冷却期内的调用直接失败，冷却后只放行一次试探，
+/// 试探期间其他调用仍直接失败；试探成功则恢复，失败则再次熔断
+pub struct CircuitBreaker {
+    inner: Arc<dyn TranslationBackend>,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+    /// 冷却结束后是否已有试探调用在进行
+    probing: AtomicBool,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// 试探调用结束（包括被取消或丢弃）时允许下一次试探
+struct Probe<'a>(&'a AtomicBool);
+
+impl Drop for Probe<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: Arc<dyn TranslationBackend>, threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            inner,
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+            probing: AtomicBool::new(false),
+        }
+    }
+
+    /// 执行一次调用并记录结果；取消不计为失败，只有 `is_outage` 的错误计入失败次数
+    async fn guarded<T>(
+        &self,
+        cancel: &CancellationToken,
+        call: impl Future<Output = Result<T>> + Send,
+    ) -> Result<T> {
+        let _probe = {
+            let state = self.state.lock().unwrap();
+            match state.open_until {
+                Some(until) => {
+                    if let Some(retry_in) = until.checked_duration_since(Instant::now()) {
+                        return Err(CircuitOpen { retry_in }.into());
+                    }
+                    if self.probing.swap(true, Ordering::SeqCst) {
+                        // 试探结果未知，按一个冷却期计
+                        return Err(CircuitOpen {
+                            retry_in: self.cooldown,
+                        }
+                        .into());
+                    }
+                    Some(Probe(&self.probing))
+                }
+                None => None,
+            }
+        };
+        let result = call.await;
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Err(e) if cancel.is_cancelled() => Err(e),
+            Err(e) if is_outage(&e) => {
+                state.failures = state.failures.saturating_add(1);
+                if state.failures < self.threshold {
+                    return Err(e);
+                }
+                // 熔断后失败次数不清零，冷却后的试探仍失败时立即再次熔断
+                state.open_until = Some(Instant::now() + self.cooldown);
+                warn!(
+                    "{} keeps failing, pausing for {:?}",
+                    self.inner.name(),
+                    self.cooldown
+                );
+                Err(e.context(CircuitOpen {
+                    retry_in: self.cooldown,
+                }))
+            }
+            // 成功，或服务正常答复了一个被拒绝的请求，说明服务可用
+            result => {
+                *state = BreakerState::default();
+                result
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TranslationBackend for CircuitBreaker {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn translate_text(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        self.guarded(cancel, self.inner.translate_text(input, ctx, cancel))
+            .await
+    }
+
+    async fn translate_streaming(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        partial: Option<&StreamSender>,
+        cancel: &CancellationToken,
+    ) -> Result<(String, String)> {
+        self.guarded(
+            cancel,
+            self.inner.translate_streaming(input, ctx, partial, cancel),
+        )
+        .await
+    }
+
+    async fn translate_note(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        self.guarded(cancel, self.inner.translate_note(input, ctx, cancel))
+            .await
+    }
+
+    async fn extract_keywords(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Keyword>> {
+        self.guarded(cancel, self.inner.extract_keywords(zh, jp, ctx, cancel))
+            .await
+    }
+
+    async fn summarize(
+        &self,
+        summary: &str,
+        chapter: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        self.guarded(cancel, self.inner.summarize(summary, chapter, ctx, cancel))
+            .await
+    }
+
+    async fn update_characters(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Character>> {
+        self.guarded(cancel, self.inner.update_characters(zh, jp, ctx, cancel))
+            .await
+    }
+
+    async fn review(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        self.guarded(cancel, self.inner.review(zh, jp, ctx, cancel))
+            .await
+    }
+
+    async fn translate_titles(
+        &self,
+        titles: &[String],
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Option<String>>> {
+        self.guarded(cancel, self.inner.translate_titles(titles, ctx, cancel))
+            .await
+    }
+}
+
+/// 期望的回复格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyFormat {
+    /// 普通文本
+    Text,
+    /// 单个 JSON 对象；支持的服务会开启结构化输出模式
+    Json,
+}
+
+/// 对话式大模型：只需实现单轮对话请求，提示词拼装与结果解析由通用的 `TranslationBackend` 实现提供
+#[async_trait]
+pub trait ChatModel: Send + Sync {
+    /// 引擎名称，用于日志
+    const NAME: &'static str;
+
+    /// 正文、附言与专有名词提取使用的提示词模板，默认为内置模板
+    fn templates(&self) -> &PromptTemplates {
+        &BUILTIN_TEMPLATES
+    }
+
+    /// 发送一条用户消息并返回回复内容，`call` 为指标中使用的调用类型
+    async fn chat(
+        &self,
+        call: &str,
+        prompt: &str,
+        params: &ChatParams,
+        format: ReplyFormat,
+    ) -> Result<String>;
+
+    /// 与 `chat` 相同，但边生成边把新片段发给 `partial`；默认在回复完成后一次性发送
+    async fn chat_streaming(
+        &self,
+        call: &str,
+        prompt: &str,
+        params: &ChatParams,
+        partial: &StreamSender,
+    ) -> Result<String> {
+        let output = self.chat(call, prompt, params, ReplyFormat::Text).await?;
+        let _ = partial.send(StreamEvent::Delta(output.clone()));
+        Ok(output)
+    }
+}
+
+/// 单次对话请求的参数
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatParams {
+    /// 采样温度
+    pub temperature: f64,
+    /// 覆盖引擎默认使用的模型
+    pub model: Option<String>,
+    /// 回复的最大 token 数，未设置时使用 `DEFAULT_MAX_TOKENS`（Ollama 使用模型默认值）
+    pub max_tokens: Option<u32>,
+    /// 核采样阈值，未设置时不发送
+    pub top_p: Option<f64>,
+}
+
+/// 可由命令行与各小说设置调整的采样参数，未设置的项使用内置默认值
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sampling {
+    /// 正文与附言翻译的温度，其他请求使用各自固定的温度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// 回复的最大 token 数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// 核采样阈值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+}
+
+impl Sampling {
+    /// 逐项合并，`self` 中已设置的项优先
+    pub fn or(self, fallback: Sampling) -> Sampling {
+        Sampling {
+            temperature: self.temperature.or(fallback.temperature),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            top_p: self.top_p.or(fallback.top_p),
+        }
+    }
+}
+
+/// 解析温度，大多数接口只接受 0 到 2 之间的值
+pub fn parse_temperature(value: &str) -> Result<f64> {
+    let temperature: f64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid temperature `{value}`"))?;
+    if !(0.0..=2.0).contains(&temperature) {
+        bail!("temperature must be between 0 and 2, got {temperature}");
+    }
+    Ok(temperature)
+}
+
+/// 解析核采样阈值，须大于 0 且不超过 1
+pub fn parse_top_p(value: &str) -> Result<f64> {
+    let top_p: f64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid top_p `{value}`"))?;
+    if !(top_p > 0.0 && top_p <= 1.0) {
+        bail!("top_p must be greater than 0 and at most 1, got {top_p}");
+    }
+    Ok(top_p)
+}
+
+/// 解析回复的最大 token 数，须为正整数
+pub fn parse_max_tokens(value: &str) -> Result<u32> {
+    match value.parse::<u32>() {
+        Ok(0) | Err(_) => bail!("max_tokens must be a positive integer, got `{value}`"),
+        Ok(n) => Ok(n),
+    }
+}
+
+/// 回复的默认最大 token 数
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// 正文翻译使用的温度（DeepSeek 推荐翻译任务使用 1.3）
+const TEXT_TEMPERATURE: f64 = 1.3;
+
+/// 标题需逐行对应，使用较低的温度
+const TITLE_TEMPERATURE: f64 = 0.3;
+
+/// 重试可疑译文时使用较低的温度，减少漏译与中断
+const RETRY_TEMPERATURE: f64 = 0.7;
+
+/// 剧情摘要与人物设定需忠于译文，不需要文采
+const MEMORY_TEMPERATURE: f64 = 0.5;
+
+/// 审校只修正错误，尽量少改动译文
+const REVIEW_TEMPERATURE: f64 = 0.3;
+
+/// 附在原文之前的已知专有名词对照，没有时为空
+fn known_pairs(keywords: &[(String, String)]) -> String {
+    if keywords.is_empty() {
+        return String::new();
+    }
+    let pairs = keywords
+        .iter()
+        .map(|(jp, zh)| format!("{jp}:{zh}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("已知翻译对照：{pairs}\n")
+}
+
+/// 从模型回复中解析专有名词对照：兼容 `{"keywords":[..]}`、JSON 数组与 JSONL，
+/// 忽略 markdown 代码块标记及前后的说明文字
+pub fn parse_keywords(output: &str) -> Vec<Keyword> {
+    fn collect(value: &serde_json::Value, keywords: &mut Vec<Keyword>) {
+        match value {
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, keywords)),
+            serde_json::Value::Object(map) => {
+                // 旧版提示词输出 `chinese` 字段
+                if let (Some(jp), Some(zh)) = (
+                    map.get("japanese").and_then(|v| v.as_str()),
+                    map.get("translated")
+                        .or_else(|| map.get("chinese"))
+                        .and_then(|v| v.as_str()),
+                ) {
+                    keywords.push(Keyword {
+                        category: map
+                            .get("category")
+                            .and_then(|v| v.as_str())
+                            .and_then(|c| c.parse().ok()),
+                        ..Keyword::new(jp.trim(), zh.trim())
+                    });
+                } else {
+                    map.values().for_each(|v| collect(v, keywords));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut keywords = Vec::new();
+    for value in json_values(output) {
+        collect(&value, &mut keywords);
+    }
+    keywords.retain(|k| !k.japanese.is_empty() && !k.translated.is_empty());
+    keywords
+}
+
+/// 从模型回复中解析人物设定，容错方式与 `parse_keywords` 相同
+pub fn parse_characters(output: &str) -> Vec<Character> {
+    fn collect(value: &serde_json::Value, characters: &mut Vec<Character>) {
+        match value {
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, characters)),
+            serde_json::Value::Object(map) if map.contains_key("name") => {
+                if let Ok(character) = serde_json::from_value::<Character>(value.clone()) {
+                    characters.push(character);
+                }
+            }
+            serde_json::Value::Object(map) => map.values().for_each(|v| collect(v, characters)),
+            _ => {}
+        }
+    }
+
+    let mut characters = Vec::new();
+    for value in json_values(output) {
+        collect(&value, &mut characters);
+    }
+    characters.retain(|c| !c.name.trim().is_empty());
+    characters
+}
+
+/// 模型回复中的 JSON 值：整段不是合法 JSON 时逐个解析其中出现的值，跳过代码块标记与说明文字
+fn json_values(output: &str) -> Vec<serde_json::Value> {
+    let text = output.trim();
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(text) {
+        return vec![value];
+    }
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '[']) {
+        let mut values =
+            serde_json::Deserializer::from_str(&rest[start..]).into_iter::<serde_json::Value>();
+        match values.next() {
+            Some(Ok(value)) => {
+                found.push(value);
+                rest = &rest[start + values.byte_offset()..];
+            }
+            _ => rest = &rest[start + 1..],
+        }
+    }
+    found
+}
+
+/// 单次请求翻译的原文字数上限；译文与原文长度相近，留出余量以免超过 8192 token 的输出上限被截断
+pub const CHUNK_CHARS: usize = 3000;
+
+/// 作为上下文附在下一段之前的前文译文字数
+const OVERLAP_CHARS: usize = 200;
+
+/// 按段落边界把长文本切成不超过 `max_chars` 字的若干段；单个段落超长时自成一段
+pub fn split_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut len = 0;
+    for line in text.split('\n') {
+        let line_len = line.chars().count();
+        if len > 0 && len + line_len > max_chars {
+            chunks.push(std::mem::take(&mut current));
+            len = 0;
+        }
+        if len > 0 {
+            current.push('\n');
+        }
+        current.push_str(line);
+        len += line_len + 1;
+    }
+    if !current.trim().is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 取译文末尾的若干整段，作为翻译下一段时的上下文
+fn overlap_tail(translated: &str) -> &str {
+    let mut start = translated.len();
+    for (i, _) in translated.rmatch_indices('\n') {
+        if translated[i..].chars().count() > OVERLAP_CHARS {
+            break;
+        }
+        start = i + 1;
+    }
+    if start == translated.len() {
+        // 最后一段本身就超长时只取末尾的一部分
+        let skip = translated.chars().count().saturating_sub(OVERLAP_CHARS);
+        return translated
+            .char_indices()
+            .nth(skip)
+            .map_or("", |(i, _)| &translated[i..]);
+    }
+    &translated[start..]
+}
+
+/// 用指定提示词翻译文本，已知的专有名词对照附在原文之前；长文本按段落分段依次翻译后拼接，
+/// 每段附上前一段译文的末尾以保持衔接；提供 `partial` 时边生成边回传
+async fn translate_with<M: ChatModel>(
+    model: &M,
+    template: &str,
+    call: &str,
+    input: &str,
+    ctx: &TranslationContext,
+    partial: Option<&StreamSender>,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let known = known_pairs(&ctx.keywords);
+    let instructions = ctx.instructions();
+    let params = ctx.chat_params(if ctx.retry {
+        RETRY_TEMPERATURE
+    } else {
+        ctx.sampling.temperature.unwrap_or(TEXT_TEMPERATURE)
+    });
+    let mut output = String::new();
+    for chunk in split_chunks(input, CHUNK_CHARS) {
+        let context = if output.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "前文译文（仅供衔接参考，不要重复输出）：\n{}\n\n",
+                overlap_tail(&output)
+            )
+        };
+        let text = format!("{context}{chunk}");
+        let prompt = render(
+            template,
+            &[
+                ("instructions", &instructions),
+                ("glossary", &known),
+                ("text", &text),
+                ("novel_title", &ctx.novel_title),
+                ("target_lang", ctx.target_lang.prompt_name()),
+            ],
+        );
+        if !output.is_empty() {
+            output.push('\n');
+            if let Some(tx) = partial {
+                let _ = tx.send(StreamEvent::Delta("\n".to_string()));
+            }
+        }
+        let translated = match partial {
+            Some(tx) => {
+                cancellable(cancel, model.chat_streaming(call, &prompt, &params, tx)).await?
+            }
+            None => {
+                cancellable(
+                    cancel,
+                    model.chat(call, &prompt, &params, ReplyFormat::Text),
+                )
+                .await?
+            }
+        };
+        output.push_str(translated.trim_end_matches('\n'));
+    }
+    Ok(output)
+}
+
+#[async_trait]
+impl<M: ChatModel> TranslationBackend for M {
+    fn name(&self) -> &str {
+        M::NAME
+    }
+
+    async fn translate_text(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        translate_with(
+            self,
+            &self.templates().translate,
+            "translate",
+            input,
+            ctx,
+            None,
+            cancel,
+        )
+        .await
+    }
+
+    async fn translate_streaming(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        partial: Option<&StreamSender>,
+        cancel: &CancellationToken,
+    ) -> Result<(String, String)> {
+        let text = translate_with(
+            self,
+            &self.templates().translate,
+            "translate",
+            input,
+            ctx,
+            partial,
+            cancel,
+        )
+        .await?;
+        Ok((text, M::NAME.to_string()))
+    }
+
+    async fn translate_note(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        translate_with(
+            self,
+            &self.templates().note,
+            "notes",
+            input,
+            ctx,
+            None,
+            cancel,
+        )
+        .await
+    }
+
+    async fn extract_keywords(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Keyword>> {
+        let existing = ctx
+            .keywords
+            .iter()
+            .map(|(jp, zh)| serde_json::json!({"japanese": jp, "translated": zh}).to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = render(
+            &self.templates().keyword,
+            &[
+                ("glossary", &existing),
+                ("text", jp),
+                ("translation", zh),
+                ("novel_title", &ctx.novel_title),
+                ("target_lang", ctx.target_lang.prompt_name()),
+            ],
+        );
+        let output = cancellable(
+            cancel,
+            self.chat(
+                "extract",
+                &prompt,
+                &ctx.chat_params(TEXT_TEMPERATURE),
+                ReplyFormat::Json,
+            ),
+        )
+        .await?;
+        Ok(parse_keywords(&output))
+    }
+
+    async fn summarize(
+        &self,
+        summary: &str,
+        chapter: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let prompt = render(
+            SUMMARY_PROMPT,
+            &[
+                ("novel_title", &ctx.novel_title),
+                ("target_lang", ctx.target_lang.prompt_name()),
+                ("summary", summary),
+                ("text", chapter),
+            ],
+        );
+        let output = cancellable(
+            cancel,
+            self.chat(
+                "summary",
+                &prompt,
+                &ctx.chat_params(MEMORY_TEMPERATURE),
+                ReplyFormat::Text,
+            ),
+        )
+        .await?;
+        Ok(output.trim().to_string())
+    }
+
+    async fn update_characters(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Character>> {
+        let existing = serde_json::to_string(&ctx.characters)?;
+        let prompt = render(
+            CHARACTER_PROMPT,
+            &[
+                ("characters", &existing),
+                ("text", jp),
+                ("translation", zh),
+                ("target_lang", ctx.target_lang.prompt_name()),
+            ],
+        );
+        let output = cancellable(
+            cancel,
+            self.chat(
+                "characters",
+                &prompt,
+                &ctx.chat_params(MEMORY_TEMPERATURE),
+                ReplyFormat::Json,
+            ),
+        )
+        .await?;
+        Ok(parse_characters(&output))
+    }
+
+    async fn review(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let prompt = render(
+            REVIEW_PROMPT,
+            &[
+                ("instructions", &ctx.instructions()),
+                ("glossary", &known_pairs(&ctx.keywords)),
+                ("novel_title", &ctx.novel_title),
+                ("target_lang", ctx.target_lang.prompt_name()),
+                ("text", jp),
+                ("translation", zh),
+            ],
+        );
+        let output = cancellable(
+            cancel,
+            self.chat(
+                "review",
+                &prompt,
+                &ctx.chat_params(REVIEW_TEMPERATURE),
+                ReplyFormat::Text,
+            ),
+        )
+        .await?;
+        Ok(output.trim_end_matches('\n').to_string())
+    }
+
+    async fn translate_titles(
+        &self,
+        titles: &[String],
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Option<String>>> {
+        let mut known = known_pairs(&ctx.keywords);
+        if !known.is_empty() {
+            known.push('\n');
+        }
+        let mut result = Vec::with_capacity(titles.len());
+        for batch in titles.chunks(TITLE_BATCH) {
+            let items = batch
+                .iter()
+                .enumerate()
+                .map(|(i, t)| format!("{}\t{}", i + 1, t.replace(['\t', '\n'], " ")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let prompt = TITLE_PROMPT
+                .replace("{target_lang}", ctx.target_lang.prompt_name())
+                .replace("{known}", &known)
+                .replace("{items}", &items);
+            let output = cancellable(
+                cancel,
+                self.chat(
+                    "titles",
+                    &prompt,
+                    &ctx.chat_params(TITLE_TEMPERATURE),
+                    ReplyFormat::Text,
+                ),
+            )
+            .await?;
+            // 漏译的条目为 `None`，由调用方下次重试
+            let mut translated = vec![None; batch.len()];
+            for line in output.lines() {
+                if let Some((n, text)) = line.trim().split_once('\t')
+                    && let Ok(n) = n.trim().parse::<usize>()
+                    && (1..=batch.len()).contains(&n)
+                    && !text.trim().is_empty()
+                {
+                    translated[n - 1] = Some(text.trim().to_string());
+                }
+            }
+            result.extend(translated);
+        }
+        Ok(result)
+    }
+}
+
+/// 记录接口返回的 token 用量
+fn record_tokens(prompt: Option<u64>, completion: Option<u64>) {
+    for (kind, n) in [("prompt", prompt), ("completion", completion)] {
+        if let Some(n) = n {
+            metrics::API_TOKENS.with_label_values(&[kind]).inc_by(n);
+        }
+    }
+}
+
+/// 调用 DeepSeek 或其他 OpenAI 兼容（OpenRouter、vLLM、LM Studio 等）对话补全接口的翻译引擎
+pub struct DeepSeekTranslator {
+    client: HttpClient,
+    api_key: String,
+    model: String,
+    /// 对话补全接口的完整地址
+    endpoint: String,
+    /// 每次请求附加的请求头
+    headers: Vec<(String, String)>,
+    templates: PromptTemplates,
+}
+
+impl DeepSeekTranslator {
+    /// 创建新的翻译客户端
+    pub fn new(api_key: String, model: String) -> Self {
+        DeepSeekTranslator {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            api_key,
+            model,
+            endpoint: chat_completions_url(DEEPSEEK_API_BASE),
+            headers: Vec::new(),
+            templates: PromptTemplates::default(),
+        }
+    }
+
+    /// 改用其他 OpenAI 兼容的接口；OpenRouter 会自动附加其标识应用的请求头
+    pub fn with_api_base(mut self, base: &str) -> Self {
+        self.endpoint = chat_completions_url(base);
+        if reqwest::Url::parse(&self.endpoint)
+            .is_ok_and(|u| u.host_str().is_some_and(|h| h.ends_with("openrouter.ai")))
+        {
+            for (name, value) in OPENROUTER_HEADERS {
+                self = self.with_header(name, value);
+            }
+        }
+        self
+    }
+
+    /// 每次请求附加一个请求头，例如 Azure 等使用 `api-key` 认证的服务
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// 改用指定的 HTTP 客户端，例如按命令行配置了代理的客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 改用自定义的提示词模板
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// 构造发往对话补全接口的请求，附带认证与自定义请求头
+    fn request(&self, body: &serde_json::Value) -> reqwest_middleware::RequestBuilder {
+        let mut request = self.client.post(&self.endpoint).json(body);
+        // 本地服务通常不需要认证，API key 为空时不发送
+        if !self.api_key.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request
+    }
+}
+
+#[async_trait]
+impl ChatModel for DeepSeekTranslator {
+    const NAME: &'static str = "deepseek";
+
+    fn templates(&self) -> &PromptTemplates {
+        &self.templates
+    }
+
+    async fn chat(
+        &self,
+        call: &str,
+        prompt: &str,
+        params: &ChatParams,
+        format: ReplyFormat,
+    ) -> Result<String> {
+        let started = Instant::now();
+        let mut req = serde_json::json!({
+           "model": params.model.as_deref().unwrap_or(&self.model),
+           "messages": [
+               {"role": "user", "content": prompt}
+           ],
+           "max_tokens": params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+           "temperature": params.temperature,
+           "stream": false,
+        });
+        if let Some(top_p) = params.top_p {
+            req["top_p"] = serde_json::json!(top_p);
+        }
+        if format == ReplyFormat::Json {
+            req["response_format"] = serde_json::json!({"type": "json_object"});
+        }
+        let resp = self.request(&req).send().await?;
+        let status = resp.status();
+        let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+        metrics::observe_latency(call, started);
+        if !status.is_success() || body.get("error").is_some() {
+            return Err(ApiError::from_response(status.as_u16(), &body).into());
+        }
+        record_tokens(
+            body.pointer("/usage/prompt_tokens")
+                .and_then(|v| v.as_u64()),
+            body.pointer("/usage/completion_tokens")
+                .and_then(|v| v.as_u64()),
+        );
+        let output = body
+            .pointer("/choices/0/message/content")
+            .ok_or_else(|| anyhow!("chat completions API returned no content"))?
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        Ok(output)
+    }
+
+    async fn chat_streaming(
+        &self,
+        call: &str,
+        prompt: &str,
+        params: &ChatParams,
+        partial: &StreamSender,
+    ) -> Result<String> {
+        let started = Instant::now();
+        let mut req = serde_json::json!({
+           "model": params.model.as_deref().unwrap_or(&self.model),
+           "messages": [
+               {"role": "user", "content": prompt}
+           ],
+           "max_tokens": params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+           "temperature": params.temperature,
+           "stream": true,
+           "stream_options": {"include_usage": true},
+        });
+        if let Some(top_p) = params.top_p {
+            req["top_p"] = serde_json::json!(top_p);
+        }
+        let mut resp = self.request(&req).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+            return Err(ApiError::from_response(status, &body).into());
+        }
+        // SSE 事件以行为单位，一个数据块可能只包含半行
+        let mut pending = Vec::new();
+        let mut output = String::new();
+        while let Some(chunk) = resp.chunk().await? {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data == "[DONE]" {
+                    continue;
+                }
+                let event: serde_json::Value = serde_json::from_str(data)?;
+                if event.get("error").is_some() {
+                    return Err(ApiError::from_response(200, &event).into());
+                }
+                if let Some(delta) = event
+                    .pointer("/choices/0/delta/content")
+                    .and_then(|v| v.as_str())
+                    && !delta.is_empty()
+                {
+                    output.push_str(delta);
+                    let _ = partial.send(StreamEvent::Delta(delta.to_string()));
+                }
+                // 开启 include_usage 后最后一个事件带有用量统计
+                if event.get("usage").is_some_and(|u| !u.is_null()) {
+                    record_tokens(
+                        event
+                            .pointer("/usage/prompt_tokens")
+                            .and_then(|v| v.as_u64()),
+                        event
+                            .pointer("/usage/completion_tokens")
+                            .and_then(|v| v.as_u64()),
+                    );
+                }
+            }
+        }
+        metrics::observe_latency(call, started);
+        Ok(output)
+    }
+}
+
+/// Ollama 默认监听的地址
+pub const OLLAMA_API_BASE: &str = "http://localhost:11434";
+
+/// Ollama 的上下文长度；默认值只有数千 token，长章节会被悄悄截断
+const OLLAMA_CONTEXT: u32 = 32768;
+
+/// 调用本地 Ollama 服务（`/api/chat`）的翻译引擎，无需 API key，可完全离线使用
+pub struct OllamaBackend {
+    client: HttpClient,
+    model: String,
+    /// `/api/chat` 的完整地址
+    endpoint: String,
+    templates: PromptTemplates,
+}
+
+impl OllamaBackend {
+    pub fn new(model: String) -> Self {
+        OllamaBackend {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            model,
+            endpoint: format!("{OLLAMA_API_BASE}/api/chat"),
+            templates: PromptTemplates::default(),
+        }
+    }
+
+    /// 改用其他地址上的 Ollama 服务
+    pub fn with_api_base(mut self, base: &str) -> Self {
+        self.endpoint = format!("{}/api/chat", base.trim_end_matches('/'));
+        self
+    }
+
+    /// 改用指定的 HTTP 客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 改用自定义的提示词模板
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+}
+
+#[async_trait]
+impl ChatModel for OllamaBackend {
+    const NAME: &'static str = "ollama";
+
+    fn templates(&self) -> &PromptTemplates {
+        &self.templates
+    }
+
+    async fn chat(
+        &self,
+        call: &str,
+        prompt: &str,
+        params: &ChatParams,
+        format: ReplyFormat,
+    ) -> Result<String> {
+        let started = Instant::now();
+        let mut req = serde_json::json!({
+           "model": params.model.as_deref().unwrap_or(&self.model),
+           "messages": [
+               {"role": "user", "content": prompt}
+           ],
+           "stream": false,
+           "options": {
+               "temperature": params.temperature,
+               "num_ctx": OLLAMA_CONTEXT,
+           },
+        });
+        if let Some(max_tokens) = params.max_tokens {
+            req["options"]["num_predict"] = serde_json::json!(max_tokens);
+        }
+        if let Some(top_p) = params.top_p {
+            req["options"]["top_p"] = serde_json::json!(top_p);
+        }
+        if format == ReplyFormat::Json {
+            req["format"] = serde_json::json!("json");
+        }
+        let body = self
+            .client
+            .post(&self.endpoint)
+            .json(&req)
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+        metrics::observe_latency(call, started);
+        if let Some(error) = body["error"].as_str() {
+            bail!("Ollama error: {error}");
+        }
+        record_tokens(
+            body["prompt_eval_count"].as_u64(),
+            body["eval_count"].as_u64(),
+        );
+        let content = body
+            .pointer("/message/content")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Ollama returned no content"))?;
+        Ok(strip_thinking(content))
+    }
+}
+
+/// Gemini（Generative Language API）的默认地址
+pub const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Gemini 可调整拦截阈值的内容类别
+const GEMINI_SAFETY_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+/// 调用 Google Gemini `generateContent` 接口的翻译引擎
+pub struct GeminiBackend {
+    client: HttpClient,
+    api_key: String,
+    model: String,
+    api_base: String,
+    /// 各类别的拦截阈值，默认 `BLOCK_NONE`：小说中的战斗、恋爱描写很容易被默认阈值拦截
+    safety_threshold: String,
+    templates: PromptTemplates,
+}
+
+impl GeminiBackend {
+    pub fn new(api_key: String, model: String) -> Self {
+        GeminiBackend {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            api_key,
+            model,
+            api_base: GEMINI_API_BASE.to_string(),
+            safety_threshold: "BLOCK_NONE".to_string(),
+            templates: PromptTemplates::default(),
+        }
+    }
+
+    /// 改用其他地址，例如代理或兼容网关
+    pub fn with_api_base(mut self, base: &str) -> Self {
+        self.api_base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// 设置安全过滤的拦截阈值，如 `BLOCK_ONLY_HIGH`、`BLOCK_MEDIUM_AND_ABOVE`
+    pub fn with_safety_threshold(mut self, threshold: &str) -> Self {
+        self.safety_threshold = threshold.to_string();
+        self
+    }
+
+    /// 改用指定的 HTTP 客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 改用自定义的提示词模板
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+}
+
+#[async_trait]
+impl ChatModel for GeminiBackend {
+    const NAME: &'static str = "gemini";
+
+    fn templates(&self) -> &PromptTemplates {
+        &self.templates
+    }
+
+    async fn chat(
+        &self,
+        call: &str,
+        prompt: &str,
+        params: &ChatParams,
+        format: ReplyFormat,
+    ) -> Result<String> {
+        let started = Instant::now();
+        let safety: Vec<serde_json::Value> = GEMINI_SAFETY_CATEGORIES
+            .iter()
+            .map(|category| {
+                serde_json::json!({"category": category, "threshold": self.safety_threshold})
+            })
+            .collect();
+        let mut req = serde_json::json!({
+            "contents": [
+                {"role": "user", "parts": [{"text": prompt}]}
+            ],
+            "generationConfig": {
+                "temperature": params.temperature,
+                "maxOutputTokens": params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            },
+            "safetySettings": safety,
+        });
+        if let Some(top_p) = params.top_p {
+            req["generationConfig"]["topP"] = serde_json::json!(top_p);
+        }
+        if format == ReplyFormat::Json {
+            req["generationConfig"]["responseMimeType"] = serde_json::json!("application/json");
+        }
+        let url = format!(
+            "{}/models/{}:generateContent",
+            self.api_base,
+            params.model.as_deref().unwrap_or(&self.model)
+        );
+        let resp = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&req)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+        metrics::observe_latency(call, started);
+        if !status.is_success() || body.get("error").is_some() {
+            return Err(ApiError::from_response(status.as_u16(), &body).into());
+        }
+        record_tokens(
+            body.pointer("/usageMetadata/promptTokenCount")
+                .and_then(|v| v.as_u64()),
+            body.pointer("/usageMetadata/candidatesTokenCount")
+                .and_then(|v| v.as_u64()),
+        );
+        // 整个请求被拦截时没有候选结果，只有 promptFeedback
+        if let Some(reason) = body
+            .pointer("/promptFeedback/blockReason")
+            .and_then(|v| v.as_str())
+        {
+            bail!("Gemini blocked the prompt: {reason}");
+        }
+        let candidate = body
+            .pointer("/candidates/0")
+            .ok_or_else(|| anyhow!("Gemini returned no candidates"))?;
+        let text: String = candidate
+            .pointer("/content/parts")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|part| part["text"].as_str())
+            .collect();
+        let finish = candidate["finishReason"].as_str().unwrap_or_default();
+        // 输出中途被安全过滤截断时，残缺的译文不能当作结果保存
+        if matches!(finish, "SAFETY" | "RECITATION" | "PROHIBITED_CONTENT") {
+            bail!("Gemini stopped the response: {finish}");
+        }
+        if text.is_empty() {
+            bail!("Gemini returned no content (finish reason {finish:?})");
+        }
+        Ok(text)
+    }
+}
+
+/// DeepL API 的地址；免费版 key（以 `:fx` 结尾）使用单独的域名
+const DEEPL_API_BASE: &str = "https://api.deepl.com";
+const DEEPL_FREE_API_BASE: &str = "https://api-free.deepl.com";
+
+/// DeepL 单次请求最多可翻译的文本条数
+const DEEPL_BATCH: usize = 50;
+
+/// 调用 DeepL API 的翻译引擎；DeepL 不能按提示词提取专有名词，
+/// 需要时交给另一个（大模型）引擎处理，否则跳过提取
+pub struct DeepLBackend {
+    client: HttpClient,
+    api_key: String,
+    api_base: String,
+    /// 覆盖 `TranslationContext::target_lang` 的目标语言代码，如 `EN-GB`
+    target_lang: Option<String>,
+    /// 提取专有名词、更新剧情摘要与人物设定以及审校译文使用的引擎
+    extractor: Option<Arc<dyn TranslationBackend>>,
+}
+
+/// DeepL 译文中代替锁定术语占位符的标签
+static DEEPL_TERM_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<t i="(\d+)"\s*(?:/>|></t>)"#).unwrap());
+
+/// 转义文本并把 `⟦T1⟧` 占位符换成 `<t i="1"/>` 标签
+fn placeholders_to_tags(text: &str) -> String {
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    LOCK_PLACEHOLDER
+        .replace_all(&escaped, r#"<t i="$1"/>"#)
+        .into_owned()
+}
+
+/// `placeholders_to_tags` 的逆操作
+fn tags_to_placeholders(text: &str) -> String {
+    DEEPL_TERM_TAG
+        .replace_all(text, "⟦T$1⟧")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+impl DeepLBackend {
+    pub fn new(api_key: String) -> Self {
+        let api_base = if api_key.ends_with(":fx") {
+            DEEPL_FREE_API_BASE
+        } else {
+            DEEPL_API_BASE
+        };
+        DeepLBackend {
+            client: build_client(&HttpOptions::default()).expect("failed to build http client"),
+            api_key,
+            api_base: api_base.to_string(),
+            target_lang: None,
+            extractor: None,
+        }
+    }
+
+    /// 改用其他地址
+    pub fn with_api_base(mut self, base: &str) -> Self {
+        self.api_base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// 指定 DeepL 的目标语言代码，默认按翻译时的目标语言选择
+    pub fn with_target_lang(mut self, lang: &str) -> Self {
+        self.target_lang = Some(lang.to_ascii_uppercase());
+        self
+    }
+
+    /// 使用另一个引擎从译文中提取专有名词、更新剧情摘要与人物设定并审校译文
+    pub fn with_keyword_extractor(mut self, extractor: Arc<dyn TranslationBackend>) -> Self {
+        self.extractor = Some(extractor);
+        self
+    }
+
+    /// 改用指定的 HTTP 客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 翻译一批文本，返回顺序与输入一致；`xml` 时文本中的 XML 标签原样保留
+    async fn translate_batch(
+        &self,
+        call: &str,
+        texts: &[String],
+        lang: TargetLang,
+        xml: bool,
+    ) -> Result<Vec<String>> {
+        let started = Instant::now();
+        let target_lang = self.target_lang.as_deref().unwrap_or(lang.deepl_code());
+        let mut req = serde_json::json!({
+            "text": texts,
+            "source_lang": "JA",
+            "target_lang": target_lang,
+            "preserve_formatting": true,
+        });
+        if xml {
+            req["tag_handling"] = "xml".into();
+        }
+        let resp = self
+            .client
+            .post(format!("{}/v2/translate", self.api_base))
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+            .json(&req)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+        metrics::observe_latency(call, started);
+        if !status.is_success() {
+            return Err(ApiError::from_response(status.as_u16(), &body).into());
+        }
+        let translations: Vec<String> = body["translations"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t["text"].as_str().map(str::to_string))
+            .collect();
+        if translations.len() != texts.len() {
+            bail!(
+                "DeepL returned {} translations for {} texts",
+                translations.len(),
+                texts.len()
+            );
+        }
+        Ok(translations)
+    }
+}
+
+#[async_trait]
+impl TranslationBackend for DeepLBackend {
+    fn name(&self) -> &str {
+        "deepl"
+    }
+
+    /// DeepL 不接受提示词，已知的专有名词对照不会生效；锁定的术语占位符改写成 XML 标签
+    /// 交给 DeepL 原样保留，译文中再换回占位符
+    async fn translate_text(
+        &self,
+        input: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let xml = ctx.lock_terms && LOCK_PLACEHOLDER.is_match(input);
+        let texts = [if xml {
+            placeholders_to_tags(input)
+        } else {
+            input.to_string()
+        }];
+        let mut translated = cancellable(
+            cancel,
+            self.translate_batch("translate", &texts, ctx.target_lang, xml),
+        )
+        .await?;
+        let translated = translated.remove(0);
+        Ok(if xml {
+            tags_to_placeholders(&translated)
+        } else {
+            translated
+        })
+    }
+
+    async fn extract_keywords(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Keyword>> {
+        match &self.extractor {
+            Some(extractor) => extractor.extract_keywords(zh, jp, ctx, cancel).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn summarize(
+        &self,
+        summary: &str,
+        chapter: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        match &self.extractor {
+            Some(extractor) => extractor.summarize(summary, chapter, ctx, cancel).await,
+            None => Ok(summary.to_string()),
+        }
+    }
+
+    async fn update_characters(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Character>> {
+        match &self.extractor {
+            Some(extractor) => extractor.update_characters(zh, jp, ctx, cancel).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn review(
+        &self,
+        zh: &str,
+        jp: &str,
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        match &self.extractor {
+            Some(extractor) => extractor.review(zh, jp, ctx, cancel).await,
+            None => Ok(zh.to_string()),
+        }
+    }
+
+    async fn translate_titles(
+        &self,
+        titles: &[String],
+        ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Option<String>>> {
+        let mut result = Vec::with_capacity(titles.len());
+        for batch in titles.chunks(DEEPL_BATCH) {
+            result.extend(
+                cancellable(
+                    cancel,
+                    self.translate_batch("titles", batch, ctx.target_lang, false),
+                )
+                .await?
+                .into_iter()
+                .map(Some),
+            );
+        }
+        Ok(result)
+    }
+}
+
+/// 去掉推理模型（qwen3、deepseek-r1 等）输出在回复开头的 `<think>…</think>` 段落
+fn strip_thinking(content: &str) -> String {
+    match content.trim_start().strip_prefix("<think>") {
+        Some(rest) => rest
+            .split_once("</think>")
+            .map_or(rest, |(_, answer)| answer)
+            .trim_start()
+            .to_string(),
+        None => content.to_string(),
+    }
+}
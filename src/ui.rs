@@ -1,7 +1,12 @@
+use std::time::{Duration, UNIX_EPOCH};
+
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
 
-use crate::app::{App, InputMode};
+use crate::app::{App, Cleanup, DirRow, GlossaryInput, InputMode};
+use crate::cost::Estimate;
+use crate::memory::{CHARACTER_FIELDS, DiffLine, SETTINGS_FIELDS, diff_lines};
+use crate::syosetu::{illustrations_to_links, ruby_to_parens, strip_ruby};
 
 /// 在全屏区域绘制一个带标题的空白块，用于提示加载状态
 pub fn draw_loading(frame: &mut Frame, message: &str) {
@@ -20,35 +25,549 @@ pub fn draw_directory(frame: &mut Frame, app: &App, state: &mut ListState) {
     let items: Vec<ListItem> = app
         .filtered
         .iter()
-        .map(|&i| {
+        .map(|row| {
+            let i = match row {
+                DirRow::Arc(name) => {
+                    let fold = if app.collapsed.contains(name) {
+                        "▶"
+                    } else {
+                        "▼"
+                    };
+                    return ListItem::new(format!("{fold} {name}"))
+                        .style(Style::default().add_modifier(Modifier::BOLD));
+                }
+                DirRow::Chapter(i) => *i,
+            };
             let ch = &app.chapters[i];
-            let mark = if app.cached_chapters.contains(&ch.path) {
+            let mark = if app.waiting.contains(&ch.path) || app.queued.contains(&ch.path) {
+                "[Q] "
+            } else if app.processing.contains_key(&ch.path) {
+                "[P] "
+            } else if app.is_stale(ch) {
+                "[U] "
+            } else if app.suspects.contains_key(&ch.path) {
+                "[?] "
+            } else if app.drift.contains_key(&ch.path) {
+                "[!] "
+            } else if app.cached_chapters.contains(&ch.path) {
                 "[C] "
             } else {
                 "[ ] "
             };
-            ListItem::new(format!("{}{}", mark, ch.title))
+            let mut line = format!("{}{}", mark, app.display_title(ch));
+            if let Some(estimate) = app.estimates.get(&ch.path)
+                && !app.cached_chapters.contains(&ch.path)
+            {
+                line.push_str(&format!("  {}", format_estimate(estimate)));
+            }
+            ListItem::new(line)
         })
         .collect();
+    let mut title = format!("{} ('i' info)", app.novel_title());
+    let (total, count) = app.pending_estimate();
+    if count > 0 {
+        title.push_str(&format!(" — {count} chapters {}", format_estimate(&total)));
+    }
+    if !app.pending_keywords.is_empty() {
+        title.push_str(&format!(
+            " — {} keywords to review ('K')",
+            app.pending_keywords.len()
+        ));
+    }
+    if !app.conflicts.is_empty() {
+        title.push_str(&format!(
+            " — {} glossary conflicts ('X')",
+            app.conflicts.len()
+        ));
+    }
+    let mut title = vec![Span::raw(title)];
+    if let Some(banner) = &app.banner {
+        title.insert(
+            0,
+            Span::styled(
+                format!("⚠ {banner} "),
+                Style::default().fg(Color::White).bg(Color::Red),
+            ),
+        );
+    }
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Chapters"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Line::from(title)),
+        )
         .highlight_symbol(">>");
     frame.render_stateful_widget(list, chunks[0], state);
 
-    let search = Paragraph::new(app.search.as_str()).block(
-        Block::default().borders(Borders::ALL).title(match app.mode {
-            InputMode::Navigate => "Press '/' to search",
-            InputMode::Search => "Search",
-        }),
-    );
+    if let Some(cleanup) = &app.cleanup {
+        let (action, removal) = match cleanup {
+            Cleanup::Remove(removal) => ("Delete all stored data of this novel", removal),
+            Cleanup::Prune(removal) => ("Delete records of chapters no longer listed", removal),
+        };
+        let counts: Vec<String> = removal
+            .iter()
+            .map(|(store, count)| format!("{store} {count}"))
+            .collect();
+        let prompt = Paragraph::new(counts.join(", ")).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{action}? ('y' delete, 'n' keep)")),
+        );
+        frame.render_widget(prompt, chunks[1]);
+        return;
+    }
+    let title = match app.mode {
+        InputMode::Navigate => app
+            .message
+            .as_deref()
+            .unwrap_or("Press '/' to search, 'c' to cancel, 'p' to pause, 't' to toggle titles, 'u' to refresh updated, 'r' to re-translate, 'e' to estimate cost, 'i' for info, 'g' for glossary, 'v' to check glossary consistency, 'M' to mine term candidates, 'C' for characters, 's' for settings, 'O' to prune removed chapters, 'D' to delete the novel's data, Enter on a heading to fold"),
+        InputMode::Search => "Search",
+    };
+    let search = Paragraph::new(app.search.as_str())
+        .block(Block::default().borders(Borders::ALL).title(title));
     frame.render_widget(search, chunks[1]);
 }
 
+/// 以 `~12.3k tok $0.0123` 的形式显示预估用量
+fn format_estimate(estimate: &Estimate) -> String {
+    let tokens = estimate.prompt_tokens + estimate.output_tokens;
+    let tokens = if tokens >= 1000 {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    } else {
+        tokens.to_string()
+    };
+    format!("~{tokens} tok ${:.4}", estimate.cost)
+}
+
 /// 显示翻译文本并根据滚动位置偏移
 pub fn draw_reading(frame: &mut Frame, app: &App) {
     let area = frame.size();
-    let para = Paragraph::new(app.translation.as_str())
-        .block(Block::default().borders(Borders::ALL).title("Translation"))
+    let text = illustrations_to_links(&app.translation, &app.illustrations);
+    let text = if app.show_furigana {
+        ruby_to_parens(&text)
+    } else {
+        strip_ruby(&text)
+    };
+    let mut title = match &app.reading {
+        Some(path) if app.processing.contains_key(path) => "Translating... ('f' furigana)",
+        _ => "Translation ('f' furigana, 'h' history, 'E' edit)",
+    }
+    .to_string();
+    if let Some(reason) = app.reading.as_ref().and_then(|path| app.suspects.get(path)) {
+        title.push_str(&format!(" [?] suspect: {reason}"));
+    }
+    if let Some(terms) = app.reading.as_ref().and_then(|path| app.drift.get(path)) {
+        title.push_str(&format!(" [!] glossary not used: {}", terms.join(", ")));
+    }
+    if app.source_changed.is_some() && app.source_changed == app.reading {
+        title.push_str(" [changed] source edited: 'y' re-fetch and re-translate, 'n' keep");
+    }
+    let para = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((app.scroll, 0));
+    frame.render_widget(para, area);
+}
+
+/// 作品信息面板：标题、作者、标签以及简介原文与译文
+pub fn draw_info(frame: &mut Frame, app: &App) {
+    let area = frame.size();
+    let info = &app.info;
+    let mut text = format!("{}\n", app.novel_title());
+    if !info.author.is_empty() {
+        text.push_str(&format!("作者：{}\n", info.author));
+    }
+    if !info.tags.is_empty() {
+        text.push_str(&format!("标签：{}\n", info.tags.join(" / ")));
+    }
+    if info.synopsis.is_empty() {
+        text.push_str("\n（站点未提供简介）");
+    } else {
+        let translated = app.synopsis.as_deref().unwrap_or("（翻译中…）");
+        text.push_str(&format!("\n{}\n\n{}", translated, info.synopsis));
+    }
+    let para = Paragraph::new(ruby_to_parens(&text))
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Info ('i' back)"),
+        )
         .scroll((app.scroll, 0));
     frame.render_widget(para, area);
 }
+
+/// 人物设定界面：上方为人物列表，下方显示选中人物的全部字段或编辑框
+pub fn draw_characters(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(7)])
+        .split(frame.size());
+
+    let items: Vec<ListItem> = app
+        .characters
+        .iter()
+        .map(|c| {
+            let mut line = c.name.clone();
+            if !c.translated.is_empty() {
+                line.push_str(&format!(" → {}", c.translated));
+            }
+            if !c.gender.is_empty() {
+                line.push_str(&format!("（{}）", c.gender));
+            }
+            ListItem::new(line)
+        })
+        .collect();
+    let title = if items.is_empty() {
+        "Characters (none recorded yet, 'q' back)"
+    } else {
+        "Characters ('q' back)"
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !app.characters.is_empty() {
+        state.select(Some(app.character_selected));
+    }
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let lines: Vec<Line> = match app.characters.get(app.character_selected) {
+        Some(c) => CHARACTER_FIELDS
+            .iter()
+            .zip(c.fields())
+            .enumerate()
+            .map(|(i, (name, value))| {
+                if i != app.character_field {
+                    return Line::from(format!("  {name}: {value}"));
+                }
+                let value = match &app.character_edit {
+                    Some(buffer) => format!("{buffer}_"),
+                    None => value.to_string(),
+                };
+                Line::styled(
+                    format!("> {name}: {value}"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let help = if app.character_edit.is_some() {
+        "Enter save, Esc cancel"
+    } else {
+        "Tab field, Enter edit, 'd' delete"
+    };
+    let para = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(help));
+    frame.render_widget(para, chunks[1]);
+}
+
+/// 专有名词对照表界面：上方为过滤后的词条，下方为搜索框或正在输入的内容
+pub fn draw_glossary(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(frame.size());
+
+    if let Some(GlossaryInput::ConfirmReplace { old, new, chapters }) = &app.glossary_input {
+        let items: Vec<ListItem> = chapters
+            .iter()
+            .map(|(path, count)| ListItem::new(format!("{count:>5}  {}", app.chapter_title(path))))
+            .collect();
+        let title = format!("Cached chapters using {old} ({})", chapters.len());
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(list, chunks[0]);
+        let prompt = format!("Replace {old} with {new} in these chapters? ('y' replace, 'n' keep)");
+        let input = Paragraph::new(prompt).block(Block::default().borders(Borders::ALL));
+        frame.render_widget(input, chunks[1]);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .glossary
+        .iter()
+        .enumerate()
+        .map(|(i, (jp, zh))| {
+            let zh = match &app.glossary_input {
+                Some(GlossaryInput::Edit(buffer)) if i == app.glossary_selected => {
+                    format!("{buffer}_")
+                }
+                _ => zh.clone(),
+            };
+            let count = app.usage.get(jp).copied().unwrap_or_default();
+            match app.categories.get(jp) {
+                Some(category) => ListItem::new(format!("{count:>5}  {jp} → {zh} [{category}]")),
+                None => ListItem::new(format!("{count:>5}  {jp} → {zh}")),
+            }
+        })
+        .collect();
+    let title = format!(
+        "Glossary {}/{} ('/' search, 'a' add, Enter edit, 'c' category, 'd' delete, 'b' blacklist, 'B' show blacklist, 'P' make global, 'x' export, 'i' import, 'm' merge, 'o' sort by {}, 'u' recount usage, 'q' back)",
+        app.glossary.len(),
+        app.keywords.len(),
+        if app.glossary_by_usage {
+            "term"
+        } else {
+            "usage"
+        }
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !app.glossary.is_empty() {
+        state.select(Some(app.glossary_selected));
+    }
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let (title, text) = match &app.glossary_input {
+        Some(GlossaryInput::Search) => ("Search", format!("{}_", app.glossary_search)),
+        Some(GlossaryInput::Edit(_)) => ("Enter save, Esc cancel", String::new()),
+        Some(GlossaryInput::NewTerm(term)) => ("New term (Japanese)", format!("{term}_")),
+        Some(GlossaryInput::NewTranslation { term, translation }) => {
+            ("New term (translation)", format!("{term} → {translation}_"))
+        }
+        Some(GlossaryInput::MergeFrom(from)) => ("Merge glossary from novel", format!("{from}_")),
+        Some(GlossaryInput::ConfirmReplace { .. }) | None => (
+            app.message.as_deref().unwrap_or("Search"),
+            app.glossary_search.clone(),
+        ),
+    };
+    let input = Paragraph::new(text).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(input, chunks[1]);
+}
+
+/// 译名冲突列表：对照表中的译名、模型给出的译名及给出该译名的章节数
+pub fn draw_conflicts(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(frame.size());
+
+    let items: Vec<ListItem> = app
+        .conflicts
+        .iter()
+        .map(|(jp, current, conflict)| {
+            ListItem::new(format!(
+                "{jp}: {current} ↔ {} ({} chapters)",
+                conflict.proposed,
+                conflict.chapters.len()
+            ))
+        })
+        .collect();
+    let title = if items.is_empty() {
+        "Glossary conflicts (none, 'q' back)"
+    } else {
+        "Glossary conflicts ('c' keep current, 'n' use new, uppercase also re-translates, 'q' back)"
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !app.conflicts.is_empty() {
+        state.select(Some(app.conflict_selected));
+    }
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let message = Paragraph::new(app.message.as_deref().unwrap_or(""))
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(message, chunks[1]);
+}
+
+/// 一致性检查结果：每章列出原文出现但译文没有使用的对照译名
+pub fn draw_consistency(frame: &mut Frame, app: &App) {
+    let Some(report) = &app.consistency else {
+        draw_loading(frame, "Checking cached chapters against the glossary...");
+        return;
+    };
+    let items: Vec<ListItem> = report
+        .iter()
+        .map(|item| {
+            let terms: Vec<String> = item
+                .terms
+                .iter()
+                .map(|(jp, tr)| format!("{jp} → {tr}"))
+                .collect();
+            ListItem::new(format!("{}  {}", item.title, terms.join(", ")))
+        })
+        .collect();
+    let title = match &app.message {
+        Some(message) if report.is_empty() => message.clone(),
+        _ => format!(
+            "Chapters missing glossary translations {} (Enter read, 'q' back)",
+            report.len()
+        ),
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !report.is_empty() {
+        state.select(Some(app.consistency_selected));
+    }
+    frame.render_stateful_widget(list, frame.size(), &mut state);
+}
+
+/// 历史界面：上方为旧译文列表，下方为恢复选中版本会带来的改动
+pub fn draw_history(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(12), Constraint::Min(1)])
+        .split(frame.size());
+
+    let items: Vec<ListItem> = app
+        .history
+        .iter()
+        .enumerate()
+        .map(|(i, revision)| {
+            let saved_at = UNIX_EPOCH + Duration::from_secs(revision.saved_at);
+            ListItem::new(format!(
+                "#{}  {}  {} chars",
+                i + 1,
+                httpdate::fmt_http_date(saved_at),
+                revision.text.chars().count()
+            ))
+        })
+        .collect();
+    let title = match &app.message {
+        Some(message) => message.clone(),
+        None if app.history.is_empty() => "No previous versions ('q' back)".to_string(),
+        None => format!(
+            "Previous versions {} ('r' revert, 'q' back)",
+            app.history.len()
+        ),
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !app.history.is_empty() {
+        state.select(Some(app.history_selected));
+    }
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let lines: Vec<Line> = app
+        .history
+        .get(app.history_selected)
+        .map(|revision| diff_lines(&app.translation, &revision.text))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Same(text) => Line::raw(format!("  {text}")),
+            DiffLine::Removed(text) => Line::styled(format!("- {text}"), Color::Red),
+            DiffLine::Added(text) => Line::styled(format!("+ {text}"), Color::Green),
+        })
+        .collect();
+    let diff = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Changes to the current translation if reverted"),
+    );
+    frame.render_widget(diff, chunks[1]);
+}
+
+/// 本小说的屏蔽词列表，正在输入的新屏蔽词显示在底部
+pub fn draw_blacklist(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(frame.size());
+
+    let items: Vec<ListItem> = app
+        .blacklist
+        .iter()
+        .map(|jp| ListItem::new(jp.as_str()))
+        .collect();
+    let title = format!(
+        "Blacklist {} (never added to the glossary; 'a' add, 'd' remove, 'q' back)",
+        app.blacklist.len()
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !app.blacklist.is_empty() {
+        state.select(Some(app.blacklist_selected));
+    }
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let (title, text) = match &app.blacklist_input {
+        Some(term) => (
+            "New blacklisted term (Enter save, Esc cancel)",
+            format!("{term}_"),
+        ),
+        None => (app.message.as_deref().unwrap_or(""), String::new()),
+    };
+    let input = Paragraph::new(text).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(input, chunks[1]);
+}
+
+/// 待审核的新专有名词列表，修改中的译名显示在选中行
+pub fn draw_pending_keywords(frame: &mut Frame, app: &App) {
+    let items: Vec<ListItem> = app
+        .pending_keywords
+        .iter()
+        .enumerate()
+        .map(|(i, (jp, zh))| {
+            let zh = match &app.pending_edit {
+                Some(buffer) if i == app.pending_selected => format!("{buffer}_"),
+                _ => zh.clone(),
+            };
+            ListItem::new(format!("{jp} → {zh}"))
+        })
+        .collect();
+    let title = if app.pending_edit.is_some() {
+        "Keywords to review (Enter accept, Esc cancel)"
+    } else if items.is_empty() {
+        "Keywords to review (none, 'q' back)"
+    } else {
+        "Keywords to review ('a' accept, 'A' accept all, Enter edit, 'd' reject, 'b' blacklist, 'q' back)"
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_symbol(">>");
+    let mut state = ListState::default();
+    if !app.pending_keywords.is_empty() {
+        state.select(Some(app.pending_selected));
+    }
+    frame.render_stateful_widget(list, frame.size(), &mut state);
+}
+
+/// 本小说的设置面板：每行一个字段，未设置的字段沿用命令行参数
+pub fn draw_settings(frame: &mut Frame, app: &App) {
+    let lines: Vec<Line> = SETTINGS_FIELDS
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let value = match &app.settings_edit {
+                Some(buffer) if i == app.settings_field => format!("{buffer}_"),
+                _ => {
+                    let value = app.settings.field(i);
+                    if value.is_empty() {
+                        "(default)".to_string()
+                    } else {
+                        value
+                    }
+                }
+            };
+            if i == app.settings_field {
+                Line::styled(
+                    format!("> {name}: {value}"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Line::from(format!("  {name}: {value}"))
+            }
+        })
+        .collect();
+    let title = if app.settings_edit.is_some() {
+        "Settings (Enter save, Esc cancel)"
+    } else {
+        "Settings (Enter edit, 'd' reset to default, 'q' back)"
+    };
+    let para = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(para, frame.size());
+}
@@ -0,0 +1,241 @@
+//! 终端界面的状态与操作
+#![cfg(feature = "tui")]
+
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossterm::event::KeyCode;
+use syosetu_rs::app::{App, AppState, fuzzy_score};
+use syosetu_rs::http::{FixtureFetcher, HttpOptions, build_client};
+use syosetu_rs::memory::{JsonUsageStore, UsageStore};
+use syosetu_rs::pipeline::{Pipeline, term_usage};
+use syosetu_rs::syosetu::{Chapter, NcodeSite};
+use syosetu_rs::translate::DeepSeekTranslator;
+use tokio_util::sync::CancellationToken;
+
+use common::{GatedBackend, NCODE_CHAPTER, test_pipeline};
+
+#[tokio::test]
+async fn chapters_wait_for_a_free_translation_slot() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-slots-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let chapters: Vec<Chapter> = (1..=3)
+        .map(|n| Chapter {
+            path: format!("https://ncode.syosetu.com/n0000aa/{n}/"),
+            title: format!("第{n}話"),
+            ..Default::default()
+        })
+        .collect();
+    let fetcher = chapters.iter().fold(FixtureFetcher::new(), |f, c| {
+        f.with_page(&c.path, NCODE_CHAPTER)
+    });
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let release = CancellationToken::new();
+    let pipeline = test_pipeline(
+        &dir,
+        Arc::new(NcodeSite::new().with_client(client)),
+        Arc::new(GatedBackend {
+            release: release.clone(),
+        }),
+    );
+    let mut app = App::new("n1".to_string()).with_concurrency(1);
+    app.chapters = chapters.clone();
+    let paths: Vec<&str> = chapters.iter().map(|c| c.path.as_str()).collect();
+    // 处理回传的事件，直到条件成立
+    async fn pump(app: &mut App, pipeline: &Pipeline, done: impl Fn(&App) -> bool) {
+        for _ in 0..500 {
+            app.handle_events(pipeline).await.unwrap();
+            if done(app) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+
+    for chapter in &chapters {
+        app.spawn_processing(chapter, &pipeline);
+    }
+    pump(&mut app, &pipeline, |app| app.waiting.len() == 2).await;
+    // 哪个任务先拿到名额不确定，按当前状态区分
+    let running = *paths.iter().find(|p| !app.waiting.contains(**p)).unwrap();
+    let waiting: Vec<&str> = paths.iter().copied().filter(|p| *p != running).collect();
+    // 先开始的章节翻译期间其余章节一直等待
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    app.handle_events(&pipeline).await.unwrap();
+    assert_eq!(app.waiting.len(), 2);
+    assert!(!app.waiting.contains(running));
+
+    // 取消等待中的章节即移出队列，正在翻译的章节不受影响
+    app.cancel_processing(waiting[1]);
+    pump(&mut app, &pipeline, |app| {
+        !app.processing.contains_key(waiting[1])
+    })
+    .await;
+    assert!(!app.waiting.contains(waiting[1]));
+    assert!(app.processing.contains_key(running));
+    assert!(app.waiting.contains(waiting[0]));
+
+    // 先开始的章节完成后剩下的章节才开始
+    release.cancel();
+    pump(&mut app, &pipeline, |app| app.processing.is_empty()).await;
+    assert!(app.waiting.is_empty());
+    let cached: HashSet<&str> = app.cached_chapters.iter().map(String::as_str).collect();
+    assert_eq!(cached, HashSet::from([running, waiting[0]]));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn reader_edits_and_reverts_translations() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-edit-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pipeline = test_pipeline(
+        &dir,
+        Arc::new(NcodeSite::new()),
+        Arc::new(DeepSeekTranslator::new(
+            "key".to_string(),
+            "deepseek-chat".to_string(),
+        )),
+    );
+    let path = "/n1/1/";
+    pipeline.trans_store.save("n1", path, "一稿").await.unwrap();
+    let mut app = App::new("n1".to_string());
+    app.reading = Some(path.to_string());
+    app.translation = "一稿".to_string();
+
+    // 编辑器只在临时文件仅本用户可读写时改写内容
+    let editor = dir.join("editor.sh");
+    std::fs::write(
+        &editor,
+        "#!/bin/sh
+[ \"$(ls -l \"$1\" | cut -c1-10)\" = -rw------- ] || exit 1
+printf '二稿' > \"$1\"
+",
+    )
+    .unwrap();
+    std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let editor = editor.to_str().unwrap();
+    assert!(app.edit_reading(editor, &pipeline).await.unwrap());
+    assert_eq!(app.translation, "二稿");
+    assert_eq!(
+        pipeline
+            .trans_store
+            .load("n1", path)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("二稿")
+    );
+    // 编辑器失败或未改动时不保存，翻译中的章节不能编辑
+    assert!(app.edit_reading("false", &pipeline).await.is_err());
+    assert!(!app.edit_reading("true", &pipeline).await.unwrap());
+    app.processing
+        .insert(path.to_string(), CancellationToken::new());
+    assert!(!app.edit_reading(editor, &pipeline).await.unwrap());
+    app.processing.clear();
+    assert_eq!(app.translation, "二稿");
+
+    // 历史界面选中最近的旧译文，恢复后回到阅读界面
+    app.open_history(&pipeline).await;
+    assert_eq!(app.state, AppState::History);
+    let texts: Vec<_> = app.history.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(texts, ["一稿"]);
+    app.handle_history_key(KeyCode::Char('j'), &pipeline).await;
+    assert_eq!(app.history_selected, 0);
+    app.handle_history_key(KeyCode::Enter, &pipeline).await;
+    assert_eq!(app.state, AppState::Reading);
+    assert_eq!(app.translation, "一稿");
+    assert_eq!(
+        pipeline
+            .trans_store
+            .load("n1", path)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("一稿")
+    );
+    // 被恢复覆盖的译文也留在历史中
+    app.open_history(&pipeline).await;
+    let texts: Vec<_> = app.history.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(texts, ["二稿"]);
+    app.handle_history_key(KeyCode::Esc, &pipeline).await;
+    assert_eq!(app.state, AppState::Reading);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn glossary_search_is_fuzzy() {
+    assert_eq!(fuzzy_score("", "魔王"), Some(0));
+    assert_eq!(fuzzy_score("魔王", "魔王城"), Some(0));
+    assert_eq!(fuzzy_score("魔城", "魔王城"), Some(1));
+    assert_eq!(fuzzy_score("Tor", "tory"), Some(0));
+    assert_eq!(fuzzy_score("城魔", "魔王城"), None);
+    let mut app = App::new("n1".to_string());
+    app.keywords = HashMap::from([
+        ("魔王城".to_string(), "魔王城".to_string()),
+        ("魔法".to_string(), "魔法".to_string()),
+        ("トリー".to_string(), "托莉".to_string()),
+        ("アルト".to_string(), "阿尔托".to_string()),
+    ]);
+    app.filter_glossary();
+    assert_eq!(app.glossary.len(), 4);
+    app.glossary_search = "托莉".to_string();
+    app.filter_glossary();
+    assert_eq!(app.glossary, [("トリー".to_string(), "托莉".to_string())]);
+    // 越接近开头、越连续的匹配排在越前面
+    app.glossary_search = "ト".to_string();
+    app.filter_glossary();
+    let terms: Vec<&str> = app.glossary.iter().map(|(jp, _)| jp.as_str()).collect();
+    assert_eq!(terms, ["トリー", "アルト"]);
+    app.glossary_search = "魔城".to_string();
+    app.filter_glossary();
+    assert_eq!(app.glossary[0].0, "魔王城");
+    assert_eq!(app.glossary.len(), 1);
+}
+
+#[test]
+fn glossary_terms_are_ranked_by_usage() {
+    let usage = term_usage(
+        "トリーは魔王城へ向かった。トリーと魔法。",
+        ["トリー", "魔法", "アルト", ""].map(String::from),
+    );
+    assert_eq!(
+        usage,
+        HashMap::from([("トリー".to_string(), 2), ("魔法".to_string(), 1)])
+    );
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-usage-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonUsageStore::new(dir.join("usage.json"));
+    store.save("n1", "/1/", &usage).unwrap();
+    store
+        .save("n1", "/2/", &HashMap::from([("魔法".to_string(), 3)]))
+        .unwrap();
+    assert_eq!(store.load("n1").unwrap()["魔法"], 4);
+    // 重新翻译时覆盖该章的记录
+    store.save("n1", "/2/", &HashMap::new()).unwrap();
+    let totals = store.load("n1").unwrap();
+    assert_eq!(totals["魔法"], 1);
+    assert_eq!(totals["トリー"], 2);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut app = App::new("n1".to_string());
+    app.keywords = HashMap::from([
+        ("魔法".to_string(), "魔法".to_string()),
+        ("トリー".to_string(), "托莉".to_string()),
+        ("アルト".to_string(), "阿尔托".to_string()),
+    ]);
+    app.usage = totals;
+    app.glossary_by_usage = true;
+    app.filter_glossary();
+    let terms: Vec<&str> = app.glossary.iter().map(|(jp, _)| jp.as_str()).collect();
+    assert_eq!(terms, ["トリー", "魔法", "アルト"]);
+}
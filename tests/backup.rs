@@ -0,0 +1,134 @@
+//! 备份与恢复
+
+use syosetu_rs::backup::{Backup, BackupStores, NovelBackup};
+use syosetu_rs::memory::{
+    HistoryStore, JsonHistoryStore, JsonSettingsStore, JsonStore, JsonSummaryStore,
+    JsonTranslationStore, KeywordStore, NovelSettings, SettingsStore, SummaryStore,
+    TranslationStore,
+};
+
+#[tokio::test]
+async fn backups_restore_novels_and_data_files() {
+    type Stores = (
+        JsonStore,
+        JsonTranslationStore,
+        JsonHistoryStore,
+        JsonSummaryStore,
+        JsonSettingsStore,
+    );
+    fn open(root: &std::path::Path) -> Stores {
+        (
+            JsonStore::new(root.join("keywords.json")),
+            JsonTranslationStore::new(root.join("translations.json")),
+            JsonHistoryStore::new(root.join("history.json")),
+            JsonSummaryStore::new(root.join("summaries.json")),
+            JsonSettingsStore::new(root.join("settings.json")),
+        )
+    }
+    fn stores((kw, trans, history, summaries, settings): &Stores) -> BackupStores<'_> {
+        BackupStores {
+            keywords: kw,
+            translations: trans,
+            history,
+            summaries,
+            settings,
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-backup-{}", std::process::id()));
+    let source = dir.join("source");
+    std::fs::create_dir_all(&source).unwrap();
+    let source_stores = open(&source);
+    let (kw, trans, _, summaries, settings) = &source_stores;
+    kw.set("n1", "アリス", "爱丽丝").await.unwrap();
+    trans.save("n1", "/n1/1/", "第一章").await.unwrap();
+    trans.save("n2", "/n2/1/", "别的小说").await.unwrap();
+    summaries.save("n1", "摘要").unwrap();
+    let novel_settings = NovelSettings {
+        review: Some(true),
+        ..Default::default()
+    };
+    settings.save("n1", &novel_settings).unwrap();
+    std::fs::write(source.join("translations.json.lock"), "1").unwrap();
+
+    let novel = NovelBackup::collect(stores(&source_stores), "n1")
+        .await
+        .unwrap();
+    assert!(!novel.is_empty());
+    assert!(
+        NovelBackup::collect(stores(&source_stores), "missing")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let archive = dir.join("n1.zip");
+    Backup {
+        novels: vec![novel],
+        ..Default::default()
+    }
+    .write(&archive)
+    .unwrap();
+
+    // 小说条目并入已有存储，不同的旧译文存入历史
+    let target = dir.join("target");
+    std::fs::create_dir_all(&target).unwrap();
+    let target_stores = open(&target);
+    let (kw, trans, history, summaries, settings) = &target_stores;
+    trans.save("n1", "/n1/1/", "旧译文").await.unwrap();
+    trans.save("n1", "/n1/2/", "第二章").await.unwrap();
+    let backup = Backup::read(&archive).unwrap();
+    assert!(backup.files.is_empty());
+    assert_eq!(
+        backup.novels[0]
+            .restore(stores(&target_stores))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(kw.load("n1").await.unwrap()["アリス"], "爱丽丝");
+    assert_eq!(trans.list("n1").await.unwrap().len(), 2);
+    assert_eq!(
+        trans.load("n1", "/n1/1/").await.unwrap().as_deref(),
+        Some("第一章")
+    );
+    assert_eq!(history.load("n1", "/n1/1/").unwrap()[0].text, "旧译文");
+    assert_eq!(summaries.load("n1").unwrap().as_deref(), Some("摘要"));
+    assert_eq!(settings.load("n1").unwrap(), novel_settings);
+    assert!(trans.list("n2").await.unwrap().is_empty());
+
+    // 全部数据：锁文件不打包，已存在的文件默认保留
+    let config = dir.join("config");
+    std::fs::create_dir_all(config.join("prompts")).unwrap();
+    std::fs::write(config.join("prompts").join("system.txt"), "提示词").unwrap();
+    let full = dir.join("full.zip");
+    Backup::from_dirs(&source, &config)
+        .unwrap()
+        .write(&full)
+        .unwrap();
+    let backup = Backup::read(&full).unwrap();
+    assert!(backup.novels.is_empty());
+    assert!(
+        backup
+            .files
+            .keys()
+            .all(|path| !path.to_string_lossy().ends_with(".lock"))
+    );
+    let (data, new_config) = (dir.join("restored"), dir.join("restored-config"));
+    std::fs::create_dir_all(&data).unwrap();
+    std::fs::write(data.join("summaries.json"), "{}").unwrap();
+    let (written, kept) = backup.restore_files(&data, &new_config, false).unwrap();
+    assert_eq!(kept, [data.join("summaries.json")]);
+    assert!(written.contains(&new_config.join("prompts").join("system.txt")));
+    let restored = JsonSummaryStore::new(data.join("summaries.json"));
+    assert!(restored.load("n1").unwrap().is_none());
+    assert_eq!(
+        JsonTranslationStore::new(data.join("translations.json"))
+            .list("n2")
+            .await
+            .unwrap(),
+        ["/n2/1/"]
+    );
+    backup.restore_files(&data, &new_config, true).unwrap();
+    assert_eq!(restored.load("n1").unwrap().as_deref(), Some("摘要"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,68 @@
+//! Telegram 机器人的命令解析与消息拆分
+
+use syosetu_rs::bot::{BotCommand, MESSAGE_LIMIT, split_message};
+
+#[test]
+fn bot_commands_are_parsed() {
+    let parse = |text| BotCommand::parse(text).unwrap();
+    assert_eq!(parse("/start"), BotCommand::Help);
+    assert_eq!(parse("/novels@my_bot"), BotCommand::Novels);
+    assert_eq!(parse("/chapters 2"), BotCommand::Chapters { novel: 2 });
+    assert_eq!(
+        parse("/read@my_bot  1   12"),
+        BotCommand::Read {
+            novel: 1,
+            chapter: 12
+        }
+    );
+    assert_eq!(
+        parse("/epub 1 1-10,15"),
+        BotCommand::Epub {
+            novel: 1,
+            range: Some("1-10,15".to_string())
+        }
+    );
+    assert_eq!(
+        parse("/epub 3"),
+        BotCommand::Epub {
+            novel: 3,
+            range: None
+        }
+    );
+    assert_eq!(parse("hello"), BotCommand::Unknown);
+    assert_eq!(parse(""), BotCommand::Unknown);
+    let err = |text| BotCommand::parse(text).unwrap_err().to_string();
+    assert_eq!(err("/chapters"), "missing novel number, see /novels");
+    assert_eq!(err("/read 1"), "missing chapter number");
+    assert_eq!(err("/read x 1"), "invalid novel number x, see /novels");
+}
+
+#[test]
+fn bot_messages_are_split_at_the_character_limit() {
+    let chars = |s: &String| s.chars().count();
+    assert_eq!(
+        split_message("一行\n\n二行", MESSAGE_LIMIT),
+        ["一行\n\n二行"]
+    );
+    assert!(split_message("\n\n", MESSAGE_LIMIT).is_empty());
+
+    // 多字节字符按字符数计算：4096 个汉字正好一条消息
+    let full = "字".repeat(MESSAGE_LIMIT);
+    assert_eq!(split_message(&full, MESSAGE_LIMIT), [full]);
+
+    // 按行拆分，不超过上限，内容不丢失
+    let line = "あ".repeat(1500);
+    let text = [line.as_str(); 5].join("\n");
+    let messages = split_message(&text, MESSAGE_LIMIT);
+    assert_eq!(messages.len(), 3);
+    assert!(messages.iter().all(|m| chars(m) <= MESSAGE_LIMIT));
+    assert_eq!(messages[0], format!("{line}\n{line}"));
+    assert_eq!(messages.join("\n"), text);
+
+    // 单行超长时在字符边界处切开
+    let long = "语".repeat(MESSAGE_LIMIT * 2 + 10);
+    let messages = split_message(&long, MESSAGE_LIMIT);
+    let lengths: Vec<_> = messages.iter().map(chars).collect();
+    assert_eq!(lengths, [MESSAGE_LIMIT, MESSAGE_LIMIT, 10]);
+    assert_eq!(messages.concat(), long);
+}
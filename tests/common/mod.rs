@@ -0,0 +1,120 @@
+//! 各测试文件共用的夹具与辅助函数
+// 每个测试文件只用到其中一部分
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::sync::Arc;
+
+use syosetu_rs::library::Library;
+use syosetu_rs::memory::{
+    JsonBlacklistStore, JsonCategoryStore, JsonCharacterStore, JsonConflictStore, JsonDriftStore,
+    JsonEngineStore, JsonExampleStore, JsonHistoryStore, JsonIllustrationStore, JsonSettingsStore,
+    JsonSourceHashStore, JsonSourceStore, JsonStampStore, JsonStore, JsonSummaryStore,
+    JsonSuspectStore, JsonTitleStore, JsonTranslationStore, JsonUsageStore,
+};
+use syosetu_rs::pipeline::Pipeline;
+use syosetu_rs::syosetu::{NovelSite, cancellable};
+use syosetu_rs::translate::{
+    Keyword, Sampling, TargetLang, TranslationBackend, TranslationContext,
+};
+use tokio_util::sync::CancellationToken;
+
+pub const NCODE_DIRECTORY: &str = include_str!("../fixtures/ncode_directory.html");
+pub const NCODE_CHAPTER: &str = include_str!("../fixtures/ncode_chapter.html");
+pub const ORG_DIRECTORY: &str = include_str!("../fixtures/org_directory.html");
+pub const ORG_CHAPTER: &str = include_str!("../fixtures/org_chapter.html");
+pub const ORG_URL: &str = "https://syosetu.org/novel/12345/";
+pub const ALPHAPOLIS_DIRECTORY: &str = include_str!("../fixtures/alphapolis_directory.html");
+pub const ALPHAPOLIS_CHAPTER: &str = include_str!("../fixtures/alphapolis_chapter.html");
+pub const NOVELUP_DIRECTORY: &str = include_str!("../fixtures/novelup_directory.html");
+pub const NOVELUP_CHAPTER: &str = include_str!("../fixtures/novelup_chapter.html");
+pub const NOVELUP_URL: &str = "https://novelup.plus/story/123456789";
+pub const GENERIC_DIRECTORY: &str = include_str!("../fixtures/generic_directory.html");
+pub const GENERIC_CHAPTER: &str = include_str!("../fixtures/generic_chapter.html");
+pub const GENERIC_URL: &str = "https://example.com/novels/long/";
+pub const KAKUYOMU_DIRECTORY: &str = include_str!("../fixtures/kakuyomu_directory.html");
+pub const KAKUYOMU_CHAPTER: &str = include_str!("../fixtures/kakuyomu_chapter.html");
+pub const KAKUYOMU_URL: &str = "https://kakuyomu.jp/works/1177354054000000001";
+pub const AOZORA_CARD: &str = include_str!("../fixtures/aozora_card.html");
+pub const AOZORA_TEXT: &str = include_str!("../fixtures/aozora_text.html");
+pub const AOZORA_CARD_URL: &str = "https://www.aozora.gr.jp/cards/009999/card99999.html";
+pub const AOZORA_TEXT_URL: &str = "https://www.aozora.gr.jp/cards/009999/files/99999_12346.html";
+pub const NAROU_BOOKMARKS: &str = include_str!("../fixtures/narou_bookmarks.html");
+/// 存储都在 `dir` 下、开启剧情摘要与人物表的流水线
+pub fn test_pipeline(
+    dir: &Path,
+    site: Arc<dyn NovelSite>,
+    translator: Arc<dyn TranslationBackend>,
+) -> Pipeline {
+    let trans_store = Arc::new(JsonTranslationStore::new(dir.join("translations.json")));
+    Pipeline {
+        site,
+        translator,
+        kw_store: Arc::new(JsonStore::new(dir.join("keywords.json"))),
+        pending_store: Arc::new(JsonStore::new(dir.join("pending_keywords.json"))),
+        category_store: Arc::new(JsonCategoryStore::new(dir.join("categories.json"))),
+        blacklist_store: Arc::new(JsonBlacklistStore::new(dir.join("blacklist.json"))),
+        conflict_store: Arc::new(JsonConflictStore::new(dir.join("conflicts.json"))),
+        usage_store: Arc::new(JsonUsageStore::new(dir.join("usage.json"))),
+        review_keywords: false,
+        trans_store: trans_store.clone(),
+        source_store: Arc::new(JsonSourceStore::new(dir.join("sources.json"))),
+        hash_store: Arc::new(JsonSourceHashStore::new(dir.join("source_hashes.json"))),
+        history_store: Arc::new(JsonHistoryStore::new(dir.join("history.json"))),
+        title_store: Arc::new(JsonTitleStore::new(dir.join("titles.json"))),
+        stamp_store: Arc::new(JsonStampStore::new(dir.join("stamps.json"))),
+        illust_store: Arc::new(JsonIllustrationStore::new(dir.join("illustrations.json"))),
+        engine_store: Arc::new(JsonEngineStore::new(dir.join("engines.json"))),
+        drift_store: Arc::new(JsonDriftStore::new(dir.join("drift.json"))),
+        suspect_store: Arc::new(JsonSuspectStore::new(dir.join("suspects.json"))),
+        suspect_retries: 1,
+        settings_store: Arc::new(JsonSettingsStore::new(dir.join("settings.json"))),
+        example_store: Arc::new(JsonExampleStore::new(dir.join("examples.json"))),
+        review: false,
+        lock_terms: false,
+        target_lang: TargetLang::Zh,
+        honorifics: None,
+        sampling: Sampling::default(),
+        summary_store: Arc::new(JsonSummaryStore::new(dir.join("summaries.json"))),
+        rolling_summary: true,
+        previous_paragraphs: 3,
+        character_store: Arc::new(JsonCharacterStore::new(dir.join("characters.json"))),
+        track_characters: true,
+        library: Arc::new(Library::new(trans_store)),
+    }
+}
+
+/// 直到 `release` 被取消才返回译文的翻译器，用于观察进行中的任务
+pub struct GatedBackend {
+    pub release: CancellationToken,
+}
+
+#[async_trait::async_trait]
+impl TranslationBackend for GatedBackend {
+    fn name(&self) -> &str {
+        "gated"
+    }
+
+    async fn translate_text(
+        &self,
+        _input: &str,
+        _ctx: &TranslationContext,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<String> {
+        cancellable(cancel, async {
+            self.release.cancelled().await;
+            Ok("译文".to_string())
+        })
+        .await
+    }
+
+    async fn extract_keywords(
+        &self,
+        _zh: &str,
+        _jp: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> anyhow::Result<Vec<Keyword>> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,57 @@
+//! JSON-RPC 控制 socket
+
+use std::time::Duration;
+
+use syosetu_rs::control::{self, ControlCommand};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn control_socket_requires_loopback_and_token() {
+    let cancel = CancellationToken::new();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let public = "0.0.0.0:0".parse().unwrap();
+    let err = control::serve(public, "secret".into(), tx.clone(), cancel.clone()).await;
+    assert!(err.unwrap_err().to_string().contains("loopback"));
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(control::serve(addr, "secret".into(), tx, cancel.clone()));
+    tokio::spawn(async move {
+        while let Some(req) = rx.recv().await {
+            assert!(matches!(req.command, ControlCommand::Status));
+            let _ = req.reply.send(Ok(serde_json::json!({"novel": req.novel})));
+        }
+    });
+    let stream = loop {
+        match tokio::net::TcpStream::connect(addr).await {
+            Ok(stream) => break stream,
+            Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut call = async |line: &str| {
+        writer
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .unwrap();
+        let resp = lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str::<serde_json::Value>(&resp).unwrap()
+    };
+    for params in ["", r#","params":{"token":"wrong"}"#] {
+        let resp = call(&format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"status"{params}}}"#
+        ))
+        .await;
+        assert_eq!(resp["error"]["code"], -32001);
+    }
+    let resp = call(
+        r#"{"jsonrpc":"2.0","id":2,"method":"status","params":{"token":"secret","novel":"n1"}}"#,
+    )
+    .await;
+    assert_eq!(resp["result"]["novel"], "n1");
+    cancel.cancel();
+}
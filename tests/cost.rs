@@ -0,0 +1,25 @@
+//! 翻译费用预估
+
+use syosetu_rs::cost::{Pricing, count_tokens, estimate_chapter};
+
+#[test]
+fn estimates_chapter_cost() {
+    assert_eq!(count_tokens("hello world"), 4);
+    assert_eq!(count_tokens("こんにちは"), 3);
+    let pricing = Pricing::for_model("deepseek-chat");
+    let source = "あ".repeat(1000);
+    let small = estimate_chapter(&source, &[], &pricing);
+    // 翻译 + 提取：原文三次计入输入，译文按与原文相当估计
+    assert!(small.prompt_tokens >= 1800 && small.output_tokens >= 600);
+    assert!(small.cost > 0.0);
+    // 分段越多，提示词与对照表重复发送越多
+    let keywords = vec![("トウリ".to_string(), "托莉".to_string())];
+    let long = estimate_chapter(&"あ\n".repeat(7000), &keywords, &pricing);
+    assert!(long.cost > small.cost * 5.0);
+    assert_eq!(
+        estimate_chapter(&source, &[], &Pricing::for_model("qwen2.5")).cost,
+        0.0
+    );
+    let deepl = estimate_chapter(&source, &[], &Pricing::for_model("deepl"));
+    assert!((deepl.cost - 0.025).abs() < 1e-9);
+}
@@ -0,0 +1,137 @@
+//! 抓取策略：按域名限速、并发上限与 robots.txt
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use syosetu_rs::crawl::{CrawlPolicy, HostOverride, HostRule, RateLimiter, Robots};
+use syosetu_rs::http::{HttpOptions, build_client};
+use syosetu_rs::syosetu::SiteRegistry;
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn rate_limiter_spaces_requests_per_domain() {
+    let limiter = RateLimiter::new(Duration::from_millis(100), 2);
+    let cancel = CancellationToken::new();
+    let started = Instant::now();
+    for _ in 0..2 {
+        limiter
+            .acquire("https://ncode.syosetu.com/n1/1/", &cancel)
+            .await
+            .unwrap();
+    }
+    // 其他域名与本地文件不受影响
+    limiter
+        .acquire("https://kakuyomu.jp/works/1", &cancel)
+        .await
+        .unwrap();
+    limiter.acquire("novels/local.txt", &cancel).await.unwrap();
+    assert!(started.elapsed() < Duration::from_millis(80));
+    limiter
+        .acquire("https://ncode.syosetu.com/n1/2/", &cancel)
+        .await
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(90));
+}
+
+#[test]
+fn robots_rules_use_longest_match() {
+    let text = "User-agent: Googlebot\nDisallow: /\n\n\
+                User-agent: *\nDisallow: /search\nDisallow: /*.pdf$\nAllow: /search/help\n\
+                Disallow:\n";
+    let robots = Robots::parse(text, "syosetu-rs");
+    assert!(robots.allows("/n1234ab/1/"));
+    assert!(!robots.allows("/search?word=x"));
+    assert!(robots.allows("/search/help"));
+    assert!(!robots.allows("/files/a.pdf"));
+    assert!(robots.allows("/files/a.pdf.html"));
+    // 名称匹配的分组优先于 `*`
+    assert!(!Robots::parse(text, "Googlebot").allows("/n1234ab/"));
+    assert!(Robots::parse("", "syosetu-rs").allows("/anything"));
+}
+
+#[tokio::test]
+async fn crawl_policy_caps_concurrency_per_host() {
+    let overrides = HashMap::from([(
+        "syosetu.com".to_string(),
+        HostOverride {
+            max_concurrent: Some(1),
+            ..Default::default()
+        },
+    )]);
+    let policy = CrawlPolicy::new(HostRule {
+        max_concurrent: 2,
+        ..Default::default()
+    })
+    .with_overrides(&overrides);
+    assert_eq!(policy.rule_for("ncode.syosetu.com").max_concurrent, 1);
+    assert_eq!(policy.rule_for("kakuyomu.jp").max_concurrent, 2);
+    let cancel = CancellationToken::new();
+    let first = policy
+        .acquire("https://ncode.syosetu.com/n1/1/", &cancel)
+        .await
+        .unwrap();
+    // 同一主机的第二个请求需等待第一个结束
+    let second = policy.acquire("https://ncode.syosetu.com/n1/2/", &cancel);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), second)
+            .await
+            .is_err()
+    );
+    policy
+        .acquire("https://kakuyomu.jp/works/1", &cancel)
+        .await
+        .unwrap();
+    drop(first);
+    policy
+        .acquire("https://ncode.syosetu.com/n1/2/", &cancel)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn every_request_of_a_crawl_client_goes_through_the_policy() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    // 记录同时在处理的请求数的最大值
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let active = Arc::new(AtomicU32::new(0));
+    let peak = Arc::new(AtomicU32::new(0));
+    let (active_in, peak_in) = (active.clone(), peak.clone());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (active, peak) = (active_in.clone(), peak_in.clone());
+            tokio::spawn(async move {
+                let mut buf = vec![0; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    )
+                    .await
+                    .unwrap();
+            });
+        }
+    });
+    let registry = SiteRegistry::new().with_crawl_policy(CrawlPolicy::new(HostRule {
+        max_concurrent: 1,
+        ..Default::default()
+    }));
+    let client = registry.crawl_client(build_client(&HttpOptions::default()).unwrap());
+    // 同一站点的多个分页请求同时发出，也只能逐个进行
+    let mut pages = tokio::task::JoinSet::new();
+    for page in 1..=4 {
+        let client = client.clone();
+        let url = format!("http://{addr}/toc?p={page}");
+        pages.spawn(async move { client.get(&url).send().await.unwrap().text().await.unwrap() });
+    }
+    while let Some(body) = pages.join_next().await {
+        assert_eq!(body.unwrap(), "ok");
+    }
+    assert_eq!(peak.load(Ordering::SeqCst), 1);
+}
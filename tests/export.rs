@@ -0,0 +1,216 @@
+//! 导出 HTML、mdBook 与专有名词表
+
+use std::collections::HashMap;
+
+use syosetu_rs::export::{
+    ExportOptions, ExportReport, GlossaryFormat, export_glossary, export_html, export_mdbook,
+    glossary_to_table, import_glossary, parse_glossary_table,
+};
+use syosetu_rs::memory::{JsonStore, JsonTranslationStore, KeywordStore, TranslationStore};
+use syosetu_rs::syosetu::Chapter;
+
+#[tokio::test]
+async fn html_export_rewrites_only_changed_chapters() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-html-{}", std::process::id()));
+    let out = dir.join("html");
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonTranslationStore::new(dir.join("translations.json"));
+    let chapter = |path: &str, title: &str| Chapter {
+        path: path.into(),
+        title: title.into(),
+        ..Default::default()
+    };
+    let options = ExportOptions::default();
+    let export = |chapters: Vec<Chapter>| {
+        let (out, store, options) = (&out, &store, &options);
+        async move {
+            export_html(out, "本", "n", &chapters, store, options)
+                .await
+                .unwrap()
+        }
+    };
+    let counts = |r: ExportReport| (r.written, r.unchanged, r.removed);
+    store.save("n", "a", "春の話。").await.unwrap();
+    store.save("n", "b", "夏の話。").await.unwrap();
+    let report = export(vec![chapter("a", "一"), chapter("b", "二")]).await;
+    assert_eq!(counts(report), (2, 0, 0));
+
+    // 新增章节只写入新的文件，未变的文件保持原样
+    std::fs::write(out.join("0001.html"), "untouched").unwrap();
+    store.save("n", "c", "秋の話。").await.unwrap();
+    let all = vec![chapter("a", "一"), chapter("b", "二"), chapter("c", "三")];
+    assert_eq!(counts(export(all.clone()).await), (1, 2, 0));
+    assert_eq!(
+        std::fs::read_to_string(out.join("0001.html")).unwrap(),
+        "untouched"
+    );
+    assert!(
+        std::fs::read_to_string(out.join("index.html"))
+            .unwrap()
+            .contains("0003.html")
+    );
+
+    // 修改译文只重写该章
+    store.save("n", "b", "夏の話、改。").await.unwrap();
+    assert_eq!(counts(export(all).await), (1, 2, 0));
+    assert!(
+        std::fs::read_to_string(out.join("0002.html"))
+            .unwrap()
+            .contains("夏の話、改。")
+    );
+
+    // 目录中删去的章节：之后的章节重新编号，多出的文件被删除
+    let report = export(vec![chapter("b", "二"), chapter("c", "三")]).await;
+    assert_eq!(counts(report), (2, 0, 1));
+    assert!(!out.join("0003.html").exists());
+    let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+    assert!(!index.contains(">一<"));
+    assert!(
+        std::fs::read_to_string(out.join("0001.html"))
+            .unwrap()
+            .contains("夏の話、改。")
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn mdbook_export_only_removes_its_own_pages() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-mdbook-{}", std::process::id()));
+    let out = dir.join("book");
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonTranslationStore::new(dir.join("translations.json"));
+    let chapter = |path: &str, title: &str| Chapter {
+        path: path.into(),
+        title: title.into(),
+        ..Default::default()
+    };
+    let options = ExportOptions::default();
+    let export = |out: std::path::PathBuf, chapters: Vec<Chapter>| {
+        let (store, options) = (&store, &options);
+        async move { export_mdbook(&out, "本", "n", &chapters, store, None, options).await }
+    };
+    store.save("n", "a", "春の話。").await.unwrap();
+    store.save("n", "b", "夏の話。").await.unwrap();
+
+    // 其他项目的目录不被改动
+    let project = dir.join("project");
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+    assert!(
+        export(project.clone(), vec![chapter("a", "一")])
+            .await
+            .is_err()
+    );
+    assert_eq!(std::fs::read_dir(project.join("src")).unwrap().count(), 1);
+
+    let both = vec![chapter("a", "一"), chapter("b", "二")];
+    assert_eq!(export(out.clone(), both).await.unwrap(), 2);
+    assert!(out.join("src/0002.md").exists());
+    // 再次导出时只删除已不再导出的页面，用户自己加的文件保留
+    std::fs::write(out.join("src/notes.md"), "メモ").unwrap();
+    assert_eq!(
+        export(out.clone(), vec![chapter("a", "一")]).await.unwrap(),
+        1
+    );
+    assert!(out.join("src/0001.md").exists());
+    assert!(!out.join("src/0002.md").exists());
+    assert!(out.join("src/notes.md").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn vertical_mdbook_styles_bilingual_pages() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-mdbook-v-{}", std::process::id()));
+    let out = dir.join("book");
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonTranslationStore::new(dir.join("translations.json"));
+    store.save("n", "a", "春天的故事。").await.unwrap();
+    let chapters = vec![Chapter {
+        path: "a".into(),
+        title: "一".into(),
+        ..Default::default()
+    }];
+    let sources = HashMap::from([("a".to_string(), "春の話。".to_string())]);
+    let export = |vertical: bool| {
+        let (out, store, chapters, sources) = (&out, &store, &chapters, &sources);
+        async move {
+            let options = ExportOptions {
+                vertical,
+                ..Default::default()
+            };
+            export_mdbook(out, "本", "n", chapters, store, Some(sources), &options)
+                .await
+                .unwrap()
+        }
+    };
+    export(true).await;
+    let page = std::fs::read_to_string(out.join("src/0001.md")).unwrap();
+    assert!(page.contains("> 春の話。\n\n春天的故事。"));
+    let book = std::fs::read_to_string(out.join("book.toml")).unwrap();
+    assert!(book.contains("additional-css = [\"vertical.css\"]"));
+    let css = std::fs::read_to_string(out.join("vertical.css")).unwrap();
+    assert!(css.contains("writing-mode: vertical-rl"));
+    // 改回横排时去掉样式表
+    export(false).await;
+    let book = std::fs::read_to_string(out.join("book.toml")).unwrap();
+    assert!(!book.contains("additional-css"));
+    assert!(!out.join("vertical.css").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn glossary_tables_round_trip() {
+    let keywords = std::collections::BTreeMap::from([
+        ("トリー".to_string(), "托莉".to_string()),
+        ("ギルド".to_string(), "公会, \"冒险者\"".to_string()),
+    ]);
+    let csv = glossary_to_table(&keywords, GlossaryFormat::Csv);
+    assert_eq!(
+        csv,
+        "japanese,translated\nギルド,\"公会, \"\"冒险者\"\"\"\nトリー,托莉\n"
+    );
+    let parsed: std::collections::BTreeMap<_, _> = parse_glossary_table(&csv, GlossaryFormat::Csv)
+        .into_iter()
+        .collect();
+    assert_eq!(parsed, keywords);
+    // 其余列、空行与缺少译名的行被忽略
+    let tsv = "\u{feff}魔王\t魔王\t备注\n\n勇者\t\nアル\t阿尔\r\n";
+    assert_eq!(
+        parse_glossary_table(tsv, GlossaryFormat::Tsv),
+        [
+            ("魔王".to_string(), "魔王".to_string()),
+            ("アル".to_string(), "阿尔".to_string()),
+        ]
+    );
+    assert_eq!(
+        GlossaryFormat::from_path(std::path::Path::new("terms.TSV")),
+        GlossaryFormat::Tsv
+    );
+
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-glossary-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonStore::new(dir.join("keywords.json"));
+    store.set("n1", "トリー", "特莉").await.unwrap();
+    let file = dir.join("terms.tsv");
+    std::fs::write(&file, tsv).unwrap();
+    assert_eq!(
+        import_glossary(&store, "n1", &file, false).await.unwrap(),
+        (2, 0)
+    );
+    std::fs::write(&file, "トリー\t托莉\nアル\t阿鲁\n").unwrap();
+    assert_eq!(
+        import_glossary(&store, "n1", &file, true).await.unwrap(),
+        (0, 0)
+    );
+    assert_eq!(
+        import_glossary(&store, "n1", &file, false).await.unwrap(),
+        (0, 2)
+    );
+    let out = dir.join("out.csv");
+    assert_eq!(export_glossary(&store, "n1", &out).await.unwrap(), 3);
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap(),
+        "japanese,translated\nアル,阿鲁\nトリー,托莉\n魔王,魔王\n"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
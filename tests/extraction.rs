@@ -0,0 +1,163 @@
+//! 针对章节目录与正文解析的健壮性测试：畸形、截断或恶意构造的 HTML
+//! 不应导致 panic，只能得到内容或 `ExtractError`。
+
+mod common;
+
+use syosetu_rs::syosetu::{
+    ExtractError, NCODE_ORIGIN, parse_alphapolis_chapter, parse_alphapolis_directory,
+    parse_aozora_card, parse_aozora_chapter, parse_aozora_directory, parse_generic_chapter,
+    parse_generic_directory, parse_kakuyomu_chapter, parse_kakuyomu_directory, parse_ncode_chapter,
+    parse_ncode_directory, parse_novelup_chapter, parse_novelup_directory, parse_novelup_next_page,
+    parse_org_chapter, parse_org_directory,
+};
+
+use common::{
+    ALPHAPOLIS_CHAPTER, ALPHAPOLIS_DIRECTORY, AOZORA_CARD, AOZORA_CARD_URL, AOZORA_TEXT,
+    AOZORA_TEXT_URL, GENERIC_CHAPTER, GENERIC_DIRECTORY, GENERIC_URL, KAKUYOMU_CHAPTER,
+    KAKUYOMU_DIRECTORY, KAKUYOMU_URL, NCODE_CHAPTER, NCODE_DIRECTORY, NOVELUP_CHAPTER,
+    NOVELUP_DIRECTORY, NOVELUP_URL, ORG_CHAPTER, ORG_DIRECTORY, ORG_URL,
+};
+
+/// 对同一输入运行全部解析函数
+fn parse_all(html: &str) {
+    let _ = parse_ncode_directory(html, NCODE_ORIGIN);
+    let _ = parse_org_directory(html, ORG_URL);
+    let _ = parse_alphapolis_directory(html);
+    let _ = parse_kakuyomu_directory(html, KAKUYOMU_URL);
+    let _ = parse_novelup_directory(html);
+    let _ = parse_generic_directory(html, GENERIC_URL);
+    let _ = parse_novelup_next_page(html, NOVELUP_URL);
+    let _ = parse_aozora_card(html, AOZORA_CARD_URL);
+    let _ = parse_aozora_directory(html, AOZORA_TEXT_URL);
+    for body in [
+        parse_ncode_chapter(html),
+        parse_org_chapter(html),
+        parse_alphapolis_chapter(html),
+        parse_kakuyomu_chapter(html),
+        parse_novelup_chapter(html),
+        parse_generic_chapter(html),
+        parse_aozora_chapter(html, None),
+        parse_aozora_chapter(html, Some("midashi30")),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        assert!(!body.is_empty(), "Ok result must carry content");
+    }
+}
+
+/// 不依赖外部 crate 的简易伪随机数生成器
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+#[test]
+fn missing_or_empty_body_is_a_typed_error() {
+    assert_eq!(parse_ncode_chapter(""), Err(ExtractError::BodyNotFound));
+    assert_eq!(
+        parse_ncode_chapter(NCODE_DIRECTORY),
+        Err(ExtractError::BodyNotFound)
+    );
+    assert_eq!(
+        parse_ncode_chapter(r#"<div class="p-novel__body">  <p> </p></div>"#),
+        Err(ExtractError::EmptyBody)
+    );
+    assert_eq!(
+        parse_org_chapter(r#"<div id="honbun"></div>"#),
+        Err(ExtractError::EmptyBody)
+    );
+}
+
+#[test]
+fn directory_skips_links_without_href() {
+    let html = r#"<a class="p-eplist__subtitle">no href</a>
+        <a class="p-eplist__subtitle" href="">empty</a>
+        <a class="p-eplist__subtitle" href="/n1/1/">ok</a>"#;
+    let chapters = parse_ncode_directory(html, NCODE_ORIGIN).unwrap();
+    assert_eq!(chapters.len(), 1);
+    assert_eq!(chapters[0].path, "https://ncode.syosetu.com/n1/1/");
+}
+
+#[test]
+fn hostile_inputs_do_not_panic() {
+    let deep_nesting = format!(
+        r#"{}<div class="p-novel__body">deep</div>{}"#,
+        "<div>".repeat(5000),
+        "</div>".repeat(5000)
+    );
+    let huge_attribute = format!(
+        r#"<a class="p-eplist__subtitle" href="/{}">x</a>"#,
+        "a".repeat(1 << 20)
+    );
+    let inputs = [
+        String::new(),
+        "<".to_string(),
+        "<<<>>></></>".to_string(),
+        "\0\0\0<div id=\"honbun\">\0</div>".to_string(),
+        "<div class=\"p-novel__body\"><div class=\"p-novel__body\">".to_string(),
+        "<table><a href='./x.html'><div class=ss>".to_string(),
+        "<!-- unterminated comment <div id=\"honbun\">text</div>".to_string(),
+        "<![CDATA[<div id=\"honbun\">text</div>]]>".to_string(),
+        "<script><div class=\"p-novel__body\">x</div>".to_string(),
+        "\u{feff}\u{202e}<div id=honbun>\u{fffd}\u{fffd}</div>".to_string(),
+        deep_nesting,
+        huge_attribute,
+    ];
+    for input in &inputs {
+        parse_all(input);
+    }
+}
+
+#[test]
+fn truncated_fixtures_do_not_panic() {
+    for fixture in [NCODE_DIRECTORY, NCODE_CHAPTER, ORG_DIRECTORY, ORG_CHAPTER] {
+        for (idx, _) in fixture.char_indices() {
+            parse_all(&fixture[..idx]);
+        }
+    }
+}
+
+#[test]
+fn mutated_fixtures_do_not_panic() {
+    let mut rng = Lcg(0x5eed);
+    let specials = b"<>/\"'=&;!- \n\0";
+    for fixture in [
+        NCODE_DIRECTORY,
+        NCODE_CHAPTER,
+        ORG_DIRECTORY,
+        ORG_CHAPTER,
+        ALPHAPOLIS_DIRECTORY,
+        ALPHAPOLIS_CHAPTER,
+        KAKUYOMU_DIRECTORY,
+        KAKUYOMU_CHAPTER,
+        NOVELUP_DIRECTORY,
+        NOVELUP_CHAPTER,
+        GENERIC_DIRECTORY,
+        GENERIC_CHAPTER,
+        AOZORA_CARD,
+        AOZORA_TEXT,
+    ] {
+        for _ in 0..200 {
+            let mut bytes = fixture.as_bytes().to_vec();
+            for _ in 0..1 + rng.next() % 16 {
+                let pos = (rng.next() as usize) % bytes.len();
+                match rng.next() % 3 {
+                    0 => bytes[pos] = specials[(rng.next() as usize) % specials.len()],
+                    1 => {
+                        bytes.remove(pos);
+                    }
+                    _ => bytes.insert(pos, rng.next() as u8),
+                }
+            }
+            parse_all(&String::from_utf8_lossy(&bytes));
+        }
+    }
+}
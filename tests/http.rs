@@ -0,0 +1,245 @@
+//! HTTP 客户端：代理、Cookie、缓存、超时与限流重试，以及重试策略
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use syosetu_rs::http::{
+    CookieJar, HttpOptions, RetryPolicy, build_client, cookie_header, import_netscape_cookies,
+};
+use syosetu_rs::syosetu::ExtractError;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+#[test]
+fn clients_accept_http_and_socks_proxies() {
+    for proxy in ["http://127.0.0.1:8080", "socks5h://127.0.0.1:1080"] {
+        let options = HttpOptions {
+            proxy: Some(proxy.to_string()),
+            ..Default::default()
+        };
+        assert!(build_client(&options).is_ok(), "{proxy}");
+    }
+    let invalid = HttpOptions {
+        proxy: Some("not a proxy".to_string()),
+        ..Default::default()
+    };
+    assert!(build_client(&invalid).is_err());
+}
+
+#[test]
+fn imports_netscape_cookies() {
+    let text = "# Netscape HTTP Cookie File\n\
+        .syosetu.com\tTRUE\t/\tFALSE\t0\tover18\tyes\n\
+        #HttpOnly_ssl.syosetu.com\tFALSE\t/\tTRUE\t4102444800\tuserl\tsecret\n\
+        kakuyomu.jp\tFALSE\t/\tFALSE\t1\texpired\tgone\n\
+        malformed line\n";
+    let jar = CookieJar::default();
+    assert_eq!(import_netscape_cookies(&jar, text).unwrap(), 2);
+    assert_eq!(
+        cookie_header(&jar, "https://novel18.syosetu.com/n1234ab/").as_deref(),
+        Some("over18=yes")
+    );
+    let login = cookie_header(&jar, "https://ssl.syosetu.com/").unwrap();
+    assert!(login.contains("userl=secret"), "{login}");
+    // host-only 与 Secure Cookie 不会发送到其他子域名或明文 HTTP
+    assert!(
+        !cookie_header(&jar, "https://ncode.syosetu.com/")
+            .unwrap()
+            .contains("userl")
+    );
+    assert!(
+        !cookie_header(&jar, "http://ssl.syosetu.com/")
+            .unwrap()
+            .contains("userl")
+    );
+    assert_eq!(cookie_header(&jar, "https://kakuyomu.jp/"), None);
+}
+
+#[tokio::test]
+async fn cached_pages_are_revalidated() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    // 带 If-None-Match 时返回 304，否则返回完整页面，并记录完整响应的次数
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let full = Arc::new(AtomicU32::new(0));
+    let served = full.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = vec![0; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+            let response = if request.contains("if-none-match: \"v1\"") {
+                "HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n".to_string()
+            } else {
+                served.fetch_add(1, Ordering::SeqCst);
+                "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-type: text/html\r\n\
+                 content-length: 5\r\nconnection: close\r\n\r\nhello"
+                    .to_string()
+            };
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-cache-{}", std::process::id()));
+    let options = HttpOptions {
+        cache_dir: Some(dir.clone()),
+        ..Default::default()
+    };
+    let url = format!("http://{addr}/page");
+    for refresh in [false, false, true] {
+        let client = build_client(&HttpOptions {
+            refresh,
+            ..options.clone()
+        })
+        .unwrap();
+        let resp = client.get(&url).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.text().await.unwrap(), "hello");
+    }
+    // 第二次请求由 304 命中缓存，--refresh 时重新下载
+    assert_eq!(full.load(Ordering::SeqCst), 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn hung_requests_time_out() {
+    // 接受连接但从不响应的服务器
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            held.push(socket);
+        }
+    });
+    let client = build_client(&HttpOptions {
+        timeout: Duration::from_millis(200),
+        max_retries: 0,
+        ..Default::default()
+    })
+    .unwrap();
+    let started = Instant::now();
+    let err = client
+        .get(format!("http://{addr}/"))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_timeout(), "{err:?}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn rate_limited_requests_honor_retry_after() {
+    // 第一次返回 429，之后返回 200
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut served = 0;
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let response = if served == 0 {
+                "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+            };
+            served += 1;
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    let client = build_client(&HttpOptions {
+        max_retries: 2,
+        ..Default::default()
+    })
+    .unwrap();
+    let started = Instant::now();
+    let resp = client.get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn retry_policy_retries_only_network_errors() {
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::ZERO,
+        ..Default::default()
+    };
+    let cancel = CancellationToken::new();
+    let calls = AtomicU32::new(0);
+    let result = policy
+        .run("test", &cancel, || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into())
+            } else {
+                Ok("ok")
+            }
+        })
+        .await;
+    assert_eq!(result.unwrap(), "ok");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // 解析错误重试也不会成功，应立即返回
+    calls.store(0, Ordering::SeqCst);
+    let result: anyhow::Result<()> = policy
+        .run("test", &cancel, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ExtractError::BodyNotFound.into())
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let backoff = RetryPolicy {
+        jitter: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(backoff.delay(1), Duration::from_secs(1));
+    assert_eq!(backoff.delay(3), Duration::from_secs(4));
+    assert_eq!(backoff.delay(20), backoff.max_delay);
+    // 中间件与 curl 下载共用 `--retries` 与 `--retry-delay-ms` 生成的策略，等待时间带抖动
+    let policy = HttpOptions {
+        max_retries: 4,
+        retry_delay: Duration::from_secs(2),
+        ..Default::default()
+    }
+    .retry_policy();
+    assert_eq!(policy.max_attempts, 5);
+    let delay = policy.delay(2);
+    assert!(delay >= Duration::from_millis(3200) && delay <= Duration::from_millis(4800));
+}
+
+#[tokio::test]
+async fn gzip_responses_are_decompressed() {
+    // gzip 压缩的「こんにちは」
+    const BODY: &[u8] = &[
+        31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 123, 220, 56, 249, 113, 211, 228, 199, 141, 171, 31, 55,
+        46, 124, 220, 184, 30, 0, 231, 27, 132, 184, 15, 0, 0, 0,
+    ];
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        let request = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        assert!(request.contains("accept-encoding: gzip"));
+        let head = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html; charset=utf-8\r\n\
+             content-encoding: gzip\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            BODY.len()
+        );
+        socket.write_all(head.as_bytes()).await.unwrap();
+        socket.write_all(BODY).await.unwrap();
+    });
+    let client = build_client(&HttpOptions::default()).unwrap();
+    let text = client
+        .get(format!("http://{addr}/"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "こんにちは");
+}
@@ -0,0 +1,203 @@
+//! 按小说整理存储数据，以及数据目录的定位与迁移
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use syosetu_rs::library::{Library, NovelFile};
+use syosetu_rs::memory::{
+    DirTranslationStore, Example, ExampleScope, ExampleStore, GLOBAL_GLOSSARY, HistoryStore,
+    JsonExampleStore, JsonHistoryStore, JsonStore, KeywordStore, Revision, TranslationStore,
+};
+use syosetu_rs::paths::AppDirs;
+
+#[tokio::test]
+async fn library_removes_and_prunes_novel_data() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-library-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let trans = Arc::new(DirTranslationStore::new(dir.join("data")));
+    let keywords = JsonStore::new(dir.join("keywords.json"));
+    let history = JsonHistoryStore::new(dir.join("history.json"));
+    let examples = JsonExampleStore::new(dir.join("examples.json"));
+    let library = Library::new(trans.clone())
+        .with_file(NovelFile::new(dir.join("keywords.json")))
+        .with_file(NovelFile::new(dir.join("history.json")).by_chapter())
+        .with_file(NovelFile::new(dir.join("examples.json")).in_section("novels"));
+    for chapter in ["/n1/1/", "/n1/2/", "/n1/3/"] {
+        trans.save("n1", chapter, "译文").await.unwrap();
+    }
+    trans.save("n2", "/n2/1/", "别的小说").await.unwrap();
+    keywords.set("n1", "アリス", "爱丽丝").await.unwrap();
+    keywords.set("n2", "ボブ", "鲍勃").await.unwrap();
+    for chapter in ["/n1/1/", "/n1/3/"] {
+        history
+            .save("n1", chapter, &[Revision::now("旧译文")])
+            .unwrap();
+    }
+    let example = Example {
+        source: "原文".to_string(),
+        translation: "译文".to_string(),
+    };
+    examples
+        .save(
+            &ExampleScope::Novel("n1".to_string()),
+            std::slice::from_ref(&example),
+        )
+        .unwrap();
+    examples
+        .save(&ExampleScope::Genre("fantasy".to_string()), &[example])
+        .unwrap();
+
+    let usage = library.usage().await.unwrap();
+    assert_eq!(usage[0].novel_id, "n1");
+    assert_eq!(usage[0].chapters, 3);
+    assert_eq!(usage[0].stores["history.json"].entries, 2);
+    assert_eq!(usage[0].stores["examples.json"].entries, 1);
+    assert_eq!(usage[1].chapters, 1);
+    assert!(usage[0].bytes > usage[1].bytes);
+
+    // 目录中只剩第 1、2 章：预览时不删除，目录为空时拒绝清理
+    let toc: HashSet<String> = ["/n1/1/", "/n1/2/"].map(String::from).into();
+    let expected = [
+        ("history.json".to_string(), 1),
+        ("translations".to_string(), 1),
+    ]
+    .into();
+    assert_eq!(library.prune("n1", &toc, false).await.unwrap(), expected);
+    assert_eq!(trans.list("n1").await.unwrap().len(), 3);
+    assert!(library.prune("n1", &HashSet::new(), true).await.is_err());
+    assert_eq!(library.prune("n1", &toc, true).await.unwrap(), expected);
+    assert_eq!(trans.list("n1").await.unwrap(), ["/n1/1/", "/n1/2/"]);
+    assert!(history.load("n1", "/n1/3/").unwrap().is_empty());
+    assert_eq!(history.load("n1", "/n1/1/").unwrap().len(), 1);
+
+    assert!(library.remove(GLOBAL_GLOSSARY, true).await.is_err());
+    let removal = library.remove("n1", true).await.unwrap();
+    assert_eq!(removal["translations"], 2);
+    assert_eq!(removal["keywords.json"], 1);
+    assert!(trans.list("n1").await.unwrap().is_empty());
+    assert!(!dir.join("data").join("n1").exists());
+    assert!(keywords.load("n1").await.unwrap().is_empty());
+    assert!(
+        examples
+            .load(&ExampleScope::Novel("n1".to_string()))
+            .unwrap()
+            .is_empty()
+    );
+    // 其他小说与题材示例保持不变
+    assert_eq!(keywords.load("n2").await.unwrap()["ボブ"], "鲍勃");
+    assert_eq!(trans.list("n2").await.unwrap(), ["/n2/1/"]);
+    assert_eq!(
+        examples
+            .load(&ExampleScope::Genre("fantasy".to_string()))
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(library.remove("n1", true).await.unwrap().is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn data_files_are_copied_from_the_working_directory() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-dirs-{}", std::process::id()));
+    let legacy = dir.join("cwd");
+    std::fs::create_dir_all(legacy.join("data/n1")).unwrap();
+    for file in [
+        "keywords.json",
+        "translations.en.json",
+        "app.log",
+        "crawl.toml",
+        "notes.json",
+        "Cargo.toml",
+    ] {
+        std::fs::write(legacy.join(file), file).unwrap();
+    }
+    std::fs::write(legacy.join("data/n1/index.json"), "{}").unwrap();
+    let dirs = AppDirs {
+        data: dir.join("share"),
+        config: dir.join("config"),
+        cache: dir.join("cache"),
+    };
+    let moved = dirs.prepare(&legacy).unwrap();
+    assert_eq!(
+        moved,
+        [
+            dir.join("cache/app.log"),
+            dir.join("config/crawl.toml"),
+            dir.join("share/data"),
+            dir.join("share/keywords.json"),
+            dir.join("share/translations.en.json"),
+        ]
+    );
+    assert!(dir.join("share/data/n1/index.json").is_file());
+    // 原文件保留，不是本程序的文件不复制
+    assert!(legacy.join("keywords.json").is_file());
+    assert!(legacy.join("data/n1/index.json").is_file());
+    assert!(!dir.join("share/notes.json").exists());
+    assert!(legacy.join("notes.json").is_file());
+    assert!(legacy.join("Cargo.toml").is_file());
+    // 数据目录已存在时不再迁移
+    std::fs::write(legacy.join("titles.json"), "{}").unwrap();
+    assert!(dirs.prepare(&legacy).unwrap().is_empty());
+    assert!(legacy.join("titles.json").is_file());
+
+    let single = AppDirs::single(dir.join("portable"));
+    assert_eq!(single.cache, dir.join("portable"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn unrelated_working_directories_are_not_migrated() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-other-{}", std::process::id()));
+    let cwd = dir.join("cwd");
+    // 其他程序的同名文件：没有 `keywords.json` 或 `translations.json`
+    std::fs::create_dir_all(cwd.join("data")).unwrap();
+    for file in [
+        "settings.json",
+        "history.json",
+        "app.log",
+        "data/records.csv",
+    ] {
+        std::fs::write(cwd.join(file), file).unwrap();
+    }
+    let dirs = AppDirs {
+        data: dir.join("share"),
+        config: dir.join("config"),
+        cache: dir.join("cache"),
+    };
+    assert!(dirs.prepare(&cwd).unwrap().is_empty());
+    assert!(dir.join("share").is_dir());
+    assert!(!dir.join("share/settings.json").exists());
+    assert!(!dir.join("share/data").exists());
+    for file in [
+        "settings.json",
+        "history.json",
+        "app.log",
+        "data/records.csv",
+    ] {
+        assert!(cwd.join(file).is_file(), "{file}");
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn data_dirs_follow_xdg_variables() {
+    let env = |name: &str| match name {
+        "HOME" => Some("/home/u".into()),
+        "XDG_DATA_HOME" => Some("/data".into()),
+        "XDG_CACHE_HOME" => Some("".into()),
+        _ => None,
+    };
+    let dirs = AppDirs::from_env(env).unwrap();
+    assert_eq!(dirs.data, std::path::Path::new("/data/syosetu-rs"));
+    assert_eq!(
+        dirs.config,
+        std::path::Path::new("/home/u/.config/syosetu-rs")
+    );
+    assert_eq!(
+        dirs.cache,
+        std::path::Path::new("/home/u/.cache/syosetu-rs")
+    );
+    assert!(AppDirs::from_env(|_| None).is_err());
+}
@@ -0,0 +1,37 @@
+//! 从原文挖掘专有名词候选
+
+use std::collections::{HashMap, HashSet};
+
+use syosetu_rs::mining::{Candidate, TermMiner, katakana_words, rank_candidates};
+
+#[test]
+fn katakana_term_candidates_are_mined_locally() {
+    let text = "トリーは・アルと王都へ。トリーのナイフ。ーアル";
+    assert_eq!(
+        katakana_words(text),
+        ["トリー", "アル", "トリー", "ナイフ", "アル"]
+    );
+    let mut counts = HashMap::new();
+    TermMiner::katakana().count(text, &mut counts).unwrap();
+    TermMiner::new(None)
+        .unwrap()
+        .count("ナイフ", &mut counts)
+        .unwrap();
+    let known = HashSet::from(["アル".to_string()]);
+    let candidates = rank_candidates(counts, 2, &known);
+    assert_eq!(
+        candidates,
+        [
+            Candidate {
+                term: "トリー".to_string(),
+                count: 2
+            },
+            Candidate {
+                term: "ナイフ".to_string(),
+                count: 2
+            },
+        ]
+    );
+    // 词典目录不存在或未启用 lindera 特性时报错
+    assert!(TermMiner::new(Some(std::path::Path::new("no-such-dictionary"))).is_err());
+}
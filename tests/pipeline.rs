@@ -0,0 +1,685 @@
+//! 章节抓取→翻译→提取专有名词的处理流程
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use syosetu_rs::http::{FixtureFetcher, HttpOptions, build_client};
+use syosetu_rs::memory::{
+    Conflict, DiffLine, DriftStore, GLOBAL_GLOSSARY, HistoryStore, JsonDriftStore,
+    JsonHistoryStore, JsonSourceStore, JsonStore, JsonTranslationStore, KeywordStore,
+    NovelSettings, SourceStore, TranslationStore, blacklist_keyword, diff_lines, promote_keyword,
+};
+use syosetu_rs::pipeline::{
+    ChapterSource, Inconsistency, Pipeline, check_consistency, commit_keywords, load_categories,
+    load_conflicts, process_chapter, replace_in_translations, replace_term, resolve_conflict,
+    rollback_translation, save_translation, source_changed, source_hash, translate_missing_titles,
+};
+use syosetu_rs::syosetu::{Chapter, NcodeSite};
+use syosetu_rs::translate::{
+    DeepSeekTranslator, Keyword, KeywordCategory, StreamEvent, StreamSender, TranslationBackend,
+    TranslationContext,
+};
+use tokio_util::sync::CancellationToken;
+
+use common::{NCODE_CHAPTER, test_pipeline};
+
+#[tokio::test]
+async fn omitted_titles_are_retried_instead_of_cached() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-titles-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let translator = |reply: &str| -> Arc<dyn TranslationBackend> {
+        let fetcher = FixtureFetcher::new().with_json(
+            "https://api.deepseek.com/chat/completions",
+            format!(r#"{{"choices":[{{"message":{{"content":"{reply}"}}}}]}}"#),
+        );
+        let client = build_client(&HttpOptions {
+            fetcher: Some(Arc::new(fetcher)),
+            ..Default::default()
+        })
+        .unwrap();
+        Arc::new(
+            DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string())
+                .with_client(client),
+        )
+    };
+    let chapters = [
+        Chapter {
+            path: "a".into(),
+            title: "第一話　出会い".into(),
+            ..Default::default()
+        },
+        Chapter {
+            path: "b".into(),
+            title: "第二話　別れ".into(),
+            ..Default::default()
+        },
+    ];
+    let ctx = TranslationContext::default();
+    let cancel = CancellationToken::new();
+    // 模型漏掉了第二行
+    let pipeline = test_pipeline(
+        &dir,
+        Arc::new(NcodeSite::new()),
+        translator(r"1\t第一话 相遇"),
+    );
+    let titles = translate_missing_titles(&pipeline, "n1", &chapters, &ctx, &cancel)
+        .await
+        .unwrap();
+    assert_eq!(
+        titles,
+        HashMap::from([("a".to_string(), "第一话 相遇".to_string())])
+    );
+    assert!(!pipeline.title_store.load("n1").unwrap().contains_key("b"));
+    // 下次只重新翻译漏掉的标题
+    let pipeline = Pipeline {
+        translator: translator(r"1\t第二话 离别"),
+        ..pipeline
+    };
+    translate_missing_titles(&pipeline, "n1", &chapters, &ctx, &cancel)
+        .await
+        .unwrap();
+    let stored = pipeline.title_store.load("n1").unwrap();
+    assert_eq!(stored["a"], "第一话 相遇");
+    assert_eq!(stored["b"], "第二话 离别");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// 原样返回输入、每三个字发送一个片段的翻译器，记录收到的原文
+#[derive(Default)]
+struct EchoStreamBackend {
+    input: Mutex<String>,
+}
+
+#[async_trait::async_trait]
+impl TranslationBackend for EchoStreamBackend {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    async fn translate_text(
+        &self,
+        input: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> anyhow::Result<String> {
+        Ok(input.to_string())
+    }
+
+    async fn translate_streaming(
+        &self,
+        input: &str,
+        _ctx: &TranslationContext,
+        partial: Option<&StreamSender>,
+        _cancel: &CancellationToken,
+    ) -> anyhow::Result<(String, String)> {
+        *self.input.lock().unwrap() = input.to_string();
+        let chars: Vec<char> = input.chars().collect();
+        for chunk in chars.chunks(3) {
+            if let Some(tx) = partial {
+                let _ = tx.send(StreamEvent::Delta(chunk.iter().collect()));
+            }
+        }
+        Ok((input.to_string(), "echo".to_string()))
+    }
+
+    async fn extract_keywords(
+        &self,
+        _zh: &str,
+        _jp: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> anyhow::Result<Vec<Keyword>> {
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn streamed_chapters_show_locked_terms_unlocked() {
+    let chapter_url = "https://ncode.syosetu.com/n1234ab/1/";
+    let fetcher = FixtureFetcher::new().with_page(chapter_url, NCODE_CHAPTER);
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-lockstream-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let backend = Arc::new(EchoStreamBackend::default());
+    let mut pipeline = test_pipeline(
+        &dir,
+        Arc::new(NcodeSite::new().with_client(client)),
+        backend.clone(),
+    );
+    pipeline.lock_terms = true;
+    let ctx = pipeline
+        .context(vec![("勇者".to_string(), "勇者大人".to_string())], "テスト")
+        .await;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    process_chapter(
+        &pipeline,
+        "n1234ab",
+        chapter_url,
+        &ctx,
+        Some(&tx),
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    let input = backend.input.lock().unwrap().clone();
+    assert!(input.contains("⟦T1⟧"), "{input}");
+    // 原样返回的译文被判为可疑而重试，重试前的片段作废
+    let mut streamed = String::new();
+    let mut restarts = 0;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            StreamEvent::Delta(delta) => streamed.push_str(&delta),
+            StreamEvent::Restart => {
+                streamed.clear();
+                restarts += 1;
+            }
+        }
+    }
+    assert!(restarts > 0);
+    assert_eq!(streamed, input.replace("⟦T1⟧", "勇者大人"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn processed_chapters_update_the_story_summary() {
+    let chapter_url = "https://ncode.syosetu.com/n1234ab/1/";
+    let reply = r#"{"choices":[{"message":{"content":"托莉来到王都。"}}]}"#;
+    let fetcher = FixtureFetcher::new()
+        .with_page(chapter_url, NCODE_CHAPTER)
+        .with_page("https://ncode.syosetu.com/n1234ab/2/", NCODE_CHAPTER)
+        .with_json("https://api.deepseek.com/chat/completions", reply);
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-summary-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pipeline = test_pipeline(
+        &dir,
+        Arc::new(NcodeSite::new().with_client(client.clone())),
+        Arc::new(
+            DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string())
+                .with_client(client),
+        ),
+    );
+    let summaries = pipeline.summary_store.clone();
+    let ctx = pipeline.context(Vec::new(), "テスト").await;
+    process_chapter(
+        &pipeline,
+        "n1234ab",
+        chapter_url,
+        &ctx,
+        None,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        summaries.load("n1234ab").unwrap().as_deref(),
+        Some("托莉来到王都。")
+    );
+    // 翻译时记下原文摘要，原文未改动
+    let cancel = CancellationToken::new();
+    let cached = pipeline
+        .source_store
+        .load("n1234ab", chapter_url)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        pipeline.hash_store.load("n1234ab").await.unwrap()[chapter_url],
+        source_hash(&cached)
+    );
+    assert!(
+        !source_changed(&pipeline, "n1234ab", chapter_url, &cancel)
+            .await
+            .unwrap()
+    );
+    // 作者改稿后摘要不同
+    let page = NCODE_CHAPTER.replace("朝の光が差し込んでいた。", "夕日が差し込んでいた。");
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(FixtureFetcher::new().with_page(chapter_url, page))),
+        ..Default::default()
+    })
+    .unwrap();
+    let edited = Pipeline {
+        site: Arc::new(NcodeSite::new().with_client(client)),
+        ..pipeline.clone()
+    };
+    assert!(
+        source_changed(&edited, "n1234ab", chapter_url, &cancel)
+            .await
+            .unwrap()
+    );
+    // 原文很短，不检查长度比例
+    assert!(
+        pipeline
+            .suspect_store
+            .load("n1234ab")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    // 按小说开启审校
+    assert_eq!(
+        pipeline.settings_store.load("n1234ab").unwrap(),
+        NovelSettings::default()
+    );
+    let settings = NovelSettings {
+        review: Some(true),
+        ..Default::default()
+    };
+    pipeline.settings_store.save("n1234ab", &settings).unwrap();
+    assert_eq!(pipeline.settings_store.load("n1234ab").unwrap(), settings);
+    // 下一章附带上一章结尾
+    let next_url = "https://ncode.syosetu.com/n1234ab/2/";
+    let ctx = ctx.with_previous_chapter(Some(chapter_url.to_string()));
+    process_chapter(
+        &pipeline,
+        "n1234ab",
+        next_url,
+        &ctx,
+        None,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    assert!(
+        pipeline
+            .trans_store
+            .load("n1234ab", next_url)
+            .await
+            .unwrap()
+            .is_some()
+    );
+    // 开启审核时新词条只进入待审核列表，已在对照表中的词条不重复审核
+    let pipeline = Pipeline {
+        review_keywords: true,
+        ..pipeline
+    };
+    let mut keywords = HashMap::from([("トリー".to_string(), "托莉".to_string())]);
+    let found = vec![
+        Keyword::new("トリー", "特莉"),
+        Keyword {
+            category: Some(KeywordCategory::Place),
+            ..Keyword::new("王都", "王都")
+        },
+    ];
+    commit_keywords(&pipeline, "n1234ab", chapter_url, &mut keywords, found)
+        .await
+        .unwrap();
+    assert_eq!(keywords.len(), 1);
+    assert!(
+        !pipeline
+            .kw_store
+            .load("n1234ab")
+            .await
+            .unwrap()
+            .contains_key("王都")
+    );
+    assert_eq!(
+        pipeline.pending_store.load("n1234ab").await.unwrap(),
+        HashMap::from([("王都".to_string(), "王都".to_string())])
+    );
+    // 类别在审核前就已记录，手动设置的类别不被之后的提取结果覆盖
+    let categories = &pipeline.category_store;
+    assert_eq!(
+        categories.load("n1234ab").unwrap()["王都"],
+        KeywordCategory::Place
+    );
+    categories
+        .set("n1234ab", "トリー", Some(KeywordCategory::Person))
+        .unwrap();
+    let mut relabeled = HashMap::from([("トリー".to_string(), KeywordCategory::Organization)]);
+    relabeled.insert("アル".to_string(), KeywordCategory::Person);
+    categories.save("n1234ab", &relabeled).unwrap();
+    categories.set("n1234ab", "王都", None).unwrap();
+    categories
+        .save(
+            GLOBAL_GLOSSARY,
+            &HashMap::from([("ギルド".to_string(), KeywordCategory::Organization)]),
+        )
+        .unwrap();
+    let merged = load_categories(&pipeline, "n1234ab").unwrap();
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged["トリー"], KeywordCategory::Person);
+    assert_eq!(merged["ギルド"], KeywordCategory::Organization);
+    // 全局词条垫在小说词条之下，提升后仍会用于翻译
+    let kw = pipeline.kw_store.as_ref();
+    kw.set(GLOBAL_GLOSSARY, "ギルド", "行会").await.unwrap();
+    kw.set("n1234ab", "ギルド", "公会").await.unwrap();
+    kw.set("n1234ab", "魔王", "魔王").await.unwrap();
+    assert!(promote_keyword(kw, "n1234ab", "魔王").await.unwrap());
+    assert!(!promote_keyword(kw, "n1234ab", "魔王").await.unwrap());
+    assert!(!kw.load("n1234ab").await.unwrap().contains_key("魔王"));
+    let ctx = pipeline.context(kw.load("n1234ab").await.unwrap().into_iter().collect(), "");
+    let glossary: HashMap<String, String> = ctx.await.keywords.into_iter().collect();
+    assert_eq!(glossary["ギルド"], "公会");
+    assert_eq!(glossary["魔王"], "魔王");
+    // 全局表中已有的词条不再进入待审核列表
+    let found = vec![Keyword::new("魔王", "魔王")];
+    commit_keywords(&pipeline, "n1234ab", chapter_url, &mut keywords, found)
+        .await
+        .unwrap();
+    assert!(
+        !pipeline
+            .pending_store
+            .load("n1234ab")
+            .await
+            .unwrap()
+            .contains_key("魔王")
+    );
+    // 屏蔽词从提取结果中丢弃，全局屏蔽词对所有小说生效
+    let blacklist = pipeline.blacklist_store.as_ref();
+    assert!(blacklist.add(GLOBAL_GLOSSARY, "剣").unwrap());
+    assert!(!blacklist.add(GLOBAL_GLOSSARY, "剣").unwrap());
+    assert!(
+        blacklist_keyword(kw, blacklist, "n1234ab", "ギルド")
+            .await
+            .unwrap()
+    );
+    let direct = Pipeline {
+        review_keywords: false,
+        ..pipeline.clone()
+    };
+    let mut keywords = HashMap::new();
+    let found = vec![
+        Keyword::new("剣", "剑"),
+        Keyword::new("ギルド", "公会"),
+        Keyword::new("聖女", "圣女"),
+        // 规范化后重复的词条只保留第一个
+        Keyword::new("ﾄｳﾘさん", "托莉さん"),
+        Keyword::new("「トウリ」", "托丽"),
+    ];
+    commit_keywords(&direct, "n1234ab", chapter_url, &mut keywords, found)
+        .await
+        .unwrap();
+    assert_eq!(
+        keywords,
+        HashMap::from([
+            ("聖女".to_string(), "圣女".to_string()),
+            ("トウリ".to_string(), "托莉".to_string()),
+        ])
+    );
+    assert_eq!(blacklist.load("n1234ab").unwrap(), vec!["ギルド"]);
+    assert!(blacklist.remove("n1234ab", "ギルド").unwrap());
+    assert!(blacklist.load("n1234ab").unwrap().is_empty());
+    // 提取出的译名与对照表不同时保留原译名并记为冲突
+    let trans = direct.trans_store.as_ref();
+    trans
+        .save("n1234ab", chapter_url, "托丽来到王都。")
+        .await
+        .unwrap();
+    trans
+        .save("n1234ab", next_url, "托莉来到王都。")
+        .await
+        .unwrap();
+    let conflicting = || vec![Keyword::new("トウリ", "托丽")];
+    commit_keywords(
+        &direct,
+        "n1234ab",
+        chapter_url,
+        &mut keywords,
+        conflicting(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(keywords["トウリ"], "托莉");
+    assert_eq!(
+        load_conflicts(&direct, "n1234ab").await.unwrap(),
+        [(
+            "トウリ".to_string(),
+            "托莉".to_string(),
+            Conflict {
+                proposed: "托丽".to_string(),
+                chapters: vec![chapter_url.to_string()],
+            }
+        )]
+    );
+    // 保留原译名时返回给出新译名的章节，改用新译名时返回仍使用原译名的章节
+    assert_eq!(
+        resolve_conflict(&direct, "n1234ab", "トウリ", false)
+            .await
+            .unwrap(),
+        [chapter_url]
+    );
+    assert!(load_conflicts(&direct, "n1234ab").await.unwrap().is_empty());
+    commit_keywords(
+        &direct,
+        "n1234ab",
+        chapter_url,
+        &mut keywords,
+        conflicting(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        resolve_conflict(&direct, "n1234ab", "トウリ", true)
+            .await
+            .unwrap(),
+        [next_url]
+    );
+    assert_eq!(kw.load("n1234ab").await.unwrap()["トウリ"], "托丽");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn chapter_sources_are_cached() {
+    let url = "https://ncode.syosetu.com/n1234ab/1/";
+    let site = |fetcher: FixtureFetcher| {
+        let client = build_client(&HttpOptions {
+            fetcher: Some(Arc::new(fetcher)),
+            ..Default::default()
+        })
+        .unwrap();
+        NcodeSite::new().with_client(client)
+    };
+    let online = site(FixtureFetcher::new().with_page(url, NCODE_CHAPTER));
+    let offline = site(FixtureFetcher::new());
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-sources-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let sources = JsonSourceStore::new(dir.join("sources.json"));
+    let (online, offline) = (
+        ChapterSource {
+            site: &online,
+            cache: &sources,
+        },
+        ChapterSource {
+            site: &offline,
+            cache: &sources,
+        },
+    );
+    let cancel = CancellationToken::new();
+    assert!(offline.fetch("n1234ab", url, &cancel).await.is_err());
+    let fetched = online.fetch("n1234ab", url, &cancel).await.unwrap();
+    assert!(!fetched.body.is_empty());
+    // 缓存后无需再次下载
+    let cached = offline.fetch("n1234ab", url, &cancel).await.unwrap();
+    assert_eq!(cached, fetched);
+    assert!(sources.remove("n1234ab", url).await.unwrap());
+    assert!(!sources.remove("n1234ab", url).await.unwrap());
+    assert_eq!(sources.load("n1234ab", url).await.unwrap(), None);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn consistency_check_flags_chapters_missing_glossary_translations() {
+    let url = |n: u32| format!("https://ncode.syosetu.com/n1234ab/{n}/");
+    let fetcher = FixtureFetcher::new()
+        .with_page(&url(1), NCODE_CHAPTER)
+        .with_page(&url(2), NCODE_CHAPTER);
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let site = NcodeSite::new().with_client(client);
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-consistency-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let kw = JsonStore::new(dir.join("keywords.json"));
+    let trans = JsonTranslationStore::new(dir.join("translations.json"));
+    let drift = JsonDriftStore::new(dir.join("drift.json"));
+    kw.set("n1234ab", "朝の光", "晨光").await.unwrap();
+    kw.set(GLOBAL_GLOSSARY, "勇者", "勇者大人").await.unwrap();
+    trans
+        .save("n1234ab", &url(1), "晨光照了进来，勇者大人醒了。")
+        .await
+        .unwrap();
+    trans
+        .save("n1234ab", &url(2), "早上的阳光照了进来。")
+        .await
+        .unwrap();
+    drift
+        .save("n1234ab", &url(1), &["朝の光".to_string()])
+        .await
+        .unwrap();
+    // 第 3 章未缓存，不会被抓取
+    let chapters: Vec<Chapter> = (1..=3)
+        .map(|n| Chapter {
+            path: url(n),
+            title: format!("第{n}話"),
+            ..Default::default()
+        })
+        .collect();
+    let sources = JsonSourceStore::new(dir.join("sources.json"));
+    let report = check_consistency(
+        ChapterSource {
+            site: &site,
+            cache: &sources,
+        },
+        &kw,
+        &trans,
+        &drift,
+        "n1234ab",
+        &chapters,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        report,
+        vec![Inconsistency {
+            path: url(2),
+            title: "第2話".to_string(),
+            terms: vec![
+                ("勇者".to_string(), "勇者大人".to_string()),
+                ("朝の光".to_string(), "晨光".to_string()),
+            ],
+        }]
+    );
+    // 检查结果覆盖漂移记录，已修正的章节不再标出
+    let drifted = drift.load("n1234ab").await.unwrap();
+    assert_eq!(drifted.len(), 1);
+    assert_eq!(drifted[&url(2)], ["勇者", "朝の光"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn retranslations_keep_previous_versions() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-history-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let trans = JsonTranslationStore::new(dir.join("translations.json"));
+    let history = JsonHistoryStore::new(dir.join("history.json"));
+    let save = async |text: &str| {
+        save_translation(&trans, &history, "n1", "/n1/1/", text)
+            .await
+            .unwrap()
+    };
+    save("トリー来了。\n她笑了。").await;
+    // 译文相同时不记录历史
+    save("トリー来了。\n她笑了。").await;
+    assert!(history.load("n1", "/n1/1/").unwrap().is_empty());
+    save("托莉来了。\n她笑了。").await;
+    let revisions = history.load("n1", "/n1/1/").unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].text, "トリー来了。\n她笑了。");
+    assert_eq!(
+        diff_lines(&revisions[0].text, "托莉来了。\n她笑了。"),
+        [
+            DiffLine::Removed("トリー来了。".to_string()),
+            DiffLine::Added("托莉来了。".to_string()),
+            DiffLine::Same("她笑了。".to_string()),
+        ]
+    );
+    // 回滚后当前译文进入历史，可再次回滚
+    let restored = rollback_translation(&trans, &history, "n1", "/n1/1/", 0)
+        .await
+        .unwrap();
+    assert_eq!(restored, "トリー来了。\n她笑了。");
+    assert_eq!(
+        trans.load("n1", "/n1/1/").await.unwrap().as_deref(),
+        Some("トリー来了。\n她笑了。")
+    );
+    let revisions = history.load("n1", "/n1/1/").unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].text, "托莉来了。\n她笑了。");
+    assert!(
+        rollback_translation(&trans, &history, "n1", "/n1/1/", 1)
+            .await
+            .is_err()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn glossary_edits_are_applied_to_cached_translations() {
+    assert_eq!(
+        replace_term("纱耶和纱耶香见到了纱耶。", "纱耶", "沙耶", &["纱耶香"]),
+        ("沙耶和纱耶香见到了沙耶。".to_string(), 2)
+    );
+    // 已经改好的新译名不会被再次替换
+    assert_eq!(
+        replace_term("托莉亚与托莉", "托莉", "托莉亚", &["托莉亚"]),
+        ("托莉亚与托莉亚".to_string(), 1)
+    );
+    assert_eq!(replace_term("托莉", "", "x", &[]), ("托莉".to_string(), 0));
+
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-replace-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let trans = JsonTranslationStore::new(dir.join("translations.json"));
+    let history = JsonHistoryStore::new(dir.join("history.json"));
+    let kw = JsonStore::new(dir.join("keywords.json"));
+    kw.set("n1", "沙耶", "沙耶").await.unwrap();
+    kw.set("n1", "沙耶香", "纱耶香").await.unwrap();
+    trans.save("n1", "/1/", "纱耶笑了。").await.unwrap();
+    trans.save("n1", "/2/", "纱耶香来了。").await.unwrap();
+    trans
+        .save("n1", "/3/", "纱耶对纱耶香说：纱耶在这里。")
+        .await
+        .unwrap();
+    let preview = replace_in_translations(&trans, &history, &kw, "n1", "纱耶", "沙耶", false)
+        .await
+        .unwrap();
+    assert_eq!(preview, [("/1/".to_string(), 1), ("/3/".to_string(), 2)]);
+    assert_eq!(
+        trans.load("n1", "/1/").await.unwrap().unwrap(),
+        "纱耶笑了。"
+    );
+    let applied = replace_in_translations(&trans, &history, &kw, "n1", "纱耶", "沙耶", true)
+        .await
+        .unwrap();
+    assert_eq!(applied, preview);
+    assert_eq!(
+        trans.load("n1", "/1/").await.unwrap().unwrap(),
+        "沙耶笑了。"
+    );
+    assert_eq!(
+        trans.load("n1", "/3/").await.unwrap().unwrap(),
+        "沙耶对纱耶香说：沙耶在这里。"
+    );
+    assert_eq!(
+        trans.load("n1", "/2/").await.unwrap().unwrap(),
+        "纱耶香来了。"
+    );
+    // 改写前的译文可以回滚
+    assert_eq!(history.load("n1", "/1/").unwrap()[0].text, "纱耶笑了。");
+    assert!(history.load("n1", "/2/").unwrap().is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+}
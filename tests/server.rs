@@ -0,0 +1,233 @@
+//! `serve` 模式的 REST API
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use syosetu_rs::control::{ControlCommand, ControlRequest};
+use syosetu_rs::http::{FixtureFetcher, HttpOptions, build_client};
+use syosetu_rs::metrics;
+use syosetu_rs::server::{ServedNovel, router};
+use syosetu_rs::syosetu::NcodeSite;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+use common::{GatedBackend, NCODE_CHAPTER, NCODE_DIRECTORY, test_pipeline};
+
+#[tokio::test]
+async fn rest_api_serves_chapters_jobs_and_glossary() {
+    use reqwest::Method;
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-server-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let index = "https://ncode.syosetu.com/n0000aa/";
+    let fetcher = FixtureFetcher::new()
+        .with_page(index, NCODE_DIRECTORY)
+        .with_page("https://ncode.syosetu.com/n0000aa/2/", NCODE_CHAPTER)
+        .with_page("https://ncode.syosetu.com/n0000aa/3/", NCODE_CHAPTER);
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let release = CancellationToken::new();
+    let novel = |id: &str, url: &str| {
+        std::fs::create_dir_all(dir.join(id)).unwrap();
+        ServedNovel {
+            url: url.to_string(),
+            novel_id: id.to_string(),
+            pipeline: test_pipeline(
+                &dir.join(id),
+                Arc::new(NcodeSite::new().with_client(client.clone())),
+                Arc::new(GatedBackend {
+                    release: release.clone(),
+                }),
+            ),
+        }
+    };
+    // 第二部小说的目录页不存在
+    let novels = vec![
+        novel("n1", index),
+        novel("n2", "https://ncode.syosetu.com/n9999zz/"),
+        novel("n3", index),
+    ];
+    novels[0]
+        .pipeline
+        .trans_store
+        .save("n1", "https://ncode.syosetu.com/n0000aa/1/", "第一章译文")
+        .await
+        .unwrap();
+    let cancel = CancellationToken::new();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api/novels", listener.local_addr().unwrap());
+    // 同时只翻译一章
+    let (control_tx, control_rx) = tokio::sync::mpsc::unbounded_channel();
+    let app = router(
+        novels,
+        Arc::new(Semaphore::new(1)),
+        Some(control_rx),
+        cancel.clone(),
+    );
+    let shutdown = cancel.clone();
+    tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move { shutdown.cancelled().await })
+            .await
+    });
+    let http = reqwest::Client::new();
+    let call = |method: reqwest::Method, path: &str, body: Option<serde_json::Value>| {
+        let mut req = http.request(method, format!("{base}{path}"));
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        async move {
+            let resp = req.send().await.unwrap();
+            let status = resp.status().as_u16();
+            let text = resp.text().await.unwrap();
+            (
+                status,
+                serde_json::from_str(&text).unwrap_or(serde_json::Value::Null),
+            )
+        }
+    };
+    let statuses = || async {
+        let (_, list) = call(Method::GET, "/n1/chapters", None).await;
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["status"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let (status, list) = call(Method::GET, "", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(list[0]["id"], "n1");
+    assert_eq!(list[0]["cached"], 1);
+    assert_eq!(statuses().await, ["cached", "pending", "pending"]);
+    let (status, chapter) = call(Method::GET, "/n1/chapters/1", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(chapter["translation"], "第一章译文");
+
+    // 错误以 JSON 返回：不存在的小说、章节与译文为 404，抓取失败为 500
+    for path in ["/n9/chapters", "/n1/chapters/9", "/n1/chapters/2"] {
+        let (status, body) = call(Method::GET, path, None).await;
+        assert_eq!(status, 404, "{path}");
+        assert!(body["error"].as_str().unwrap().ends_with("not found"));
+    }
+    let (status, body) = call(Method::GET, "/n2/chapters", None).await;
+    assert_eq!(status, 500);
+    assert!(body["error"].is_string());
+
+    // 读取专有名词表失败时返回错误，章节不会一直显示为处理中
+    let keywords = dir.join("n1").join("keywords.json");
+    std::fs::write(&keywords, "{broken").unwrap();
+    let (status, _) = call(Method::POST, "/n1/chapters/2/translate", None).await;
+    assert_eq!(status, 500);
+    assert_eq!(statuses().await, ["cached", "pending", "pending"]);
+    std::fs::remove_file(&keywords).unwrap();
+
+    // 翻译失败时章节显示为 failed 并附上原因；第一章的页面不存在
+    let (status, _) = call(Method::POST, "/n3/chapters/1/translate", None).await;
+    assert_eq!(status, 202);
+    let failed = loop {
+        let (_, list) = call(Method::GET, "/n3/chapters", None).await;
+        if list[0]["status"] == "failed" {
+            break list[0].clone();
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert!(failed["error"].as_str().unwrap().contains("404"));
+
+    // 控制 socket 的命令：暂停期间排队的章节等待，可按路径取消
+    let control = |novel: Option<&str>, command| {
+        let (reply, rx) = tokio::sync::oneshot::channel();
+        let novel = novel.map(str::to_string);
+        control_tx
+            .send(ControlRequest {
+                command,
+                novel,
+                reply,
+            })
+            .unwrap();
+        async move { rx.await.unwrap() }
+    };
+    assert_eq!(
+        control(None, ControlCommand::Pause).await.unwrap()["paused"],
+        true
+    );
+    let err = control(None, ControlCommand::Queue("2".into())).await;
+    assert!(err.unwrap_err().contains("novel"));
+    let queued = control(Some("n1"), ControlCommand::Queue("2".into())).await;
+    assert_eq!(queued.unwrap()["status"], "processing");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(statuses().await, ["cached", "waiting", "pending"]);
+    let status = control(None, ControlCommand::Status).await.unwrap();
+    assert_eq!(status["waiting"][0]["novel"], "n1");
+    assert_eq!(status["paused"], true);
+    let path = "https://ncode.syosetu.com/n0000aa/2/";
+    let cancelled = control(Some("n1"), ControlCommand::Cancel(path.into())).await;
+    assert_eq!(cancelled.unwrap()["cancelled"], true);
+    while statuses().await[1] != "pending" {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    control(None, ControlCommand::Resume).await.unwrap();
+
+    // 已缓存的章节不再排队；名额用完时后来的章节等待，排队中的章节可以取消
+    let (status, body) = call(Method::POST, "/n1/chapters/1/translate", None).await;
+    assert_eq!((status, body["status"].as_str()), (200, Some("cached")));
+    let (status, body) = call(Method::POST, "/n1/chapters/2/translate", None).await;
+    assert_eq!((status, body["status"].as_str()), (202, Some("processing")));
+    let (status, _) = call(Method::POST, "/n1/chapters/2/translate", None).await;
+    assert_eq!(status, 202);
+    while statuses().await[1] != "processing" {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    call(Method::POST, "/n1/chapters/3/translate", None).await;
+    assert_eq!(statuses().await, ["cached", "processing", "waiting"]);
+    assert!(metrics::render().contains("syosetu_queue_depth 2"));
+    let (_, body) = call(Method::DELETE, "/n1/chapters/3/translate", None).await;
+    assert_eq!(body["cancelled"], true);
+    while statuses().await[2] != "pending" {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let (_, body) = call(Method::DELETE, "/n1/chapters/2/translate", None).await;
+    assert_eq!(body["cancelled"], true);
+    while statuses().await[1] != "pending" {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let (_, body) = call(Method::DELETE, "/n1/chapters/2/translate", None).await;
+    assert_eq!(body["cancelled"], false);
+
+    // 完成的任务保存译文
+    call(Method::POST, "/n1/chapters/3/translate", None).await;
+    release.cancel();
+    while statuses().await[2] != "cached" {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let (_, chapter) = call(Method::GET, "/n1/chapters/3", None).await;
+    assert!(chapter["translation"].as_str().unwrap().contains("译文"));
+
+    // 专有名词表的增删查
+    let (_, glossary) = call(Method::GET, "/n1/glossary", None).await;
+    assert_eq!(glossary, serde_json::json!({}));
+    let term = "/n1/glossary/%E5%8B%87%E8%80%85";
+    let (status, body) = call(
+        Method::PUT,
+        term,
+        Some(serde_json::json!({"translation": "勇者大人"})),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["term"], "勇者");
+    let (_, glossary) = call(Method::GET, "/n1/glossary", None).await;
+    assert_eq!(glossary, serde_json::json!({"勇者": "勇者大人"}));
+    let (status, _) = call(Method::PUT, term, Some(serde_json::json!({}))).await;
+    assert_eq!(status, 422);
+    let (status, _) = call(Method::DELETE, term, None).await;
+    assert_eq!(status, 204);
+    let (status, body) = call(Method::DELETE, term, None).await;
+    assert_eq!(status, 404);
+    assert_eq!(body["error"], "term 勇者 not found");
+    cancel.cancel();
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,597 @@
+//! 各站点目录、正文与作品信息的解析，以及站点注册
+
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use syosetu_rs::export::{ExportOptions, export_epub};
+use syosetu_rs::http::{FixtureFetcher, HttpOptions, build_client};
+use syosetu_rs::memory::{JsonTranslationStore, TranslationStore};
+use syosetu_rs::syosetu::{
+    Chapter, ChapterSections, ConfigSite, EpubSite, ExtractError, NCODE_ORIGIN, NOTE_LABELS_JA,
+    NOTE_LABELS_ZH, NOVEL18_ORIGIN, NcodeSite, NovelSite, SiteDefinition, SiteOptions,
+    SiteRegistry, StatusError, decode_japanese, illustration_index, illustrations_to_links,
+    is_transient, joined_body, narou_api_chapters, ncode_from_url, parse_alphapolis_chapter,
+    parse_alphapolis_directory, parse_aozora_card, parse_aozora_chapter, parse_aozora_directory,
+    parse_config_directory, parse_generic_chapter, parse_generic_directory, parse_kakuyomu_chapter,
+    parse_kakuyomu_directory, parse_kakuyomu_info, parse_narou_api, parse_narou_bookmarks,
+    parse_ncode_chapter, parse_ncode_directory, parse_ncode_info, parse_ncode_page_count,
+    parse_ncode_sections, parse_ncode_short_story, parse_novelup_chapter, parse_novelup_directory,
+    parse_novelup_next_page, parse_org_chapter, parse_org_directory, parse_pixiv_novel,
+    parse_pixiv_series, parse_xhtml_text, ruby_to_parens, split_text_chapters, strip_ruby,
+};
+use syosetu_rs::translate::{DeepSeekTranslator, TranslationBackend, TranslationContext};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+use common::{
+    ALPHAPOLIS_CHAPTER, ALPHAPOLIS_DIRECTORY, AOZORA_CARD, AOZORA_CARD_URL, AOZORA_TEXT,
+    AOZORA_TEXT_URL, GENERIC_CHAPTER, GENERIC_DIRECTORY, GENERIC_URL, KAKUYOMU_CHAPTER,
+    KAKUYOMU_DIRECTORY, KAKUYOMU_URL, NAROU_BOOKMARKS, NCODE_CHAPTER, NCODE_DIRECTORY,
+    NOVELUP_CHAPTER, NOVELUP_DIRECTORY, NOVELUP_URL, ORG_CHAPTER, ORG_DIRECTORY, ORG_URL,
+};
+
+#[test]
+fn parses_ncode_fixtures() {
+    let chapters = parse_ncode_directory(NCODE_DIRECTORY, NCODE_ORIGIN).unwrap();
+    let paths: Vec<_> = chapters.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "https://ncode.syosetu.com/n0000aa/1/",
+            "https://ncode.syosetu.com/n0000aa/2/",
+            "https://ncode.syosetu.com/n0000aa/3/",
+        ]
+    );
+    assert_eq!(chapters[0].title, "プロローグ");
+    assert_eq!(chapters[2].title, "第二話旅立ち");
+    assert_eq!(chapters[0].published.as_deref(), Some("2020/01/01 00:00"));
+    assert_eq!(chapters[0].stamp(), Some("2020/01/01 00:00"));
+    assert_eq!(chapters[1].stamp(), Some("2020/03/04 12:00"));
+    assert_eq!(chapters[2].stamp(), None);
+    assert!(chapters.iter().all(|c| c.arc.as_deref() == Some("第一章")));
+
+    let body = parse_ncode_chapter(NCODE_CHAPTER).unwrap();
+    assert!(body.starts_with("朝の光が差し込んでいた。"));
+}
+
+#[test]
+fn parses_org_fixtures() {
+    let chapters = parse_org_directory(ORG_DIRECTORY, ORG_URL).unwrap();
+    let paths: Vec<_> = chapters.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "https://syosetu.org/novel/12345/1.html",
+            "https://syosetu.org/novel/12345/2.html",
+            "https://syosetu.org/novel/12345/3.html",
+        ]
+    );
+    assert_eq!(chapters[2].title, "第3話");
+
+    let body = parse_org_chapter(ORG_CHAPTER).unwrap();
+    assert_eq!(body, "夜の街は静かだった。\n誰もいない。");
+}
+
+#[test]
+fn parses_alphapolis_fixtures() {
+    let chapters = parse_alphapolis_directory(ALPHAPOLIS_DIRECTORY).unwrap();
+    let paths: Vec<_> = chapters.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "https://www.alphapolis.co.jp/novel/111111111/222222222/episode/3000001",
+            "https://www.alphapolis.co.jp/novel/111111111/222222222/episode/3000002",
+        ]
+    );
+    assert_eq!(chapters[1].title, "第1話 旅立ち");
+
+    let body = parse_alphapolis_chapter(ALPHAPOLIS_CHAPTER).unwrap();
+    assert_eq!(body, "城の鐘が鳴った。\n王女は目を覚ました。");
+}
+
+#[test]
+fn splits_plain_text_by_headings() {
+    let text = "あらすじ\n\n# プロローグ\n始まり。\n第1話　出会い\n本文一。\n第十二章\n本文二。\n第一話を読んだ彼は笑った。\n";
+    let chapters = split_text_chapters(text);
+    let titles: Vec<_> = chapters.iter().map(|(t, _)| t.as_str()).collect();
+    assert_eq!(titles, ["プロローグ", "第1話　出会い", "第十二章"]);
+    assert_eq!(chapters[0].1, "あらすじ\n\n始まり。\n");
+    assert_eq!(chapters[2].1, "本文二。\n第一話を読んだ彼は笑った。\n");
+    assert!(split_text_chapters("見出しのない短編。\n").is_empty());
+}
+
+#[tokio::test]
+async fn reads_epub_spine_and_text() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-epub-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonTranslationStore::new(dir.join("translations.json"));
+    let chapters = [
+        Chapter {
+            path: "a".into(),
+            title: "第一章".into(),
+            ..Default::default()
+        },
+        Chapter {
+            path: "b".into(),
+            title: "第二章".into(),
+            ..Default::default()
+        },
+    ];
+    store.save("n", "a", "春の話。\n二行目。").await.unwrap();
+    store.save("n", "b", "夏の話。").await.unwrap();
+    let book = dir.join("book.epub");
+    export_epub(
+        &book,
+        "本",
+        "n",
+        &chapters,
+        &store,
+        &ExportOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let site = EpubSite::new();
+    let cancel = Default::default();
+    let url = book.to_string_lossy().to_string();
+    let listed = site.fetch_directory(&url, &cancel).await.unwrap();
+    let titles: Vec<_> = listed.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["第一章", "第二章"]);
+    assert!(listed[0].path.ends_with("book.epub!/OEBPS/c0001.xhtml"));
+    let text = site.fetch_chapter(&listed[0].path, &cancel).await.unwrap();
+    assert_eq!(text, "第一章\n春の話。\n二行目。");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parses_config_defined_directories() {
+    let mut definition: SiteDefinition = toml::from_str(
+        r#"
+        name = "ncode-copy"
+        url_pattern = '^https://ncode\.syosetu\.com/'
+        directory_selector = "a.p-eplist__subtitle"
+        body_selector = "div.p-novel__body"
+        "#,
+    )
+    .unwrap();
+    let url = "https://ncode.syosetu.com/n0000aa/";
+    let chapters = parse_config_directory(&definition, NCODE_DIRECTORY, url).unwrap();
+    let paths: Vec<_> = chapters.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "https://ncode.syosetu.com/n0000aa/1/",
+            "https://ncode.syosetu.com/n0000aa/2/",
+            "https://ncode.syosetu.com/n0000aa/3/",
+        ]
+    );
+    assert_eq!(chapters[0].title, "プロローグ");
+
+    definition.base_url = Some("https://mirror.example/".into());
+    let chapters = parse_config_directory(&definition, NCODE_DIRECTORY, url).unwrap();
+    assert_eq!(chapters[0].path, "https://mirror.example/n0000aa/1/");
+
+    definition.directory_selector = "a[".into();
+    assert!(matches!(
+        parse_config_directory(&definition, NCODE_DIRECTORY, url),
+        Err(ExtractError::Selector(_))
+    ));
+}
+
+#[test]
+fn parses_generic_fixtures() {
+    let chapters = parse_generic_directory(GENERIC_DIRECTORY, GENERIC_URL).unwrap();
+    let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["第一話　春", "第二話　夏", "第三話　秋"]);
+    assert_eq!(chapters[1].path, "https://example.com/novels/long/02.html");
+
+    // 没有目录链接时整页作为一章
+    let single =
+        parse_generic_directory(GENERIC_CHAPTER, "https://example.com/novels/long/01.html")
+            .unwrap();
+    assert_eq!(single.len(), 1);
+    assert_eq!(single[0].title, "第一話　春");
+
+    let body = parse_generic_chapter(GENERIC_CHAPTER).unwrap();
+    assert_eq!(
+        body,
+        "桜が咲いた。\n少年\nは走り出した。\n遠くで鐘が鳴っている。"
+    );
+}
+
+#[test]
+fn parses_novelup_fixtures() {
+    let chapters = parse_novelup_directory(NOVELUP_DIRECTORY).unwrap();
+    let paths: Vec<_> = chapters.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "https://novelup.plus/story/123456789/900000001",
+            "https://novelup.plus/story/123456789/900000002",
+        ]
+    );
+    assert_eq!(chapters[1].title, "第2話　つづき");
+    assert_eq!(
+        parse_novelup_next_page(NOVELUP_DIRECTORY, &format!("{NOVELUP_URL}?p=1")).as_deref(),
+        Some("https://novelup.plus/story/123456789?p=2")
+    );
+    assert_eq!(parse_novelup_next_page(NOVELUP_CHAPTER, NOVELUP_URL), None);
+
+    let body = parse_novelup_chapter(NOVELUP_CHAPTER).unwrap();
+    assert_eq!(body, "雨が降っていた。\n傘はなかった。");
+}
+
+#[test]
+fn parses_kakuyomu_fixtures() {
+    let chapters = parse_kakuyomu_directory(KAKUYOMU_DIRECTORY, KAKUYOMU_URL).unwrap();
+    let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["第1話　出会い", "第2話　別れ", "第3話　再会"]);
+    assert_eq!(
+        chapters[2].path,
+        "https://kakuyomu.jp/works/1177354054000000001/episodes/1177354054000000012"
+    );
+    let arcs: Vec<_> = chapters.iter().map(|c| c.arc.as_deref()).collect();
+    assert_eq!(arcs, [Some("第一章"), Some("第一章"), Some("第二章")]);
+    let info = parse_kakuyomu_info(KAKUYOMU_DIRECTORY, KAKUYOMU_URL).unwrap();
+    assert_eq!(info.title, "テスト作品");
+    assert_eq!(info.author, "テスト作者");
+    assert_eq!(info.synopsis, "平凡な少年の物語。");
+    assert_eq!(info.tags, ["異世界", "ファンタジー"]);
+
+    // 旧版页面没有内嵌 JSON，直接读取链接
+    let legacy = r#"<a class="widget-toc-episode-episodeTitle" href="/works/1/episodes/2">
+        <span class="widget-toc-episode-titleLabel">プロローグ</span>
+        <time>2020年1月1日</time></a>"#;
+    let chapters = parse_kakuyomu_directory(legacy, "https://kakuyomu.jp/works/1").unwrap();
+    assert_eq!(chapters[0].path, "https://kakuyomu.jp/works/1/episodes/2");
+    assert_eq!(chapters[0].title, "プロローグ");
+
+    let body = parse_kakuyomu_chapter(KAKUYOMU_CHAPTER).unwrap();
+    assert_eq!(body, "春の風が吹いていた。\n彼女は振り返った。");
+}
+
+#[test]
+fn parses_pixiv_responses() {
+    let series = r#"{"error":false,"message":"","body":[
+        {"id":"20000001","title":"第一話","available":true},
+        {"id":"20000002","title":"非公開","available":false},
+        {"id":"20000003","title":" 第三話 ","available":true}]}"#;
+    let chapters = parse_pixiv_series(series).unwrap();
+    let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["第一話", "第三話"]);
+    assert_eq!(
+        chapters[1].path,
+        "https://www.pixiv.net/novel/show.php?id=20000003"
+    );
+
+    let novel = r#"{"error":false,"body":{"title":"第一話","content":"[chapter:はじまり]\n[[rb:魔法 > まほう]]が使えた。[pixivimage:123-1]\n[newpage]\n[[jumpuri:公式 > https://example.com]]を見た。"}}"#;
+    assert_eq!(
+        parse_pixiv_novel(novel).unwrap(),
+        "はじまり\n｜魔法《まほう》が使えた。\n公式を見た。"
+    );
+    assert_eq!(
+        parse_pixiv_novel(
+            r#"{"error":true,"message":"該当作品は削除されたか、存在しない作品IDです。","body":[]}"#
+        ),
+        Err(ExtractError::BodyNotFound)
+    );
+}
+
+#[test]
+fn parses_aozora_fixtures() {
+    assert_eq!(
+        parse_aozora_card(AOZORA_CARD, AOZORA_CARD_URL).unwrap(),
+        AOZORA_TEXT_URL
+    );
+
+    let chapters = parse_aozora_directory(AOZORA_TEXT, AOZORA_TEXT_URL).unwrap();
+    let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["上", "下"]);
+    assert_eq!(chapters[1].path, format!("{AOZORA_TEXT_URL}#midashi30"));
+
+    // 见出し前的内容归入第一章，振り仮名转为注音标记，注记被去除，外字被替换
+    let first = parse_aozora_chapter(AOZORA_TEXT, Some("midashi10")).unwrap();
+    assert_eq!(
+        first,
+        "はしがき。\n上\n｜吾輩《わがはい》は猫である。\n〔鬼＋未〕が出た。\n一\nどこで生れたか挘見当がつかぬ。"
+    );
+    let second = parse_aozora_chapter(AOZORA_TEXT, Some("midashi30")).unwrap();
+    assert_eq!(second, "下\n名前はまだ無い。");
+    assert_eq!(
+        parse_aozora_chapter(AOZORA_TEXT, Some("missing")),
+        Err(ExtractError::BodyNotFound)
+    );
+    assert!(
+        parse_aozora_chapter(AOZORA_TEXT, None)
+            .unwrap()
+            .ends_with("名前はまだ無い。")
+    );
+}
+
+#[test]
+fn decodes_shift_jis_pages() {
+    let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode("吾輩は猫である");
+    assert_eq!(decode_japanese(&bytes), "吾輩は猫である");
+    assert_eq!(decode_japanese("猫".as_bytes()), "猫");
+
+    // 声明为 EUC-JP 的页面不会被误当作 Shift_JIS
+    let html = "<meta http-equiv=\"Content-Type\" content=\"text/html; charset=EUC-JP\">吾輩";
+    let (bytes, _, _) = encoding_rs::EUC_JP.encode(html);
+    assert_eq!(decode_japanese(&bytes), html);
+}
+
+#[test]
+fn r18_directory_links_use_novel18_origin() {
+    let html = r#"<a class="p-eplist__subtitle" href="/n9999zz/1/">ok</a>"#;
+    let chapters = parse_ncode_directory(html, NOVEL18_ORIGIN).unwrap();
+    assert_eq!(chapters[0].path, "https://novel18.syosetu.com/n9999zz/1/");
+}
+
+#[test]
+fn registry_selects_site_by_url() {
+    let mut registry = SiteRegistry::new();
+    registry.register_builtin(
+        &SiteOptions::default(),
+        build_client(&HttpOptions::default()).unwrap(),
+    );
+    let name = |url: &str| registry.find(url).unwrap().name().to_string();
+    assert_eq!(name("https://ncode.syosetu.com/n1234ab/"), "ncode");
+    assert_eq!(name("https://novel18.syosetu.com/n1234ab/"), "novel18");
+    assert_eq!(name(ORG_URL), "hameln");
+    assert_eq!(name("https://kakuyomu.jp/works/1"), "kakuyomu");
+    assert_eq!(name("https://example.com/novel/"), "generic");
+    let err = registry.find("not-a-site").err().unwrap().to_string();
+    assert!(err.contains("supported sites"));
+    assert!(err.contains("syosetu.org"));
+}
+
+#[tokio::test]
+async fn sites_and_translator_replay_fixtures() {
+    let chapter_url = "https://ncode.syosetu.com/n1234ab/1/";
+    let reply = r#"{"choices":[{"message":{"content":"译文"}}]}"#;
+    let fetcher = FixtureFetcher::new()
+        .with_page(chapter_url, NCODE_CHAPTER)
+        .with_json("https://api.deepseek.com/chat/completions", reply);
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let cancel = CancellationToken::new();
+    let site = NcodeSite::new().with_client(client.clone());
+    let text = site.fetch_chapter(chapter_url, &cancel).await.unwrap();
+    assert_eq!(text, parse_ncode_chapter(NCODE_CHAPTER).unwrap());
+    let translator = DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string())
+        .with_client(client.clone());
+    assert_eq!(
+        translator
+            .translate_text(&text, &TranslationContext::default(), &cancel)
+            .await
+            .unwrap(),
+        "译文"
+    );
+    // 未登记的地址返回 404
+    let missing = client
+        .get("https://ncode.syosetu.com/n1234ab/2/")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
+
+#[test]
+fn joined_body_drops_author_notes() {
+    let sections = ChapterSections {
+        preface: Some("前言".to_string()),
+        body: "第一段\n第二段".to_string(),
+        afterword: Some("后记".to_string()),
+        ..Default::default()
+    };
+    let text = sections.join(NOTE_LABELS_ZH);
+    assert_eq!(joined_body(&text, NOTE_LABELS_ZH), "第一段\n第二段");
+    assert_eq!(joined_body("正文", NOTE_LABELS_ZH), "正文");
+}
+
+#[tokio::test]
+async fn failed_pages_are_retried_only_by_the_client() {
+    // 始终返回 503，并记录收到的请求数
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicU32::new(0));
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            counter.fetch_add(1, Ordering::SeqCst);
+            let _ = socket
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\nConnection: close\r\n\r\nbusy")
+                .await;
+        }
+    });
+    let url = format!("http://{addr}/n1/1/");
+    let cancel = CancellationToken::new();
+    // `--retries 1` 即不重试，`--retries 3` 共请求三次
+    for (max_retries, expected) in [(0, 1), (2, 3)] {
+        requests.store(0, Ordering::SeqCst);
+        let client = build_client(&HttpOptions {
+            max_retries,
+            retry_delay: Duration::ZERO,
+            ..Default::default()
+        })
+        .unwrap();
+        let definition: SiteDefinition = toml::from_str(
+            r#"
+            name = "local"
+            url_pattern = '^http://127\.0\.0\.1'
+            directory_selector = "a"
+            body_selector = "div"
+            "#,
+        )
+        .unwrap();
+        let mut registry = SiteRegistry::new();
+        registry.register(Arc::new(
+            ConfigSite::new(definition)
+                .unwrap()
+                .with_client(registry.crawl_client(client)),
+        ));
+        let site = registry.find(&url).unwrap();
+        let err = site.fetch_chapter(&url, &cancel).await.unwrap_err();
+        // 错误页不会被当作正文解析
+        let status = err.downcast_ref::<StatusError>().unwrap();
+        assert_eq!(status.status, 503);
+        assert!(is_transient(&err));
+        assert_eq!(requests.load(Ordering::SeqCst), expected);
+    }
+}
+
+#[test]
+fn ncode_page_count_reads_pager() {
+    let html = r#"<div class="c-pager">
+        <a href="/n1234ab/?p=2" class="c-pager__item c-pager__item--next">次へ</a>
+        <a href="/n1234ab/?p=7" class="c-pager__item c-pager__item--last">最後へ</a>
+    </div>"#;
+    assert_eq!(parse_ncode_page_count(html), 7);
+    assert_eq!(parse_ncode_page_count(NCODE_DIRECTORY), 1);
+}
+
+#[test]
+fn short_story_index_is_a_single_chapter() {
+    let url = "https://ncode.syosetu.com/n5555cc/";
+    let chapter = parse_ncode_short_story(NCODE_CHAPTER, url).unwrap();
+    assert_eq!(chapter.path, url);
+    assert_eq!(chapter.title, "第一話　出会い");
+    assert!(parse_ncode_short_story("<div class=\"p-eplist\"></div>", url).is_none());
+}
+
+#[test]
+fn parses_narou_api_response() {
+    let json = r#"[{"allcount":1},{"title":"テスト作品","ncode":"N1234AB","writer":"作者",
+        "general_all_no":3,"novel_type":1,"general_lastup":"2024-05-01 12:00:00",
+        "novelupdated_at":"2024-05-02 08:00:00","story":"あらすじ。","keyword":"異世界 R15"}]"#;
+    let info = parse_narou_api(json).unwrap();
+    assert_eq!(info.ncode, "n1234ab");
+    assert_eq!(info.writer, "作者");
+    assert_eq!(info.keywords, ["異世界", "R15"]);
+    let chapters = narou_api_chapters(&info, NCODE_ORIGIN);
+    assert_eq!(chapters.len(), 3);
+    assert_eq!(chapters[2].path, "https://ncode.syosetu.com/n1234ab/3/");
+    assert!(matches!(
+        parse_narou_api(r#"[{"allcount":0}]"#),
+        Err(ExtractError::BodyNotFound)
+    ));
+    assert_eq!(
+        ncode_from_url("https://ncode.syosetu.com/N1234AB/5/").as_deref(),
+        Some("n1234ab")
+    );
+}
+
+#[test]
+fn parses_narou_bookmarks() {
+    let bookmarks = parse_narou_bookmarks(NAROU_BOOKMARKS);
+    assert_eq!(bookmarks.len(), 2);
+    assert_eq!(bookmarks[0].url, "https://ncode.syosetu.com/n1234ab/");
+    assert_eq!(bookmarks[0].title, "異世界で始める翻訳生活");
+    assert_eq!(bookmarks[0].shiori, Some(37));
+    assert_eq!(bookmarks[1].url, "https://novel18.syosetu.com/n9876zz/");
+    assert_eq!(bookmarks[1].shiori, None);
+}
+
+#[test]
+fn parses_ncode_novel_info() {
+    let html = r#"<h1 class="p-novel__title">テスト小説</h1>
+        <div class="p-novel__author">作者：<a href="/user/1/">山田</a></div>
+        <div id="novel_ex" class="p-novel__summary">一行目。<br>二行目。</div>"#;
+    let info = parse_ncode_info(html).unwrap();
+    assert_eq!(info.title, "テスト小説");
+    assert_eq!(info.author, "山田");
+    assert_eq!(info.synopsis, "一行目。\n二行目。");
+    assert!(info.tags.is_empty());
+    // 旧版页面只有 `<title>` 时也能取到作品名
+    assert_eq!(
+        parse_ncode_info(NCODE_DIRECTORY).unwrap().title,
+        "テスト小説"
+    );
+}
+
+#[test]
+fn ncode_author_notes_are_separate_sections() {
+    let html = r#"<div class="p-novel__body">
+        <div class="js-novel-text p-novel__text p-novel__text--preface"><p>いつも読んでいただき感謝です。</p></div>
+        <div class="js-novel-text p-novel__text"><p>本文一。</p><p>本文二。</p></div>
+        <div class="js-novel-text p-novel__text p-novel__text--afterword"><p>次回は来週です。</p></div>
+    </div>"#;
+    let sections = parse_ncode_sections(html).unwrap();
+    assert_eq!(
+        sections.preface.as_deref(),
+        Some("いつも読んでいただき感謝です。")
+    );
+    assert_eq!(sections.body, "本文一。\n本文二。");
+    assert_eq!(sections.afterword.as_deref(), Some("次回は来週です。"));
+    assert_eq!(parse_ncode_chapter(html).unwrap(), "本文一。\n本文二。");
+    let joined = sections.join(NOTE_LABELS_JA);
+    assert!(joined.starts_with("【前書き】\nいつも"));
+    assert!(joined.ends_with("【後書き】\n次回は来週です。"));
+
+    let plain = parse_ncode_sections(NCODE_CHAPTER).unwrap();
+    assert!(plain.preface.is_none() && plain.afterword.is_none());
+    assert_eq!(plain.join(NOTE_LABELS_JA), plain.body);
+}
+
+#[test]
+fn ruby_is_kept_as_markup() {
+    let html = r#"<div class="p-novel__body">
+        <p>彼は<ruby>魔法<rp>(</rp><rt>まほう</rt><rp>)</rp></ruby>を使った。</p>
+        <p><ruby><rb>聖剣</rb><rt>エクスカリバー</rt></ruby></p>
+        <p>次の行。</p></div>"#;
+    let body = parse_ncode_chapter(html).unwrap();
+    assert_eq!(
+        body,
+        "彼は｜魔法《まほう》を使った。\n｜聖剣《エクスカリバー》\n次の行。"
+    );
+    assert_eq!(strip_ruby(&body), "彼は魔法を使った。\n聖剣\n次の行。");
+    assert_eq!(
+        ruby_to_parens("｜圣剑《エクスカリバー》"),
+        "圣剑（エクスカリバー）"
+    );
+    // 没有 `｜` 的书名号不是注音
+    assert_eq!(strip_ruby("他读了《三国演义》"), "他读了《三国演义》");
+    // EPUB 的 XHTML 页面同样保留注音
+    let page = r#"<html><body><nav>目次</nav>
+        <p>彼は<ruby>魔法<rp>(</rp><rt>まほう</rt><rp>)</rp></ruby>を使った。</p>
+        <p><ruby><rb>聖剣</rb><rt>エクスカリバー</rt></ruby></p>
+        <p>次の行。</p></body></html>"#;
+    assert_eq!(parse_xhtml_text(page).unwrap(), body);
+}
+
+#[test]
+fn illustrations_become_numbered_placeholders() {
+    let html = r#"<div class="js-novel-text p-novel__text">
+        <p>扉を開けた。</p>
+        <p><a href="//12345.mitemin.net/i100/"><img src="//12345.mitemin.net/userpageimage/viewimage/icode/i100/" alt="挿絵(By みてみん)"></a></p>
+        <p>そこには<img data-src="/img/b.png" src="placeholder.gif">があった。</p></div>"#;
+    let sections = parse_ncode_sections(html)
+        .unwrap()
+        .with_illustrations("https://ncode.syosetu.com/n1234ab/1/");
+    assert_eq!(
+        sections.body,
+        "扉を開けた。\n[挿絵 #1]\nそこには\n[挿絵 #2]\nがあった。"
+    );
+    assert_eq!(
+        sections.images,
+        [
+            "https://12345.mitemin.net/userpageimage/viewimage/icode/i100/",
+            "https://ncode.syosetu.com/img/b.png",
+        ]
+    );
+    assert_eq!(illustration_index("[挿絵 #2]"), Some(1));
+    assert_eq!(illustration_index("[插图 #1]"), Some(0));
+    assert_eq!(illustration_index("见[挿絵 #1]"), None);
+    assert_eq!(
+        illustrations_to_links("推开门。\n[插图 #1]", &sections.images),
+        "推开门。\n[插图 #1] https://12345.mitemin.net/userpageimage/viewimage/icode/i100/"
+    );
+}
@@ -0,0 +1,654 @@
+//! 译文、专有名词表等存储的读写、压缩、加密与文件锁
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use syosetu_rs::crypto::{Cipher, ENCRYPTION_FILE};
+use syosetu_rs::http::{FixtureFetcher, HttpOptions, build_client};
+use syosetu_rs::library::{Library, NovelFile};
+use syosetu_rs::memory::{
+    CategoryStore, Character, CharacterStore, Conflict, ConflictStore, DirTranslationStore,
+    FileLock, JsonCategoryStore, JsonCharacterStore, JsonConflictStore, JsonHistoryStore,
+    JsonSourceStore, JsonStore, JsonSummaryStore, JsonTitleStore, JsonTranslationStore,
+    KeywordStore, MergeStrategy, MsgpackTranslationStore, SummaryStore, TitleStore,
+    TranslationStore, merge_characters, merge_glossaries,
+};
+use syosetu_rs::pipeline::{Pipeline, process_chapter, save_translation};
+use syosetu_rs::syosetu::NcodeSite;
+use syosetu_rs::translate::{DeepSeekTranslator, KeywordCategory, parse_characters};
+use tokio_util::sync::CancellationToken;
+
+use common::{NCODE_CHAPTER, test_pipeline};
+
+#[test]
+fn character_sheet_merges_model_updates() {
+    let output = "```json\n{\"characters\":[{\"name\":\"トリー\",\"translated\":\"托莉\",\"gender\":\"女\"},{\"name\":\"アル\",\"translated\":\"阿尔\",\"speech\":\"自称俺\"}]}\n```";
+    let updates = parse_characters(output);
+    assert_eq!(updates.len(), 2);
+    let mut characters = vec![Character {
+        name: "トリー".to_string(),
+        translated: "托莉".to_string(),
+        relationships: "アルの妹".to_string(),
+        ..Default::default()
+    }];
+    merge_characters(&mut characters, updates);
+    assert_eq!(characters.len(), 2);
+    // 已有人物只覆盖非空字段
+    assert_eq!(characters[0].gender, "女");
+    assert_eq!(characters[0].relationships, "アルの妹");
+    assert_eq!(characters[1].fields()[3], "自称俺");
+
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-characters-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = JsonCharacterStore::new(dir.join("characters.json"));
+    assert!(store.load("n1234ab").unwrap().is_empty());
+    store.save("n1234ab", &characters).unwrap();
+    assert_eq!(store.load("n1234ab").unwrap(), characters);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn chapters_can_be_stored_one_file_each() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-dirstore-{}", std::process::id()));
+    let store = DirTranslationStore::new(dir.join("data"));
+    assert_eq!(store.load("n1", "/n1/1/").await.unwrap(), None);
+    assert!(store.list("n1").await.unwrap().is_empty());
+    store.save("n1", "/n1/1/", "第一章").await.unwrap();
+    store.save("n1", "/n1/2/", "第二章").await.unwrap();
+    store.save("n1", "/n1/1/", "第一章（重译）").await.unwrap();
+    assert_eq!(
+        store.load("n1", "/n1/1/").await.unwrap().as_deref(),
+        Some("第一章（重译）")
+    );
+    assert_eq!(store.list("n1").await.unwrap(), ["/n1/1/", "/n1/2/"]);
+    let files: Vec<_> = std::fs::read_dir(dir.join("data/n1")).unwrap().collect();
+    assert_eq!(files.len(), 3);
+    // 不适合作为文件名的字符被替换
+    store.save("../x?id=1", "/1/", "正文").await.unwrap();
+    assert!(dir.join("data/___x_id_1").is_dir());
+    assert_eq!(store.list("../x?id=1").await.unwrap(), ["/1/"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn stored_translations_can_be_compressed() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-zstd-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let json = dir.join("translations.json");
+    let zst = dir.join("translations.json.zst");
+    JsonTranslationStore::new(&json)
+        .save("n1", "/n1/1/", "第一章")
+        .await
+        .unwrap();
+    // 开启压缩后仍能读取未压缩的旧文件，迁移后只剩压缩文件
+    let store = JsonTranslationStore::new(&json).with_compression(true);
+    assert_eq!(
+        store.load("n1", "/n1/1/").await.unwrap().as_deref(),
+        Some("第一章")
+    );
+    assert!(store.migrate().await.unwrap());
+    assert!(!store.migrate().await.unwrap());
+    assert!(!json.exists() && zst.exists());
+    assert!(!std::fs::read(&zst).unwrap().starts_with(b"{"));
+    store.save("n1", "/n1/2/", "第二章").await.unwrap();
+    // 关闭压缩时转换回来
+    let store = JsonTranslationStore::new(&json);
+    assert!(store.migrate().await.unwrap());
+    assert!(json.exists() && !zst.exists());
+    assert_eq!(
+        store.load("n1", "/n1/2/").await.unwrap().as_deref(),
+        Some("第二章")
+    );
+
+    let root = dir.join("data");
+    let plain = DirTranslationStore::new(&root);
+    plain.save("n1", "/n1/1/", "第一章").await.unwrap();
+    plain.save("n1", "/n1/2/", "第二章").await.unwrap();
+    let store = DirTranslationStore::new(&root).with_compression(true);
+    store.save("n1", "/n1/2/", "第二章（重译）").await.unwrap();
+    assert_eq!(store.list("n1").await.unwrap(), ["/n1/1/", "/n1/2/"]);
+    assert_eq!(store.migrate().await.unwrap(), 1);
+    assert_eq!(store.migrate().await.unwrap(), 0);
+    let mut files: Vec<String> = std::fs::read_dir(root.join("n1"))
+        .unwrap()
+        .map(|f| f.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files.len(), 3);
+    assert!(files[..2].iter().all(|f| f.ends_with(".txt.zst")));
+    assert_eq!(files[2], "index.json");
+    assert_eq!(
+        plain.load("n1", "/n1/2/").await.unwrap().as_deref(),
+        Some("第二章（重译）")
+    );
+    assert_eq!(plain.migrate().await.unwrap(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn stored_translations_can_be_encrypted() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-encrypt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cipher = Arc::new(Cipher::open(&dir, "secret").unwrap());
+    assert!(dir.join(ENCRYPTION_FILE).exists());
+    // 之后必须使用同一口令
+    assert!(Cipher::open(&dir, "wrong").is_err());
+    let cipher_again = Cipher::open(&dir, "secret").unwrap();
+    let sealed = cipher.encrypt("第一章".as_bytes()).unwrap();
+    assert_eq!(cipher_again.decrypt(&sealed).unwrap(), "第一章".as_bytes());
+
+    let json = dir.join("translations.json");
+    JsonTranslationStore::new(&json)
+        .save("n1", "/n1/1/", "第一章")
+        .await
+        .unwrap();
+    let store = JsonTranslationStore::new(&json)
+        .with_compression(true)
+        .with_encryption(Some(cipher.clone()));
+    assert!(store.migrate().await.unwrap());
+    let encrypted = dir.join("translations.json.zst.enc");
+    assert!(!json.exists() && encrypted.exists());
+    store.save("n1", "/n1/2/", "第二章").await.unwrap();
+    let bytes = std::fs::read(&encrypted).unwrap();
+    assert!(!bytes.windows(3).any(|w| w == "第".as_bytes()));
+
+    // 未提供口令时读取报错，保存也不会覆盖加密的文件
+    let plain = JsonTranslationStore::new(&json);
+    assert!(plain.load_all().await.is_err());
+    assert!(plain.save("n1", "/n1/3/", "第三章").await.is_err());
+    assert!(encrypted.exists());
+
+    let root = dir.join("data");
+    DirTranslationStore::new(&root)
+        .save("n1", "/n1/1/", "第一章")
+        .await
+        .unwrap();
+    let store = DirTranslationStore::new(&root).with_encryption(Some(cipher));
+    assert_eq!(store.migrate().await.unwrap(), 1);
+    assert_eq!(
+        store.load("n1", "/n1/1/").await.unwrap().as_deref(),
+        Some("第一章")
+    );
+    assert_eq!(store.list("n1").await.unwrap(), ["/n1/1/"]);
+    assert!(
+        DirTranslationStore::new(&root)
+            .load("n1", "/n1/1/")
+            .await
+            .is_err()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn encrypted_stores_keep_no_text_in_plaintext() {
+    let chapter_url = "https://ncode.syosetu.com/n1234ab/1/";
+    let reply = r#"{"choices":[{"message":{"content":"托莉来到王都。"}}]}"#;
+    let fetcher = FixtureFetcher::new()
+        .with_page(chapter_url, NCODE_CHAPTER)
+        .with_json("https://api.deepseek.com/chat/completions", reply);
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-sealed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // 开启加密前留下的明文文件在启动时转换
+    let titles = HashMap::from([(chapter_url.to_string(), "托莉的旅程".to_string())]);
+    JsonTitleStore::new(dir.join("titles.json"))
+        .save("n1234ab", &titles)
+        .unwrap();
+    JsonStore::new(dir.join("keywords.json"))
+        .set("n1234ab", "トリー", "托莉")
+        .await
+        .unwrap();
+    JsonConflictStore::new(dir.join("conflicts.json"))
+        .record("n1234ab", "トリー", "托莉丝", chapter_url)
+        .unwrap();
+    let cipher = Some(Arc::new(Cipher::open(&dir, "secret").unwrap()));
+    let trans = Arc::new(
+        JsonTranslationStore::new(dir.join("translations.json")).with_encryption(cipher.clone()),
+    );
+    let library = [
+        "keywords.json",
+        "sources.json",
+        "history.json",
+        "titles.json",
+        "conflicts.json",
+    ]
+    .into_iter()
+    .map(|file| NovelFile::new(dir.join(file)).with_encryption(cipher.clone()))
+    .fold(Library::new(trans.clone()), Library::with_file);
+    assert_eq!(library.migrate().unwrap(), 3);
+    let base = test_pipeline(
+        &dir,
+        Arc::new(NcodeSite::new().with_client(client.clone())),
+        Arc::new(
+            DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string())
+                .with_client(client),
+        ),
+    );
+    let pipeline = Pipeline {
+        kw_store: Arc::new(
+            JsonStore::new(dir.join("keywords.json")).with_encryption(cipher.clone()),
+        ),
+        trans_store: trans.clone(),
+        source_store: Arc::new(
+            JsonSourceStore::new(dir.join("sources.json")).with_encryption(cipher.clone()),
+        ),
+        history_store: Arc::new(
+            JsonHistoryStore::new(dir.join("history.json")).with_encryption(cipher.clone()),
+        ),
+        title_store: Arc::new(
+            JsonTitleStore::new(dir.join("titles.json")).with_encryption(cipher.clone()),
+        ),
+        summary_store: Arc::new(
+            JsonSummaryStore::new(dir.join("summaries.json")).with_encryption(cipher.clone()),
+        ),
+        character_store: Arc::new(
+            JsonCharacterStore::new(dir.join("characters.json")).with_encryption(cipher.clone()),
+        ),
+        conflict_store: Arc::new(
+            JsonConflictStore::new(dir.join("conflicts.json")).with_encryption(cipher.clone()),
+        ),
+        library: Arc::new(library),
+        ..base
+    };
+    let ctx = pipeline
+        .context(
+            pipeline
+                .kw_store
+                .load("n1234ab")
+                .await
+                .unwrap()
+                .into_iter()
+                .collect(),
+            "テスト",
+        )
+        .await;
+    let cancel = CancellationToken::new();
+    process_chapter(&pipeline, "n1234ab", chapter_url, &ctx, None, &cancel)
+        .await
+        .unwrap();
+    // 重译后旧译文进入历史
+    save_translation(
+        trans.as_ref(),
+        pipeline.history_store.as_ref(),
+        "n1234ab",
+        chapter_url,
+        "托莉再次来到王都。",
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        pipeline.history_store.load("n1234ab", chapter_url).unwrap()[0].text,
+        "托莉来到王都。"
+    );
+    assert_eq!(
+        pipeline.summary_store.load("n1234ab").unwrap().as_deref(),
+        Some("托莉来到王都。")
+    );
+    assert_eq!(
+        pipeline.title_store.load("n1234ab").unwrap()[chapter_url],
+        "托莉的旅程"
+    );
+    assert_eq!(
+        pipeline.conflict_store.load("n1234ab").unwrap()["トリー"].proposed,
+        "托莉丝"
+    );
+    assert!(
+        pipeline
+            .source_store
+            .load("n1234ab", chapter_url)
+            .await
+            .unwrap()
+            .is_some()
+    );
+    // 数据目录中的文件都不含明文的译文与原文
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let content = String::from_utf8_lossy(&std::fs::read(&path).unwrap()).into_owned();
+        for plain in ["托莉", "朝の光"] {
+            assert!(
+                !content.contains(plain),
+                "{} contains {plain}",
+                path.display()
+            );
+        }
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn json_translations_append_to_a_journal() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-journal-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("translations.json");
+    let journal = dir.join("translations.json.journal");
+    let store = JsonTranslationStore::new(&path);
+    let other = JsonTranslationStore::new(&path);
+    store.save("n1", "/n1/1/", "第一章").await.unwrap();
+    assert!(path.exists() && !journal.exists());
+    assert_eq!(other.list("n1").await.unwrap(), ["/n1/1/"]);
+
+    // 之后的章节只追加到日志，另一个实例增量读入
+    let main = std::fs::read_to_string(&path).unwrap();
+    store.save("n1", "/n1/2/", "第二章").await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), main);
+    assert_eq!(
+        std::fs::read_to_string(&journal).unwrap().lines().count(),
+        1
+    );
+    assert_eq!(
+        other.load("n1", "/n1/2/").await.unwrap().as_deref(),
+        Some("第二章")
+    );
+    // 尚未写完的行留到下次读取
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&journal)
+        .unwrap();
+    std::io::Write::write_all(&mut file, br#"{"novel":"n1","chapter":"/n1/3/","#).unwrap();
+    assert_eq!(other.list("n1").await.unwrap().len(), 2);
+    std::io::Write::write_all(&mut file, "\"text\":\"第三章\"}\n".as_bytes()).unwrap();
+    assert_eq!(
+        other.load("n1", "/n1/3/").await.unwrap().as_deref(),
+        Some("第三章")
+    );
+
+    // 日志超过主文件大小时合并
+    let long = "長".repeat(1 << 19);
+    store.save("n1", "/n1/4/", &long).await.unwrap();
+    assert!(!journal.exists());
+    assert_eq!(
+        JsonTranslationStore::new(&path)
+            .list("n1")
+            .await
+            .unwrap()
+            .len(),
+        4
+    );
+    assert_eq!(
+        other
+            .load("n1", "/n1/4/")
+            .await
+            .unwrap()
+            .map(|text| text.len()),
+        Some(long.len())
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn store_writes_wait_for_other_processes() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-lock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("summaries.json");
+    let lock = FileLock::try_acquire(&path).unwrap().unwrap();
+    assert!(FileLock::try_acquire(&path).unwrap().is_none());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("summaries.json.lock")).unwrap(),
+        std::process::id().to_string()
+    );
+
+    // 锁被持有期间写入等待，释放后才读改写文件
+    let store = JsonSummaryStore::new(&path);
+    let writer = std::thread::spawn(move || store.save("n1", "摘要"));
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!path.exists());
+    drop(lock);
+    writer.join().unwrap().unwrap();
+    assert_eq!(
+        JsonSummaryStore::new(&path).load("n1").unwrap().as_deref(),
+        Some("摘要")
+    );
+
+    let lock = FileLock::try_acquire(&dir.join("translations.json"))
+        .unwrap()
+        .unwrap();
+    let store = Arc::new(JsonTranslationStore::new(dir.join("translations.json")));
+    let saving = tokio::spawn({
+        let store = store.clone();
+        async move { store.save("n1", "/n1/1/", "第一章").await }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!saving.is_finished());
+    drop(lock);
+    saving.await.unwrap().unwrap();
+    assert_eq!(store.list("n1").await.unwrap(), ["/n1/1/"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn sync_store_writes_do_not_stall_other_tasks() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-lock-mt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("titles.json");
+    let lock = FileLock::try_acquire(&path).unwrap().unwrap();
+    // 唯一的工作线程上，一个任务等待标题存储的锁，另一个任务照常计时
+    let ticks = Arc::new(AtomicU32::new(0));
+    let ticker = tokio::spawn({
+        let ticks = ticks.clone();
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                ticks.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    });
+    let saving = tokio::spawn({
+        let path = path.clone();
+        async move {
+            let titles = HashMap::from([("/n1/1/".to_string(), "第一章".to_string())]);
+            JsonTitleStore::new(path).save("n1", &titles)
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!saving.is_finished());
+    assert!(ticks.load(Ordering::SeqCst) >= 10);
+    drop(lock);
+    saving.await.unwrap().unwrap();
+    ticker.abort();
+    assert_eq!(JsonTitleStore::new(&path).load("n1").unwrap().len(), 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn dir_store_keeps_chapters_when_the_index_is_damaged() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-index-{}", std::process::id()));
+    let store = DirTranslationStore::new(&dir);
+    for chapter in ["/n1/1/", "/n1/2/"] {
+        store.save("n1", chapter, "译文").await.unwrap();
+    }
+    let index = dir.join("n1").join("index.json");
+    assert!(!dir.join("n1").join("index.json.tmp").exists());
+    // 索引损坏时报错，不当作没有章节而删除整个目录
+    std::fs::write(&index, "{\"/n1/1/\":").unwrap();
+    assert!(store.list("n1").await.is_err());
+    assert!(store.remove("n1", &["/n1/1/".to_string()]).await.is_err());
+    assert!(store.load("n1", "/n1/1/").await.unwrap().is_some());
+    // 索引丢失时只删除指定的章节，其余章节文件保留
+    std::fs::remove_file(&index).unwrap();
+    assert_eq!(
+        store.remove("n1", &["/n1/1/".to_string()]).await.unwrap(),
+        1
+    );
+    assert!(store.load("n1", "/n1/1/").await.unwrap().is_none());
+    assert_eq!(
+        store.load("n1", "/n1/2/").await.unwrap().as_deref(),
+        Some("译文")
+    );
+    // 章节文件都删除后才删除目录
+    assert_eq!(
+        store.remove("n1", &["/n1/2/".to_string()]).await.unwrap(),
+        1
+    );
+    assert!(!dir.join("n1").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn translations_convert_between_json_and_msgpack() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-msgpack-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let json = JsonTranslationStore::new(dir.join("translations.json"));
+    json.save("n1", "/n1/1/", "第一章").await.unwrap();
+    json.save("n2", "/n2/1/", "另一部").await.unwrap();
+
+    let msgpack = MsgpackTranslationStore::new(dir.join("translations.msgpack"));
+    assert!(msgpack.load_all().await.unwrap().is_empty());
+    msgpack
+        .save_all(&json.load_all().await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        msgpack.load("n1", "/n1/1/").await.unwrap().as_deref(),
+        Some("第一章")
+    );
+    msgpack.save("n1", "/n1/2/", "第二章").await.unwrap();
+    let mut chapters = msgpack.list("n1").await.unwrap();
+    chapters.sort();
+    assert_eq!(chapters, ["/n1/1/", "/n1/2/"]);
+
+    // 再转换回 JSON，压缩后的文件同样可读
+    let back = JsonTranslationStore::new(dir.join("back.json")).with_compression(true);
+    back.save_all(&msgpack.load_all().await.unwrap())
+        .await
+        .unwrap();
+    assert!(dir.join("back.json.zst").exists());
+    assert_eq!(
+        back.load_all().await.unwrap(),
+        msgpack.load_all().await.unwrap()
+    );
+
+    // 损坏的文件报错，而不是当作空的存储
+    std::fs::write(dir.join("translations.msgpack"), b"\xc1").unwrap();
+    assert!(msgpack.load_all().await.is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn keyword_store_keeps_files_it_cannot_parse() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("keywords.json");
+    let kw = JsonStore::new(&path);
+    assert_eq!(kw.modified(), None);
+    kw.set("n1", "トリー", "托莉").await.unwrap();
+    let saved = kw.modified().unwrap();
+    // 外部编辑后修改时间变化，读取到的是编辑后的内容
+    std::thread::sleep(Duration::from_millis(20));
+    std::fs::write(&path, r#"{"n1": {"トリー": "特莉"}}"#).unwrap();
+    assert!(kw.modified().unwrap() > saved);
+    assert_eq!(kw.load("n1").await.unwrap()["トリー"], "特莉");
+    // 编辑到一半的文件不会被当作空表覆盖
+    let partial = r#"{"n1": {"トリー": "特莉","#;
+    std::fs::write(&path, partial).unwrap();
+    assert!(kw.load("n1").await.is_err());
+    assert!(kw.set("n1", "ギルド", "公会").await.is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), partial);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn glossaries_merge_across_novels() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-merge-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let kw = JsonStore::new(dir.join("keywords.json"));
+    let categories = JsonCategoryStore::new(dir.join("categories.json"));
+    let conflicts = JsonConflictStore::new(dir.join("conflicts.json"));
+    kw.save(
+        "n1",
+        &HashMap::from([
+            ("トリー".to_string(), "托莉".to_string()),
+            ("ギルド".to_string(), "公会".to_string()),
+            ("魔王".to_string(), "魔王".to_string()),
+        ]),
+    )
+    .await
+    .unwrap();
+    kw.save(
+        "n2",
+        &HashMap::from([
+            ("ギルド".to_string(), "行会".to_string()),
+            ("魔王".to_string(), "魔王".to_string()),
+        ]),
+    )
+    .await
+    .unwrap();
+    categories
+        .set("n1", "トリー", Some(KeywordCategory::Person))
+        .unwrap();
+    assert!(
+        merge_glossaries(
+            &kw,
+            &categories,
+            &conflicts,
+            "n1",
+            "n1",
+            MergeStrategy::Review
+        )
+        .await
+        .is_err()
+    );
+
+    let report = merge_glossaries(
+        &kw,
+        &categories,
+        &conflicts,
+        "n1",
+        "n2",
+        MergeStrategy::Keep,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.added, 1);
+    assert_eq!(report.conflicts, ["ギルド"]);
+    assert_eq!(kw.load("n2").await.unwrap()["ギルド"], "行会");
+    assert_eq!(kw.load("n2").await.unwrap()["トリー"], "托莉");
+    assert_eq!(
+        categories.load("n2").unwrap()["トリー"],
+        KeywordCategory::Person
+    );
+    assert!(conflicts.load("n2").unwrap().is_empty());
+
+    let report = merge_glossaries(
+        &kw,
+        &categories,
+        &conflicts,
+        "n1",
+        "n2",
+        MergeStrategy::Review,
+    )
+    .await
+    .unwrap();
+    assert_eq!((report.added, report.overwritten), (0, 0));
+    assert_eq!(kw.load("n2").await.unwrap()["ギルド"], "行会");
+    assert_eq!(
+        conflicts.load("n2").unwrap()["ギルド"],
+        Conflict {
+            proposed: "公会".to_string(),
+            chapters: Vec::new(),
+        }
+    );
+
+    assert!(kw.remove("n2", "トリー").await.unwrap());
+    let report = merge_glossaries(
+        &kw,
+        &categories,
+        &conflicts,
+        "n1",
+        "n2",
+        MergeStrategy::Overwrite,
+    )
+    .await
+    .unwrap();
+    assert_eq!((report.added, report.overwritten), (1, 1));
+    assert!(report.conflicts.is_empty());
+    assert_eq!(kw.load("n2").await.unwrap()["ギルド"], "公会");
+    let _ = std::fs::remove_dir_all(&dir);
+}
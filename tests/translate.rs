@@ -0,0 +1,807 @@
+//! 各翻译引擎的请求与回复解析、提示词、熔断及译文检查
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use syosetu_rs::http::{FixtureFetcher, HttpFetcher, HttpOptions, build_client};
+use syosetu_rs::memory::{Example, ExampleScope, ExampleStore, JsonExampleStore, NovelSettings};
+use syosetu_rs::translate::{
+    ApiError, CHUNK_CHARS, CircuitBreaker, CircuitOpen, DeepLBackend, DeepSeekTranslator,
+    FallbackBackend, GeminiBackend, Honorifics, Keyword, KeywordCategory, OllamaBackend,
+    PromptTemplates, Sampling, StreamEvent, Suspect, TargetLang, TermLock, TranslationBackend,
+    TranslationContext, chat_completions_url, is_circuit_open, normalize_term, parse_keywords,
+    split_chunks, suspect_translation, verify_glossary,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn translator_targets_openai_compatible_servers() {
+    assert_eq!(
+        chat_completions_url("https://openrouter.ai/api/v1/"),
+        "https://openrouter.ai/api/v1/chat/completions"
+    );
+    assert_eq!(
+        chat_completions_url("http://localhost:1234/v1/chat/completions"),
+        "http://localhost:1234/v1/chat/completions"
+    );
+    let fetcher = FixtureFetcher::new()
+        .with_json(
+            "http://localhost:1234/v1/chat/completions",
+            r#"{"choices":[{"message":{"content":"你好"}}]}"#,
+        )
+        .with_json(
+            "http://localhost:8000/v1/chat/completions",
+            r#"{"error":{"message":"model not found"}}"#,
+        );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let cancel = CancellationToken::new();
+    let translator = DeepSeekTranslator::new(String::new(), "local-model".to_string())
+        .with_client(client.clone())
+        .with_api_base("http://localhost:1234/v1");
+    assert_eq!(
+        translator
+            .translate_text("こんにちは", &TranslationContext::default(), &cancel)
+            .await
+            .unwrap(),
+        "你好"
+    );
+    let failing = DeepSeekTranslator::new(String::new(), "missing".to_string())
+        .with_client(client)
+        .with_api_base("http://localhost:8000/v1");
+    let err = failing
+        .translate_text("こんにちは", &TranslationContext::default(), &cancel)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("model not found"), "{err}");
+}
+
+#[tokio::test]
+async fn ollama_backend_strips_thinking() {
+    let fetcher = FixtureFetcher::new().with_json(
+        "http://localhost:11434/api/chat",
+        r#"{"message":{"role":"assistant","content":"<think>考虑语气</think>\n你好"},"done":true}"#,
+    );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let backend = OllamaBackend::new("qwen2.5".to_string()).with_client(client);
+    assert_eq!(backend.name(), "ollama");
+    let cancel = CancellationToken::new();
+    assert_eq!(
+        backend
+            .translate_text("こんにちは", &TranslationContext::default(), &cancel)
+            .await
+            .unwrap(),
+        "你好"
+    );
+}
+
+#[tokio::test]
+async fn fallback_chain_reports_the_engine_that_succeeded() {
+    // DeepSeek 地址没有夹具（404），应改用 Ollama
+    let fetcher = FixtureFetcher::new().with_json(
+        "http://localhost:11434/api/chat",
+        r#"{"message":{"role":"assistant","content":"你好"},"done":true}"#,
+    );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let chain = FallbackBackend::new(vec![
+        Arc::new(
+            DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string())
+                .with_client(client.clone()),
+        ),
+        Arc::new(OllamaBackend::new("qwen2.5".to_string()).with_client(client)),
+    ]);
+    assert_eq!(chain.name(), "deepseek > ollama");
+    let cancel = CancellationToken::new();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (text, engine) = chain
+        .translate_streaming(
+            "こんにちは",
+            &TranslationContext::default(),
+            Some(&tx),
+            &cancel,
+        )
+        .await
+        .unwrap();
+    assert_eq!((text.as_str(), engine.as_str()), ("你好", "ollama"));
+    // 改用下一个引擎前通知界面丢弃已显示的片段
+    assert_eq!(rx.try_recv().unwrap(), StreamEvent::Restart);
+    assert_eq!(
+        rx.try_recv().unwrap(),
+        StreamEvent::Delta("你好".to_string())
+    );
+}
+
+#[test]
+fn long_text_is_split_on_paragraphs() {
+    let text = ["あ".repeat(2000), "い".repeat(800), "う".repeat(500)].join("\n");
+    let chunks = split_chunks(&text, CHUNK_CHARS);
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0], "あ".repeat(2000) + "\n" + &"い".repeat(800));
+    assert_eq!(chunks[1], "う".repeat(500));
+    // 超长的单个段落不拆开
+    assert_eq!(split_chunks(&"え".repeat(5000), CHUNK_CHARS).len(), 1);
+    assert_eq!(split_chunks("短い", CHUNK_CHARS), ["短い"]);
+}
+
+#[tokio::test]
+async fn long_chapters_are_translated_in_chunks() {
+    let fetcher = FixtureFetcher::new().with_json(
+        "http://localhost:11434/api/chat",
+        r#"{"message":{"role":"assistant","content":"译文\n"},"done":true}"#,
+    );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let backend = OllamaBackend::new("qwen2.5".to_string()).with_client(client);
+    let input = ["あ".repeat(2500), "い".repeat(2500)].join("\n");
+    let cancel = CancellationToken::new();
+    // 每段各请求一次，译文按段落拼接
+    assert_eq!(
+        backend
+            .translate_text(&input, &TranslationContext::default(), &cancel)
+            .await
+            .unwrap(),
+        "译文\n译文"
+    );
+}
+
+#[tokio::test]
+async fn api_errors_are_classified() {
+    let body = |message: &str| serde_json::json!({"error": {"message": message}});
+    assert!(matches!(
+        ApiError::from_response(401, &body("Authentication Fails")),
+        ApiError::InvalidKey(_)
+    ));
+    assert!(matches!(
+        ApiError::from_response(402, &body("Insufficient Balance")),
+        ApiError::InsufficientBalance(_)
+    ));
+    assert!(matches!(
+        ApiError::from_response(
+            400,
+            &body("This model's maximum context length is 65536 tokens")
+        ),
+        ApiError::ContextOverflow(_)
+    ));
+    assert!(matches!(
+        ApiError::from_response(429, &body("Rate limit reached")),
+        ApiError::RateLimited(_)
+    ));
+    // DeepL 的额度用尽使用 456
+    assert!(matches!(
+        ApiError::from_response(456, &serde_json::json!({"message": "Quota exceeded"})),
+        ApiError::InsufficientBalance(_)
+    ));
+
+    let fetcher = FixtureFetcher::new().with_json(
+        "https://api.deepseek.com/chat/completions",
+        r#"{"error":{"message":"Insufficient Balance","type":"unknown_error"}}"#,
+    );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let translator =
+        DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string()).with_client(client);
+    let err = translator
+        .translate_text(
+            "こんにちは",
+            &TranslationContext::default(),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ApiError>(),
+        Some(ApiError::InsufficientBalance(_))
+    ));
+    assert!(err.to_string().contains("top up"), "{err}");
+}
+
+#[test]
+fn keyword_output_tolerates_fences_and_commentary() {
+    let expected = vec![Keyword::new("トウリ", "托莉"), Keyword::new("王都", "王都")];
+    let object = r#"{"keywords":[{"japanese":"トウリ","translated":"托莉"},{"japanese":"王都","translated":"王都"}]}"#;
+    assert_eq!(parse_keywords(object), expected);
+    let fenced = format!("以下是新的专有名词：\n```json\n{object}\n```\n以上。");
+    assert_eq!(parse_keywords(&fenced), expected);
+    // 旧版提示词使用 `chinese` 字段
+    let jsonl = "```\n{\"japanese\":\"トウリ\",\"chinese\":\"托莉\"}\n{\"japanese\":\"王都\",\"chinese\":\"王都\"}\n```";
+    assert_eq!(parse_keywords(jsonl), expected);
+    let array = r#"[{"japanese":"トウリ","chinese":"托莉"},{"japanese":"王都","chinese":"王都"}]"#;
+    assert_eq!(parse_keywords(array), expected);
+    // 类别可选，无法识别的类别视为未分类
+    let categorized = r#"{"keywords":[{"japanese":"トウリ","translated":"托莉","category":"person"},{"japanese":"王都","translated":"王都","category":"city"}]}"#;
+    let parsed = parse_keywords(categorized);
+    assert_eq!(parsed[0].category, Some(KeywordCategory::Person));
+    assert_eq!(parsed[1].category, None);
+    assert_eq!(
+        "Place".parse::<KeywordCategory>().unwrap(),
+        KeywordCategory::Place
+    );
+    assert!(parse_keywords(r#"{"keywords":[]}"#).is_empty());
+    assert!(parse_keywords("没有新的专有名词").is_empty());
+}
+
+#[test]
+fn extracted_keywords_are_normalized() {
+    assert_eq!(normalize_term("ﾊﾞﾙﾄﾞ・ﾎﾟｰｼｮﾝ"), "バルド・ポーション");
+    assert_eq!(normalize_term("ｳﾞｨﾙ"), "ヴィル");
+    assert_eq!(normalize_term("　「王都」。"), "王都");
+    assert_eq!(normalize_term("ＬＶ１０！"), "LV10");
+    assert_eq!(
+        Keyword::new("トウリさん", "托莉さん").normalized(),
+        Keyword::new("トウリ", "托莉")
+    );
+    assert_eq!(
+        Keyword::new("アル様", "Al-sama").normalized(),
+        Keyword::new("アル", "Al")
+    );
+    // 「お」开头的称呼与去掉敬称后只剩一个字的词保持原样
+    for (jp, zh) in [("お姉さん", "姐姐"), ("皆さん", "大家"), ("王様", "国王")] {
+        assert_eq!(Keyword::new(jp, zh).normalized(), Keyword::new(jp, zh));
+    }
+}
+
+#[test]
+fn target_language_selects_labels_and_store_files() {
+    assert_eq!("en".parse::<TargetLang>().unwrap(), TargetLang::En);
+    assert_eq!("ZH".parse::<TargetLang>().unwrap(), TargetLang::Zh);
+    assert!("fr".parse::<TargetLang>().is_err());
+    // 中文沿用原有文件，其他语言各自存储
+    assert_eq!(
+        TargetLang::Zh.store_path("translations.json"),
+        "translations.json"
+    );
+    assert_eq!(
+        TargetLang::En.store_path("translations.json"),
+        "translations.en.json"
+    );
+    assert_eq!(TargetLang::Ko.note_labels().1, "【후기】");
+    assert_eq!(TargetLang::En.prompt_name(), "英文");
+}
+
+#[test]
+fn suspect_translations_are_detected() {
+    let source = "彼女は静かに扉を開けた。".repeat(20);
+    let full = "她静静地打开了门。".repeat(20);
+    assert_eq!(suspect_translation(&source, &full, TargetLang::Zh), None);
+    assert_eq!(
+        suspect_translation(&source, "她静静地打开了门。", TargetLang::Zh),
+        Some(Suspect::TooShort)
+    );
+    // 中文与英文的长度比例不同
+    assert_eq!(
+        suspect_translation(&source, &full, TargetLang::En),
+        Some(Suspect::TooShort)
+    );
+    let untranslated = "她静静地打开了门。彼女は静かに扉を開けた。".repeat(20);
+    assert_eq!(
+        suspect_translation(&source, &untranslated, TargetLang::Zh),
+        Some(Suspect::Untranslated)
+    );
+    let truncated = format!("{full}她走进房间，看");
+    assert_eq!(
+        suspect_translation(&source, &truncated, TargetLang::Zh),
+        Some(Suspect::Truncated)
+    );
+    // 注音中的假名与短篇不计
+    assert_eq!(
+        suspect_translation("扉を開けた。", "｜打开《あけた》了门。", TargetLang::Zh),
+        None
+    );
+}
+
+#[test]
+fn glossary_drift_is_fixed_or_reported() {
+    let keywords = vec![
+        ("トリー".to_string(), "托莉".to_string()),
+        ("トリー・アル".to_string(), "托莉·阿尔".to_string()),
+        ("魔王".to_string(), "魔王大人".to_string()),
+        ("王都".to_string(), "王都".to_string()),
+        ("ギルド".to_string(), "公会".to_string()),
+    ];
+    let source = "トリー・アルはトリーと呼ばれ、魔王と王都へ向かった。";
+    // 残留的片假名按对照表替换，长术语优先
+    let (fixed, drifted) = verify_glossary(
+        source,
+        "トリー・アル被称为トリー，和魔王大人一起前往王都。",
+        &keywords,
+    );
+    assert_eq!(fixed, "托莉·阿尔被称为托莉，和魔王大人一起前往王都。");
+    assert!(drifted.is_empty());
+    // 模型换了译名时无法自动修正，只报告；原文没有出现的术语不检查
+    let (fixed, drifted) = verify_glossary(
+        source,
+        "特莉·阿尔被称为特莉，和魔王一起前往王都。",
+        &keywords,
+    );
+    assert_eq!(fixed, "特莉·阿尔被称为特莉，和魔王一起前往王都。");
+    assert_eq!(drifted, ["トリー・アル", "トリー", "魔王"]);
+}
+
+#[test]
+fn locked_terms_keep_their_translations() {
+    let keywords = vec![
+        ("トリー".to_string(), "托莉".to_string()),
+        ("トリー・アル".to_string(), "托莉·阿尔".to_string()),
+        ("魔王".to_string(), "魔王大人".to_string()),
+        ("ギルド".to_string(), "公会".to_string()),
+    ];
+    let (lock, locked) =
+        TermLock::lock("トリー・アルはトリーと呼ばれ、魔王とトリーへ。", &keywords);
+    assert_eq!(locked, "⟦T1⟧は⟦T2⟧と呼ばれ、⟦T3⟧と⟦T2⟧へ。");
+    // 模型多加的空白被容忍，编造的编号原样保留
+    let (text, lost) = lock.unlock("⟦T1⟧被称为⟦ T2 ⟧，和⟦T9⟧一起去找⟦T2⟧。");
+    assert_eq!(text, "托莉·阿尔被称为托莉，和⟦T9⟧一起去找托莉。");
+    assert_eq!(lost, ["魔王"]);
+    let (lock, locked) = TermLock::lock("没有术语", &keywords);
+    assert!(lock.is_empty());
+    assert_eq!(locked, "没有术语");
+}
+
+#[test]
+fn honorifics_are_enforced_on_translations() {
+    let text = "托莉さん和阿尔-chan走了。｜叔叔《おじさん》说：“大家ちゃんと来了吗？”";
+    let (kept, n) = Honorifics::Keep.enforce(text);
+    assert_eq!(
+        kept,
+        "托莉さん和阿尔ちゃん走了。｜叔叔《おじさん》说：“大家ちゃんと来了吗？”"
+    );
+    assert_eq!(n, 1);
+    let (romaji, n) = Honorifics::Transliterate.enforce(text);
+    assert_eq!(
+        romaji,
+        "托莉-san和阿尔-chan走了。｜叔叔《おじさん》说：“大家ちゃんと来了吗？”"
+    );
+    assert_eq!(n, 1);
+    let (localized, n) = Honorifics::Localize.enforce(text);
+    assert_eq!(
+        localized,
+        "托莉和阿尔走了。｜叔叔《おじさん》说：“大家ちゃんと来了吗？”"
+    );
+    assert_eq!(n, 2);
+    assert_eq!(
+        "localize".parse::<Honorifics>().unwrap(),
+        Honorifics::Localize
+    );
+}
+
+#[tokio::test]
+async fn deepseek_streams_sse_deltas() {
+    let events = [
+        r#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+        r#"data: {"choices":[{"delta":{"content":"你"}}]}"#,
+        r#"data: {"choices":[{"delta":{"content":"好"}}]}"#,
+        r#"data: {"choices":[],"usage":{"prompt_tokens":10,"completion_tokens":2}}"#,
+        "data: [DONE]",
+    ];
+    let fetcher = FixtureFetcher::new().with_page(
+        "https://api.deepseek.com/chat/completions",
+        events.map(|e| format!("{e}\n\n")).concat(),
+    );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let translator =
+        DeepSeekTranslator::new("key".to_string(), "deepseek-chat".to_string()).with_client(client);
+    let cancel = CancellationToken::new();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (text, engine) = translator
+        .translate_streaming(
+            "こんにちは",
+            &TranslationContext::default(),
+            Some(&tx),
+            &cancel,
+        )
+        .await
+        .unwrap();
+    assert_eq!((text.as_str(), engine.as_str()), ("你好", "deepseek"));
+    let mut deltas = Vec::new();
+    while let Ok(StreamEvent::Delta(delta)) = rx.try_recv() {
+        deltas.push(delta);
+    }
+    assert_eq!(deltas, ["你", "好"]);
+}
+
+#[tokio::test]
+async fn gemini_backend_reports_safety_blocks() {
+    let base = "https://generativelanguage.googleapis.com/v1beta/models";
+    let fetcher = FixtureFetcher::new()
+        .with_json(
+            &format!("{base}/gemini-2.5-flash:generateContent"),
+            r#"{"candidates":[{"content":{"parts":[{"text":"你"},{"text":"好"}]},"finishReason":"STOP"}]}"#,
+        )
+        .with_json(
+            &format!("{base}/strict:generateContent"),
+            r#"{"candidates":[{"content":{"parts":[{"text":"残"}]},"finishReason":"SAFETY"}]}"#,
+        );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let cancel = CancellationToken::new();
+    let gemini = GeminiBackend::new("key".to_string(), "gemini-2.5-flash".to_string())
+        .with_client(client.clone());
+    assert_eq!(
+        gemini
+            .translate_text("こんにちは", &TranslationContext::default(), &cancel)
+            .await
+            .unwrap(),
+        "你好"
+    );
+    // 被安全过滤截断的回复不能当作译文
+    let strict = GeminiBackend::new("key".to_string(), "strict".to_string()).with_client(client);
+    let err = strict
+        .translate_text("こんにちは", &TranslationContext::default(), &cancel)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("SAFETY"), "{err}");
+}
+
+/// 记录请求体并给出固定回复的 DeepL 接口
+#[derive(Debug)]
+struct DeepLRecorder {
+    reply: &'static str,
+    request: Mutex<Option<serde_json::Value>>,
+}
+
+#[async_trait::async_trait]
+impl HttpFetcher for DeepLRecorder {
+    async fn fetch(&self, req: reqwest::Request) -> anyhow::Result<reqwest::Response> {
+        let body = req.body().and_then(|b| b.as_bytes()).unwrap_or_default();
+        *self.request.lock().unwrap() = Some(serde_json::from_slice(body)?);
+        Ok(http::Response::new(self.reply).into())
+    }
+}
+
+#[tokio::test]
+async fn deepl_keeps_locked_terms_as_tags() {
+    let recorder = Arc::new(DeepLRecorder {
+        reply: r#"{"translations":[{"text":"<t i=\"1\"/>来了 &amp; 走了"}]}"#,
+        request: Mutex::new(None),
+    });
+    let client = build_client(&HttpOptions {
+        fetcher: Some(recorder.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+    let deepl = DeepLBackend::new("key:fx".to_string()).with_client(client);
+    let cancel = CancellationToken::new();
+    let ctx = TranslationContext::default().with_term_lock(true);
+    let translated = deepl
+        .translate_text("⟦T1⟧が来た & 去った", &ctx, &cancel)
+        .await
+        .unwrap();
+    assert_eq!(translated, "⟦T1⟧来了 & 走了");
+    let request = recorder.request.lock().unwrap().take().unwrap();
+    assert_eq!(request["tag_handling"], "xml");
+    assert_eq!(request["text"][0], r#"<t i="1"/>が来た &amp; 去った"#);
+    // 没有锁定术语时按纯文本翻译
+    deepl
+        .translate_text("が来た & 去った", &ctx, &cancel)
+        .await
+        .unwrap();
+    let request = recorder.request.lock().unwrap().take().unwrap();
+    assert!(request.get("tag_handling").is_none());
+    assert_eq!(request["text"][0], "が来た & 去った");
+}
+
+#[tokio::test]
+async fn deepl_backend_uses_free_endpoint_for_fx_keys() {
+    let fetcher = FixtureFetcher::new().with_json(
+        "https://api-free.deepl.com/v2/translate",
+        r#"{"translations":[{"detected_source_language":"JA","text":"你好"}]}"#,
+    );
+    let client = build_client(&HttpOptions {
+        fetcher: Some(Arc::new(fetcher)),
+        ..Default::default()
+    })
+    .unwrap();
+    let cancel = CancellationToken::new();
+    let deepl = DeepLBackend::new("key:fx".to_string()).with_client(client.clone());
+    assert_eq!(
+        deepl
+            .translate_text("こんにちは", &TranslationContext::default(), &cancel)
+            .await
+            .unwrap(),
+        "你好"
+    );
+    // 未配置提取引擎时不提取专有名词
+    assert!(
+        deepl
+            .extract_keywords(
+                "你好",
+                "こんにちは",
+                &TranslationContext::default(),
+                &cancel
+            )
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    // 付费 key 使用另一个域名，夹具中没有对应页面
+    let pro = DeepLBackend::new("key".to_string()).with_client(client);
+    assert!(
+        pro.translate_text("こんにちは", &TranslationContext::default(), &cancel)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn prompt_templates_are_loaded_from_files() {
+    let dir = std::env::temp_dir().join(format!("syosetu-rs-prompts-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("translate.txt"),
+        "作品：{novel_title}\n{instructions}{glossary}---\n{text}",
+    )
+    .unwrap();
+    let templates = PromptTemplates::load(&dir).unwrap();
+    // 缺少的文件使用内置模板
+    assert_eq!(
+        templates.note,
+        PromptTemplates::default().note,
+        "note.txt is missing"
+    );
+    // 本地服务记录收到的提示词
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (prompt_tx, mut prompt_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // 读到请求体完整为止
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        let value: serde_json::Value = serde_json::from_str(body).unwrap();
+                        let _ = prompt_tx.send(value);
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            let reply = r#"{"message":{"content":"你好"}}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                reply.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    let backend = OllamaBackend::new("qwen2.5".to_string())
+        .with_client(build_client(&HttpOptions::default()).unwrap())
+        .with_api_base(&format!("http://{addr}"))
+        .with_templates(templates);
+    let ctx = TranslationContext::new(vec![("勇者".to_string(), "勇者".to_string())])
+        .with_novel_title("転生したら")
+        .with_honorifics(Some(Honorifics::Transliterate));
+    let translated = backend
+        .translate_text("こんにちは", &ctx, &CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(translated, "你好");
+    let request = prompt_rx.recv().await.unwrap();
+    assert_eq!(
+        request["messages"][0]["content"],
+        "作品：転生したら\n补充要求：\n- 人名后的日文敬称按罗马字音译，用连字符接在名字后：さん→-san、ちゃん→-chan、くん→-kun、様→-sama。\n\n已知翻译对照：勇者:勇者\n---\nこんにちは"
+    );
+    assert_eq!(request["model"], "qwen2.5");
+    // 按小说覆盖模型、温度、风格与作品说明
+    let mut settings = NovelSettings::default();
+    settings.set_field(1, "qwen3").unwrap();
+    settings.set_field(2, "0.6").unwrap();
+    settings.set_field(4, "0.9").unwrap();
+    settings.set_field(5, "casual").unwrap();
+    settings.set_field(7, "这是一部搞笑的异世界作品").unwrap();
+    assert!(settings.set_field(2, "3").is_err());
+    assert!(settings.set_field(3, "0").is_err());
+    assert!(settings.set_field(4, "1.5").is_err());
+    assert!(settings.set_field(5, "poetic").is_err());
+    assert_eq!(settings.field(2), "0.6");
+    // 题材示例排在小说自己的示例之后
+    settings.set_field(6, "isekai").unwrap();
+    let examples = JsonExampleStore::new(dir.join("examples.json"));
+    let example = |source: &str, translation: &str| Example {
+        source: source.to_string(),
+        translation: translation.to_string(),
+    };
+    examples
+        .save(
+            &ExampleScope::Genre("isekai".to_string()),
+            &[example("ステータスオープン", "打开状态栏")],
+        )
+        .unwrap();
+    examples
+        .save(
+            &ExampleScope::Novel("n1234ab".to_string()),
+            &[example("おはよう", "早上好")],
+        )
+        .unwrap();
+    let mut bank = examples
+        .load(&ExampleScope::Novel("n1234ab".to_string()))
+        .unwrap();
+    bank.extend(
+        examples
+            .load(&ExampleScope::Genre(settings.genre.clone().unwrap()))
+            .unwrap(),
+    );
+    // 未在小说设置中覆盖的采样参数沿用命令行的值
+    let ctx = ctx
+        .with_sampling(Sampling {
+            temperature: Some(1.0),
+            max_tokens: Some(4096),
+            top_p: Some(0.5),
+        })
+        .with_settings(&settings)
+        .with_examples(bank)
+        .with_categories(HashMap::from([(
+            "勇者".to_string(),
+            KeywordCategory::Person,
+        )]));
+    backend
+        .translate_text("こんにちは", &ctx, &CancellationToken::new())
+        .await
+        .unwrap();
+    let request = prompt_rx.recv().await.unwrap();
+    assert_eq!(request["model"], "qwen3");
+    assert_eq!(request["options"]["temperature"], 0.6);
+    assert_eq!(request["options"]["num_predict"], 4096);
+    assert_eq!(request["options"]["top_p"], 0.9);
+    let prompt = request["messages"][0]["content"].as_str().unwrap();
+    assert!(prompt.starts_with(
+        "作品：転生したら\n作品说明：\n这是一部搞笑的异世界作品\n\n翻译示例（参考其文风与用词，不要翻译或输出）：\n原文：\nおはよう\n译文：\n早上好\n\n原文：\nステータスオープン\n译文：\n打开状态栏\n\n"
+    ));
+    assert!(prompt.contains("- 译文使用轻松口语化的表达"));
+    assert!(
+        prompt.contains("以下人名必须始终使用已知翻译对照中的译名，不要改写或换用其他译法：勇者")
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// 按顺序给出预设结果的翻译器；成功的结果要等 `release` 被取消才返回
+struct ScriptedBackend {
+    replies: Mutex<VecDeque<anyhow::Result<String>>>,
+    calls: AtomicU32,
+    release: CancellationToken,
+}
+
+#[async_trait::async_trait]
+impl TranslationBackend for ScriptedBackend {
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    async fn translate_text(
+        &self,
+        _input: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> anyhow::Result<String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let reply = self.replies.lock().unwrap().pop_front().unwrap();
+        if reply.is_ok() {
+            self.release.cancelled().await;
+        }
+        reply
+    }
+
+    async fn extract_keywords(
+        &self,
+        _zh: &str,
+        _jp: &str,
+        _ctx: &TranslationContext,
+        _cancel: &CancellationToken,
+    ) -> anyhow::Result<Vec<Keyword>> {
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn circuit_breaker_opens_after_repeated_outages() {
+    let unavailable = || {
+        Err(ApiError::Other {
+            status: 503,
+            message: "overloaded".to_string(),
+        }
+        .into())
+    };
+    let inner = Arc::new(ScriptedBackend {
+        replies: Mutex::new(VecDeque::from([
+            // 请求本身被拒绝不计入失败
+            Err(ApiError::Other {
+                status: 400,
+                message: "bad request".to_string(),
+            }
+            .into()),
+            Err(anyhow::anyhow!("Gemini blocked the prompt: SAFETY")),
+            unavailable(),
+            Err(ApiError::Other {
+                status: 400,
+                message: "bad request".to_string(),
+            }
+            .into()),
+            unavailable(),
+            unavailable(),
+            Ok("译文".to_string()),
+            Ok("译文".to_string()),
+        ])),
+        calls: AtomicU32::new(0),
+        release: CancellationToken::new(),
+    });
+    let cooldown = Duration::from_millis(200);
+    let breaker = Arc::new(CircuitBreaker::new(inner.clone(), 2, cooldown));
+    let call = |breaker: Arc<CircuitBreaker>| async move {
+        breaker
+            .translate_text(
+                "a",
+                &TranslationContext::default(),
+                &CancellationToken::new(),
+            )
+            .await
+    };
+    for _ in 0..5 {
+        let err = call(breaker.clone()).await.unwrap_err();
+        assert!(!is_circuit_open(&err), "{err:?}");
+    }
+    let err = call(breaker.clone()).await.unwrap_err();
+    assert!(is_circuit_open(&err), "{err:?}");
+    // 冷却期内直接失败，不再请求
+    let err = call(breaker.clone()).await.unwrap_err();
+    assert!(
+        err.downcast_ref::<CircuitOpen>()
+            .is_some_and(|e| e.retry_in > Duration::ZERO)
+    );
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 6);
+
+    // 冷却后只放行一次试探
+    tokio::time::sleep(cooldown).await;
+    let probe = tokio::spawn(call(breaker.clone()));
+    while inner.calls.load(Ordering::SeqCst) < 7 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let err = call(breaker.clone()).await.unwrap_err();
+    assert!(is_circuit_open(&err), "{err:?}");
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 7);
+    inner.release.cancel();
+    assert_eq!(probe.await.unwrap().unwrap(), "译文");
+    // 试探成功后恢复
+    assert_eq!(call(breaker.clone()).await.unwrap(), "译文");
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 8);
+}